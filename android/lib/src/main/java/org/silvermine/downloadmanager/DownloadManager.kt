@@ -223,6 +223,39 @@ class DownloadManager private constructor(context: Context) {
       return DownloadActionResponse.new(canceled)
    }
 
+   /**
+    * Cancels all downloads and clears the store.
+    *
+    * Removes every store entry in a single write and deletes the temp file of each entry.
+    * No change events are emitted for the removed items.
+    *
+    * @return A summary of what was removed.
+    */
+   @Synchronized
+   fun reset(): ResetSummary {
+      workManager.cancelAllWorkByTag(WORK_TAG)
+
+      val items = store.clear()
+      var canceled = 0
+      var tempFilesRemoved = 0
+      for (item in items) {
+         if (item.status == DownloadStatus.Idle ||
+            item.status == DownloadStatus.InProgress ||
+            item.status == DownloadStatus.Paused
+         ) {
+            canceled++
+         }
+
+         val tempFile = File("${item.path}${DownloadWorker.DOWNLOAD_SUFFIX}")
+         if (tempFile.exists() && tempFile.delete()) {
+            tempFilesRemoved++
+         }
+      }
+
+      Log.d(TAG, "Reset download store: removed ${items.size}, canceled $canceled")
+      return ResetSummary(canceled = canceled, removed = items.size, tempFilesRemoved = tempFilesRemoved)
+   }
+
    /**
     * Emits a download item change event.
     * Called by DownloadWorker to report progress and completion.
@@ -51,6 +51,14 @@ internal class DownloadStore(context: Context) {
       save()
    }
 
+   @Synchronized
+   fun clear(): List<DownloadItem> {
+      val removed = downloads.values.toList()
+      downloads.clear()
+      save()
+      return removed
+   }
+
    private fun load() {
       try {
          val bytes = file.readFully()
@@ -0,0 +1,22 @@
+package org.silvermine.downloadmanager
+
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+
+/**
+ * Summary of a [DownloadManager.reset] operation.
+ */
+@Serializable
+data class ResetSummary(
+   /** Number of idle, in progress or paused downloads that were canceled. */
+   @SerialName("canceled")
+   val canceled: Int,
+
+   /** Total number of store entries removed. */
+   @SerialName("removed")
+   val removed: Int,
+
+   /** Number of temp files deleted from disk. */
+   @SerialName("tempFilesRemoved")
+   val tempFilesRemoved: Int,
+)
@@ -181,6 +181,20 @@ class DownloadPlugin(activity: Activity) : Plugin(activity) {
       }
    }
 
+   @Command
+   fun reset(invoke: Invoke) {
+      scope.launch {
+         try {
+            val summary = withContext(Dispatchers.IO) { downloadManager.reset() }
+            val payload = JSObject(json.encodeToString(summary))
+            trigger("reset", payload)
+            invoke.resolve(payload)
+         } catch (e: Exception) {
+            invoke.reject(e.message)
+         }
+      }
+   }
+
    companion object {
       private const val TAG = "DownloadPlugin"
    }
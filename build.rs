@@ -1,12 +1,44 @@
 const COMMANDS: &[&str] = &[
    "create",
+   "create_in_dir",
+   "create_batch",
+   "validate_create",
    "list",
+   "list_by_status",
+   "list_by_tag",
+   "count_by_status",
+   "aggregate_progress",
+   "stats",
+   "reset_stats",
+   "recovered",
    "get",
+   "get_or_create",
+   "downloaded_bytes",
+   "download_now",
+   "probe",
+   "verify",
+   "action",
    "start",
    "cancel",
    "pause",
    "resume",
+   "retry",
+   "restart",
+   "schedule",
    "is_native",
+   "capabilities",
+   "config",
+   "move_to",
+   "set_priority",
+   "reveal",
+   "pause_all",
+   "resume_all",
+   "cancel_all",
+   "pause_paths",
+   "resume_paths",
+   "cancel_paths",
+   "clear_completed",
+   "reset",
    "registerListener",
 ];
 
@@ -0,0 +1,244 @@
+// Generated from the download-manager models by ts-rs. Don't edit by hand; see
+// `test_typescript_bindings_are_up_to_date`.
+
+export type DownloadActionResponse = { download: DownloadItem, expectedStatus: DownloadStatus, isExpectedStatus: boolean, };
+export type DownloadItem = { url: string, path: string, 
+/**
+ * Percentage of the download received, from 0 to 100. `None` while bytes are
+ * received of a resource whose size is unknown, see `downloaded_bytes`.
+ */
+progress: number | null, status: DownloadStatus, 
+/**
+ * `ETag` of the remote resource, captured from the first response. Sent as
+ * `If-Range` on resume so a changed resource restarts instead of being appended to.
+ */
+etag?: string | null, 
+/**
+ * `Last-Modified` of the remote resource, used for `If-Range` when no strong
+ * `ETag` is available.
+ */
+lastModified?: string | null, 
+/**
+ * Total size of the download in bytes, if known: the size of the remote resource,
+ * capped at `max_bytes_truncate`. Lets a resume report its progress before any new
+ * bytes arrive.
+ */
+totalSize?: number, 
+/**
+ * Bytes received so far, updated with `progress` while the download runs. Unlike
+ * `progress`, it is meaningful when `total_size` is unknown.
+ */
+downloadedBytes?: number, 
+/**
+ * Mirror currently used in place of `url` after a failover, if any.
+ */
+activeMirror?: string | null, 
+/**
+ * `Content-Type` of the resource, captured from the response, e.g. to categorize
+ * finished files without sniffing them.
+ */
+contentType?: string | null, 
+/**
+ * Whether the server accepts range requests for the resource, from its first
+ * response. A download it doesn't accept them for restarts from zero on resume.
+ */
+acceptsRanges?: boolean | null, 
+/**
+ * Priority of the download among those `Queued` for a free slot on their host:
+ * downloads of higher priority start first, those of equal priority in the order
+ * they were created. Defaults to 0.
+ */
+priority?: number, 
+/**
+ * Milliseconds the download waits before its next attempt because the server
+ * answered with `Retry-After`, while `status` is `InProgress`. Never persisted.
+ */
+retryAfterMs?: number, 
+/**
+ * Reason the download failed, if `status` is `Failed`.
+ */
+error?: string | null, 
+/**
+ * Time the download is scheduled to start at, in milliseconds since the Unix epoch,
+ * while `status` is `Idle`. Cleared once it starts.
+ */
+scheduledAt?: number, 
+/**
+ * Time the download has to complete by, in milliseconds since the Unix epoch, set
+ * when it first starts if `deadline_ms` is configured. It is kept when the download
+ * is paused and resumed, and replaced when a download that missed it is started
+ * again.
+ */
+deadlineAt?: number, 
+/**
+ * Whether the download completed early because it reached `max_bytes_truncate`.
+ */
+truncated?: boolean, 
+/**
+ * Whether the filename in `path` is a placeholder derived from the URL, to be
+ * replaced by the `Content-Disposition` filename once the download starts.
+ */
+resolveFilename?: boolean, 
+/**
+ * Time the item was last written to the store, in milliseconds since the Unix
+ * epoch, or 0 if unknown. Set by the store.
+ */
+updatedAt?: number, 
+/**
+ * Fallback URLs tried in order, from the current offset, when `url` can't be
+ * reached or returns a server error.
+ */
+mirrors?: Array<string>, 
+/**
+ * Download only the first N bytes of the resource, then stop and complete the
+ * download with `truncated` set, e.g. to preview a large file. Unlike a range
+ * request this doesn't need the server to support ranges.
+ */
+maxBytesTruncate?: number, 
+/**
+ * First byte of the resource to download, for a download of only part of it. The
+ * file then holds the bytes from `range_start` to `range_end`, and progress is
+ * relative to their number. Needs the server to support range requests.
+ */
+rangeStart?: number, 
+/**
+ * Last byte of the resource to download, inclusive, like the end of an HTTP `Range`.
+ * Without it, the download runs to the end of the resource.
+ */
+rangeEnd?: number, 
+/**
+ * Path of a download that must complete before this one starts. Starting this
+ * download while the prerequisite is incomplete queues it; it then starts when the
+ * prerequisite completes, or fails if the prerequisite fails or is canceled.
+ */
+dependsOn?: string | null, 
+/**
+ * Expected `Content-Type` of the response, either exact (`application/zip`) or a
+ * prefix ending in `/` (`image/`). A response of another type fails the download
+ * before anything is written.
+ */
+expectedContentType?: string | null, 
+/**
+ * `Content-Type`s the response may have, each like `expected_content_type` or with
+ * a wildcard subtype (`video/*`). A response matching none of them, nor
+ * `expected_content_type`, fails the download before anything is written.
+ */
+allowedContentTypes?: Array<string>, 
+/**
+ * Headers sent with every request of the download. They take precedence over the
+ * configured default headers of the same name.
+ */
+headers?: { [key in string]?: string }, 
+/**
+ * Cookies sent with the requests of the download, by name, e.g. a session copied
+ * from the webview after logging in. They are added to the manager's cookie store
+ * when the download is created, where the cookies the host sets update them, so a
+ * resumed download carries the current session. The store is kept in memory only:
+ * cookies are neither persisted nor sent in change events, and are lost when the
+ * app exits. Only the download's host gets them, and a `Cookie` header replaces them.
+ */
+cookies?: { [key in string]?: string }, 
+/**
+ * `ETag` of the file already at the download path, e.g. the `etag` of the completed
+ * item that downloaded it. Sent as `If-None-Match` while the file exists, so that if
+ * the resource is unchanged (`304 Not Modified`) the download completes with the
+ * existing file instead of downloading it again. Defaults to the `ETag` the store
+ * kept for the path from the last download to it.
+ */
+ifNoneMatch?: string | null, 
+/**
+ * Unix permissions the completed file is given, e.g. `0o755` for a helper binary.
+ * Otherwise it keeps those it was created with. Ignored on Windows.
+ */
+mode?: number | null, 
+/**
+ * What to do when a file already exists at the download path, checked on creation
+ * and again before the completed download is moved into place.
+ */
+overwrite?: OverwritePolicy, 
+/**
+ * Labels to group and filter downloads by, e.g. the feature of the app that created
+ * them. They don't affect the download.
+ */
+tags?: Array<string>, };
+export type DownloadOptions = { 
+/**
+ * Fallback URLs tried in order, from the current offset, when `url` can't be
+ * reached or returns a server error.
+ */
+mirrors?: Array<string>, 
+/**
+ * Download only the first N bytes of the resource, then stop and complete the
+ * download with `truncated` set, e.g. to preview a large file. Unlike a range
+ * request this doesn't need the server to support ranges.
+ */
+maxBytesTruncate?: number, 
+/**
+ * First byte of the resource to download, for a download of only part of it. The
+ * file then holds the bytes from `range_start` to `range_end`, and progress is
+ * relative to their number. Needs the server to support range requests.
+ */
+rangeStart?: number, 
+/**
+ * Last byte of the resource to download, inclusive, like the end of an HTTP `Range`.
+ * Without it, the download runs to the end of the resource.
+ */
+rangeEnd?: number, 
+/**
+ * Path of a download that must complete before this one starts. Starting this
+ * download while the prerequisite is incomplete queues it; it then starts when the
+ * prerequisite completes, or fails if the prerequisite fails or is canceled.
+ */
+dependsOn?: string | null, 
+/**
+ * Expected `Content-Type` of the response, either exact (`application/zip`) or a
+ * prefix ending in `/` (`image/`). A response of another type fails the download
+ * before anything is written.
+ */
+expectedContentType?: string | null, 
+/**
+ * `Content-Type`s the response may have, each like `expected_content_type` or with
+ * a wildcard subtype (`video/*`). A response matching none of them, nor
+ * `expected_content_type`, fails the download before anything is written.
+ */
+allowedContentTypes?: Array<string>, 
+/**
+ * Headers sent with every request of the download. They take precedence over the
+ * configured default headers of the same name.
+ */
+headers?: { [key in string]?: string }, 
+/**
+ * Cookies sent with the requests of the download, by name, e.g. a session copied
+ * from the webview after logging in. They are added to the manager's cookie store
+ * when the download is created, where the cookies the host sets update them, so a
+ * resumed download carries the current session. The store is kept in memory only:
+ * cookies are neither persisted nor sent in change events, and are lost when the
+ * app exits. Only the download's host gets them, and a `Cookie` header replaces them.
+ */
+cookies?: { [key in string]?: string }, 
+/**
+ * `ETag` of the file already at the download path, e.g. the `etag` of the completed
+ * item that downloaded it. Sent as `If-None-Match` while the file exists, so that if
+ * the resource is unchanged (`304 Not Modified`) the download completes with the
+ * existing file instead of downloading it again. Defaults to the `ETag` the store
+ * kept for the path from the last download to it.
+ */
+ifNoneMatch?: string | null, 
+/**
+ * Unix permissions the completed file is given, e.g. `0o755` for a helper binary.
+ * Otherwise it keeps those it was created with. Ignored on Windows.
+ */
+mode?: number | null, 
+/**
+ * What to do when a file already exists at the download path, checked on creation
+ * and again before the completed download is moved into place.
+ */
+overwrite?: OverwritePolicy, 
+/**
+ * Labels to group and filter downloads by, e.g. the feature of the app that created
+ * them. They don't affect the download.
+ */
+tags?: Array<string>, };
+export type DownloadStatus = "unknown" | "pending" | "idle" | "inProgress" | "paused" | "queued" | "failed" | "canceled" | "completed";
+export type OverwritePolicy = "overwrite" | "fail" | "rename";
+export type DownloadAction = "start" | "pause" | "resume" | "cancel" | "retry";
@@ -0,0 +1,47 @@
+//! Downloads a file, printing its progress from the stream of changes.
+//!
+//! ```sh
+//! cargo run -p download-manager --example progress -- https://example.com/file.zip /tmp/file.zip
+//! ```
+
+use std::sync::Arc;
+
+use download_manager::{DownloadManager, DownloadStatus};
+use futures::StreamExt;
+
+#[tokio::main]
+async fn main() {
+   let mut args = std::env::args().skip(1);
+   let (Some(url), Some(path)) = (args.next(), args.next()) else {
+      eprintln!("Usage: progress <url> <path>");
+      std::process::exit(2);
+   };
+
+   let data_dir = std::env::temp_dir().join("download-manager-example");
+   let manager = DownloadManager::new(data_dir, Arc::new(|_| {}));
+   let mut changes = manager.subscribe();
+   manager
+      .create(&path, &url)
+      .expect("Failed to create download");
+   manager.start(&path).expect("Failed to start download");
+
+   while let Some(item) = changes.next().await {
+      match item.status {
+         DownloadStatus::Completed => {
+            println!(
+               "Downloaded {} bytes to {}",
+               item.downloaded_bytes, item.path
+            );
+            break;
+         }
+         DownloadStatus::Failed => {
+            eprintln!("Download failed: {}", item.error.unwrap_or_default());
+            std::process::exit(1);
+         }
+         _ => match item.progress {
+            Some(progress) => println!("{:>5.1}%", progress),
+            None => println!("{} bytes", item.downloaded_bytes),
+         },
+      }
+   }
+}
@@ -0,0 +1,18 @@
+//! Prints the JSON Schema of the download operations sent to the frontend, e.g. to
+//! validate them in other tools. The TypeScript declarations are generated in
+//! `bindings/download-manager.d.ts`.
+//!
+//! ```sh
+//! cargo run -p download-manager --example schema --features schema > download.schema.json
+//! ```
+
+use std::error::Error;
+use std::io::{self, Write};
+
+use download_manager::DownloadActionResponse;
+
+fn main() -> Result<(), Box<dyn Error>> {
+   let schema = schemars::schema_for!(DownloadActionResponse);
+   writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&schema)?)?;
+   Ok(())
+}
@@ -1,14 +1,54 @@
 use futures::StreamExt;
-use reqwest::header::{HeaderMap, RANGE};
-use reqwest_middleware::ClientBuilder;
-use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use reqwest::cookie::Jar;
+use reqwest::header::{
+   ACCEPT_ENCODING, ACCEPT_RANGES, AUTHORIZATION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+   ETAG, HeaderMap, HeaderName, HeaderValue, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+   RETRY_AFTER, USER_AGENT,
+};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{
+   RetryTransientMiddleware, Retryable, RetryableStrategy, default_on_request_failure,
+   default_on_request_success, policies::ExponentialBackoff,
+};
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use crate::Error;
-use crate::manager::{DOWNLOAD_SUFFIX, DownloadManager};
+use crate::config::{
+   CrossHostRedirects, DownloadManagerConfig, ProgressConfig, ProxyConfig, TlsConfig, TlsVersion,
+};
+use crate::filename;
+use crate::manager::{DownloadManager, move_file, prerequisite_failed};
 use crate::models::*;
+use crate::validate::long_path;
+
+/// Bytes between two progress events of a download of unknown size, unless
+/// [`ProgressConfig::interval_ms`] is set.
+const BYTES_THRESHOLD: u64 = 1024 * 1024;
+
+/// Minimum size of a segment of a segmented download. Smaller resources use fewer
+/// segments, or a single connection.
+const MIN_SEGMENT_SIZE: u64 = 64 * 1024;
+
+/// Longest `Retry-After` wait honored. A server asking for a longer wait fails the
+/// download with its HTTP error instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Number of `Retry-After` waits honored per download attempt.
+const MAX_RETRY_AFTER_WAITS: usize = 3;
+
+/// Default capacity of the buffer a download is written through, see
+/// [`DownloadManagerConfig::write_buffer_bytes`].
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Performs the actual HTTP download with resume support.
 ///
@@ -18,15 +58,16 @@ use crate::models::*;
 /// - Streaming response chunks to disk
 /// - Progress tracking and throttling
 /// - State updates and event emission
-pub(crate) async fn download(manager: &DownloadManager, item: DownloadItem) -> crate::Result<()> {
-   // Build client with retry middleware for transient failures.
-   let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-   let client = ClientBuilder::new(reqwest::Client::new())
-      .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-      .build();
+///
+/// `item.path` is updated in place if the filename is resolved from the response.
+pub(crate) async fn download(
+   manager: &DownloadManager,
+   item: &mut DownloadItem,
+) -> crate::Result<()> {
+   let client = manager.client()?;
 
    // Check the size of the already downloaded part, if any.
-   let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
+   let mut temp_path = manager.temp_path(&item.path);
    let mut downloaded_size = if Path::new(&temp_path).exists() {
       fs::metadata(&temp_path)
          .map(|metadata| metadata.len())
@@ -35,27 +76,156 @@ pub(crate) async fn download(manager: &DownloadManager, item: DownloadItem) -> c
       0
    };
 
-   // Set the Range header for resuming the download.
-   let mut headers = HeaderMap::new();
-   if downloaded_size > 0 {
+   // All bytes arrived before, but the download couldn't be moved into place, e.g.
+   // because its path was taken. No request is needed to complete it.
+   if item
+      .total_size
+      .is_some_and(|size| size > 0 && downloaded_size == size)
+   {
+      return complete(manager, item, &temp_path, false);
+   }
+
+   // A server that doesn't accept range requests can only send the resource from its
+   // first byte, so the bytes received before are discarded.
+   let range_start = item.options.range_start.unwrap_or(0);
+   let ranged = range_start > 0 || item.options.range_end.is_some();
+   if downloaded_size > 0 && !ranged && item.accepts_ranges == Some(false) {
+      tracing::warn!(file = %item.path, "Server doesn't accept range requests; restarting download from zero");
+      remove_temp_file(&temp_path)?;
+      downloaded_size = 0;
+      report_restart(manager, &item.path);
+   }
+
+   // Set the Range header for resuming the download, or for downloading only part of
+   // the resource, from where the temp file ends. If a validator from the first response
+   // is known, send it as If-Range on resume so the server returns the full resource
+   // (200) rather than the range (206) when the resource has changed since.
+   let mut headers = request_headers(&manager.config, &item.options)?;
+   authorize(manager, &mut headers, false).await?;
+   if downloaded_size > 0 || ranged {
+      let end = item
+         .options
+         .range_end
+         .map_or(String::new(), |end| end.to_string());
       headers.insert(
          RANGE,
-         format!("bytes={}-", downloaded_size)
+         format!("bytes={}-{}", range_start + downloaded_size, end)
             .parse()
             .map_err(|e| Error::Http(format!("Invalid range header: {}", e)))?,
       );
    }
+   if downloaded_size > 0 {
+      if let Some(validator) = if_range_validator(item) {
+         headers.insert(
+            IF_RANGE,
+            validator
+               .parse()
+               .map_err(|e| Error::Http(format!("Invalid If-Range header: {}", e)))?,
+         );
+      }
+   } else if Path::new(&item.path).exists()
+      && let Some(etag) = match &item.options.if_none_match {
+         Some(etag) => Some(etag.clone()),
+         None => manager.store.etag(&item.path)?,
+      }
+   {
+      // Ask for the resource only if it changed since the file at the path was downloaded.
+      headers.insert(
+         IF_NONE_MATCH,
+         etag
+            .parse()
+            .map_err(|e| Error::Http(format!("Invalid If-None-Match header: {}", e)))?,
+      );
+   }
 
-   // Send the request.
-   let response = match client.get(&item.url).headers(headers).send().await {
-      Ok(res) => res,
-      Err(e) => {
-         return Err(Error::Http(format!("Failed to send request: {}", e)));
+   // Send the request. A server that is overloaded or rate limits the client may ask
+   // to come back later with `Retry-After`; wait as long as it asks, announcing the wait
+   // so the UI can show a countdown, then try the same URL again. A request rejected
+   // because the bearer token expired is sent again once with a refreshed token.
+   //
+   // A resume from beyond the end of the resource is answered with 416, e.g. if the
+   // temp file holds bytes of a previous, larger version of it. The temp file already
+   // holds the whole resource if it has exactly the size the server reports; otherwise
+   // it's discarded and the resource requested again from its first byte.
+   let urls: Vec<&str> = std::iter::once(item.url.as_str())
+      .chain(item.options.mirrors.iter().map(String::as_str))
+      .collect();
+   let mut attempt = 0;
+   let mut waits = 0;
+   let mut refreshed = false;
+   let response = loop {
+      let response;
+      (attempt, response) =
+         send_with_failover(&client, &urls, attempt, &headers, &item.path).await?;
+      if response.status() == reqwest::StatusCode::UNAUTHORIZED
+         && !refreshed
+         && authorize(manager, &mut headers, true).await?
+      {
+         tracing::info!(file = %item.path, "Retrying with a refreshed token");
+         refreshed = true;
+         continue;
+      }
+      if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+         && downloaded_size > 0
+         && !ranged
+      {
+         if unsatisfied_range_size(response.headers()) == Some(downloaded_size) {
+            return complete(manager, item, &temp_path, false);
+         }
+         tracing::warn!(file = %item.path, downloaded_size, "Temp file is larger than the resource; restarting download from zero");
+         remove_temp_file(&temp_path)?;
+         downloaded_size = 0;
+         headers.remove(RANGE);
+         headers.remove(IF_RANGE);
+         report_restart(manager, &item.path);
+         continue;
+      }
+      let wait = retry_after(&response).filter(|&wait| wait <= MAX_RETRY_AFTER);
+      let Some(wait) = wait.filter(|_| waits < MAX_RETRY_AFTER_WAITS) else {
+         break response;
+      };
+      waits += 1;
+      tracing::warn!(file = %item.path, status = response.status().as_u16(), wait_ms = wait.as_millis() as u64, "Waiting to retry as requested by the server");
+      if !set_retry_after(manager, &item.path, Some(wait))? {
+         return Ok(());
+      }
+      tokio::time::sleep(wait).await;
+      if !set_retry_after(manager, &item.path, None)? {
+         // Paused or canceled while waiting.
+         return Ok(());
       }
    };
+   let url = urls[attempt].to_string();
+   let active_mirror = (attempt > 0).then(|| url.clone());
 
-   // Validate response status before streaming the body.
+   // The download may have been canceled and started again while the request was sent.
+   if manager.is_superseded() {
+      return Ok(());
+   }
+
+   // Announce which mirror serves the download, or that the primary URL does again.
+   let Ok(Some(current)) = manager.store.find_by_path(&item.path) else {
+      // Download item was not found i.e. removed.
+      return Ok(());
+   };
+   if current.active_mirror != active_mirror {
+      let updated = DownloadItem {
+         active_mirror: active_mirror.clone(),
+         ..current
+      };
+      manager.store.update(updated.clone())?;
+      manager.emit_changed(updated);
+   }
+   item.active_mirror = active_mirror;
+
+   // The file at the path is still current.
    let status = response.status();
+   if status == reqwest::StatusCode::NOT_MODIFIED && headers.contains_key(IF_NONE_MATCH) {
+      let etag = header_string(response.headers(), ETAG);
+      return complete_unchanged(manager, item, etag);
+   }
+
+   // Validate response status before streaming the body.
    if !status.is_success() {
       return Err(Error::Http(format!(
          "HTTP {}: {}",
@@ -64,117 +234,727 @@ pub(crate) async fn download(manager: &DownloadManager, item: DownloadItem) -> c
       )));
    }
 
+   // Refuse content of an unexpected type, e.g. an error or login page, before writing.
+   let content_type = header_string(response.headers(), CONTENT_TYPE);
+   let allowed: Vec<&str> = item
+      .options
+      .expected_content_type
+      .iter()
+      .chain(&item.options.allowed_content_types)
+      .map(String::as_str)
+      .collect();
+   if !allowed.is_empty()
+      && !allowed
+         .iter()
+         .any(|expected| content_type_matches(expected, content_type.as_deref()))
+   {
+      return Err(Error::Http(format!(
+         "Unexpected Content-Type '{}', expected '{}'",
+         content_type.as_deref().unwrap_or_default(),
+         allowed.join("' or '")
+      )));
+   }
+
+   // Only a 206 response holds just the requested part of the resource. Bytes of another
+   // version of it, if it changed since the temp file was written, can't be kept either.
+   if ranged && status != reqwest::StatusCode::PARTIAL_CONTENT {
+      if downloaded_size > 0 {
+         remove_temp_file(&temp_path)?;
+      }
+      return Err(Error::Http(
+         "Range not honored or resource changed (got 200, not 206)".to_string(),
+      ));
+   }
+
    // A 200 (rather than 206) response to a Range request means the server didn't
-   // honor the range for this request, so resuming isn't possible. Discard the
-   // existing temp file and restart from zero rather than failing — mirrors the
-   // Kotlin fallback for transient server-config blips.
+   // honor the range for this request, or the If-Range validator no longer matches
+   // because the resource changed, so resuming isn't possible. Discard the existing
+   // temp file and restart from zero rather than failing or appending mismatched
+   // bytes — mirrors the Kotlin fallback for transient server-config blips.
    if downloaded_size > 0 && status != reqwest::StatusCode::PARTIAL_CONTENT {
       tracing::warn!(
          file = %item.path,
-         "Range not honored (got 200, not 206); restarting download from zero"
+         "Range not honored or resource changed (got 200, not 206); restarting download from zero"
       );
       if Path::new(&temp_path).exists() {
          fs::remove_file(&temp_path)
             .map_err(|e| Error::File(format!("Failed to delete stale temp file: {}", e)))?;
       }
       downloaded_size = 0;
+      report_restart(manager, &item.path);
+   }
+
+   // A 206 response must continue where the temp file ends. Appending a range starting
+   // elsewhere, e.g. from a misbehaving proxy, would corrupt the file.
+   if (downloaded_size > 0 || ranged)
+      && let Some(start) = content_range_start(response.headers())
+      && start != range_start + downloaded_size
+   {
+      return Err(Error::Http(format!(
+         "Content-Range starts at byte {}, expected {}",
+         start,
+         range_start + downloaded_size
+      )));
+   }
+
+   // Replace a placeholder filename with the one provided by the server. This happens
+   // only before the first byte is written; once a temp file exists the path is fixed
+   // so the download can resume.
+   if item.resolve_filename {
+      let resolved = filename::from_content_disposition(response.headers())
+         .filter(|_| downloaded_size == 0)
+         .and_then(|name| Path::new(&item.path).parent().map(|dir| dir.join(name)))
+         .map(|path| path.to_string_lossy().into_owned())
+         .unwrap_or_else(|| item.path.clone());
+
+      let Ok(Some(current)) = manager.store.find_by_path(&item.path) else {
+         // Download item was not found i.e. removed.
+         return Ok(());
+      };
+      let updated = DownloadItem {
+         path: resolved,
+         resolve_filename: false,
+         ..current
+      };
+      manager.store.rename(&item.path, updated.clone())?;
+
+      if updated.path != item.path {
+         tracing::info!(from = %item.path, to = %updated.path, "Resolved download filename");
+         manager.rename_in_flight(&item.path, &updated.path);
+         item.path = updated.path.clone();
+         temp_path = manager.temp_path(&item.path);
+         manager.emit_changed(updated);
+      }
+      item.resolve_filename = false;
    }
 
    // Get the total size of the file from headers (if available).
-   let total_size = response
+   let content_length = response
       .headers()
       .get("content-length")
       .and_then(|len| len.to_str().ok())
-      .and_then(|len| len.parse::<u64>().ok())
-      .map(|len| len + downloaded_size)
-      .unwrap_or(0);
+      .and_then(|len| len.parse::<u64>().ok());
+   let max_bytes = item.options.max_bytes_truncate;
+   let total_size = match (content_length.map(|len| len + downloaded_size), max_bytes) {
+      (Some(size), Some(max)) => size.min(max),
+      (None, Some(max)) => max,
+      (size, None) => size.unwrap_or(0),
+   };
+
+   // Reject a resource larger than the configured maximum before writing anything.
+   let max_size = manager.config.max_size_bytes;
+   if let Some(max) = max_size
+      && content_length.is_some()
+      && total_size > max
+   {
+      remove_temp_file(&temp_path)?;
+      return Err(exceeded_max_size(max));
+   }
+
+   // Persist the validators of a full response so a later resume can detect whether
+   // the resource changed in between, the total size so a later resume can report its
+   // progress before the first new chunk, and the content type to categorize the file.
+   if let Ok(Some(current)) = manager.store.find_by_path(&item.path) {
+      let (etag, last_modified, accepts) = if downloaded_size == 0 {
+         (
+            header_string(response.headers(), ETAG),
+            header_string(response.headers(), LAST_MODIFIED),
+            ranges_accepted(status, response.headers()),
+         )
+      } else {
+         (
+            current.etag.clone(),
+            current.last_modified.clone(),
+            current.accepts_ranges,
+         )
+      };
+      let stored_total_size = Some(total_size)
+         .filter(|&size| size > 0)
+         .or(current.total_size);
+      let content_type = content_type.or(current.content_type.clone());
+      if current.etag != etag
+         || current.last_modified != last_modified
+         || current.total_size != stored_total_size
+         || current.content_type != content_type
+         || current.accepts_ranges != accepts
+      {
+         manager.store.update(DownloadItem {
+            etag,
+            last_modified,
+            total_size: stored_total_size,
+            content_type,
+            accepts_ranges: accepts,
+            ..current
+         })?;
+      }
+   }
 
    // Ensure the output folder exists.
-   let folder = Path::new(&temp_path)
-      .parent()
-      .ok_or_else(|| Error::File("File path has no parent directory".to_string()))?;
-   if !folder.exists() {
-      fs::create_dir_all(folder)
-         .map_err(|e| Error::File(format!("Failed to create directory: {}", e)))?;
+   let folder = create_parent_dir(&temp_path)?;
+
+   // Fail fast rather than writing until the disk is full. The bytes already in the
+   // temp file are on the same volume, so only the remainder needs to fit.
+   if content_length.is_some() {
+      ensure_space(folder, total_size.saturating_sub(downloaded_size))?;
+   }
+
+   // Split a fresh download into concurrent range requests if configured and the
+   // server supports them. Resumes and truncated downloads use a single connection.
+   let segments = manager
+      .config
+      .segments
+      .map_or(1, u64::from)
+      .min(total_size / MIN_SEGMENT_SIZE);
+   if segments > 1
+      && downloaded_size == 0
+      && content_length.is_some()
+      && max_bytes.is_none()
+      && !ranged
+      && accepts_ranges(response.headers())
+   {
+      return download_segmented(manager, item, &client, &url, response, &temp_path, segments)
+         .await;
    }
 
-   // Open the temp file in append mode.
-   let mut file = OpenOptions::new()
+   // Open the temp file in append mode, writing through a buffer.
+   let file = OpenOptions::new()
       .create(true)
       .append(true)
-      .open(&temp_path)
+      .open(long_path(Path::new(&temp_path)))
       .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+   let mut file = BufWriter::with_capacity(
+      manager
+         .config
+         .write_buffer_bytes
+         .unwrap_or(WRITE_BUFFER_SIZE),
+      file,
+   );
 
    // Write the response body to the file in chunks.
    let mut downloaded = downloaded_size;
    let mut stream = response.bytes_stream();
 
-   // Throttle progress updates:
-   // - Known size: emit when progress increases by at least 1%.
-   // - Unknown size: emit every BYTES_THRESHOLD bytes.
-   let mut last_emitted_progress = 0.0;
-   let mut last_emitted_bytes = downloaded_size;
-   const PROGRESS_THRESHOLD: f64 = 1.0;
-   const BYTES_THRESHOLD: u64 = 1024 * 1024;
+   // Throttle progress updates as configured.
+   let mut throttle = ProgressThrottle::new(
+      &manager.config.progress,
+      progress_of(downloaded_size, total_size),
+      downloaded_size,
+   );
 
+   let mut truncated = false;
    while let Some(chunk) = stream.next().await {
       match chunk {
          Ok(data) => {
-            file
-               .write_all(&data)
-               .map_err(|e| Error::File(format!("Failed to write file: {}", e)))?;
-
-            downloaded += data.len() as u64;
-            let progress = if total_size > 0 {
-               (downloaded as f64 / total_size as f64) * 100.0
-            } else {
-               0.0
-            };
+            // A superseded task leaves the path to the download that replaced it, without
+            // flushing its buffer.
+            if manager.is_superseded() {
+               let _ = file.into_parts();
+               return Ok(());
+            }
 
-            let should_throttle = if total_size > 0 {
-               progress < 100.0 && progress - last_emitted_progress <= PROGRESS_THRESHOLD
-            } else {
-               downloaded - last_emitted_bytes < BYTES_THRESHOLD
+            // Write no further than `max_bytes_truncate`, then stop reading.
+            let len = match max_bytes {
+               Some(max) => (data.len() as u64).min(max.saturating_sub(downloaded)) as usize,
+               None => data.len(),
             };
-            if should_throttle {
-               continue;
+            file.write_all(&data[..len]).map_err(write_error)?;
+
+            downloaded += len as u64;
+            manager.count_bytes(len as u64);
+            if let Some(max) = max_bytes
+               && downloaded >= max
+            {
+               // Only flag truncation if the resource has more bytes than were kept.
+               truncated =
+                  len < data.len() || content_length.is_none_or(|len| len + downloaded_size > max);
+               break;
             }
 
-            last_emitted_progress = progress;
-            last_emitted_bytes = downloaded;
-            let Ok(Some(current_item)) = manager.store.find_by_path(&item.path) else {
-               // Download item was not found i.e. removed.
-               return Ok(());
-            };
-            match current_item.status {
-               // Download is in progress.
-               DownloadStatus::InProgress => {
-                  if progress < 100.0 {
-                     // Download is not yet complete.
-                     // Update item in store and emit change event.
-                     let updated = current_item.with_progress(progress);
-                     manager.store.update_no_persist(updated.clone())?;
-                     manager.emit_changed(updated);
-                  }
-                  // Completion is handled after the loop exits naturally.
-               }
-               // Paused: stop, but keep the temp file so the download can resume.
-               DownloadStatus::Paused => return Ok(()),
-               // Canceled/Completed/Idle: stop and leave the temp file. A real cancel
-               // removes the store entry, so it hits the `None` branch above, not here.
-               _ => return Ok(()),
+            // Guard against a body without `Content-Length` growing beyond the maximum.
+            if let Some(max) = max_size
+               && downloaded > max
+            {
+               drop(file);
+               remove_temp_file(&temp_path)?;
+               return Err(exceeded_max_size(max));
+            }
+            let progress = progress_of(downloaded, total_size);
+            if !throttle.should_emit(progress, downloaded, total_size > 0) {
+               continue;
+            }
+            if !report_progress(manager, &item.path, downloaded, total_size)? {
+               // Keep the bytes received so a paused download can resume from them.
+               return flush(file);
             }
          }
          Err(e) => {
+            flush(file)?;
             return Err(Error::Http(format!("Failed to download: {}", e)));
          }
       }
    }
 
-   // Download stream ended naturally — rename temp file to final path and emit completion.
-   if let Ok(Some(current_item)) = manager.store.find_by_path(&item.path)
+   // Download stream ended naturally. Completion depends on the bytes received rather
+   // than on the last reported progress, which may be throttled or not exactly 100.
+   flush(file)?;
+   if content_length.is_some() && !truncated && downloaded < total_size {
+      // Keep the temp file so the download can resume from the bytes received.
+      return Err(Error::Http(format!(
+         "Download ended early: received {} of {} bytes",
+         downloaded, total_size
+      )));
+   }
+   complete(manager, item, &temp_path, truncated)
+}
+
+/// Streams the resource at `url` into `writer` from byte `offset`, as a download does
+/// into its temp file, but without a store entry: progress goes to `on_progress`, as the
+/// bytes of the resource in the writer and its total size, if known.
+///
+/// Returns the number of bytes written, after `offset`.
+pub(crate) async fn to_writer<W, F>(
+   manager: &DownloadManager,
+   url: &str,
+   writer: &mut W,
+   offset: u64,
+   mut on_progress: F,
+) -> crate::Result<u64>
+where
+   W: AsyncWrite + Unpin,
+   F: FnMut(u64, Option<u64>),
+{
+   let client = manager.client()?;
+   let mut headers = request_headers(&manager.config, &DownloadOptions::default())?;
+   authorize(manager, &mut headers, false).await?;
+   if offset > 0 {
+      headers.insert(
+         RANGE,
+         format!("bytes={}-", offset)
+            .parse()
+            .map_err(|e| Error::Http(format!("Invalid range header: {}", e)))?,
+      );
+   }
+
+   // Send the request, waiting as long as the server asks with `Retry-After`, and once
+   // with a refreshed token if the bearer token expired.
+   let mut waits = 0;
+   let mut refreshed = false;
+   let response = loop {
+      let (_, response) = send_with_failover(&client, &[url], 0, &headers, url).await?;
+      if response.status() == reqwest::StatusCode::UNAUTHORIZED
+         && !refreshed
+         && authorize(manager, &mut headers, true).await?
+      {
+         refreshed = true;
+         continue;
+      }
+      let wait = retry_after(&response).filter(|&wait| wait <= MAX_RETRY_AFTER);
+      let Some(wait) = wait.filter(|_| waits < MAX_RETRY_AFTER_WAITS) else {
+         break response;
+      };
+      waits += 1;
+      tracing::warn!(
+         url,
+         status = response.status().as_u16(),
+         wait_ms = wait.as_millis() as u64,
+         "Waiting to retry as requested by the server"
+      );
+      tokio::time::sleep(wait).await;
+   };
+
+   let status = response.status();
+   if !status.is_success() {
+      return Err(Error::Http(format!(
+         "HTTP {}: {}",
+         status.as_u16(),
+         status.canonical_reason().unwrap_or("Unknown")
+      )));
+   }
+   // The writer holds the bytes before `offset`, so only those after them can follow.
+   if offset > 0
+      && (status != reqwest::StatusCode::PARTIAL_CONTENT
+         || content_range_start(response.headers()) != Some(offset))
+   {
+      return Err(Error::Http(format!(
+         "Range not honored: can't resume from byte {}",
+         offset
+      )));
+   }
+
+   let content_length = response
+      .headers()
+      .get("content-length")
+      .and_then(|len| len.to_str().ok())
+      .and_then(|len| len.parse::<u64>().ok());
+   let total_size = content_length.map(|len| len + offset);
+   let max_size = manager.config.max_size_bytes;
+   if let (Some(max), Some(size)) = (max_size, total_size)
+      && size > max
+   {
+      return Err(exceeded_max_size(max));
+   }
+
+   let mut written = 0;
+   let mut stream = response.bytes_stream();
+   let mut throttle = ProgressThrottle::new(
+      &manager.config.progress,
+      total_size.map_or(0.0, |size| progress_of(offset, size)),
+      offset,
+   );
+   while let Some(chunk) = stream.next().await {
+      let data = chunk.map_err(|e| Error::Http(format!("Failed to download: {}", e)))?;
+      // The writer can't take bytes back, so none past the limit are written.
+      if let Some(max) = max_size
+         && offset + written + data.len() as u64 > max
+      {
+         return Err(exceeded_max_size(max));
+      }
+      writer.write_all(&data).await.map_err(write_error)?;
+      written += data.len() as u64;
+      let bytes = offset + written;
+      let progress = total_size.map_or(0.0, |size| progress_of(bytes, size));
+      if throttle.should_emit(progress, bytes, total_size.is_some()) {
+         on_progress(bytes, total_size);
+      }
+   }
+   writer.flush().await.map_err(write_error)?;
+
+   if let Some(size) = total_size
+      && offset + written < size
+   {
+      return Err(Error::Http(format!(
+         "Download ended early: received {} of {} bytes",
+         offset + written,
+         size
+      )));
+   }
+   Ok(written)
+}
+
+/// Sends the request for a download to `urls[attempt]`, failing over to the next
+/// mirrors in order when a URL can't be reached or returns a server error. Every
+/// attempt uses the same Range headers, so a mirror picks up from the current offset.
+///
+/// Returns the index of the URL that responded, and its response.
+async fn send_with_failover(
+   client: &ClientWithMiddleware,
+   urls: &[&str],
+   mut attempt: usize,
+   headers: &HeaderMap,
+   path: &str,
+) -> crate::Result<(usize, reqwest::Response)> {
+   loop {
+      let url = urls[attempt];
+      let has_fallback = attempt + 1 < urls.len();
+      match client.get(url).headers(headers.clone()).send().await {
+         Ok(res) if has_fallback && res.status().is_server_error() => {
+            tracing::warn!(file = %path, url, status = res.status().as_u16(), "Failing over to next mirror");
+         }
+         Err(e) if has_fallback && (e.is_connect() || e.is_timeout()) => {
+            tracing::warn!(file = %path, url, "Failing over to next mirror: {}", e);
+         }
+         Ok(res) => return Ok((attempt, res)),
+         Err(e) => return Err(send_error(e)),
+      }
+      attempt += 1;
+   }
+}
+
+/// Sets the `Authorization` header to the bearer token of the manager's token provider,
+/// refreshing the token first if `refresh` is set. Returns `false` without a provider.
+async fn authorize(
+   manager: &DownloadManager,
+   headers: &mut HeaderMap,
+   refresh: bool,
+) -> crate::Result<bool> {
+   let Some(token) = manager.bearer_token(refresh).await else {
+      return Ok(false);
+   };
+   let mut value: HeaderValue = format!("Bearer {}", token)
+      .parse()
+      .map_err(|e| Error::Http(format!("Invalid bearer token: {}", e)))?;
+   value.set_sensitive(true);
+   headers.insert(AUTHORIZATION, value);
+   Ok(true)
+}
+
+/// Converts the error of a request that couldn't be sent.
+fn send_error(e: reqwest_middleware::Error) -> Error {
+   if let Some(blocked) = find_source::<RedirectBlocked>(&e) {
+      return Error::Http(blocked.to_string());
+   }
+   // A certificate that was rejected, or no client certificate where one is required,
+   // rather than a server that can't be reached.
+   if let Some(tls) = find_source::<rustls::Error>(&e) {
+      return Error::Http(format!("TLS handshake failed: {}", tls));
+   }
+   Error::Http(format!("Failed to send request: {}", e))
+}
+
+/// Learns what downloading the resource at `url` would involve without downloading
+/// it, with a `HEAD` request, or a `GET` of its first byte if the server refuses `HEAD`.
+pub(crate) async fn probe(manager: &DownloadManager, url: &str) -> crate::Result<ProbeResult> {
+   let client = manager.client()?;
+   let mut headers = request_headers(&manager.config, &DownloadOptions::default())?;
+   authorize(manager, &mut headers, false).await?;
+   let head = client
+      .head(url)
+      .headers(headers.clone())
+      .send()
+      .await
+      .map_err(send_error)?;
+   let response = if head.status().is_success() {
+      head
+   } else {
+      tracing::debug!(
+         url,
+         status = head.status().as_u16(),
+         "HEAD refused, probing with a range request"
+      );
+      headers.insert(RANGE, HeaderValue::from_static("bytes=0-0"));
+      client
+         .get(url)
+         .headers(headers)
+         .send()
+         .await
+         .map_err(send_error)?
+   };
+
+   let status = response.status();
+   if !status.is_success() {
+      return Err(Error::Http(format!(
+         "HTTP {}: {}",
+         status.as_u16(),
+         status.canonical_reason().unwrap_or("Unknown")
+      )));
+   }
+
+   // A range response is only partial, its `Content-Range` carries the total size.
+   let headers = response.headers();
+   let (size, accepts_ranges) = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+      let size = header_string(headers, CONTENT_RANGE)
+         .and_then(|range| range.rsplit_once('/')?.1.parse().ok());
+      (size, true)
+   } else {
+      let size = header_string(headers, CONTENT_LENGTH).and_then(|len| len.parse().ok());
+      (size, accepts_ranges(headers))
+   };
+   Ok(ProbeResult {
+      size,
+      accepts_ranges,
+      content_type: header_string(headers, CONTENT_TYPE),
+      filename: filename::from_content_disposition(headers)
+         .or_else(|| filename::from_url(response.url().as_str())),
+   })
+}
+
+/// The wait a `429 Too Many Requests` or `503 Service Unavailable` response asks for
+/// with its `Retry-After` header, given in seconds or as an HTTP date.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+   let status = response.status();
+   if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+      && status != reqwest::StatusCode::SERVICE_UNAVAILABLE
+   {
+      return None;
+   }
+   let value = header_string(response.headers(), RETRY_AFTER)?;
+   let value = value.trim();
+   if let Ok(seconds) = value.parse::<u64>() {
+      return Some(Duration::from_secs(seconds));
+   }
+   let date = httpdate::parse_http_date(value).ok()?;
+   // A date in the past means the server can be retried right away.
+   Some(
+      date
+         .duration_since(SystemTime::now())
+         .unwrap_or(Duration::ZERO),
+   )
+}
+
+/// Stores and emits the wait before the next attempt of a download, or that the wait
+/// is over. Returns `false` if the download must stop because it is no longer in
+/// progress.
+fn set_retry_after(
+   manager: &DownloadManager,
+   path: &str,
+   wait: Option<Duration>,
+) -> crate::Result<bool> {
+   let Ok(Some(current)) = manager.store.find_by_path(path) else {
+      // Download item was not found i.e. removed.
+      return Ok(false);
+   };
+   if current.status != DownloadStatus::InProgress {
+      return Ok(false);
+   }
+   let updated = DownloadItem {
+      retry_after_ms: wait.map(|wait| wait.as_millis() as u64),
+      ..current
+   };
+   manager.store.update_no_persist(updated.clone())?;
+   manager.emit_changed(updated);
+   Ok(true)
+}
+
+/// Writes the buffered bytes of a download to its temp file and closes it.
+fn flush(file: BufWriter<File>) -> crate::Result<()> {
+   file.into_inner().map_err(|e| write_error(e.into_error()))?;
+   Ok(())
+}
+
+/// Converts an error writing a temp file. Errors the user can fix, such as a full disk
+/// or revoked permissions, become [`Error::Write`] so the download fails resumably.
+fn write_error(e: std::io::Error) -> Error {
+   use std::io::ErrorKind;
+   let message = format!("Failed to write file: {}", e);
+   match e.kind() {
+      ErrorKind::StorageFull
+      | ErrorKind::QuotaExceeded
+      | ErrorKind::FileTooLarge
+      | ErrorKind::ReadOnlyFilesystem
+      | ErrorKind::PermissionDenied => Error::Write(message),
+      _ => Error::File(message),
+   }
+}
+
+/// Decides which progress updates of a download are emitted, see [`ProgressConfig`].
+struct ProgressThrottle {
+   threshold: f64,
+   interval: Option<Duration>,
+   heartbeat: Option<Duration>,
+   last_progress: f64,
+   last_bytes: u64,
+   last_emitted: Option<Instant>,
+   started: Instant,
+}
+
+impl ProgressThrottle {
+   /// Creates a throttle for a download starting at `progress` percent and `bytes`.
+   fn new(config: &ProgressConfig, progress: f64, bytes: u64) -> Self {
+      Self {
+         threshold: config.threshold,
+         interval: config.interval_ms.map(Duration::from_millis),
+         heartbeat: config.heartbeat_ms.map(Duration::from_millis),
+         last_progress: progress,
+         last_bytes: bytes,
+         last_emitted: None,
+         started: Instant::now(),
+      }
+   }
+
+   /// Whether an update to `progress` percent and `bytes` is emitted, in which case it
+   /// becomes the reference for the next updates. `known_size` is whether `progress`
+   /// is meaningful. A complete download of known size is always emitted, and so is an
+   /// update that advanced once the heartbeat is due.
+   fn should_emit(&mut self, progress: f64, bytes: u64, known_size: bool) -> bool {
+      let emit = if known_size && progress >= 100.0 {
+         true
+      } else {
+         let advanced = if known_size {
+            progress - self.last_progress > self.threshold
+         } else if self.interval.is_some() {
+            bytes > self.last_bytes
+         } else {
+            bytes - self.last_bytes >= BYTES_THRESHOLD
+         };
+         let due = self.interval.is_none_or(|interval| {
+            self
+               .last_emitted
+               .is_none_or(|last_emitted| last_emitted.elapsed() >= interval)
+         });
+         let heartbeat = self.heartbeat.is_some_and(|heartbeat| {
+            bytes > self.last_bytes
+               && self.last_emitted.unwrap_or(self.started).elapsed() >= heartbeat
+         });
+         (advanced && due) || heartbeat
+      };
+      if emit {
+         self.last_progress = progress;
+         self.last_bytes = bytes;
+         self.last_emitted = Some(Instant::now());
+      }
+      emit
+   }
+}
+
+/// Stores and emits the progress of a running download, `downloaded` bytes of
+/// `total_size` (0 if unknown). Returns `false` if the download must stop because it
+/// is no longer in progress.
+fn report_progress(
+   manager: &DownloadManager,
+   path: &str,
+   downloaded: u64,
+   total_size: u64,
+) -> crate::Result<bool> {
+   let Ok(Some(current_item)) = manager.store.find_by_path(path) else {
+      // Download item was not found i.e. removed.
+      return Ok(false);
+   };
+   match current_item.status {
+      // Download is in progress.
+      DownloadStatus::InProgress => {
+         let updated = current_item.with_bytes(downloaded, Some(total_size));
+         if updated.progress.is_none_or(|progress| progress < 100.0) {
+            // Download is not yet complete.
+            // Update item in store and emit change event.
+            manager.store.update_no_persist(updated.clone())?;
+            manager.flush_stats();
+            manager.emit_changed(updated);
+         }
+         // Completion is handled once the body is fully written.
+         Ok(true)
+      }
+      // Paused: stop, but keep the temp file so the download can resume.
+      DownloadStatus::Paused => Ok(false),
+      // Canceled/Completed/Idle: stop and leave the temp file. A real cancel removes
+      // the store entry, so it hits the `None` branch above, not here.
+      _ => Ok(false),
+   }
+}
+
+/// Renames the temp file of a fully written download to its final path and emits
+/// completion. A file created at the path in the meantime is handled according to the
+/// [`OverwritePolicy`] of the download. Once completed, `item` is marked completed with
+/// the bytes downloaded.
+fn complete(
+   manager: &DownloadManager,
+   item: &mut DownloadItem,
+   temp_path: &str,
+   truncated: bool,
+) -> crate::Result<()> {
+   if manager.is_superseded() {
+      return Ok(());
+   }
+   if let Ok(Some(mut current_item)) = manager.store.find_by_path(&item.path)
       && matches!(current_item.status, DownloadStatus::InProgress)
    {
+      // Dependents are queued on the path they were created with, even once renamed.
+      let prerequisite = item.path.clone();
+      if Path::new(&item.path).exists() {
+         match current_item.options.overwrite {
+            OverwritePolicy::Overwrite => {}
+            OverwritePolicy::Fail => {
+               // Keep the temp file: once the path is free, a retry only moves it.
+               let failed =
+                  current_item.with_error(format!("Destination already exists: {}", item.path));
+               manager.store.update(failed.clone())?;
+               manager.emit_changed(failed);
+               manager.fail_dependents(&item.path, &prerequisite_failed(&item.path));
+               return Ok(());
+            }
+            OverwritePolicy::Rename => {
+               let renamed = DownloadItem {
+                  path: manager.free_path(&item.path, &HashSet::new())?,
+                  ..current_item
+               };
+               manager.store.rename(&item.path, renamed.clone())?;
+               manager.rename_in_flight(&item.path, &renamed.path);
+               tracing::info!(from = %item.path, to = %renamed.path, "Renamed download to a free path");
+               current_item = renamed;
+               item.path = current_item.path.clone();
+            }
+         }
+      }
+
       // Rename before deleting the store entry: if the rename fails, the entry stays
       // InProgress and the temp file survives, so the caller can revert it to a
       // resumable state instead of the download silently vanishing.
@@ -182,67 +962,742 @@ pub(crate) async fn download(manager: &DownloadManager, item: DownloadItem) -> c
       // On Windows `fs::rename` fails if the destination exists, so remove it first.
       // On Unix `fs::rename` replaces atomically — skipping the pre-delete preserves that.
       #[cfg(windows)]
-      if Path::new(&item.path).exists() {
-         fs::remove_file(&item.path).map_err(|e| {
+      if Path::new(&current_item.path).exists() {
+         fs::remove_file(&current_item.path).map_err(|e| {
             Error::File(format!("Failed to remove existing destination file: {}", e))
          })?;
       }
 
-      fs::rename(&temp_path, &item.path)
-         .map_err(|e| Error::File(format!("Failed to rename temp file to destination: {}", e)))?;
+      // A download staged in the temp directory is moved into place only now, possibly
+      // from another volume.
+      let downloaded_bytes = fs::metadata(temp_path).map_or(0, |metadata| metadata.len());
+      create_parent_dir(&current_item.path)?;
+      move_file(Path::new(temp_path), Path::new(&current_item.path))?;
 
-      // File is safely in place; now drop the store entry and signal completion.
-      manager.store.delete(&item.path)?;
-      manager.emit_changed(current_item.with_status(DownloadStatus::Completed));
+      // File is safely in place; post-process it, then store it as completed, or drop
+      // the store entry, and signal completion.
+      let completed = DownloadItem {
+         truncated,
+         downloaded_bytes,
+         ..current_item.with_status(DownloadStatus::Completed)
+      };
+      let failure = set_mode(&completed.path, completed.options.mode)
+         .err()
+         .map(|e| e.to_string())
+         .or_else(|| manager.run_completion_hooks(&completed));
+      if let Some(e) = failure {
+         let failed = current_item.with_error(format!("Post-processing failed: {}", e));
+         manager.store.update(failed.clone())?;
+         manager.emit_changed(failed);
+         manager.fail_dependents(&prerequisite, &prerequisite_failed(&prerequisite));
+         return Ok(());
+      }
+      manager.store_completed(&completed)?;
+      manager.emit_changed(completed);
+      manager.start_dependents(&prerequisite);
+      item.status = DownloadStatus::Completed;
+      item.downloaded_bytes = downloaded_bytes;
    }
 
    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-   use super::*;
-   use crate::manager::{DownloadManager, OnChanged};
-   use crate::store::DownloadStore;
-   use std::sync::{Arc, Mutex};
-   use tempfile::TempDir;
-   use wiremock::matchers::{header, method, path as wm_path};
-   use wiremock::{Mock, MockServer, ResponseTemplate};
+/// Downloads the resource over `segments` concurrent connections, each writing its
+/// byte range at its offset in the temp file. `response` is the response to the initial
+/// request for the whole resource and serves the first segment.
+///
+/// If the download stops early, the temp file is truncated to the bytes received
+/// without gap from its start, so it can resume like a single-stream download.
+async fn download_segmented(
+   manager: &DownloadManager,
+   item: &mut DownloadItem,
+   client: &ClientWithMiddleware,
+   url: &str,
+   response: reqwest::Response,
+   temp_path: &str,
+   segments: u64,
+) -> crate::Result<()> {
+   let total_size = response.content_length().unwrap_or_default();
+   // Guard the other segments against the resource changing in between.
+   let validator = header_string(response.headers(), ETAG)
+      .filter(|etag| !etag.starts_with("W/"))
+      .or_else(|| header_string(response.headers(), LAST_MODIFIED));
 
-   type EventLog = Arc<Mutex<Vec<DownloadItem>>>;
+   let file = OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true)
+      .open(long_path(Path::new(temp_path)))
+      .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+   file
+      .set_len(total_size)
+      .map_err(|e| Error::File(format!("Failed to allocate file: {}", e)))?;
 
-   struct TestFixture {
-      manager: DownloadManager,
-      events: EventLog,
-      _dir: TempDir,
-   }
+   let segment_size = total_size / segments;
+   let ranges: Vec<Range<u64>> = (0..segments)
+      .map(|i| {
+         let start = i * segment_size;
+         let end = if i + 1 == segments {
+            total_size
+         } else {
+            start + segment_size
+         };
+         start..end
+      })
+      .collect();
+   tracing::debug!(file = %item.path, segments, "Starting segmented download");
 
-   fn make_fixture() -> TestFixture {
-      let dir = TempDir::new().unwrap();
-      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
-      let captured = events.clone();
-      let on_changed: OnChanged = Arc::new(move |item| {
-         captured.lock().unwrap().push(item);
-      });
-      let manager = DownloadManager::new(dir.path().to_path_buf(), on_changed);
-      TestFixture {
-         manager,
-         events,
-         _dir: dir,
+   let mut download = SegmentedDownload {
+      manager,
+      path: &item.path,
+      file,
+      total_size,
+      received: ranges.iter().map(|_| AtomicU64::new(0)).collect(),
+      ranges,
+      throttle: Mutex::new(ProgressThrottle::new(&manager.config.progress, 0.0, 0)),
+      stopped: AtomicBool::new(false),
+      finished: false,
+   };
+   let mut headers = request_headers(&manager.config, &item.options)?;
+   authorize(manager, &mut headers, false).await?;
+   let mut first = Some(response);
+   let writes = (0..download.ranges.len()).map(|index| {
+      let response = first.take();
+      let download = &download;
+      let validator = validator.as_deref();
+      let headers = &headers;
+      async move {
+         let response = match response {
+            Some(response) => response,
+            None => {
+               request_segment(client, url, headers, &download.ranges[index], validator).await?
+            }
+         };
+         download.write(index, response).await
       }
-   }
+   });
+   let result = futures::future::try_join_all(writes).await;
 
-   /// Seeds an `InProgress` item in the store and returns a `DownloadItem`
-   /// suitable for passing to `download()`.
-   fn seed_in_progress(manager: &DownloadManager, dest_path: &str, url: &str) -> DownloadItem {
-      let item = DownloadItem {
-         url: url.to_string(),
-         path: dest_path.to_string(),
-         progress: 0.0,
-         status: DownloadStatus::InProgress,
-      };
-      manager.store.create(item.clone()).unwrap();
-      item
+   if result.is_err() || download.stopped.load(Ordering::Relaxed) {
+      // Dropping the download truncates the file to the bytes received without gap.
+      return result.map(|_| ());
+   }
+
+   download.finished = true;
+   drop(download);
+   complete(manager, item, temp_path, false)
+}
+
+/// Gives a completed file the permissions `mode`, if set. Does nothing on Windows.
+fn set_mode(path: &str, mode: Option<u32>) -> crate::Result<()> {
+   #[cfg(unix)]
+   if let Some(mode) = mode {
+      use std::os::unix::fs::PermissionsExt;
+      fs::set_permissions(path, fs::Permissions::from_mode(mode))
+         .map_err(|e| Error::File(format!("Failed to set file mode: {}", e)))?;
+   }
+   #[cfg(not(unix))]
+   let _ = (path, mode);
+   Ok(())
+}
+
+/// Completes a download with the file already at its path, as the server answered that
+/// the resource is unchanged. Completion hooks don't run, as the file didn't change.
+fn complete_unchanged(
+   manager: &DownloadManager,
+   item: &mut DownloadItem,
+   etag: Option<String>,
+) -> crate::Result<()> {
+   let Ok(Some(current)) = manager.store.find_by_path(&item.path) else {
+      // Download item was not found i.e. removed.
+      return Ok(());
+   };
+   if current.status != DownloadStatus::InProgress {
+      return Ok(());
+   }
+   tracing::info!(file = %item.path, "Resource not modified; keeping the existing file");
+
+   let size = fs::metadata(&item.path).map_or(0, |metadata| metadata.len());
+   let completed = DownloadItem {
+      etag: etag
+         .or_else(|| current.options.if_none_match.clone())
+         .or_else(|| manager.store.etag(&item.path).ok().flatten()),
+      total_size: Some(size),
+      downloaded_bytes: size,
+      ..current.with_status(DownloadStatus::Completed)
+   };
+   manager.store_completed(&completed)?;
+   manager.emit_changed(completed);
+   manager.start_dependents(&item.path);
+   item.status = DownloadStatus::Completed;
+   Ok(())
+}
+
+/// Requests the byte `range` of the resource, failing unless the server honors it.
+async fn request_segment(
+   client: &ClientWithMiddleware,
+   url: &str,
+   headers: &HeaderMap,
+   range: &Range<u64>,
+   validator: Option<&str>,
+) -> crate::Result<reqwest::Response> {
+   let mut headers = headers.clone();
+   headers.insert(
+      RANGE,
+      format!("bytes={}-{}", range.start, range.end - 1)
+         .parse()
+         .map_err(|e| Error::Http(format!("Invalid range header: {}", e)))?,
+   );
+   if let Some(validator) = validator {
+      headers.insert(
+         IF_RANGE,
+         validator
+            .parse()
+            .map_err(|e| Error::Http(format!("Invalid If-Range header: {}", e)))?,
+      );
+   }
+   let response = client
+      .get(url)
+      .headers(headers)
+      .send()
+      .await
+      .map_err(send_error)?;
+
+   let status = response.status();
+   if status != reqwest::StatusCode::PARTIAL_CONTENT {
+      return Err(Error::Http(format!(
+         "Range of segment not honored (HTTP {})",
+         status.as_u16()
+      )));
+   }
+   if let Some(start) = content_range_start(response.headers())
+      && start != range.start
+   {
+      return Err(Error::Http(format!(
+         "Content-Range of segment starts at byte {}, expected {}",
+         start, range.start
+      )));
+   }
+   Ok(response)
+}
+
+/// State shared by the segments of a segmented download.
+struct SegmentedDownload<'a> {
+   manager: &'a DownloadManager,
+   path: &'a str,
+   file: File,
+   total_size: u64,
+   ranges: Vec<Range<u64>>,
+   /// Bytes written to each segment.
+   received: Vec<AtomicU64>,
+   throttle: Mutex<ProgressThrottle>,
+   /// Set once the download is no longer in progress, e.g. paused, to stop every segment.
+   stopped: AtomicBool,
+   /// Set once every segment is received.
+   finished: bool,
+}
+
+/// A download that stops early, including when its task is aborted, keeps only the
+/// bytes received without gap so that it resumes from there.
+impl Drop for SegmentedDownload<'_> {
+   fn drop(&mut self) {
+      if !self.finished
+         && let Err(e) = self.file.set_len(self.contiguous_len())
+      {
+         tracing::warn!(file = %self.path, "Failed to truncate file: {}", e);
+      }
+   }
+}
+
+impl SegmentedDownload<'_> {
+   /// Writes the body of `response` to segment `index`, ignoring any bytes past its end.
+   async fn write(&self, index: usize, response: reqwest::Response) -> crate::Result<()> {
+      let range = &self.ranges[index];
+      let mut offset = range.start;
+      let mut stream = response.bytes_stream();
+      while offset < range.end
+         && let Some(chunk) = stream.next().await
+      {
+         if self.stopped.load(Ordering::Relaxed) {
+            return Ok(());
+         }
+         if self.manager.is_superseded() {
+            self.stopped.store(true, Ordering::Relaxed);
+            return Ok(());
+         }
+         let data = chunk.map_err(|e| Error::Http(format!("Failed to download: {}", e)))?;
+         let len = (data.len() as u64).min(range.end - offset) as usize;
+         write_at(&self.file, &data[..len], offset).map_err(write_error)?;
+         offset += len as u64;
+         self.received[index].fetch_add(len as u64, Ordering::Relaxed);
+         self.manager.count_bytes(len as u64);
+
+         if !self.report_progress()? {
+            self.stopped.store(true, Ordering::Relaxed);
+            return Ok(());
+         }
+      }
+      if offset < range.end && !self.stopped.load(Ordering::Relaxed) {
+         return Err(Error::Http(
+            "Connection closed before the segment was complete".to_string(),
+         ));
+      }
+      Ok(())
+   }
+
+   /// Reports the progress of all segments, throttled like a single-stream download.
+   /// Returns `false` if the download must stop.
+   fn report_progress(&self) -> crate::Result<bool> {
+      let downloaded: u64 = self
+         .received
+         .iter()
+         .map(|r| r.load(Ordering::Relaxed))
+         .sum();
+      let progress = progress_of(downloaded, self.total_size);
+      let emit = self
+         .throttle
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner)
+         .should_emit(progress, downloaded, true);
+      if !emit {
+         return Ok(true);
+      }
+      report_progress(self.manager, self.path, downloaded, self.total_size)
+   }
+
+   /// Returns the number of bytes received without gap from the start of the file.
+   fn contiguous_len(&self) -> u64 {
+      let mut len = 0;
+      for (range, received) in self.ranges.iter().zip(&self.received) {
+         let received = received.load(Ordering::Relaxed);
+         len += received;
+         if received < range.end - range.start {
+            break;
+         }
+      }
+      len
+   }
+}
+
+/// Writes all of `buf` at `offset` in `file`, independently of its cursor.
+#[cfg(unix)]
+fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+   std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+/// Writes all of `buf` at `offset` in `file`, independently of its cursor.
+#[cfg(windows)]
+fn write_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+   while !buf.is_empty() {
+      match std::os::windows::fs::FileExt::seek_write(file, buf, offset)? {
+         0 => return Err(io::ErrorKind::WriteZero.into()),
+         n => {
+            buf = &buf[n..];
+            offset += n as u64;
+         }
+      }
+   }
+   Ok(())
+}
+
+/// Whether the server advertises support for byte range requests.
+fn accepts_ranges(headers: &HeaderMap) -> bool {
+   header_string(headers, ACCEPT_RANGES).is_some_and(|value| {
+      value
+         .split(',')
+         .any(|unit| unit.trim().eq_ignore_ascii_case("bytes"))
+   })
+}
+
+/// Returns whether the server accepts range requests for a resource, as far as its
+/// response tells: it does if it answered one, or advertises it with `Accept-Ranges`.
+/// Only `Accept-Ranges: none` tells that it doesn't, as many servers accept range
+/// requests without advertising it.
+fn ranges_accepted(status: reqwest::StatusCode, headers: &HeaderMap) -> Option<bool> {
+   if status == reqwest::StatusCode::PARTIAL_CONTENT || accepts_ranges(headers) {
+      return Some(true);
+   }
+   header_string(headers, ACCEPT_RANGES)
+      .filter(|value| value.trim().eq_ignore_ascii_case("none"))
+      .map(|_| false)
+}
+
+/// Reports that the download at `path` started over from zero when resumed.
+fn report_restart(manager: &DownloadManager, path: &str) {
+   if let Ok(Some(current)) = manager.store.find_by_path(path) {
+      let restarted = DownloadItem {
+         progress: Some(0.0),
+         downloaded_bytes: 0,
+         ..current
+      };
+      manager.emit_event(DownloadEvent::Restarted(Box::new(restarted)));
+   }
+}
+
+/// Returns the headers of every request of a download: `Accept-Encoding: identity`
+/// and the configured `User-Agent`, then the configured default headers, then the
+/// headers of the download, each replacing the previous ones of the same name.
+///
+/// Asking for the identity encoding keeps `Content-Length` and byte ranges in terms
+/// of the file on disk, which progress and resume rely on. A response compressed
+/// regardless is decompressed as it streams, and its size is treated as unknown.
+fn request_headers(
+   config: &DownloadManagerConfig,
+   options: &DownloadOptions,
+) -> crate::Result<HeaderMap> {
+   let mut headers = HeaderMap::new();
+   headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+   if let Some(user_agent) = &config.user_agent {
+      headers.insert(USER_AGENT, header_value(USER_AGENT.as_str(), user_agent)?);
+   }
+   for (name, value) in config.headers.iter().chain(&options.headers) {
+      let header_name = HeaderName::from_bytes(name.as_bytes())
+         .map_err(|e| Error::Http(format!("Invalid header name '{}': {}", name, e)))?;
+      headers.insert(header_name, header_value(name, value)?);
+   }
+   Ok(headers)
+}
+
+fn header_value(name: &str, value: &str) -> crate::Result<HeaderValue> {
+   HeaderValue::from_str(value)
+      .map_err(|e| Error::Http(format!("Invalid value of header '{}': {}", name, e)))
+}
+
+/// Returns the validator to send as `If-Range` when resuming, if any.
+///
+/// Weak `ETag`s (`W/"..."`) can't be used with `If-Range`, so `Last-Modified` is used
+/// instead when the stored `ETag` is weak or missing.
+fn if_range_validator(item: &DownloadItem) -> Option<&str> {
+   item
+      .etag
+      .as_deref()
+      .filter(|etag| !etag.starts_with("W/"))
+      .or(item.last_modified.as_deref())
+}
+
+/// Returns the first byte of the range of a partial response, from its `Content-Range`
+/// header, e.g. `bytes 100-199/1000`.
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+   let range = header_string(headers, CONTENT_RANGE)?;
+   let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+   start.trim().parse().ok()
+}
+
+/// Returns the size of the resource from the `Content-Range: bytes */{size}` header of
+/// a 416 response.
+fn unsatisfied_range_size(headers: &HeaderMap) -> Option<u64> {
+   let range = header_string(headers, CONTENT_RANGE)?;
+   range.strip_prefix("bytes */")?.trim().parse().ok()
+}
+
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+   headers
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_string)
+}
+
+/// Whether the media type of a `Content-Type` header matches `expected`, which is
+/// either an exact media type or a prefix ending in `/` or `/*`. Parameters such as
+/// `charset` are ignored and the comparison is case-insensitive.
+fn content_type_matches(expected: &str, actual: Option<&str>) -> bool {
+   let Some(actual) = actual else {
+      return false;
+   };
+   let media_type = actual
+      .split(';')
+      .next()
+      .unwrap_or_default()
+      .trim()
+      .to_ascii_lowercase();
+   let expected = expected.trim().to_ascii_lowercase();
+   let expected = expected
+      .strip_suffix('*')
+      .filter(|prefix| prefix.ends_with('/'))
+      .unwrap_or(&expected);
+   if expected.ends_with('/') {
+      media_type.starts_with(expected)
+   } else {
+      media_type == expected
+   }
+}
+
+/// Creates the directory containing `path` if it doesn't exist, and returns it.
+fn create_parent_dir(path: &str) -> crate::Result<&Path> {
+   let folder = Path::new(path)
+      .parent()
+      .filter(|folder| !folder.as_os_str().is_empty())
+      .ok_or_else(|| Error::File(format!("File path has no parent directory: {}", path)))?;
+   if !folder.exists() {
+      fs::create_dir_all(long_path(folder))
+         .map_err(|e| Error::File(format!("Failed to create directory: {}", e)))?;
+   }
+   Ok(folder)
+}
+
+fn exceeded_max_size(max: u64) -> Error {
+   Error::Http(format!("Exceeded max size of {} bytes", max))
+}
+
+fn remove_temp_file(temp_path: &str) -> crate::Result<()> {
+   if Path::new(temp_path).exists() {
+      fs::remove_file(temp_path)
+         .map_err(|e| Error::File(format!("Failed to delete temp file: {}", e)))?;
+   }
+   Ok(())
+}
+
+/// Checks that the volume containing `folder` has at least `needed` bytes available.
+/// If the free space can't be determined, the download proceeds.
+fn ensure_space(folder: &Path, needed: u64) -> crate::Result<()> {
+   match fs4::available_space(long_path(folder)) {
+      Ok(available) if available < needed => Err(Error::InsufficientSpace { needed, available }),
+      Ok(_) => Ok(()),
+      Err(e) => {
+         tracing::warn!(folder = %folder.display(), "Failed to query available space: {}", e);
+         Ok(())
+      }
+   }
+}
+
+/// Builds the HTTP client shared by downloads, following redirects and using the proxy
+/// as configured.
+pub(crate) fn build_client(
+   config: &DownloadManagerConfig,
+   cookies: Arc<Jar>,
+) -> crate::Result<ClientWithMiddleware> {
+   let mut http_client = reqwest::Client::builder()
+      .redirect(redirect_policy(config))
+      .cookie_provider(cookies);
+   if let Some(proxy) = &config.proxy {
+      http_client = http_client.proxy(build_proxy(proxy)?);
+   }
+   http_client = with_tls(http_client, &config.tls)?;
+   let http_client = http_client
+      .build()
+      .map_err(|e| Error::Http(format!("Failed to build HTTP client: {}", e)))?;
+   Ok(with_retries(http_client))
+}
+
+/// Adds the configured client identity, root certificates and TLS versions to `builder`.
+fn with_tls(
+   mut builder: reqwest::ClientBuilder,
+   config: &TlsConfig,
+) -> crate::Result<reqwest::ClientBuilder> {
+   let read = |path: &Path| {
+      fs::read(path).map_err(|e| Error::File(format!("Failed to read '{}': {}", path.display(), e)))
+   };
+   if let Some(path) = &config.client_identity {
+      let identity = reqwest::Identity::from_pem(&read(path)?).map_err(|e| {
+         Error::Http(format!(
+            "Invalid client identity '{}': {}",
+            path.display(),
+            e
+         ))
+      })?;
+      builder = builder.identity(identity);
+   }
+   for path in &config.root_certificates {
+      let certificates = reqwest::Certificate::from_pem_bundle(&read(path)?).map_err(|e| {
+         Error::Http(format!(
+            "Invalid root certificate '{}': {}",
+            path.display(),
+            e
+         ))
+      })?;
+      for certificate in certificates {
+         builder = builder.add_root_certificate(certificate);
+      }
+   }
+   if let Some(version) = config.min_version {
+      builder = builder.tls_version_min(match version {
+         TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+         TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+      });
+   }
+   #[cfg(feature = "danger-accept-invalid-certs")]
+   if config.danger_accept_invalid_certs {
+      tracing::warn!(
+         "DANGER: TLS certificate validation is disabled (danger_accept_invalid_certs); downloads can be intercepted"
+      );
+      builder = builder.tls_danger_accept_invalid_certs(true);
+   }
+   Ok(builder)
+}
+
+/// Wraps `client` with retry middleware for transient failures.
+pub(crate) fn with_retries(client: reqwest::Client) -> ClientWithMiddleware {
+   let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+   ClientBuilder::new(client)
+      .with(RetryTransientMiddleware::new_with_policy_and_strategy(
+         retry_policy,
+         RetryAfterStrategy,
+      ))
+      .build()
+}
+
+/// Retries transient failures with the default backoff, except responses that ask to
+/// retry after a given time, which [`download`] waits out itself.
+struct RetryAfterStrategy;
+
+impl RetryableStrategy for RetryAfterStrategy {
+   fn handle(&self, res: &reqwest_middleware::Result<reqwest::Response>) -> Option<Retryable> {
+      match res {
+         Ok(response) if retry_after(response).is_some() => None,
+         Ok(response) => default_on_request_success(response),
+         Err(error) => default_on_request_failure(error),
+      }
+   }
+}
+
+/// Builds the reqwest proxy for the configured proxy.
+fn build_proxy(config: &ProxyConfig) -> crate::Result<reqwest::Proxy> {
+   let mut proxy = reqwest::Proxy::all(&config.url)
+      .map_err(|e| Error::Http(format!("Invalid proxy URL: {}", e)))?;
+   if let Some(username) = &config.username {
+      proxy = proxy.basic_auth(username, config.password.as_deref().unwrap_or_default());
+   }
+   Ok(proxy.no_proxy(reqwest::NoProxy::from_string(&config.no_proxy.join(","))))
+}
+
+/// Maximum number of redirects followed by default, matching reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// Builds the reqwest redirect policy for the configured redirect handling and hosts.
+fn redirect_policy(config: &DownloadManagerConfig) -> reqwest::redirect::Policy {
+   let cross_host = config.redirect.cross_host;
+   let max_redirects = config.redirect.max_redirects.unwrap_or(MAX_REDIRECTS);
+   let hosts = config.hosts.clone();
+   reqwest::redirect::Policy::custom(move |attempt| {
+      // The first of the previous URLs is the download URL rather than a redirect.
+      if attempt.previous().len() > max_redirects {
+         let blocked = RedirectBlocked::Limit {
+            location: attempt.url().to_string(),
+            max: max_redirects,
+         };
+         return attempt.error(blocked);
+      }
+      let from = attempt.previous().first().and_then(|url| url.host_str());
+      let to = attempt.url().host_str();
+      if cross_host == CrossHostRedirects::Deny && from != to {
+         let blocked = RedirectBlocked::CrossHost {
+            from: from.unwrap_or_default().to_string(),
+            to: to.unwrap_or_default().to_string(),
+         };
+         return attempt.error(blocked);
+      }
+      if !hosts.allows(to.unwrap_or_default()) {
+         let blocked = RedirectBlocked::Host(to.unwrap_or_default().to_string());
+         return attempt.error(blocked);
+      }
+      attempt.follow()
+   })
+}
+
+/// A redirect rejected by the configuration.
+#[derive(Debug)]
+enum RedirectBlocked {
+   /// To another host, with [`CrossHostRedirects::Deny`].
+   CrossHost { from: String, to: String },
+   /// To a host not allowed by [`HostPolicy`](crate::config::HostPolicy).
+   Host(String),
+   /// Beyond [`RedirectConfig::max_redirects`](crate::config::RedirectConfig).
+   Limit { location: String, max: usize },
+}
+
+impl fmt::Display for RedirectBlocked {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         Self::CrossHost { from, to } => write!(
+            f,
+            "Redirect from host '{}' to different host '{}' blocked by policy",
+            from, to
+         ),
+         Self::Host(host) => write!(f, "Redirect to host '{}' is not allowed", host),
+         Self::Limit { location, max: 0 } => {
+            write!(
+               f,
+               "Redirect to '{}' not followed: redirects are disabled",
+               location
+            )
+         }
+         Self::Limit { location, max } => write!(
+            f,
+            "Redirect to '{}' not followed: more than {} redirects",
+            location, max
+         ),
+      }
+   }
+}
+
+impl StdError for RedirectBlocked {}
+
+/// Finds an error of type `T` in the source chain of `err`.
+fn find_source<'a, T: StdError + 'static>(err: &'a (dyn StdError + 'static)) -> Option<&'a T> {
+   let mut source = Some(err);
+   while let Some(e) = source {
+      if let Some(found) = e.downcast_ref::<T>() {
+         return Some(found);
+      }
+      // The source of an I/O error is that of the error it wraps, skipping the latter.
+      source = match e.downcast_ref::<io::Error>().and_then(io::Error::get_ref) {
+         Some(inner) => Some(inner),
+         None => e.source(),
+      };
+   }
+   None
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::config::{DownloadManagerConfig, HostPolicy, RedirectConfig};
+   use crate::manager::{DOWNLOAD_SUFFIX, DownloadManager, OnChanged};
+   use crate::store::Store;
+   use std::sync::{Arc, Mutex};
+   use tempfile::TempDir;
+   use wiremock::matchers::{header, header_exists, method, path as wm_path};
+   use wiremock::{Mock, MockServer, ResponseTemplate};
+
+   type EventLog = Arc<Mutex<Vec<DownloadItem>>>;
+
+   struct TestFixture {
+      manager: DownloadManager,
+      events: EventLog,
+      _dir: TempDir,
+   }
+
+   fn make_fixture() -> TestFixture {
+      make_fixture_with_config(DownloadManagerConfig::default())
+   }
+
+   fn make_fixture_with_config(config: DownloadManagerConfig) -> TestFixture {
+      let dir = TempDir::new().unwrap();
+      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+      let captured = events.clone();
+      let on_changed: OnChanged = Arc::new(move |item| {
+         captured.lock().unwrap().push(item);
+      });
+      let manager = DownloadManager::with_config(dir.path().to_path_buf(), config, on_changed);
+      TestFixture {
+         manager,
+         events,
+         _dir: dir,
+      }
+   }
+
+   /// Seeds an `InProgress` item in the store and returns a `DownloadItem`
+   /// suitable for passing to `download()`.
+   fn seed_in_progress(manager: &DownloadManager, dest_path: &str, url: &str) -> DownloadItem {
+      let item = DownloadItem {
+         url: url.to_string(),
+         path: dest_path.to_string(),
+         progress: Some(0.0),
+         status: DownloadStatus::InProgress,
+         ..Default::default()
+      };
+      manager.store.create(item.clone()).unwrap();
+      item
    }
 
    fn dest_path(fixture: &TestFixture, name: &str) -> String {
@@ -277,319 +1732,2679 @@ mod tests {
 
       let dest = dest_path(&fixture, "file.bin");
       let url = format!("{}/file", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
 
-      download(&fixture.manager, item).await.unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
 
       // Final file exists with expected bytes; temp file gone.
       assert_eq!(fs::read(&dest).unwrap(), body);
       assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
 
-      // Store entry removed.
-      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+      // Store entry removed.
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+
+      // Exactly one Completed event; no duplicate 100% progress event.
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+   }
+
+   #[tokio::test]
+   async fn test_completes_when_final_chunk_is_throttled() {
+      // The final chunk lands on 100% without a progress update in between, so
+      // completion must come from the stream ending, not from a progress report.
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         progress: ProgressConfig {
+            threshold: 100.0,
+            interval_ms: None,
+            heartbeat_ms: None,
+         },
+         ..Default::default()
+      });
+      let server = MockServer::start().await;
+      let body = b"abc".to_vec();
+
+      Mock::given(method("GET"))
+         .and(wm_path("/small"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "small.bin");
+      let url = format!("{}/small", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), body);
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::InProgress),
+         0
+      );
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+   }
+
+   /// Seeds an `InProgress` download with `overwrite` whose path is taken by a file
+   /// created after the download, and serves its body.
+   async fn seed_taken_path(
+      fixture: &TestFixture,
+      server: &MockServer,
+      overwrite: OverwritePolicy,
+   ) -> DownloadItem {
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"new".to_vec()))
+         .expect(1)
+         .mount(server)
+         .await;
+      let dest = dest_path(fixture, "taken.bin");
+      fs::write(&dest, b"existing").unwrap();
+      let item = DownloadItem {
+         options: DownloadOptions {
+            overwrite,
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()))
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      item
+   }
+
+   #[tokio::test]
+   async fn test_overwrite_policy_overwrite_replaces_existing_file() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let mut item = seed_taken_path(&fixture, &server, OverwritePolicy::Overwrite).await;
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&item.path).unwrap(), b"new");
+      assert!(
+         fixture
+            .manager
+            .store
+            .find_by_path(&item.path)
+            .unwrap()
+            .is_none()
+      );
+   }
+
+   #[tokio::test]
+   async fn test_overwrite_policy_fail_keeps_existing_file_and_temp_file() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let mut item = seed_taken_path(&fixture, &server, OverwritePolicy::Fail).await;
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&item.path).unwrap(), b"existing");
+      let failed = fixture
+         .manager
+         .store
+         .find_by_path(&item.path)
+         .unwrap()
+         .unwrap();
+      assert_eq!(failed.status, DownloadStatus::Failed);
+      assert!(failed.error.unwrap().contains("Destination already exists"));
+      let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
+      assert_eq!(fs::read(&temp_path).unwrap(), b"new");
+
+      // Once the path is free, the download completes without another request.
+      fs::remove_file(&item.path).unwrap();
+      let mut item = fixture
+         .manager
+         .store
+         .find_by_path(&item.path)
+         .unwrap()
+         .unwrap();
+      item.status = DownloadStatus::InProgress;
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&item.path).unwrap(), b"new");
+      assert!(!Path::new(&temp_path).exists());
+   }
+
+   #[tokio::test]
+   async fn test_overwrite_policy_rename_completes_at_free_path() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let mut item = seed_taken_path(&fixture, &server, OverwritePolicy::Rename).await;
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      let taken = dest_path(&fixture, "taken.bin");
+      let renamed = filename::numbered(&taken, 1);
+      assert_eq!(item.path, renamed);
+      assert_eq!(fs::read(&taken).unwrap(), b"existing");
+      assert_eq!(fs::read(&renamed).unwrap(), b"new");
+      assert!(
+         fixture
+            .manager
+            .store
+            .find_by_path(&renamed)
+            .unwrap()
+            .is_none()
+      );
+      let log = fixture.events.lock().unwrap();
+      let completed = log.last().unwrap();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.path, renamed);
+   }
+
+   #[tokio::test]
+   async fn test_completion_hook_runs_before_completed_event() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hooked".to_vec()))
+         .mount(&server)
+         .await;
+      let dest = dest_path(&fixture, "hooked.bin");
+
+      // The hook sees the file in place, and no Completed event yet. A failing hook
+      // that doesn't fail the download is only logged.
+      let calls = Arc::new(Mutex::new(Vec::new()));
+      let (seen, events) = (calls.clone(), fixture.events.clone());
+      fixture.manager.register_completion_hook(
+         Arc::new(move |item: &DownloadItem| {
+            assert_eq!(fs::read(&item.path).unwrap(), b"hooked");
+            assert_eq!(events_with_status(&events, DownloadStatus::Completed), 0);
+            seen.lock().unwrap().push(item.status.clone());
+            Ok(())
+         }),
+         true,
+      );
+      fixture
+         .manager
+         .register_completion_hook(Arc::new(|_: &DownloadItem| Err("ignored".into())), false);
+
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(*calls.lock().unwrap(), vec![DownloadStatus::Completed]);
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+   }
+
+   #[tokio::test]
+   async fn test_failing_completion_hook_fails_download_and_keeps_file() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"archive".to_vec()))
+         .mount(&server)
+         .await;
+      let dest = dest_path(&fixture, "archive.zip");
+      fixture.manager.register_completion_hook(
+         Arc::new(|_: &DownloadItem| Err("Invalid archive".into())),
+         true,
+      );
+
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"archive");
+      let failed = fixture.manager.store.find_by_path(&dest).unwrap().unwrap();
+      assert_eq!(failed.status, DownloadStatus::Failed);
+      assert_eq!(
+         failed.error.as_deref(),
+         Some("Post-processing failed: Invalid archive")
+      );
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         0
+      );
+   }
+
+   #[tokio::test]
+   async fn test_write_buffer_keeps_every_byte() {
+      let body: Vec<u8> = (0..700_001u32).map(|i| (i % 251) as u8).collect();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+         .mount(&server)
+         .await;
+
+      // Unbuffered, smaller than a chunk, and larger than the whole body.
+      for capacity in [0, 1000, 1024 * 1024] {
+         let fixture = make_fixture_with_config(DownloadManagerConfig {
+            write_buffer_bytes: Some(capacity),
+            ..Default::default()
+         });
+         let dest = dest_path(&fixture, "buffered.bin");
+         let mut item =
+            seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()));
+
+         download(&fixture.manager, &mut item).await.unwrap();
+
+         let written = fs::read(&dest).unwrap();
+         assert_eq!(written.len(), body.len(), "capacity {}", capacity);
+         assert_eq!(written, body);
+      }
+   }
+
+   #[tokio::test]
+   async fn test_completes_without_content_length() {
+      // Regression: when the server omits Content-Length, total_size is 0
+      // and progress stays at 0.0. Completion must still trigger when the
+      // stream ends naturally.
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let body = b"streamed body with no length header".to_vec();
+
+      Mock::given(method("GET"))
+         .and(wm_path("/stream"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(body.clone())
+               .append_header("Transfer-Encoding", "chunked"),
+         )
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "stream.bin");
+      let url = format!("{}/stream", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), body);
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+   }
+
+   #[tokio::test]
+   async fn test_resume_appends_to_temp_file() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      // Pre-existing temp file with the first half of the body.
+      let dest = dest_path(&fixture, "resume.bin");
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      let first_half = b"first-half-";
+      let second_half = b"second-half";
+      fs::write(&temp_path, first_half).unwrap();
+
+      // Server expects a Range request and returns 206 with only the second half.
+      Mock::given(method("GET"))
+         .and(wm_path("/resume"))
+         .and(header(
+            "range",
+            format!("bytes={}-", first_half.len()).as_str(),
+         ))
+         .respond_with(ResponseTemplate::new(206).set_body_bytes(second_half.to_vec()))
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/resume", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      let combined = [first_half.as_slice(), second_half.as_slice()].concat();
+      assert_eq!(fs::read(&dest).unwrap(), combined);
+   }
+
+   #[tokio::test]
+   async fn test_stages_download_in_temp_dir_and_moves_it_into_place() {
+      let staging = TempDir::new().unwrap();
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         temp_dir: Some(staging.path().to_path_buf()),
+         ..Default::default()
+      });
+      let server = MockServer::start().await;
+
+      // The staged temp file holds the first half; the destination folder doesn't exist
+      // yet.
+      let dest = dest_path(&fixture, "nested/staged.bin");
+      let temp_path = fixture.manager.temp_path(&dest);
+      assert!(Path::new(&temp_path).starts_with(staging.path()));
+      fs::write(&temp_path, b"staged-").unwrap();
+
+      Mock::given(method("GET"))
+         .and(wm_path("/staged"))
+         .and(header("range", "bytes=7-"))
+         .respond_with(ResponseTemplate::new(206).set_body_bytes(b"bytes".to_vec()))
+         .mount(&server)
+         .await;
+
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/staged", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"staged-bytes");
+      assert!(!Path::new(&temp_path).exists());
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+      assert_eq!(fs::read_dir(staging.path()).unwrap().count(), 0);
+   }
+
+   #[tokio::test]
+   async fn test_resume_restarts_from_zero_when_server_returns_200() {
+      // When the server ignores the Range header and returns 200 with the
+      // full body, the downloader discards the stale temp file and restarts
+      // from zero rather than erroring.
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      let dest = dest_path(&fixture, "fallback.bin");
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"stale partial bytes").unwrap();
+
+      let full_body = b"full body content";
+      Mock::given(method("GET"))
+         .and(wm_path("/fallback"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(full_body.to_vec()))
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/fallback", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      // Final file is the full body, not partial + full.
+      assert_eq!(fs::read(&dest).unwrap(), full_body);
+      // Temp file has been cleaned up.
+      assert!(!Path::new(&temp_path).exists());
+   }
+
+   #[tokio::test]
+   async fn test_resume_restarts_without_range_if_server_refuses_ranges() {
+      let fixture = make_fixture();
+      let restarts = Arc::new(Mutex::new(Vec::new()));
+      let captured = restarts.clone();
+      let manager = fixture
+         .manager
+         .clone()
+         .with_event_handler(Arc::new(move |event| {
+            if let DownloadEvent::Restarted(item) = event {
+               captured.lock().unwrap().push(*item);
+            }
+         }));
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/no-ranges"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .insert_header("Accept-Ranges", "none")
+               .set_body_bytes(b"full body".to_vec()),
+         )
+         .mount(&server)
+         .await;
+
+      // The first response told that the server doesn't accept range requests.
+      let dest = dest_path(&fixture, "no-ranges.bin");
+      let temp_path = manager.temp_path(&dest);
+      fs::write(&temp_path, b"stale").unwrap();
+      let url = format!("{}/no-ranges", server.uri());
+      let mut item = DownloadItem {
+         accepts_ranges: Some(false),
+         ..seed_in_progress(&manager, &dest, &url)
+      };
+      manager.store.update(item.clone()).unwrap();
+
+      download(&manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"full body");
+      let requests = server.received_requests().await.unwrap();
+      assert!(!requests[0].headers.contains_key("range"));
+      let restarts = restarts.lock().unwrap();
+      assert_eq!(restarts.len(), 1);
+      assert_eq!(restarts[0].path, dest);
+      assert_eq!(restarts[0].downloaded_bytes, 0);
+   }
+
+   #[tokio::test]
+   async fn test_resume_restarts_if_temp_file_is_larger_than_resource() {
+      let fixture = make_fixture();
+      let manager = fixture.manager.clone();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/shrunk"))
+         .and(header("range", "bytes=16-"))
+         .respond_with(ResponseTemplate::new(416).insert_header("Content-Range", "bytes */9"))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/shrunk"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"full body".to_vec()))
+         .mount(&server)
+         .await;
+
+      // The temp file holds more bytes than the resource has.
+      let dest = dest_path(&fixture, "shrunk.bin");
+      fs::write(manager.temp_path(&dest), b"previous version").unwrap();
+      let url = format!("{}/shrunk", server.uri());
+      let mut item = seed_in_progress(&manager, &dest, &url);
+
+      download(&manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"full body");
+      let requests = server.received_requests().await.unwrap();
+      assert_eq!(requests.len(), 2);
+      assert!(!requests[1].headers.contains_key("range"));
+   }
+
+   #[tokio::test]
+   async fn test_resume_completes_if_temp_file_holds_whole_resource() {
+      let fixture = make_fixture();
+      let manager = fixture.manager.clone();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/done"))
+         .respond_with(ResponseTemplate::new(416).insert_header("Content-Range", "bytes */9"))
+         .expect(1)
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "done.bin");
+      fs::write(manager.temp_path(&dest), b"full body").unwrap();
+      let url = format!("{}/done", server.uri());
+      let mut item = seed_in_progress(&manager, &dest, &url);
+
+      download(&manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"full body");
+      assert_eq!(item.status, DownloadStatus::Completed);
+   }
+
+   #[test]
+   fn test_ranges_accepted() {
+      let headers = |value: &str| {
+         let mut headers = HeaderMap::new();
+         headers.insert(ACCEPT_RANGES, value.parse().unwrap());
+         headers
+      };
+      let ok = reqwest::StatusCode::OK;
+      assert_eq!(ranges_accepted(ok, &headers("bytes")), Some(true));
+      assert_eq!(ranges_accepted(ok, &headers("none")), Some(false));
+      assert_eq!(ranges_accepted(ok, &HeaderMap::new()), None);
+      let partial = reqwest::StatusCode::PARTIAL_CONTENT;
+      assert_eq!(ranges_accepted(partial, &HeaderMap::new()), Some(true));
+   }
+
+   #[tokio::test]
+   async fn test_resume_rejects_range_at_another_offset() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      let dest = dest_path(&fixture, "offset.bin");
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"first-half-").unwrap();
+
+      // The server answers the range request with the resource from its start.
+      Mock::given(method("GET"))
+         .and(wm_path("/offset"))
+         .and(header("range", "bytes=11-"))
+         .respond_with(
+            ResponseTemplate::new(206)
+               .insert_header("Content-Range", "bytes 0-21/22")
+               .set_body_bytes(b"first-half-second-half".to_vec()),
+         )
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/offset", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("starts at byte 0, expected 11")));
+      assert_eq!(fs::read(&temp_path).unwrap(), b"first-half-");
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_range_downloads_only_the_requested_bytes() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/range"))
+         .and(header("range", "bytes=10-19"))
+         .respond_with(
+            ResponseTemplate::new(206)
+               .insert_header("Content-Range", "bytes 10-19/100")
+               .set_body_bytes(b"0123456789".to_vec()),
+         )
+         .mount(&server)
+         .await;
+      // Resumed after the first 4 bytes of the range.
+      Mock::given(method("GET"))
+         .and(wm_path("/range"))
+         .and(header("range", "bytes=14-19"))
+         .respond_with(
+            ResponseTemplate::new(206)
+               .insert_header("Content-Range", "bytes 14-19/100")
+               .set_body_bytes(b"456789".to_vec()),
+         )
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/range", server.uri());
+      let options = DownloadOptions {
+         range_start: Some(10),
+         range_end: Some(19),
+         ..Default::default()
+      };
+      let dest = dest_path(&fixture, "range.bin");
+      let mut item = DownloadItem {
+         options: options.clone(),
+         ..seed_in_progress(&fixture.manager, &dest, &url)
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"0123456789");
+      let completed = fixture.events.lock().unwrap().last().unwrap().clone();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.downloaded_bytes, 10);
+
+      let resumed = dest_path(&fixture, "resumed.bin");
+      fs::write(format!("{}{}", resumed, DOWNLOAD_SUFFIX), b"0123").unwrap();
+      let mut item = DownloadItem {
+         options,
+         ..seed_in_progress(&fixture.manager, &resumed, &url)
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&resumed).unwrap(), b"0123456789");
+   }
+
+   #[tokio::test]
+   async fn test_range_fails_if_not_honored() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/range"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 100]))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "range.bin");
+      let mut item = DownloadItem {
+         options: DownloadOptions {
+            range_start: Some(10),
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &format!("{}/range", server.uri()))
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("Range not honored")));
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[test]
+   fn test_content_range_start() {
+      let range = |value: &'static str| {
+         let mut headers = HeaderMap::new();
+         headers.insert(CONTENT_RANGE, HeaderValue::from_static(value));
+         content_range_start(&headers)
+      };
+      assert_eq!(range("bytes 100-199/1000"), Some(100));
+      assert_eq!(range("bytes 100-199/*"), Some(100));
+      assert_eq!(range("bytes */1000"), None);
+      assert_eq!(content_range_start(&HeaderMap::new()), None);
+   }
+
+   #[test]
+   fn test_unsatisfied_range_size() {
+      let range = |value: &'static str| {
+         let mut headers = HeaderMap::new();
+         headers.insert(CONTENT_RANGE, HeaderValue::from_static(value));
+         unsatisfied_range_size(&headers)
+      };
+      assert_eq!(range("bytes */1000"), Some(1000));
+      assert_eq!(range("bytes 100-199/1000"), None);
+      assert_eq!(range("bytes */*"), None);
+      assert_eq!(unsatisfied_range_size(&HeaderMap::new()), None);
+   }
+
+   #[tokio::test]
+   async fn test_resume_sends_if_range_with_stored_etag() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      let dest = dest_path(&fixture, "etag.bin");
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"first-half-").unwrap();
+
+      // Only a request carrying the stored ETag as If-Range gets the partial response.
+      Mock::given(method("GET"))
+         .and(wm_path("/etag"))
+         .and(header("range", "bytes=11-"))
+         .and(header("if-range", "\"v1\""))
+         .respond_with(ResponseTemplate::new(206).set_body_bytes(b"second-half".to_vec()))
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/etag", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      item.etag = Some("\"v1\"".to_string());
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"first-half-second-half");
+   }
+
+   #[tokio::test]
+   async fn test_resume_restarts_from_zero_when_etag_changed() {
+      // The remote resource changed since the partial download: the server sees a
+      // stale If-Range validator and sends the full new resource with a new ETag. The
+      // stale partial bytes must be discarded rather than appended to.
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      let dest = dest_path(&fixture, "changed.bin");
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"old partial bytes").unwrap();
+
+      let new_body = b"new version of the resource";
+      Mock::given(method("GET"))
+         .and(wm_path("/changed"))
+         .and(header("if-range", "\"v1\""))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(new_body.to_vec())
+               .append_header("ETag", "\"v2\""),
+         )
+         .mount(&server)
+         .await;
+
+      let url = format!("{}/changed", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      item.etag = Some("\"v1\"".to_string());
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), new_body);
+      assert!(!Path::new(&temp_path).exists());
+   }
+
+   #[tokio::test]
+   async fn test_persists_validators_from_first_response() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/validators"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(b"complete body".to_vec())
+               .append_header("ETag", "\"abc\"")
+               .append_header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+         )
+         .mount(&server)
+         .await;
+
+      // Occupy the destination so the final rename fails and the store entry survives.
+      let dest = dest_path(&fixture, "validators.bin");
+      fs::create_dir(&dest).unwrap();
+      let url = format!("{}/validators", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      assert!(download(&fixture.manager, &mut item).await.is_err());
+
+      let stored = fixture.manager.store.find_by_path(&dest).unwrap().unwrap();
+      assert_eq!(stored.etag.as_deref(), Some("\"abc\""));
+      assert_eq!(
+         stored.last_modified.as_deref(),
+         Some("Wed, 21 Oct 2015 07:28:00 GMT")
+      );
+      assert_eq!(stored.total_size, Some(b"complete body".len() as u64));
+   }
+
+   #[test]
+   fn test_if_range_validator_prefers_strong_etag() {
+      let mut item = DownloadItem {
+         etag: Some("\"strong\"".to_string()),
+         last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+         ..Default::default()
+      };
+      assert_eq!(if_range_validator(&item), Some("\"strong\""));
+
+      // Weak ETags can't be used with If-Range; fall back to Last-Modified.
+      item.etag = Some("W/\"weak\"".to_string());
+      assert_eq!(
+         if_range_validator(&item),
+         Some("Wed, 21 Oct 2015 07:28:00 GMT")
+      );
+
+      item.last_modified = None;
+      assert_eq!(if_range_validator(&item), None);
+   }
+
+   #[tokio::test]
+   async fn test_resolves_filename_from_content_disposition() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/download"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(b"report body".to_vec())
+               .append_header("Content-Disposition", "attachment; filename=\"report.pdf\""),
+         )
+         .mount(&server)
+         .await;
+
+      let placeholder = dest_path(&fixture, "download");
+      let url = format!("{}/download?id=123", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &placeholder, &url);
+      item.resolve_filename = true;
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      let resolved = dest_path(&fixture, "report.pdf");
+      assert_eq!(item.path, resolved);
+      assert_eq!(fs::read(&resolved).unwrap(), b"report body");
+      assert!(!Path::new(&placeholder).exists());
+
+      // The resolved path was announced before completion.
+      let log = fixture.events.lock().unwrap().clone();
+      assert_eq!(
+         log.first().map(|e| e.path.as_str()),
+         Some(resolved.as_str())
+      );
+      assert_eq!(
+         log.last().map(|e| e.status.clone()),
+         Some(DownloadStatus::Completed)
+      );
+      assert_eq!(log.last().map(|e| e.path.as_str()), Some(resolved.as_str()));
+   }
+
+   #[tokio::test]
+   async fn test_keeps_placeholder_without_content_disposition() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/files/clip.mp4"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"clip".to_vec()))
+         .mount(&server)
+         .await;
+
+      let placeholder = dest_path(&fixture, "clip.mp4");
+      let url = format!("{}/files/clip.mp4", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &placeholder, &url);
+      item.resolve_filename = true;
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(item.path, placeholder);
+      assert!(!item.resolve_filename);
+      assert_eq!(fs::read(&placeholder).unwrap(), b"clip");
+   }
+
+   #[tokio::test]
+   async fn test_http_error_returns_err_and_creates_no_file() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/missing"))
+         .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "missing.bin");
+      let url = format!("{}/missing", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      match err {
+         Error::Http(msg) => assert!(msg.contains("404"), "expected status in message: {}", msg),
+         other => panic!("expected Error::Http, got {:?}", other),
+      }
+
+      // No file is created at the destination on HTTP error.
+      assert!(!Path::new(&dest).exists());
+      // No temp file is created either (we error before opening it).
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+   }
+
+   #[tokio::test]
+   async fn test_creates_output_folder_when_missing() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/nested"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"data".to_vec()))
+         .mount(&server)
+         .await;
+
+      // Use a nested subdir that does not yet exist.
+      let dest = fixture
+         ._dir
+         .path()
+         .join("a/b/c/file.bin")
+         .to_string_lossy()
+         .into_owned();
+      let url = format!("{}/nested", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"data");
+   }
+
+   #[test]
+   fn test_create_parent_dir_at_root_and_without_parent() {
+      // A file directly under the root has `/` as its existing parent.
+      assert_eq!(
+         create_parent_dir("/file.mp4.download").unwrap(),
+         Path::new("/")
+      );
+      assert!(matches!(
+         create_parent_dir("file.mp4.download"),
+         Err(Error::File(msg)) if msg.contains("no parent directory")
+      ));
+      assert!(matches!(create_parent_dir("/"), Err(Error::File(_))));
+   }
+
+   #[tokio::test]
+   async fn test_unwritable_directory_returns_err() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"data".to_vec()))
+         .mount(&server)
+         .await;
+
+      // A regular file in place of the directory can't be written into, even as root.
+      let blocker = fixture._dir.path().join("blocker");
+      fs::write(&blocker, b"").unwrap();
+      let dest = blocker.join("sub/file.bin").to_string_lossy().into_owned();
+      let url = format!("{}/file", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(matches!(&err, Error::File(msg) if msg.contains("Failed to create directory")));
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_some());
+   }
+
+   #[tokio::test]
+   async fn test_unknown_size_emits_progress_at_byte_threshold() {
+      // Body larger than BYTES_THRESHOLD (1 MiB) ensures at least one
+      // progress event fires for the unknown-size path before completion.
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let body = vec![0u8; (1024 * 1024) + 1024]; // 1 MiB + 1 KiB
+
+      Mock::given(method("GET"))
+         .and(wm_path("/big"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(body.clone())
+               .append_header("Transfer-Encoding", "chunked"),
+         )
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "big.bin");
+      let url = format!("{}/big", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      // At least one InProgress progress event without progress (unknown size), plus
+      // the final Completed event at 100.
+      let log = fixture.events.lock().unwrap().clone();
+      assert!(
+         log.iter()
+            .any(|e| e.status == DownloadStatus::InProgress && e.progress.is_none()),
+         "expected at least one InProgress progress event without progress for unknown size"
+      );
+      assert_eq!(log.last().unwrap().progress, Some(100.0));
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+
+      // Without a total, the events still count the bytes received.
+      let progress = log
+         .iter()
+         .find(|e| e.status == DownloadStatus::InProgress)
+         .unwrap();
+      assert!(progress.downloaded_bytes >= BYTES_THRESHOLD);
+      assert_eq!(progress.total_size, None);
+      assert_eq!(log.last().unwrap().downloaded_bytes, body.len() as u64);
+   }
+
+   #[tokio::test]
+   async fn test_progress_events_carry_byte_counts() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         progress: ProgressConfig {
+            threshold: 0.0,
+            interval_ms: None,
+            heartbeat_ms: None,
+         },
+         ..Default::default()
+      });
+      let body = vec![0u8; 256 * 1024];
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "bytes.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      let log = fixture.events.lock().unwrap().clone();
+      let progress: Vec<_> = log
+         .iter()
+         .filter(|e| e.status == DownloadStatus::InProgress)
+         .collect();
+      assert!(!progress.is_empty());
+      for event in progress {
+         assert_eq!(event.total_size, Some(body.len() as u64));
+         assert_eq!(
+            event.progress,
+            Some(event.downloaded_bytes as f64 / body.len() as f64 * 100.0)
+         );
+      }
+      assert_eq!(log.last().unwrap().downloaded_bytes, body.len() as u64);
+   }
+
+   #[tokio::test]
+   async fn test_pause_mid_stream_stops_and_preserves_temp_file() {
+      // Flipping the status to Paused while the body is still streaming must
+      // stop reading, skip completion, and leave the .download temp file intact
+      // so the download can later resume. The body exceeds BYTES_THRESHOLD so the
+      // in-loop throttle checkpoint — where the status is re-read from the store —
+      // is reached while data still remains, exercising the in-loop transition.
+      let dir = TempDir::new().unwrap();
+      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+
+      // The callback flips the store status to Paused on the first in-progress
+      // checkpoint, simulating a concurrent `pause()`. The store is Arc-backed,
+      // so this clone shares state with the store `download()` reads from. The
+      // cell defers capturing the store until after the manager is constructed.
+      let store_cell: Arc<Mutex<Option<Arc<dyn Store>>>> = Arc::new(Mutex::new(None));
+      let captured = events.clone();
+      let cell = store_cell.clone();
+      let on_changed: OnChanged = Arc::new(move |item: DownloadItem| {
+         captured.lock().unwrap().push(item.clone());
+         if item.status == DownloadStatus::InProgress
+            && let Some(store) = cell.lock().unwrap().as_ref()
+         {
+            store
+               .update_no_persist(item.with_status(DownloadStatus::Paused))
+               .unwrap();
+         }
+      });
+
+      let manager = DownloadManager::new(dir.path().to_path_buf(), on_changed);
+      *store_cell.lock().unwrap() = Some(manager.store.clone());
+
+      let server = MockServer::start().await;
+      // Several times BYTES_THRESHOLD with unknown size, so the body is delivered
+      // over multiple stream chunks and several throttle checkpoints are reached.
+      let body = vec![0u8; 4 * 1024 * 1024];
+      Mock::given(method("GET"))
+         .and(wm_path("/pause"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_bytes(body.clone())
+               .append_header("Transfer-Encoding", "chunked"),
+         )
+         .mount(&server)
+         .await;
+
+      let dest = dir.path().join("pause.bin").to_string_lossy().into_owned();
+      let url = format!("{}/pause", server.uri());
+      let mut item = seed_in_progress(&manager, &dest, &url);
+
+      download(&manager, &mut item).await.unwrap();
+
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      // At least one in-progress event fired before the pause took effect.
+      assert!(
+         events_with_status(&events, DownloadStatus::InProgress) >= 1,
+         "expected at least one InProgress event before the pause"
+      );
+      // Stopped gracefully: temp file preserved, final file never created.
+      assert!(
+         Path::new(&temp_path).exists(),
+         "temp file should be preserved for resume"
+      );
+      assert!(
+         !Path::new(&dest).exists(),
+         "final file should not be created when paused mid-stream"
+      );
+      // No completion was emitted and the store entry survives for resume.
+      assert_eq!(events_with_status(&events, DownloadStatus::Completed), 0);
+      assert!(manager.store.find_by_path(&dest).unwrap().is_some());
+   }
+
+   #[tokio::test]
+   async fn test_rename_failure_keeps_store_entry_and_temp_file() {
+      // If the final rename fails, the store entry must survive (still InProgress, so
+      // the caller's error handler can revert it to a resumable state) and the temp
+      // file must be left intact. The download must not silently vanish.
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/rename-fail"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"complete body".to_vec()))
+         .mount(&server)
+         .await;
+
+      // Make the destination an existing directory so `fs::rename` cannot replace it
+      // (and so the Windows pre-delete `remove_file` also fails on a directory).
+      let dest = dest_path(&fixture, "occupied.bin");
+      fs::create_dir(&dest).unwrap();
+
+      let url = format!("{}/rename-fail", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(
+         matches!(err, Error::File(_)),
+         "expected Error::File with context, got {:?}",
+         err
+      );
+
+      // Store entry survives and is still InProgress, ready to be reverted/resumed.
+      let stored = fixture.manager.store.find_by_path(&dest).unwrap().unwrap();
+      assert_eq!(stored.status, DownloadStatus::InProgress);
+      // Temp file is left intact rather than orphaned and forgotten.
+      assert!(Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+      // No completion event was emitted.
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         0
+      );
+   }
+
+   fn deny_cross_host_config() -> DownloadManagerConfig {
+      DownloadManagerConfig {
+         redirect: RedirectConfig {
+            cross_host: CrossHostRedirects::Deny,
+            ..Default::default()
+         },
+         ..Default::default()
+      }
+   }
+
+   /// Mounts a redirect from `/start` to `{target_base}/target`, which serves `body`.
+   async fn mount_redirect(server: &MockServer, target_base: &str, body: &[u8]) {
+      Mock::given(method("GET"))
+         .and(wm_path("/start"))
+         .respond_with(
+            ResponseTemplate::new(302).append_header("Location", format!("{}/target", target_base)),
+         )
+         .mount(server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/target"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.to_vec()))
+         .mount(server)
+         .await;
+   }
+
+   #[tokio::test]
+   async fn test_deny_cross_host_follows_same_host_redirect() {
+      let fixture = make_fixture_with_config(deny_cross_host_config());
+      let server = MockServer::start().await;
+      mount_redirect(&server, &server.uri(), b"same host").await;
+
+      let dest = dest_path(&fixture, "same.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/start", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"same host");
+   }
+
+   #[tokio::test]
+   async fn test_deny_cross_host_blocks_redirect_to_other_host() {
+      let fixture = make_fixture_with_config(deny_cross_host_config());
+      let server = MockServer::start().await;
+      // `localhost` and `127.0.0.1` reach the same server but are different hosts.
+      let other_host = server.uri().replace("127.0.0.1", "localhost");
+      mount_redirect(&server, &other_host, b"other host").await;
+
+      let dest = dest_path(&fixture, "other.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/start", server.uri()));
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("to different host 'localhost'")));
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_host_policy_blocks_redirect_to_denied_host() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         hosts: HostPolicy {
+            allow: Vec::new(),
+            deny: vec!["localhost".to_string()],
+         },
+         ..Default::default()
+      });
+      let server = MockServer::start().await;
+      let other_host = server.uri().replace("127.0.0.1", "localhost");
+      mount_redirect(&server, &other_host, b"other host").await;
+
+      let dest = dest_path(&fixture, "denied.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/start", server.uri()));
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+
+      assert!(
+         matches!(&err, Error::Http(msg) if msg.contains("Redirect to host 'localhost' is not allowed"))
+      );
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_allow_cross_host_follows_redirect_to_other_host() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      let other_host = server.uri().replace("127.0.0.1", "localhost");
+      mount_redirect(&server, &other_host, b"other host").await;
+
+      let dest = dest_path(&fixture, "other.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/start", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"other host");
+   }
+
+   fn max_redirects_config(max: usize) -> DownloadManagerConfig {
+      DownloadManagerConfig {
+         redirect: RedirectConfig {
+            max_redirects: Some(max),
+            ..Default::default()
+         },
+         ..Default::default()
+      }
+   }
+
+   #[tokio::test]
+   async fn test_disabled_redirects_fail_with_location() {
+      let fixture = make_fixture_with_config(max_redirects_config(0));
+      let server = MockServer::start().await;
+      mount_redirect(&server, &server.uri(), b"signed").await;
+
+      let dest = dest_path(&fixture, "signed.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/start", server.uri()));
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+
+      let location = format!("{}/target", server.uri());
+      assert!(
+         matches!(&err, Error::Http(msg) if msg.contains(&format!("Redirect to '{}' not followed: redirects are disabled", location))),
+         "{}",
+         err
+      );
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_max_redirects_limits_redirect_chain() {
+      let server = MockServer::start().await;
+      // `/0` redirects to `/1`, which redirects to `/2`, which serves the body.
+      for hop in 0..2 {
+         Mock::given(method("GET"))
+            .and(wm_path(format!("/{}", hop)))
+            .respond_with(
+               ResponseTemplate::new(302).append_header("Location", format!("/{}", hop + 1)),
+            )
+            .mount(&server)
+            .await;
+      }
+      Mock::given(method("GET"))
+         .and(wm_path("/2"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"two hops".to_vec()))
+         .mount(&server)
+         .await;
+      let url = format!("{}/0", server.uri());
+
+      let fixture = make_fixture_with_config(max_redirects_config(2));
+      let dest = dest_path(&fixture, "two.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"two hops");
+
+      let fixture = make_fixture_with_config(max_redirects_config(1));
+      let dest = dest_path(&fixture, "one.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(
+         matches!(&err, Error::Http(msg) if msg.contains("/2' not followed: more than 1 redirects")),
+         "{}",
+         err
+      );
+   }
+
+   #[tokio::test]
+   async fn test_fails_over_to_mirror_on_server_error() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/primary"))
+         .respond_with(ResponseTemplate::new(503))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/mirror"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"from mirror".to_vec()))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "mirrored.bin");
+      let mirror = format!("{}/mirror", server.uri());
+      let mut item = seed_in_progress(
+         &fixture.manager,
+         &dest,
+         &format!("{}/primary", server.uri()),
+      );
+      item.options.mirrors = vec![mirror.clone()];
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"from mirror");
+      assert_eq!(item.active_mirror.as_deref(), Some(mirror.as_str()));
+
+      // The failover was announced before completion.
+      let log = fixture.events.lock().unwrap().clone();
+      assert_eq!(log[0].status, DownloadStatus::InProgress);
+      assert_eq!(log[0].active_mirror.as_deref(), Some(mirror.as_str()));
+   }
+
+   async fn mount_retry_after(server: &MockServer, status: u16, retry_after: &str, body: &[u8]) {
+      Mock::given(method("GET"))
+         .and(wm_path("/busy"))
+         .respond_with(ResponseTemplate::new(status).insert_header("Retry-After", retry_after))
+         .up_to_n_times(1)
+         .expect(1)
+         .mount(server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/busy"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.to_vec()))
+         .mount(server)
+         .await;
+   }
+
+   #[tokio::test]
+   async fn test_waits_retry_after_seconds_before_next_attempt() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      mount_retry_after(&server, 503, "1", b"finally").await;
+
+      let dest = dest_path(&fixture, "busy.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/busy", server.uri()));
+      let started = Instant::now();
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert!(started.elapsed() >= Duration::from_secs(1));
+      assert_eq!(fs::read(&dest).unwrap(), b"finally");
+
+      // The wait was announced, then cleared once it was over.
+      let log = fixture.events.lock().unwrap().clone();
+      assert_eq!(log[0].status, DownloadStatus::InProgress);
+      assert_eq!(log[0].retry_after_ms, Some(1000));
+      assert_eq!(log[1].retry_after_ms, None);
+      assert_eq!(log.last().unwrap().status, DownloadStatus::Completed);
+   }
+
+   #[tokio::test]
+   async fn test_retry_after_date_in_the_past_retries_right_away() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      mount_retry_after(&server, 429, "Wed, 21 Oct 2015 07:28:00 GMT", b"ok").await;
+
+      let dest = dest_path(&fixture, "limited.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/busy", server.uri()));
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"ok");
+      let log = fixture.events.lock().unwrap().clone();
+      assert_eq!(log[0].retry_after_ms, Some(0));
+   }
+
+   #[tokio::test]
+   async fn test_retry_after_beyond_maximum_fails() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      mount_retry_after(&server, 503, "86400", b"too late").await;
+
+      let dest = dest_path(&fixture, "closed.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/busy", server.uri()));
+      let result = download(&fixture.manager, &mut item).await;
+
+      assert!(matches!(result, Err(Error::Http(msg)) if msg.starts_with("HTTP 503")));
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_client_error_does_not_fail_over() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/primary"))
+         .respond_with(ResponseTemplate::new(404))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/mirror"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"from mirror".to_vec()))
+         .expect(0)
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "missing.bin");
+      let mut item = seed_in_progress(
+         &fixture.manager,
+         &dest,
+         &format!("{}/primary", server.uri()),
+      );
+      item.options.mirrors = vec![format!("{}/mirror", server.uri())];
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      assert!(download(&fixture.manager, &mut item).await.is_err());
+      assert!(item.active_mirror.is_none());
+   }
+
+   #[tokio::test]
+   async fn test_sends_requests_through_proxy_with_credentials() {
+      let proxy = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         // Base64 of "user:secret".
+         .and(header("proxy-authorization", "Basic dXNlcjpzZWNyZXQ="))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"via proxy".to_vec()))
+         .mount(&proxy)
+         .await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         proxy: Some(ProxyConfig {
+            url: proxy.uri(),
+            username: Some("user".to_string()),
+            password: Some("secret".to_string()),
+            ..Default::default()
+         }),
+         ..Default::default()
+      });
+
+      let dest = dest_path(&fixture, "proxied.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, "http://downloads.invalid/file");
+
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"via proxy");
+   }
+
+   #[tokio::test]
+   async fn test_no_proxy_hosts_bypass_proxy() {
+      // The proxy answers nothing, so only a direct request succeeds.
+      let proxy = MockServer::start().await;
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"direct".to_vec()))
+         .mount(&server)
+         .await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         proxy: Some(ProxyConfig {
+            url: proxy.uri(),
+            no_proxy: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+         }),
+         ..Default::default()
+      });
+
+      let dest = dest_path(&fixture, "direct.bin");
+      let url = format!("{}/file", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"direct");
+      assert!(proxy.received_requests().await.unwrap().is_empty());
+   }
+
+   #[test]
+   fn test_build_proxy_rejects_invalid_url() {
+      let config = ProxyConfig {
+         url: "not a url".to_string(),
+         ..Default::default()
+      };
+      assert!(
+         matches!(build_proxy(&config), Err(Error::Http(msg)) if msg.contains("Invalid proxy URL"))
+      );
+   }
+
+   #[test]
+   fn test_ensure_space() {
+      let dir = TempDir::new().unwrap();
+      assert!(ensure_space(dir.path(), 1).is_ok());
+      assert!(matches!(
+         ensure_space(dir.path(), u64::MAX),
+         Err(Error::InsufficientSpace {
+            needed: u64::MAX,
+            ..
+         })
+      ));
+   }
+
+   #[tokio::test]
+   async fn test_max_bytes_truncate_completes_with_first_bytes() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/large"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![7u8; 4096]))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "preview.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/large", server.uri()));
+      item.options.max_bytes_truncate = Some(100);
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), vec![7u8; 100]);
+      let log = fixture.events.lock().unwrap().clone();
+      let completed = log.last().unwrap();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert!(completed.truncated);
+   }
+
+   #[tokio::test]
+   async fn test_max_bytes_truncate_larger_than_resource_is_not_truncated() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/small"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"small".to_vec()))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "small.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/small", server.uri()));
+      item.options.max_bytes_truncate = Some(1024);
+      fixture.manager.store.update(item.clone()).unwrap();
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"small");
+      let log = fixture.events.lock().unwrap().clone();
+      assert!(!log.last().unwrap().truncated);
+   }
+
+   fn max_size_config(max: u64) -> DownloadManagerConfig {
+      DownloadManagerConfig {
+         max_size_bytes: Some(max),
+         ..Default::default()
+      }
+   }
+
+   /// Serves `body` once with chunked transfer encoding, i.e. without `Content-Length`,
+   /// which wiremock always sets. Returns the URL to request.
+   async fn serve_chunked(body: Vec<u8>) -> String {
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      tokio::spawn(async move {
+         let (mut socket, _) = listener.accept().await.unwrap();
+         let mut request = [0u8; 1024];
+         let _ = socket.read(&mut request).await;
+         let mut response =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n".to_vec();
+         for chunk in body.chunks(64) {
+            response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\r\n");
+         }
+         response.extend_from_slice(b"0\r\n\r\n");
+         let _ = socket.write_all(&response).await;
+      });
+      format!("http://{}/chunked", addr)
+   }
+
+   #[tokio::test]
+   async fn test_downloads_reuse_pooled_connection() {
+      use std::sync::atomic::AtomicUsize;
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      // Serves every request on a connection with a kept-alive response, counting the
+      // connections accepted.
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      let connections = Arc::new(AtomicUsize::new(0));
+      let accepted = connections.clone();
+      tokio::spawn(async move {
+         loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            accepted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+               let mut request = [0u8; 1024];
+               while socket.read(&mut request).await.is_ok_and(|read| read > 0) {
+                  let response = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\npool";
+                  if socket.write_all(response).await.is_err() {
+                     break;
+                  }
+               }
+            });
+         }
+      });
+
+      let fixture = make_fixture();
+      for name in ["a.bin", "b.bin", "c.bin"] {
+         let dest = dest_path(&fixture, name);
+         let url = format!("http://{}/{}", addr, name);
+         let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+         download(&fixture.manager, &mut item).await.unwrap();
+         assert_eq!(fs::read(&dest).unwrap(), b"pool");
+      }
+      assert_eq!(connections.load(Ordering::SeqCst), 1);
+   }
+
+   /// Returns the path of a test certificate. `ca.pem` issued `server.pem`, for
+   /// `localhost` and `127.0.0.1`, and the client identity `client.pem`.
+   fn tls_file(name: &str) -> std::path::PathBuf {
+      Path::new(env!("CARGO_MANIFEST_DIR"))
+         .join("testdata/tls")
+         .join(name)
+   }
+
+   /// Serves `body` over TLS with the test server certificate, requiring a client
+   /// certificate issued by the test CA if `client_auth` is set. Returns the URL.
+   async fn serve_tls(body: &'static [u8], client_auth: bool) -> String {
+      serve_tls_versions(body, client_auth, rustls::DEFAULT_VERSIONS).await
+   }
+
+   /// Like [`serve_tls`], speaking only the TLS `versions`.
+   async fn serve_tls_versions(
+      body: &'static [u8],
+      client_auth: bool,
+      versions: &[&'static rustls::SupportedProtocolVersion],
+   ) -> String {
+      use rustls::pki_types::pem::PemObject;
+      use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      let certs: Vec<_> = CertificateDer::pem_file_iter(tls_file("server.pem"))
+         .unwrap()
+         .map(Result::unwrap)
+         .collect();
+      let key = PrivateKeyDer::from_pem_file(tls_file("server.key")).unwrap();
+      let builder = rustls::ServerConfig::builder_with_protocol_versions(versions);
+      let builder = if client_auth {
+         let mut roots = rustls::RootCertStore::empty();
+         roots
+            .add(CertificateDer::from_pem_file(tls_file("ca.pem")).unwrap())
+            .unwrap();
+         let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .unwrap();
+         builder.with_client_cert_verifier(verifier)
+      } else {
+         builder.with_no_client_auth()
+      };
+      let config = builder.with_single_cert(certs, key).unwrap();
+      let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      tokio::spawn(async move {
+         loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+               let Ok(mut stream) = acceptor.accept(socket).await else {
+                  return;
+               };
+               let mut request = [0u8; 1024];
+               if stream.read(&mut request).await.is_ok_and(|read| read > 0) {
+                  let head = format!(
+                     "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                     body.len()
+                  );
+                  let _ = stream.write_all(head.as_bytes()).await;
+                  let _ = stream.write_all(body).await;
+                  let _ = stream.shutdown().await;
+               }
+            });
+         }
+      });
+      format!("https://127.0.0.1:{}/file", addr.port())
+   }
+
+   #[tokio::test]
+   async fn test_mutual_tls_presents_client_identity() {
+      let url = serve_tls(b"secret", true).await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         tls: TlsConfig {
+            client_identity: Some(tls_file("client.pem")),
+            root_certificates: vec![tls_file("ca.pem")],
+            ..Default::default()
+         },
+         ..Default::default()
+      });
+      let dest = dest_path(&fixture, "mtls.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"secret");
+   }
+
+   #[tokio::test]
+   async fn test_missing_client_identity_reports_tls_failure() {
+      let url = serve_tls(b"secret", true).await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         tls: TlsConfig {
+            root_certificates: vec![tls_file("ca.pem")],
+            ..Default::default()
+         },
+         ..Default::default()
+      });
+      let dest = dest_path(&fixture, "mtls.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let Err(Error::Http(message)) = download(&fixture.manager, &mut item).await else {
+         panic!("expected an HTTP error");
+      };
+      assert!(message.starts_with("TLS handshake failed"), "{}", message);
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_untrusted_certificate_is_rejected_by_default() {
+      let url = serve_tls(b"secret", false).await;
+      let fixture = make_fixture();
+      let dest = dest_path(&fixture, "untrusted.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let Err(Error::Http(message)) = download(&fixture.manager, &mut item).await else {
+         panic!("expected an HTTP error");
+      };
+      assert!(message.starts_with("TLS handshake failed"), "{}", message);
+   }
+
+   #[cfg(feature = "danger-accept-invalid-certs")]
+   #[tokio::test]
+   async fn test_danger_accept_invalid_certs_accepts_untrusted_certificate() {
+      let url = serve_tls(b"secret", false).await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         tls: TlsConfig {
+            danger_accept_invalid_certs: true,
+            ..Default::default()
+         },
+         ..Default::default()
+      });
+      let dest = dest_path(&fixture, "untrusted.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"secret");
+   }
+
+   #[tokio::test]
+   async fn test_min_tls_version_is_enforced() {
+      let url = serve_tls_versions(b"secret", false, &[&rustls::version::TLS12]).await;
+      let config = |min_version| DownloadManagerConfig {
+         tls: TlsConfig {
+            root_certificates: vec![tls_file("ca.pem")],
+            min_version: Some(min_version),
+            ..Default::default()
+         },
+         ..Default::default()
+      };
+
+      // A server speaking only TLS 1.2 is refused when TLS 1.3 is required.
+      let fixture = make_fixture_with_config(config(TlsVersion::Tls1_3));
+      let dest = dest_path(&fixture, "tls12.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      let Err(Error::Http(message)) = download(&fixture.manager, &mut item).await else {
+         panic!("expected an HTTP error");
+      };
+      assert!(message.starts_with("TLS handshake failed"), "{}", message);
+
+      let fixture = make_fixture_with_config(config(TlsVersion::Tls1_2));
+      let dest = dest_path(&fixture, "tls12.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"secret");
+   }
+
+   #[test]
+   fn test_build_client_rejects_invalid_identity() {
+      let dir = TempDir::new().unwrap();
+      let invalid = dir.path().join("identity.pem");
+      fs::write(&invalid, "not a certificate").unwrap();
+      let config = |client_identity| DownloadManagerConfig {
+         tls: TlsConfig {
+            client_identity: Some(client_identity),
+            ..Default::default()
+         },
+         ..Default::default()
+      };
+
+      assert!(matches!(
+         build_client(&config(invalid), Arc::default()),
+         Err(Error::Http(message)) if message.starts_with("Invalid client identity")
+      ));
+      assert!(matches!(
+         build_client(&config(dir.path().join("missing.pem")), Arc::default()),
+         Err(Error::File(_))
+      ));
+   }
+
+   #[tokio::test]
+   async fn test_short_body_is_not_promoted_to_final_path() {
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      // Advertises 10 bytes but closes the connection after 3.
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      tokio::spawn(async move {
+         let (mut socket, _) = listener.accept().await.unwrap();
+         let mut request = [0u8; 1024];
+         let _ = socket.read(&mut request).await;
+         let _ = socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\nabc")
+            .await;
+      });
+
+      let fixture = make_fixture();
+      let dest = dest_path(&fixture, "short.bin");
+      let url = format!("http://{}/short", addr);
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      assert!(matches!(
+         download(&fixture.manager, &mut item).await,
+         Err(Error::Http(_))
+      ));
+
+      // The partial bytes stay in the temp file so the download can resume.
+      assert!(!Path::new(&dest).exists());
+      assert_eq!(
+         fs::read(format!("{}{}", dest, DOWNLOAD_SUFFIX)).unwrap(),
+         b"abc"
+      );
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_some());
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         0
+      );
+   }
+
+   #[tokio::test]
+   async fn test_max_size_rejects_large_content_length_up_front() {
+      let fixture = make_fixture_with_config(max_size_config(100));
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/large"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 4096]))
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "large.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/large", server.uri()));
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("Exceeded max size of 100 bytes")));
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_max_size_aborts_unknown_length_body_mid_stream() {
+      let fixture = make_fixture_with_config(max_size_config(100));
+      let url = serve_chunked(vec![0u8; 4096]).await;
+
+      let dest = dest_path(&fixture, "chunked.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("Exceeded max size")));
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+   }
+
+   #[tokio::test]
+   async fn test_max_size_writes_nothing_past_limit_to_writer() {
+      let fixture = make_fixture_with_config(max_size_config(100));
+      let url = serve_chunked(vec![0u8; 4096]).await;
+
+      let mut buffer = Vec::new();
+      let err = to_writer(&fixture.manager, &url, &mut buffer, 0, |_, _| {})
+         .await
+         .unwrap_err();
+
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("Exceeded max size")));
+      assert!(buffer.len() <= 100, "{}", buffer.len());
+   }
+
+   #[tokio::test]
+   async fn test_max_size_allows_smaller_download() {
+      let fixture = make_fixture_with_config(max_size_config(100));
+      let url = serve_chunked(vec![1u8; 80]).await;
+
+      let dest = dest_path(&fixture, "chunked.bin");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), vec![1u8; 80]);
+   }
+
+   /// Polls the store until the item at `path` satisfies `done`, or gives up after 5s.
+   async fn wait_for(
+      manager: &DownloadManager,
+      path: &str,
+      done: impl Fn(Option<DownloadItem>) -> bool,
+   ) {
+      for _ in 0..100 {
+         if done(manager.store.find_by_path(path).unwrap()) {
+            return;
+         }
+         tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+      }
+      panic!("timed out waiting for {}", path);
+   }
+
+   #[tokio::test]
+   async fn test_completion_starts_queued_dependents() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"body".to_vec()))
+         .mount(&server)
+         .await;
+
+      let first = dest_path(&fixture, "first.bin");
+      let second = dest_path(&fixture, "second.bin");
+      let manager = &fixture.manager;
+      manager
+         .create(&first, &format!("{}/first", server.uri()))
+         .unwrap();
+      manager
+         .create_with_options(
+            &second,
+            &format!("{}/second", server.uri()),
+            DownloadOptions {
+               depends_on: Some(first.clone()),
+               ..Default::default()
+            },
+         )
+         .unwrap();
+
+      assert_eq!(
+         manager.start(&second).unwrap().download.status,
+         DownloadStatus::Queued
+      );
+      manager.start(&first).unwrap();
+
+      wait_for(manager, &second, |item| item.is_none()).await;
+      assert_eq!(fs::read(&second).unwrap(), b"body");
+   }
+
+   #[tokio::test]
+   async fn test_failure_fails_queued_dependents() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .respond_with(ResponseTemplate::new(404))
+         .mount(&server)
+         .await;
+
+      let first = dest_path(&fixture, "first.bin");
+      let second = dest_path(&fixture, "second.bin");
+      let manager = &fixture.manager;
+      manager
+         .create(&first, &format!("{}/first", server.uri()))
+         .unwrap();
+      manager
+         .create_with_options(
+            &second,
+            &format!("{}/second", server.uri()),
+            DownloadOptions {
+               depends_on: Some(first.clone()),
+               ..Default::default()
+            },
+         )
+         .unwrap();
+
+      manager.start(&second).unwrap();
+      manager.start(&first).unwrap();
+
+      wait_for(manager, &second, |item| {
+         item.is_some_and(|i| i.status == DownloadStatus::Failed)
+      })
+      .await;
+   }
+
+   #[test]
+   fn test_content_type_matches() {
+      assert!(content_type_matches(
+         "application/zip",
+         Some("application/zip")
+      ));
+      assert!(content_type_matches(
+         "application/zip",
+         Some("Application/ZIP; charset=binary")
+      ));
+      assert!(content_type_matches("image/", Some("image/png")));
+      assert!(!content_type_matches("image/", Some("text/html")));
+      assert!(content_type_matches("video/*", Some("video/mp4")));
+      assert!(!content_type_matches("video/*", Some("text/html")));
+      assert!(!content_type_matches(
+         "application/zip",
+         Some("application/zip-compressed")
+      ));
+      assert!(!content_type_matches("application/zip", None));
+   }
+
+   #[tokio::test]
+   async fn test_unexpected_content_type_fails_without_writing() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+
+      Mock::given(method("GET"))
+         .and(wm_path("/bundle"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_raw(b"<html>Sign in</html>".to_vec(), "text/html; charset=utf-8"),
+         )
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "bundle.zip");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/bundle", server.uri()));
+      item.options.expected_content_type = Some("application/zip".to_string());
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(
+         matches!(&err, Error::Http(msg) if msg.contains("Unexpected Content-Type 'text/html"))
+      );
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_html_error_page_is_rejected_by_allowed_content_types() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/video"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .set_body_raw(b"<html>Not found</html>".to_vec(), "text/html"),
+         )
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/clip"))
+         .respond_with(ResponseTemplate::new(200).set_body_raw(b"frames".to_vec(), "video/mp4"))
+         .mount(&server)
+         .await;
+      let allowed = vec!["video/*".to_string(), "audio/*".to_string()];
+
+      let dest = dest_path(&fixture, "video.mp4");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/video", server.uri()));
+      item.options.allowed_content_types = allowed.clone();
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(matches!(&err, Error::Http(msg)
+         if msg.contains("Unexpected Content-Type 'text/html', expected 'video/*' or 'audio/*'")));
+      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+
+      // An allowed type is downloaded, and reported on the completed item.
+      let dest = dest_path(&fixture, "clip.mp4");
+      let mut item = seed_in_progress(&fixture.manager, &dest, &format!("{}/clip", server.uri()));
+      item.options.allowed_content_types = allowed;
+      download(&fixture.manager, &mut item).await.unwrap();
+      let completed = fixture.events.lock().unwrap().last().cloned().unwrap();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.content_type.as_deref(), Some("video/mp4"));
+   }
+
+   /// Serves `body`, honoring closed byte ranges (`bytes=start-end`) only if
+   /// `honor_ranges` is set. Requests without a range get the whole body.
+   struct RangeResponder {
+      body: Vec<u8>,
+      honor_ranges: bool,
+   }
+
+   impl wiremock::Respond for RangeResponder {
+      fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+         let range = request
+            .headers
+            .get("range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes="))
+            .and_then(|value| value.split_once('-'))
+            .and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)));
+         match range {
+            Some((start, end)) if self.honor_ranges => {
+               let end: usize = end;
+               ResponseTemplate::new(206).set_body_bytes(self.body[start..=end].to_vec())
+            }
+            _ => ResponseTemplate::new(200)
+               .set_body_bytes(self.body.clone())
+               .append_header("Accept-Ranges", "bytes"),
+         }
+      }
+   }
+
+   fn segmented_body() -> Vec<u8> {
+      (0..4 * MIN_SEGMENT_SIZE).map(|i| (i % 251) as u8).collect()
+   }
+
+   #[tokio::test]
+   async fn test_segmented_download_writes_each_range() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         segments: Some(4),
+         ..Default::default()
+      });
+      let server = MockServer::start().await;
+      let body = segmented_body();
+      Mock::given(method("GET"))
+         .and(wm_path("/segmented"))
+         .respond_with(RangeResponder {
+            body: body.clone(),
+            honor_ranges: true,
+         })
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "segmented.bin");
+      let url = format!("{}/segmented", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), body);
+      assert_eq!(
+         events_with_status(&fixture.events, DownloadStatus::Completed),
+         1
+      );
+      // The initial request serves the first segment; the others are range requests.
+      let requests = server.received_requests().await.unwrap();
+      assert_eq!(requests.len(), 4);
+      assert_eq!(
+         requests
+            .iter()
+            .filter(|r| r.headers.contains_key("range"))
+            .count(),
+         3
+      );
+   }
+
+   #[tokio::test]
+   async fn test_segmented_download_keeps_contiguous_bytes_on_failure() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         segments: Some(4),
+         ..Default::default()
+      });
+      let server = MockServer::start().await;
+      let body = segmented_body();
+      Mock::given(method("GET"))
+         .and(wm_path("/segmented"))
+         .respond_with(RangeResponder {
+            body: body.clone(),
+            honor_ranges: false,
+         })
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "segmented.bin");
+      let url = format!("{}/segmented", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(matches!(&err, Error::Http(msg) if msg.contains("not honored")));
+
+      // Only bytes received without gap from the start remain, so a resume is valid.
+      let temp = fs::read(format!("{}{}", dest, DOWNLOAD_SUFFIX)).unwrap();
+      assert!(temp.len() as u64 <= MIN_SEGMENT_SIZE);
+      assert_eq!(temp, body[..temp.len()]);
+      assert!(!Path::new(&dest).exists());
+   }
+
+   #[tokio::test]
+   async fn test_segment_request_reports_blocked_redirect() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         segments: Some(4),
+         ..max_redirects_config(0)
+      });
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/segmented"))
+         .and(header_exists("range"))
+         .respond_with(
+            ResponseTemplate::new(302).append_header("Location", format!("{}/moved", server.uri())),
+         )
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/segmented"))
+         .respond_with(RangeResponder {
+            body: segmented_body(),
+            honor_ranges: true,
+         })
+         .mount(&server)
+         .await;
+
+      let dest = dest_path(&fixture, "segmented.bin");
+      let url = format!("{}/segmented", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+
+      // A segment reports why its request failed, like a single-stream download.
+      let err = download(&fixture.manager, &mut item).await.unwrap_err();
+      assert!(
+         matches!(&err, Error::Http(msg) if msg.starts_with("Redirect to") && msg.ends_with("redirects are disabled")),
+         "{}",
+         err
+      );
+   }
+
+   #[test]
+   fn test_progress_throttle_threshold() {
+      let config = ProgressConfig {
+         threshold: 5.0,
+         interval_ms: None,
+         heartbeat_ms: None,
+      };
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      assert!(!throttle.should_emit(5.0, 5, true));
+      assert!(throttle.should_emit(5.5, 6, true));
+      assert!(!throttle.should_emit(10.0, 10, true));
+      assert!(throttle.should_emit(100.0, 100, true));
+
+      // Unknown size falls back to the byte threshold.
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      assert!(!throttle.should_emit(0.0, BYTES_THRESHOLD - 1, false));
+      assert!(throttle.should_emit(0.0, BYTES_THRESHOLD, false));
+   }
+
+   #[test]
+   fn test_progress_throttle_interval() {
+      let config = ProgressConfig {
+         threshold: 0.0,
+         interval_ms: Some(60_000),
+         heartbeat_ms: None,
+      };
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      // The first update is due; later ones wait for the interval, whatever the size.
+      assert!(throttle.should_emit(1.0, 1, true));
+      assert!(!throttle.should_emit(50.0, 50, true));
+      assert!(!throttle.should_emit(0.0, 10, false));
+      // Completion is never throttled.
+      assert!(throttle.should_emit(100.0, 100, true));
+
+      let config = ProgressConfig {
+         threshold: 0.0,
+         interval_ms: Some(0),
+         heartbeat_ms: None,
+      };
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      assert!(throttle.should_emit(0.0, 1, false));
+      assert!(!throttle.should_emit(0.0, 1, false));
+   }
+
+   #[test]
+   fn test_progress_throttle_heartbeat() {
+      let config = ProgressConfig {
+         threshold: 50.0,
+         interval_ms: None,
+         heartbeat_ms: Some(0),
+      };
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      // Below the threshold, but bytes arrived since the last event.
+      assert!(throttle.should_emit(0.1, 1, true));
+      // No new bytes: a stalled download doesn't beat.
+      assert!(!throttle.should_emit(0.1, 1, true));
+      assert!(throttle.should_emit(0.2, 2, true));
+
+      let config = ProgressConfig {
+         heartbeat_ms: Some(60_000),
+         ..config
+      };
+      let mut throttle = ProgressThrottle::new(&config, 0.0, 0);
+      assert!(!throttle.should_emit(0.1, 1, true));
+      assert!(!throttle.should_emit(0.0, 10, false));
+   }
+
+   #[test]
+   fn test_request_headers_item_overrides_defaults() {
+      let config = DownloadManagerConfig {
+         user_agent: Some("MyApp/1.0".to_string()),
+         headers: [("authorization", "Bearer default"), ("x-client", "desktop")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+         ..Default::default()
+      };
+      let mut options = DownloadOptions::default();
+      options
+         .headers
+         .insert("Authorization".to_string(), "Bearer item".to_string());
 
-      // Exactly one Completed event; no duplicate 100% progress event.
-      assert_eq!(
-         events_with_status(&fixture.events, DownloadStatus::Completed),
-         1
-      );
+      let headers = request_headers(&config, &options).unwrap();
+      assert_eq!(headers[USER_AGENT], "MyApp/1.0");
+      assert_eq!(headers["authorization"], "Bearer item");
+      assert_eq!(headers["x-client"], "desktop");
+      assert_eq!(headers[ACCEPT_ENCODING], "identity");
+      assert_eq!(headers.len(), 4);
    }
 
    #[tokio::test]
-   async fn test_completes_without_content_length() {
-      // Regression: when the server omits Content-Length, total_size is 0
-      // and progress stays at 0.0. Completion must still trigger when the
-      // stream ends naturally.
-      let fixture = make_fixture();
+   async fn test_sends_cookies_and_keeps_those_set_by_the_server() {
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         progress: ProgressConfig {
+            threshold: 0.0,
+            interval_ms: None,
+            heartbeat_ms: None,
+         },
+         ..Default::default()
+      });
       let server = MockServer::start().await;
-      let body = b"streamed body with no length header".to_vec();
-
       Mock::given(method("GET"))
-         .and(wm_path("/stream"))
+         .and(wm_path("/first"))
+         .and(header("cookie", "session=old"))
          .respond_with(
             ResponseTemplate::new(200)
-               .set_body_bytes(body.clone())
-               .append_header("Transfer-Encoding", "chunked"),
+               .insert_header("set-cookie", "session=new; Path=/")
+               .set_body_bytes(vec![0u8; 256 * 1024]),
          )
          .mount(&server)
          .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/second"))
+         .and(header("cookie", "session=new"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"second".to_vec()))
+         .mount(&server)
+         .await;
 
-      let dest = dest_path(&fixture, "stream.bin");
-      let url = format!("{}/stream", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
-
-      download(&fixture.manager, item).await.unwrap();
+      let first_url = format!("{}/first", server.uri());
+      let cookies = [("session".to_string(), "old".to_string())].into();
+      fixture.manager.add_cookies(&first_url, &cookies);
+      let dest = dest_path(&fixture, "cookies.bin");
+      let mut item = DownloadItem {
+         options: DownloadOptions {
+            cookies,
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &first_url)
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
 
-      assert_eq!(fs::read(&dest).unwrap(), body);
-      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
-      assert_eq!(
-         events_with_status(&fixture.events, DownloadStatus::Completed),
-         1
+      // The session the server set is sent with later requests to its host.
+      let second = dest_path(&fixture, "second.bin");
+      let mut second_item = seed_in_progress(
+         &fixture.manager,
+         &second,
+         &format!("{}/second", server.uri()),
       );
+      download(&fixture.manager, &mut second_item).await.unwrap();
+      assert_eq!(fs::read(&second).unwrap(), b"second");
+
+      // Cookie values are neither persisted nor emitted.
+      let log = fixture.events.lock().unwrap().clone();
+      assert!(log.iter().all(|e| {
+         let value = serde_json::to_value(e).unwrap();
+         value["options"].get("cookies").is_none()
+      }));
+      assert_eq!(log.last().unwrap().status, DownloadStatus::Completed);
    }
 
+   #[cfg(unix)]
    #[tokio::test]
-   async fn test_resume_appends_to_temp_file() {
+   async fn test_mode_is_set_on_completion() {
+      use std::os::unix::fs::PermissionsExt;
+
       let fixture = make_fixture();
       let server = MockServer::start().await;
-
-      // Pre-existing temp file with the first half of the body.
-      let dest = dest_path(&fixture, "resume.bin");
-      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
-      let first_half = b"first-half-";
-      let second_half = b"second-half";
-      fs::write(&temp_path, first_half).unwrap();
-
-      // Server expects a Range request and returns 206 with only the second half.
       Mock::given(method("GET"))
-         .and(wm_path("/resume"))
-         .and(header(
-            "range",
-            format!("bytes={}-", first_half.len()).as_str(),
-         ))
-         .respond_with(ResponseTemplate::new(206).set_body_bytes(second_half.to_vec()))
+         .and(wm_path("/helper"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"#!/bin/sh".to_vec()))
          .mount(&server)
          .await;
 
-      let url = format!("{}/resume", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
-
-      download(&fixture.manager, item).await.unwrap();
+      let dest = dest_path(&fixture, "helper");
+      let mut item = DownloadItem {
+         options: DownloadOptions {
+            mode: Some(0o750),
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &format!("{}/helper", server.uri()))
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
 
-      let combined = [first_half.as_slice(), second_half.as_slice()].concat();
-      assert_eq!(fs::read(&dest).unwrap(), combined);
+      assert_eq!(item.status, DownloadStatus::Completed);
+      let mode = fs::metadata(&dest).unwrap().permissions().mode();
+      assert_eq!(mode & 0o7777, 0o750);
    }
 
    #[tokio::test]
-   async fn test_resume_restarts_from_zero_when_server_returns_200() {
-      // When the server ignores the Range header and returns 200 with the
-      // full body, the downloader discards the stale temp file and restarts
-      // from zero rather than erroring.
+   async fn test_if_none_match_keeps_unchanged_file() {
       let fixture = make_fixture();
       let server = MockServer::start().await;
-
-      let dest = dest_path(&fixture, "fallback.bin");
-      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
-      fs::write(&temp_path, b"stale partial bytes").unwrap();
-
-      let full_body = b"full body content";
       Mock::given(method("GET"))
-         .and(wm_path("/fallback"))
-         .respond_with(ResponseTemplate::new(200).set_body_bytes(full_body.to_vec()))
+         .and(wm_path("/file"))
+         .and(header("if-none-match", "\"v1\""))
+         .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"v1\""))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .insert_header("etag", "\"v2\"")
+               .set_body_bytes(b"changed".to_vec()),
+         )
          .mount(&server)
          .await;
 
-      let url = format!("{}/fallback", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
+      let dest = dest_path(&fixture, "unchanged.bin");
+      fs::write(&dest, b"current").unwrap();
+      let mut item = DownloadItem {
+         options: DownloadOptions {
+            if_none_match: Some("\"v1\"".to_string()),
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()))
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
 
-      download(&fixture.manager, item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"current");
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
+      let completed = fixture.events.lock().unwrap().last().unwrap().clone();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.etag.as_deref(), Some("\"v1\""));
+      assert_eq!(completed.downloaded_bytes, 7);
 
-      // Final file is the full body, not partial + full.
-      assert_eq!(fs::read(&dest).unwrap(), full_body);
-      // Temp file has been cleaned up.
-      assert!(!Path::new(&temp_path).exists());
+      // A changed resource is downloaded again, and its new ETag reported.
+      let mut item = DownloadItem {
+         options: DownloadOptions {
+            if_none_match: Some("\"v0\"".to_string()),
+            ..Default::default()
+         },
+         ..seed_in_progress(&fixture.manager, &dest, &format!("{}/file", server.uri()))
+      };
+      fixture.manager.store.update(item.clone()).unwrap();
+      download(&fixture.manager, &mut item).await.unwrap();
+
+      assert_eq!(fs::read(&dest).unwrap(), b"changed");
+      let completed = fixture.events.lock().unwrap().last().unwrap().clone();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.etag.as_deref(), Some("\"v2\""));
    }
 
    #[tokio::test]
-   async fn test_http_error_returns_err_and_creates_no_file() {
+   async fn test_if_none_match_defaults_to_etag_of_previous_download() {
       let fixture = make_fixture();
       let server = MockServer::start().await;
-
       Mock::given(method("GET"))
-         .and(wm_path("/missing"))
-         .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+         .and(wm_path("/file"))
+         .and(header("if-none-match", "\"v1\""))
+         .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"v1\""))
+         .expect(1)
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .insert_header("etag", "\"v1\"")
+               .set_body_bytes(b"current".to_vec()),
+         )
+         .expect(1)
          .mount(&server)
          .await;
 
-      let dest = dest_path(&fixture, "missing.bin");
-      let url = format!("{}/missing", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
+      // The completed download leaves the store, but its ETag is kept for the path.
+      let dest = dest_path(&fixture, "refreshed.bin");
+      let url = format!("{}/file", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert!(fixture.manager.store.find_by_path(&dest).unwrap().is_none());
 
-      let err = download(&fixture.manager, item).await.unwrap_err();
-      match err {
-         Error::Http(msg) => assert!(msg.contains("404"), "expected status in message: {}", msg),
-         other => panic!("expected Error::Http, got {:?}", other),
-      }
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
+      download(&fixture.manager, &mut item).await.unwrap();
 
-      // No file is created at the destination on HTTP error.
-      assert!(!Path::new(&dest).exists());
-      // No temp file is created either (we error before opening it).
-      assert!(!Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
+      assert_eq!(fs::read(&dest).unwrap(), b"current");
+      let completed = fixture.events.lock().unwrap().last().unwrap().clone();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.etag.as_deref(), Some("\"v1\""));
    }
 
    #[tokio::test]
-   async fn test_creates_output_folder_when_missing() {
-      let fixture = make_fixture();
+   async fn test_sends_user_agent_and_default_headers() {
       let server = MockServer::start().await;
-
       Mock::given(method("GET"))
-         .and(wm_path("/nested"))
-         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"data".to_vec()))
+         .and(wm_path("/file"))
+         .and(header("user-agent", "MyApp/1.0"))
+         .and(header("x-api-key", "abc"))
+         .and(header("range", "bytes=4-"))
+         .respond_with(ResponseTemplate::new(206).set_body_bytes(b"rest".to_vec()))
          .mount(&server)
          .await;
+      let fixture = make_fixture_with_config(DownloadManagerConfig {
+         user_agent: Some("MyApp/1.0".to_string()),
+         headers: [("X-Api-Key".to_string(), "abc".to_string())].into(),
+         ..Default::default()
+      });
 
-      // Use a nested subdir that does not yet exist.
-      let dest = fixture
-         ._dir
-         .path()
-         .join("a/b/c/file.bin")
-         .to_string_lossy()
-         .into_owned();
-      let url = format!("{}/nested", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
-
-      download(&fixture.manager, item).await.unwrap();
+      let dest = dest_path(&fixture, "headers.bin");
+      fs::write(format!("{}{}", dest, DOWNLOAD_SUFFIX), b"head").unwrap();
+      let url = format!("{}/file", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
 
-      assert_eq!(fs::read(&dest).unwrap(), b"data");
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"headrest");
    }
 
    #[tokio::test]
-   async fn test_unknown_size_emits_progress_at_byte_threshold() {
-      // Body larger than BYTES_THRESHOLD (1 MiB) ensures at least one
-      // progress event fires for the unknown-size path before completion.
-      let fixture = make_fixture();
+   async fn test_sends_requests_with_injected_client() {
       let server = MockServer::start().await;
-      let body = vec![0u8; (1024 * 1024) + 1024]; // 1 MiB + 1 KiB
+      Mock::given(method("GET"))
+         .and(wm_path("/file"))
+         .and(header("x-app-client", "1"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"injected".to_vec()))
+         .expect(2)
+         .mount(&server)
+         .await;
+      let client = reqwest::Client::builder()
+         .default_headers(
+            [(
+               HeaderName::from_static("x-app-client"),
+               HeaderValue::from_static("1"),
+            )]
+            .into_iter()
+            .collect(),
+         )
+         .build()
+         .unwrap();
+      let fixture = make_fixture();
+      let manager = fixture.manager.clone().with_client(client);
+
+      // The client is reused by every download.
+      for name in ["a.bin", "b.bin"] {
+         let dest = dest_path(&fixture, name);
+         let mut item = seed_in_progress(&manager, &dest, &format!("{}/file", server.uri()));
+         download(&manager, &mut item).await.unwrap();
+         assert_eq!(fs::read(&dest).unwrap(), b"injected");
+      }
+   }
 
+   #[tokio::test]
+   async fn test_decompresses_gzip_response() {
+      // `gzip` of "hello, decompressed world!", sent despite `Accept-Encoding: identity`.
+      let gzipped = vec![
+         0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+         0xd7, 0x51, 0x48, 0x49, 0x4d, 0xce, 0xcf, 0x2d, 0x28, 0x4a, 0x2d, 0x2e, 0x4e, 0x4d, 0x51,
+         0x28, 0xcf, 0x2f, 0xca, 0x49, 0x51, 0x04, 0x00, 0x92, 0x25, 0x57, 0xbb, 0x1a, 0x00, 0x00,
+         0x00,
+      ];
+      let server = MockServer::start().await;
       Mock::given(method("GET"))
-         .and(wm_path("/big"))
+         .and(wm_path("/file"))
+         .and(header("accept-encoding", "identity"))
          .respond_with(
             ResponseTemplate::new(200)
-               .set_body_bytes(body.clone())
-               .append_header("Transfer-Encoding", "chunked"),
+               .insert_header("Content-Encoding", "gzip")
+               .set_body_bytes(gzipped),
          )
          .mount(&server)
          .await;
+      let fixture = make_fixture();
 
-      let dest = dest_path(&fixture, "big.bin");
-      let url = format!("{}/big", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
-
-      download(&fixture.manager, item).await.unwrap();
+      let dest = dest_path(&fixture, "gzip.txt");
+      let url = format!("{}/file", server.uri());
+      let mut item = seed_in_progress(&fixture.manager, &dest, &url);
 
-      // At least one InProgress progress event with progress == 0.0 (unknown size),
-      // plus the final Completed event.
-      let log = fixture.events.lock().unwrap().clone();
-      assert!(
-         log.iter()
-            .any(|e| e.status == DownloadStatus::InProgress && e.progress == 0.0),
-         "expected at least one InProgress(0.0) progress event for unknown size"
-      );
+      download(&fixture.manager, &mut item).await.unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"hello, decompressed world!");
       assert_eq!(
          events_with_status(&fixture.events, DownloadStatus::Completed),
          1
       );
    }
 
+   #[test]
+   fn test_accepts_ranges() {
+      let mut headers = HeaderMap::new();
+      assert!(!accepts_ranges(&headers));
+      headers.insert(ACCEPT_RANGES, "none".parse().unwrap());
+      assert!(!accepts_ranges(&headers));
+      headers.insert(ACCEPT_RANGES, "Bytes".parse().unwrap());
+      assert!(accepts_ranges(&headers));
+   }
+
    #[tokio::test]
-   async fn test_pause_mid_stream_stops_and_preserves_temp_file() {
-      // Flipping the status to Paused while the body is still streaming must
-      // stop reading, skip completion, and leave the .download temp file intact
-      // so the download can later resume. The body exceeds BYTES_THRESHOLD so the
-      // in-loop throttle checkpoint — where the status is re-read from the store —
-      // is reached while data still remains, exercising the in-loop transition.
-      let dir = TempDir::new().unwrap();
-      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+   async fn test_probe_reads_head_response() {
+      let fixture = make_fixture();
+      let server = MockServer::start().await;
+      Mock::given(method("HEAD"))
+         .and(wm_path("/files/42"))
+         .respond_with(
+            ResponseTemplate::new(200)
+               .insert_header("Content-Length", "1234")
+               .insert_header("Accept-Ranges", "bytes")
+               .insert_header("Content-Type", "application/zip")
+               .insert_header("Content-Disposition", "attachment; filename=\"report.zip\""),
+         )
+         .expect(1)
+         .mount(&server)
+         .await;
 
-      // The callback flips the store status to Paused on the first in-progress
-      // checkpoint, simulating a concurrent `pause()`. The store is Arc-backed,
-      // so this clone shares state with the store `download()` reads from. The
-      // cell defers capturing the store until after the manager is constructed.
-      let store_cell: Arc<Mutex<Option<DownloadStore>>> = Arc::new(Mutex::new(None));
-      let captured = events.clone();
-      let cell = store_cell.clone();
-      let on_changed: OnChanged = Arc::new(move |item: DownloadItem| {
-         captured.lock().unwrap().push(item.clone());
-         if item.status == DownloadStatus::InProgress
-            && let Some(store) = cell.lock().unwrap().as_ref()
-         {
-            store
-               .update_no_persist(item.with_status(DownloadStatus::Paused))
-               .unwrap();
+      let probed = fixture
+         .manager
+         .probe(&format!("{}/files/42", server.uri()))
+         .await
+         .unwrap();
+      assert_eq!(
+         probed,
+         ProbeResult {
+            size: Some(1234),
+            accepts_ranges: true,
+            content_type: Some("application/zip".to_string()),
+            filename: Some("report.zip".to_string()),
          }
-      });
-
-      let manager = DownloadManager::new(dir.path().to_path_buf(), on_changed);
-      *store_cell.lock().unwrap() = Some(manager.store.clone());
+      );
+   }
 
+   #[tokio::test]
+   async fn test_probe_falls_back_to_range_request_when_head_is_refused() {
+      let fixture = make_fixture();
       let server = MockServer::start().await;
-      // Several times BYTES_THRESHOLD with unknown size, so the body is delivered
-      // over multiple stream chunks and several throttle checkpoints are reached.
-      let body = vec![0u8; 4 * 1024 * 1024];
+      Mock::given(method("HEAD"))
+         .respond_with(ResponseTemplate::new(405))
+         .mount(&server)
+         .await;
       Mock::given(method("GET"))
-         .and(wm_path("/pause"))
+         .and(header("Range", "bytes=0-0"))
          .respond_with(
-            ResponseTemplate::new(200)
-               .set_body_bytes(body.clone())
-               .append_header("Transfer-Encoding", "chunked"),
+            ResponseTemplate::new(206)
+               .insert_header("Content-Range", "bytes 0-0/5000")
+               .set_body_bytes(b"x".to_vec()),
          )
+         .expect(1)
          .mount(&server)
          .await;
 
-      let dest = dir.path().join("pause.bin").to_string_lossy().into_owned();
-      let url = format!("{}/pause", server.uri());
-      let item = seed_in_progress(&manager, &dest, &url);
-
-      download(&manager, item).await.unwrap();
-
-      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
-      // At least one in-progress event fired before the pause took effect.
-      assert!(
-         events_with_status(&events, DownloadStatus::InProgress) >= 1,
-         "expected at least one InProgress event before the pause"
-      );
-      // Stopped gracefully: temp file preserved, final file never created.
-      assert!(
-         Path::new(&temp_path).exists(),
-         "temp file should be preserved for resume"
-      );
-      assert!(
-         !Path::new(&dest).exists(),
-         "final file should not be created when paused mid-stream"
-      );
-      // No completion was emitted and the store entry survives for resume.
-      assert_eq!(events_with_status(&events, DownloadStatus::Completed), 0);
-      assert!(manager.store.find_by_path(&dest).unwrap().is_some());
+      let probed = fixture
+         .manager
+         .probe(&format!("{}/video.mp4", server.uri()))
+         .await
+         .unwrap();
+      assert_eq!(probed.size, Some(5000));
+      assert!(probed.accepts_ranges);
+      assert_eq!(probed.filename.as_deref(), Some("video.mp4"));
    }
 
    #[tokio::test]
-   async fn test_rename_failure_keeps_store_entry_and_temp_file() {
-      // If the final rename fails, the store entry must survive (still InProgress, so
-      // the caller's error handler can revert it to a resumable state) and the temp
-      // file must be left intact. The download must not silently vanish.
+   async fn test_probe_without_range_support() {
       let fixture = make_fixture();
       let server = MockServer::start().await;
-
+      Mock::given(method("HEAD"))
+         .respond_with(ResponseTemplate::new(405))
+         .mount(&server)
+         .await;
+      // The range is ignored: the whole resource is returned.
       Mock::given(method("GET"))
-         .and(wm_path("/rename-fail"))
-         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"complete body".to_vec()))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 300]))
          .mount(&server)
          .await;
 
-      // Make the destination an existing directory so `fs::rename` cannot replace it
-      // (and so the Windows pre-delete `remove_file` also fails on a directory).
-      let dest = dest_path(&fixture, "occupied.bin");
-      fs::create_dir(&dest).unwrap();
-
-      let url = format!("{}/rename-fail", server.uri());
-      let item = seed_in_progress(&fixture.manager, &dest, &url);
+      let probed = fixture
+         .manager
+         .probe(&format!("{}/file.bin", server.uri()))
+         .await
+         .unwrap();
+      assert_eq!(probed.size, Some(300));
+      assert!(!probed.accepts_ranges);
 
-      let err = download(&fixture.manager, item).await.unwrap_err();
+      // Neither request succeeds.
+      let missing = MockServer::start().await;
+      let err = fixture
+         .manager
+         .probe(&format!("{}/missing.bin", missing.uri()))
+         .await
+         .unwrap_err();
+      assert!(err.to_string().contains("HTTP 404"));
       assert!(
-         matches!(err, Error::File(_)),
-         "expected Error::File with context, got {:?}",
-         err
-      );
-
-      // Store entry survives and is still InProgress, ready to be reverted/resumed.
-      let stored = fixture.manager.store.find_by_path(&dest).unwrap().unwrap();
-      assert_eq!(stored.status, DownloadStatus::InProgress);
-      // Temp file is left intact rather than orphaned and forgotten.
-      assert!(Path::new(&format!("{}{}", dest, DOWNLOAD_SUFFIX)).exists());
-      // No completion event was emitted.
-      assert_eq!(
-         events_with_status(&fixture.events, DownloadStatus::Completed),
-         0
+         fixture
+            .manager
+            .probe("ftp://example.com/file.bin")
+            .await
+            .is_err()
       );
    }
 }
@@ -16,25 +16,89 @@ pub enum Error {
    #[error("File Error: {0}")]
    File(String),
 
+   /// Writing a download failed in a way the user can fix, e.g. a full disk. The bytes
+   /// written so far are kept, so the download can resume.
+   #[error("Write Error: {0}")]
+   Write(String),
+
    #[error("HTTP Error: {0}")]
    Http(String),
 
-   #[error("URL Error: {0}")]
-   Url(String),
+   #[error("URL Error: {1}")]
+   Url(UrlError, String),
+
+   #[error("Path Error: {1}")]
+   Path(PathError, String),
+
+   #[error("Download Failed: {0}")]
+   Failed(String),
+
+   #[error("Duplicate URL: {0}")]
+   DuplicateUrl(String),
 
-   #[error("Path Error: {0}")]
-   Path(String),
+   #[error("Insufficient Space: {needed} bytes needed, {available} bytes available")]
+   InsufficientSpace { needed: u64, available: u64 },
 
    #[error(transparent)]
    Io(#[from] std::io::Error),
 }
 
+/// Why a download path or directory was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PathError {
+   /// The path is empty.
+   Empty,
+   /// The path is relative.
+   NotAbsolute,
+   /// The path has no filename, e.g. `/`.
+   NoFilename,
+   /// The path is outside the configured download root.
+   OutsideRoot,
+   /// The configured download root can't be resolved.
+   InvalidRoot,
+   /// The path is used by another download of the same batch.
+   Duplicate,
+   /// The download is its own prerequisite.
+   SelfDependency,
+}
+
+/// Why a download URL was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UrlError {
+   /// The URL is empty.
+   Empty,
+   /// The URL can't be parsed.
+   Malformed,
+   /// The scheme is neither `http` nor `https`.
+   BadScheme,
+   /// The URL has no host.
+   NoHost,
+   /// The host is not allowed by the configured host policy.
+   HostNotAllowed,
+}
+
+/// A validation error with the code of its reason, serialized as `{ code, message }`.
+#[derive(Serialize)]
+struct CodedError<'a, C> {
+   code: &'a C,
+   message: String,
+}
+
+/// Validation errors are serialized as `{ code, message }` so that a frontend can tell
+/// them apart, any other error as its message.
 impl Serialize for Error {
    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
    where
       S: Serializer,
    {
-      serializer.serialize_str(self.to_string().as_ref())
+      let message = self.to_string();
+      match self {
+         Error::Path(code, _) => CodedError { code, message }.serialize(serializer),
+         Error::Url(code, _) => CodedError { code, message }.serialize(serializer),
+         _ => serializer.serialize_str(&message),
+      }
    }
 }
 
@@ -57,10 +121,30 @@ mod tests {
          Error::File("denied".to_string()).to_string(),
          "File Error: denied"
       );
+      assert_eq!(
+         Error::Write("disk full".to_string()).to_string(),
+         "Write Error: disk full"
+      );
       assert_eq!(
          Error::Http("timeout".to_string()).to_string(),
          "HTTP Error: timeout"
       );
+      assert_eq!(
+         Error::Failed("canceled".to_string()).to_string(),
+         "Download Failed: canceled"
+      );
+      assert_eq!(
+         Error::DuplicateUrl("https://example.com/a.zip".to_string()).to_string(),
+         "Duplicate URL: https://example.com/a.zip"
+      );
+      assert_eq!(
+         Error::InsufficientSpace {
+            needed: 10,
+            available: 5
+         }
+         .to_string(),
+         "Insufficient Space: 10 bytes needed, 5 bytes available"
+      );
    }
 
    #[test]
@@ -70,6 +154,28 @@ mod tests {
       assert_eq!(json, "\"HTTP Error: connection failed\"");
    }
 
+   #[test]
+   fn test_validation_error_serialize() {
+      let e = Error::Url(
+         UrlError::BadScheme,
+         "Invalid URL scheme 'ftp': must be http or https".to_string(),
+      );
+      assert_eq!(
+         e.to_string(),
+         "URL Error: Invalid URL scheme 'ftp': must be http or https"
+      );
+      assert_eq!(
+         serde_json::to_string(&e).unwrap(),
+         r#"{"code":"badScheme","message":"URL Error: Invalid URL scheme 'ftp': must be http or https"}"#
+      );
+
+      let e = Error::Path(PathError::NotAbsolute, "path must be absolute".to_string());
+      assert_eq!(
+         serde_json::to_string(&e).unwrap(),
+         r#"{"code":"notAbsolute","message":"Path Error: path must be absolute"}"#
+      );
+   }
+
    #[test]
    fn test_error_io_from() {
       let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
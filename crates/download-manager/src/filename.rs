@@ -0,0 +1,170 @@
+use percent_encoding::percent_decode_str;
+use reqwest::header::{CONTENT_DISPOSITION, HeaderMap};
+use std::path::Path;
+
+/// Fallback filename used when neither the response nor the URL provide one.
+pub(crate) static DEFAULT_FILENAME: &str = "download";
+
+/// Extracts the filename from a `Content-Disposition` header.
+///
+/// Prefers the RFC 6266 `filename*` parameter (percent-encoded, with a charset prefix)
+/// over the plain `filename` parameter. The result is sanitized to a single path
+/// component.
+pub(crate) fn from_content_disposition(headers: &HeaderMap) -> Option<String> {
+   let value = headers.get(CONTENT_DISPOSITION)?.to_str().ok()?;
+
+   let mut filename = None;
+   let mut filename_ext = None;
+   for param in value.split(';').skip(1) {
+      let Some((key, val)) = param.split_once('=') else {
+         continue;
+      };
+      let val = val.trim();
+      match key.trim().to_ascii_lowercase().as_str() {
+         "filename" => filename = Some(val.trim_matches('"').to_string()),
+         "filename*" => {
+            // e.g. `UTF-8''na%C3%AFve%20file.txt`
+            let encoded = val.split_once("''").map_or(val, |(_, encoded)| encoded);
+            filename_ext = percent_decode_str(encoded.trim_matches('"'))
+               .decode_utf8()
+               .ok()
+               .map(|decoded| decoded.into_owned());
+         }
+         _ => {}
+      }
+   }
+
+   filename_ext
+      .and_then(|name| sanitize(&name))
+      .or_else(|| filename.and_then(|name| sanitize(&name)))
+}
+
+/// Extracts the filename from the last path segment of a URL.
+pub(crate) fn from_url(url: &str) -> Option<String> {
+   let parsed = url::Url::parse(url).ok()?;
+   let segment = parsed.path_segments()?.next_back()?;
+   let decoded = percent_decode_str(segment).decode_utf8().ok()?;
+   sanitize(&decoded)
+}
+
+/// Reduces a server- or URL-provided name to a safe, single path component.
+///
+/// Strips any directory components (so `../../etc/passwd` becomes `passwd`) and control
+/// characters, and rejects names that are empty or only dots.
+pub(crate) fn sanitize(name: &str) -> Option<String> {
+   let last = name.rsplit(['/', '\\']).next().unwrap_or(name);
+   let cleaned: String = last.chars().filter(|c| !c.is_control()).collect();
+   let cleaned = cleaned.trim();
+
+   if cleaned.is_empty() || cleaned.chars().all(|c| c == '.') {
+      return None;
+   }
+   Some(cleaned.to_string())
+}
+
+/// Returns `path` with ` (n)` appended to the file stem, e.g. `report (2).pdf`.
+pub(crate) fn numbered(path: &str, n: u32) -> String {
+   let path = Path::new(path);
+   let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+   let name = match path.extension() {
+      Some(extension) => format!("{} ({}).{}", stem, n, extension.to_string_lossy()),
+      None => format!("{} ({})", stem, n),
+   };
+   path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use reqwest::header::HeaderValue;
+
+   fn disposition(value: &str) -> HeaderMap {
+      let mut headers = HeaderMap::new();
+      headers.insert(CONTENT_DISPOSITION, HeaderValue::from_str(value).unwrap());
+      headers
+   }
+
+   #[test]
+   fn test_content_disposition_quoted_filename() {
+      let headers = disposition("attachment; filename=\"report.pdf\"");
+      assert_eq!(
+         from_content_disposition(&headers).as_deref(),
+         Some("report.pdf")
+      );
+   }
+
+   #[test]
+   fn test_content_disposition_unquoted_filename() {
+      let headers = disposition("attachment; filename=report.pdf");
+      assert_eq!(
+         from_content_disposition(&headers).as_deref(),
+         Some("report.pdf")
+      );
+   }
+
+   #[test]
+   fn test_content_disposition_prefers_extended_filename() {
+      let headers = disposition(
+         "attachment; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve%20file.txt",
+      );
+      assert_eq!(
+         from_content_disposition(&headers).as_deref(),
+         Some("naïve file.txt")
+      );
+   }
+
+   #[test]
+   fn test_content_disposition_strips_directories() {
+      let headers = disposition("attachment; filename=\"../../etc/passwd\"");
+      assert_eq!(
+         from_content_disposition(&headers).as_deref(),
+         Some("passwd")
+      );
+   }
+
+   #[test]
+   fn test_content_disposition_missing_or_empty() {
+      assert_eq!(from_content_disposition(&HeaderMap::new()), None);
+      assert_eq!(from_content_disposition(&disposition("inline")), None);
+      assert_eq!(
+         from_content_disposition(&disposition("attachment; filename=\"..\"")),
+         None
+      );
+   }
+
+   #[test]
+   fn test_from_url() {
+      assert_eq!(
+         from_url("https://example.com/files/video%20clip.mp4?token=abc").as_deref(),
+         Some("video clip.mp4")
+      );
+      assert_eq!(
+         from_url("https://example.com/download?id=123").as_deref(),
+         Some("download")
+      );
+      assert_eq!(from_url("https://example.com/"), None);
+      assert_eq!(from_url("https://example.com"), None);
+   }
+
+   #[test]
+   fn test_numbered() {
+      assert_eq!(
+         numbered("/downloads/report.pdf", 1),
+         "/downloads/report (1).pdf"
+      );
+      assert_eq!(
+         numbered("/downloads/archive.tar.gz", 2),
+         "/downloads/archive.tar (2).gz"
+      );
+      assert_eq!(numbered("/downloads/README", 3), "/downloads/README (3)");
+   }
+
+   #[test]
+   fn test_sanitize() {
+      assert_eq!(sanitize("file.txt").as_deref(), Some("file.txt"));
+      assert_eq!(sanitize("C:\\temp\\file.txt").as_deref(), Some("file.txt"));
+      assert_eq!(sanitize("bad\u{0}name").as_deref(), Some("badname"));
+      assert_eq!(sanitize("   "), None);
+      assert_eq!(sanitize("."), None);
+   }
+}
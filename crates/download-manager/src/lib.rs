@@ -1,10 +1,27 @@
+mod config;
 mod downloader;
 mod error;
+mod filename;
 mod manager;
+mod metering;
 mod models;
+mod reveal;
 mod store;
 mod validate;
 
-pub use error::{Error, Result};
-pub use manager::{DownloadManager, OnChanged};
-pub use models::{DownloadActionResponse, DownloadItem, DownloadStatus};
+pub use config::{
+   CrossHostRedirects, DownloadManagerConfig, DuplicateUrlPolicy, HostPolicy, PartialFilePolicy,
+   ProgressConfig, ProxyConfig, REDACTED, RedirectConfig, TlsConfig, TlsVersion,
+};
+pub use error::{Error, PathError, Result, UrlError};
+pub use manager::{
+   DownloadManager, DownloadObserver, OnChanged, OnCompleted, OnEvent, TokenProvider,
+};
+pub use models::{
+   AggregateProgress, BatchActionResult, BatchCreateItem, BatchCreateResult, DownloadAction,
+   DownloadActionResponse, DownloadEvent, DownloadItem, DownloadOptions, DownloadStats,
+   DownloadStatus, OverwritePolicy, ProbeResult, ReadySummary, ResetSummary,
+};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+pub use store::{DownloadStore, InMemoryStore, Store};
@@ -1,24 +1,125 @@
+use futures::Stream;
+use futures::future::BoxFuture;
+use reqwest::cookie::Jar;
+use reqwest_middleware::ClientWithMiddleware;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite};
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
-use crate::Error;
+use crate::config::{DownloadManagerConfig, DuplicateUrlPolicy, PartialFilePolicy};
 use crate::downloader;
+use crate::error::{Error, PathError};
+use crate::filename;
+use crate::metering;
 use crate::models::*;
-use crate::store::DownloadStore;
+use crate::reveal;
+use crate::store::{DownloadStore, Store};
 use crate::validate;
 
+/// Suffix of temp files unless [`temp_suffix`](DownloadManagerConfig::temp_suffix) is set.
 pub(crate) static DOWNLOAD_SUFFIX: &str = ".download";
 
+/// How often the connection is checked for metering, if
+/// [`pause_on_metered`](DownloadManagerConfig::pause_on_metered) is set.
+const METERED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of changes kept for a [`subscribe`](DownloadManager::subscribe) stream that
+/// falls behind, beyond which the oldest are skipped.
+const CHANGES_CAPACITY: usize = 256;
+
+/// Name of the store file in the data directory.
+static STORE_FILE_NAME: &str = "downloads.json";
+
 /// Callback invoked whenever a download item changes state.
 pub type OnChanged = Arc<dyn Fn(DownloadItem) + Send + Sync + 'static>;
 
+/// Final outcome of a download, sent to the tasks waiting on it: the completed item,
+/// or the reason it did not complete.
+type Outcome = std::result::Result<DownloadItem, String>;
+
+/// Callback invoked for manager-wide events, e.g. a [`reset`](DownloadManager::reset).
+pub type OnEvent = Arc<dyn Fn(DownloadEvent) + Send + Sync + 'static>;
+
+/// Hook invoked once a download's file is in place, before it is reported completed,
+/// e.g. to unpack or register the file. Returns the reason post-processing failed.
+pub type OnCompleted =
+   Arc<dyn Fn(&DownloadItem) -> std::result::Result<(), String> + Send + Sync + 'static>;
+
+/// Provider of the bearer token that requests are authenticated with, e.g. a short-lived
+/// OAuth access token. Called before the first request, and again to refresh the token
+/// when a request is rejected with `401 Unauthorized`.
+pub type TokenProvider = Arc<dyn Fn() -> BoxFuture<'static, String> + Send + Sync + 'static>;
+
+/// Observer of the downloads the manager runs, e.g. to log them or record metrics. Every
+/// method does nothing unless implemented.
+pub trait DownloadObserver: Send + Sync {
+   /// Called when a download starts or resumes.
+   fn on_start(&self, _item: &DownloadItem) {}
+
+   /// Called when a download completes, with the bytes received and the time taken since
+   /// it started or resumed.
+   fn on_complete(&self, _item: &DownloadItem, _bytes: u64, _duration: Duration) {}
+
+   /// Called when a download fails with an error. The download may still be resumable.
+   fn on_fail(&self, _item: &DownloadItem, _error: &Error) {}
+}
+
 /// Tauri-agnostic download manager, mirroring the iOS/Android `DownloadManager`.
+///
+/// Downloads run as tasks on a Tokio runtime: the one set with
+/// [`with_runtime`](Self::with_runtime), or else the runtime of the caller. Any runtime
+/// with timers enabled works, including a current-thread one, as long as it is driven.
 #[derive(Clone)]
 pub struct DownloadManager {
-   pub(crate) store: DownloadStore,
+   pub(crate) config: Arc<DownloadManagerConfig>,
+   pub(crate) store: Arc<dyn Store>,
    pub(crate) on_changed: OnChanged,
+   pub(crate) on_event: OnEvent,
+   /// Items whose status `init` reconciled, kept for subscribers that attach later.
+   recovered: Arc<Mutex<Vec<DownloadItem>>>,
+   /// Tasks waiting for a download to finish, by path.
+   waiters: Arc<Mutex<HashMap<String, Vec<oneshot::Sender<Outcome>>>>>,
+   /// Runtime downloads run on, if set; otherwise the runtime of the caller.
+   runtime: Option<Handle>,
+   /// Running download tasks, by path, so that pausing or canceling a download stops it
+   /// at once. The aborted task of a paused download is kept until it is resumed.
+   tasks: Arc<Mutex<HashMap<String, DownloadTask>>>,
+   /// Completion hooks, in order of registration, and whether an error of each fails
+   /// the download.
+   completion_hooks: Arc<Mutex<Vec<(OnCompleted, bool)>>>,
+   /// HTTP client shared by downloads, so their connections are pooled. Built from the
+   /// configuration with the manager, unless one was set.
+   client: Arc<OnceLock<ClientWithMiddleware>>,
+   /// Cookie store of the built client, kept in memory only: seeded with the cookies of
+   /// new downloads, and updated with those their hosts set.
+   cookies: Arc<Jar>,
+   /// Changed items waiting to be reported in a batch, at most one per path.
+   pending_changes: Arc<Mutex<Vec<DownloadItem>>>,
+   /// Observer of the downloads run, if set.
+   observer: Option<Arc<dyn DownloadObserver>>,
+   /// Downloads paused because the connection is metered, to resume once it isn't.
+   metered_paused: Arc<Mutex<HashSet<String>>>,
+   /// Bytes received since the statistics were last added to the store.
+   received_bytes: Arc<AtomicU64>,
+   /// Held while the downloads in progress on a host are counted and one is started,
+   /// so that concurrent starts don't exceed
+   /// [`max_downloads_per_host`](DownloadManagerConfig::max_downloads_per_host).
+   host_slots: Arc<Mutex<()>>,
+   /// Sends every change to the [`subscribe`](Self::subscribe) streams.
+   changes: broadcast::Sender<DownloadItem>,
+   /// Provider of the bearer token requests are sent with, if set.
+   token_provider: Option<TokenProvider>,
+   /// Bearer token last returned by the provider, shared by all downloads.
+   token: Arc<Mutex<Option<String>>>,
 }
 
 impl DownloadManager {
@@ -28,11 +129,159 @@ impl DownloadManager {
    /// - `data_dir` - Directory where `downloads.json` will be stored.
    /// - `on_changed` - Callback invoked on every state/progress change.
    pub fn new(data_dir: PathBuf, on_changed: OnChanged) -> Self {
-      let store = DownloadStore::new(data_dir.join("downloads.json"));
+      Self::with_config(data_dir, DownloadManagerConfig::default(), on_changed)
+   }
+
+   /// Creates a new `DownloadManager` whose state is stored in `downloads-{name}.json`,
+   /// so that independent managers, e.g. one per user profile, can share a data
+   /// directory.
+   ///
+   /// # Arguments
+   /// - `data_dir` - Directory where the store file will be stored.
+   /// - `name` - Name of the download set. Path separators are stripped.
+   /// - `on_changed` - Callback invoked on every state/progress change.
+   pub fn new_named(data_dir: PathBuf, name: &str, on_changed: OnChanged) -> Self {
+      Self::with_store(
+         DownloadStore::new(named_store_path(&data_dir, name)),
+         DownloadManagerConfig::default(),
+         on_changed,
+      )
+   }
+
+   /// Creates a new `DownloadManager` with the given configuration, loading persisted
+   /// state from disk.
+   ///
+   /// # Arguments
+   /// - `data_dir` - Directory where `downloads.json` will be stored.
+   /// - `config` - Manager configuration.
+   /// - `on_changed` - Callback invoked on every state/progress change.
+   pub fn with_config(
+      data_dir: PathBuf,
+      config: DownloadManagerConfig,
+      on_changed: OnChanged,
+   ) -> Self {
+      Self::with_store(
+         DownloadStore::new(data_dir.join(STORE_FILE_NAME)),
+         config,
+         on_changed,
+      )
+   }
+
+   /// Creates a new `DownloadManager` backed by the given store, e.g. a
+   /// [`SqliteStore`](crate::SqliteStore), loading its persisted state.
+   ///
+   /// # Arguments
+   /// - `store` - Store persisting the download items.
+   /// - `on_changed` - Callback invoked on every state/progress change.
+   pub fn new_with_store(store: impl Store + 'static, on_changed: OnChanged) -> Self {
+      Self::with_store(store, DownloadManagerConfig::default(), on_changed)
+   }
+
+   /// Creates a new `DownloadManager` backed by the given store and with the given
+   /// configuration, loading the store's persisted state.
+   ///
+   /// # Arguments
+   /// - `store` - Store persisting the download items.
+   /// - `config` - Manager configuration.
+   /// - `on_changed` - Callback invoked on every state/progress change.
+   pub fn with_store(
+      store: impl Store + 'static,
+      config: DownloadManagerConfig,
+      on_changed: OnChanged,
+   ) -> Self {
       if let Err(e) = store.load() {
          warn!("Failed to load download store: {}", e);
       }
-      Self { store, on_changed }
+      // If the client can't be built, e.g. for an invalid proxy URL, each download
+      // tries again and fails with the reason.
+      let cookies = Arc::new(Jar::default());
+      let client = match downloader::build_client(&config, cookies.clone()) {
+         Ok(client) => OnceLock::from(client),
+         Err(e) => {
+            warn!("Failed to build HTTP client: {}", e);
+            OnceLock::new()
+         }
+      };
+      Self {
+         config: Arc::new(config),
+         store: Arc::new(store),
+         on_changed,
+         on_event: Arc::new(|_| {}),
+         recovered: Arc::new(Mutex::new(Vec::new())),
+         waiters: Arc::new(Mutex::new(HashMap::new())),
+         runtime: None,
+         tasks: Arc::new(Mutex::new(HashMap::new())),
+         completion_hooks: Arc::new(Mutex::new(Vec::new())),
+         client: Arc::new(client),
+         cookies,
+         pending_changes: Arc::new(Mutex::new(Vec::new())),
+         observer: None,
+         metered_paused: Arc::new(Mutex::new(HashSet::new())),
+         received_bytes: Arc::new(AtomicU64::new(0)),
+         host_slots: Arc::new(Mutex::new(())),
+         changes: broadcast::channel(CHANGES_CAPACITY).0,
+         token_provider: None,
+         token: Arc::new(Mutex::new(None)),
+      }
+   }
+
+   /// Sets the callback invoked for manager-wide events. Events are dropped when unset.
+   pub fn with_event_handler(mut self, on_event: OnEvent) -> Self {
+      self.on_event = on_event;
+      self
+   }
+
+   /// Sets the Tokio runtime downloads run on, so that the manager can be driven from
+   /// threads outside of it, e.g. a tray menu handler. When unset, downloads run on the
+   /// runtime of the caller, and starting one outside of a runtime fails.
+   pub fn with_runtime(mut self, runtime: Handle) -> Self {
+      self.runtime = Some(runtime);
+      self
+   }
+
+   /// Sets the observer notified when downloads start, complete or fail.
+   pub fn with_observer(mut self, observer: Arc<dyn DownloadObserver>) -> Self {
+      self.observer = Some(observer);
+      self
+   }
+
+   /// Sets the provider of the bearer token requests are sent with as `Authorization`,
+   /// for resources behind short-lived tokens. A request rejected with
+   /// `401 Unauthorized` is sent again once with a refreshed token, from the same offset.
+   pub fn with_token_provider(mut self, provider: TokenProvider) -> Self {
+      self.token_provider = Some(provider);
+      self
+   }
+
+   /// Sets the HTTP client downloads are sent with, e.g. one already configured by the
+   /// app with its own TLS roots or cookie store. Failed requests are still retried.
+   ///
+   /// The client is used as is: the `redirect` and `proxy` settings and the redirect
+   /// checks of `hosts` don't apply to it, and the `cookies` of downloads are only sent
+   /// by its own cookie store, if any. When unset, a client built from the
+   /// configuration is shared by all downloads.
+   pub fn with_client(mut self, client: reqwest::Client) -> Self {
+      self.client = Arc::new(OnceLock::from(downloader::with_retries(client)));
+      self
+   }
+
+   ///
+   /// Registers a hook that is invoked with each download that succeeds, after its file
+   /// was moved to its path and before the `Completed` change is emitted. Hooks run in
+   /// order of registration on the task of the download.
+   ///
+   /// An error of a hook is logged. If `fail_on_error` is set, the download is marked
+   /// `Failed` with it instead of completed, and the file is kept.
+   ///
+   /// # Arguments
+   /// - `hook` - Hook to invoke with the completed download.
+   /// - `fail_on_error` - Whether an error of the hook fails the download.
+   pub fn register_completion_hook(&self, hook: OnCompleted, fail_on_error: bool) {
+      self
+         .completion_hooks
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner)
+         .push((hook, fail_on_error));
    }
 
    ///
@@ -40,27 +289,208 @@ impl DownloadManager {
    /// Updates the state of any download operations which are still marked as "In Progress". This can occur if the
    /// application was suspended or terminated before a download was completed.
    ///
+   /// A change event is emitted for each reconciled item. As these are emitted at
+   /// startup, before most subscribers attach, the reconciled items are also kept and
+   /// can be replayed with [`recovered`](Self::recovered). Once done, a
+   /// [`DownloadEvent::Ready`] event carries the settled state of all downloads.
+   ///
    pub fn init(&self) {
       let items = match self.store.list() {
          Ok(list) => list,
          Err(e) => {
             error!("Failed to load download store: {}", e);
+            self.emit_event(DownloadEvent::StoreError(format!(
+               "Failed to load download store: {}",
+               e
+            )));
+            // Still signal that there is nothing left to reconcile.
+            self.emit_event(DownloadEvent::Ready(ReadySummary::default()));
             return;
          }
       };
 
-      for item in items
-         .into_iter()
-         .filter(|item| item.status == DownloadStatus::InProgress)
-      {
-         // Revert to a recoverable state so the download can be retried.
-         match self.revert_in_progress(&item) {
+      let mut demoted_in_progress = false;
+
+      for item in items.into_iter().filter(|item| {
+         item.status == DownloadStatus::InProgress
+            || (item.status == DownloadStatus::Queued
+               && matches!(self.find_prerequisite(item), Ok(None)))
+      }) {
+         // Revert to a recoverable state so the download can be retried. A queued item
+         // whose prerequisite completed before the app stopped, or that waited for a
+         // free slot on its host, can't be started here, outside of the async runtime,
+         // so it is reverted to `Idle` as well.
+         let reverted = if item.status == DownloadStatus::Queued {
+            let idle = item.with_status(DownloadStatus::Idle);
+            self.store.update(idle.clone()).map(|_| {
+               self.emit_changed(idle.clone());
+               idle
+            })
+         } else {
+            self.revert_in_progress(&item)
+         };
+         match reverted {
             Ok(reverted) => {
-               info!(file = %filename(&reverted.path), status = %reverted.status, "Reverted download item")
+               info!(file = %filename(&reverted.path), status = %reverted.status, "Reverted download item");
+               demoted_in_progress |= item.status == DownloadStatus::InProgress;
+               if let Ok(mut recovered) = self.recovered.lock() {
+                  recovered.push(reverted);
+               }
+            }
+            Err(e) => self.store_failed(&item.path, "Failed to revert download item", &e),
+         }
+      }
+
+      if self.config.verify_completed {
+         self.verify_completed();
+      }
+
+      // Schedules past due while the app wasn't running start right away.
+      for item in self.store.list().unwrap_or_default() {
+         if let (DownloadStatus::Idle, Some(at)) = (&item.status, item.scheduled_at) {
+            self.arm_schedule(&item.path, at);
+         }
+      }
+      if self.config.pause_on_metered {
+         self.watch_metering();
+      }
+
+      self.emit_event(DownloadEvent::Ready(ReadySummary {
+         downloads: self.store.list().unwrap_or_default(),
+         recovered: self.recovered(),
+         demoted_in_progress,
+      }));
+   }
+
+   /// Demotes the completed downloads kept in the store whose file is gone, or no longer
+   /// has the size it completed with, to `Idle` so they can be downloaded again. See
+   /// [`verify_completed`](DownloadManagerConfig::verify_completed).
+   fn verify_completed(&self) {
+      let completed = match self.store.list_by_status(&DownloadStatus::Completed) {
+         Ok(completed) => completed,
+         Err(e) => {
+            warn!("Failed to verify completed downloads: {}", e);
+            return;
+         }
+      };
+      for item in completed {
+         let intact = fs::metadata(&item.path).is_ok_and(|metadata| {
+            metadata.is_file()
+               && (item.downloaded_bytes == 0 || metadata.len() == item.downloaded_bytes)
+         });
+         if intact {
+            continue;
+         }
+         let idle = DownloadItem {
+            progress: Some(0.0),
+            downloaded_bytes: 0,
+            truncated: false,
+            ..item.with_status(DownloadStatus::Idle)
+         };
+         match self.store.update(idle.clone()) {
+            Ok(()) => {
+               info!(file = %filename(&idle.path), "Completed download is missing or changed; demoted to idle");
+               self.emit_changed(idle.clone());
+               if let Ok(mut recovered) = self.recovered.lock() {
+                  recovered.push(idle);
+               }
             }
-            Err(e) => warn!(file = %filename(&item.path), "Failed to revert download item: {}", e),
+            Err(e) => self.store_failed(&item.path, "Failed to demote completed download", &e),
+         }
+      }
+   }
+
+   ///
+   /// Gets the configuration the manager runs with.
+   ///
+   /// # Returns
+   /// A snapshot of the configuration, with secrets redacted.
+   pub fn config(&self) -> DownloadManagerConfig {
+      self.config.redacted()
+   }
+
+   ///
+   /// Writes pending progress updates to the store. Status changes are always written
+   /// right away, while progress is written periodically; call this e.g. before the
+   /// application exits. Dropping the last clone of the manager flushes as well.
+   pub fn flush(&self) -> crate::Result<()> {
+      self.flush_stats();
+      self.store.flush()
+   }
+
+   ///
+   /// Returns the lifetime statistics of the downloads, e.g. for data usage reporting.
+   /// They survive restarts and clearing the downloads, until
+   /// [`reset_stats`](Self::reset_stats).
+   ///
+   /// # Returns
+   /// The bytes received and the downloads completed and failed.
+   pub fn stats(&self) -> crate::Result<DownloadStats> {
+      let mut stats = self.store.stats()?;
+      stats.total_bytes += self.received_bytes.load(Ordering::Relaxed);
+      Ok(stats)
+   }
+
+   ///
+   /// Resets the lifetime statistics of the downloads to zero.
+   pub fn reset_stats(&self) -> crate::Result<()> {
+      self.received_bytes.store(0, Ordering::Relaxed);
+      self.store.reset_stats()
+   }
+
+   ///
+   /// Stops the manager cleanly, e.g. before the application exits: pauses the
+   /// running downloads, waits for them to write their buffered bytes and stop, then
+   /// persists the store. Downloads still running after `timeout` are aborted. Either
+   /// way they are stored `Paused`, so the next [`init`](Self::init) finds them ready to
+   /// resume from their temp files.
+   ///
+   /// # Arguments
+   /// - `timeout` - How long to wait for the running downloads to stop.
+   ///
+   /// # Returns
+   /// The paused download operations.
+   pub async fn shutdown(&self, timeout: Duration) -> crate::Result<Vec<DownloadActionResponse>> {
+      let paused = self.pause_all()?;
+
+      let tasks: Vec<JoinHandle<()>> =
+         std::mem::take(&mut *self.tasks.lock().unwrap_or_else(PoisonError::into_inner))
+            .into_values()
+            .map(DownloadTask::supersede)
+            .collect();
+      let abort_handles: Vec<_> = tasks.iter().map(JoinHandle::abort_handle).collect();
+      let mut stopped = futures::future::join_all(tasks);
+      if tokio::time::timeout(timeout, &mut stopped).await.is_err() {
+         warn!(
+            count = abort_handles.len(),
+            "Aborting downloads that did not stop in time"
+         );
+         // An aborted download is dropped, which flushes its write buffer to the temp
+         // file. Wait for that too.
+         for task in &abort_handles {
+            task.abort();
          }
+         stopped.await;
       }
+
+      info!(paused = paused.len(), "Download manager shut down");
+      self.flush_changes();
+      self.flush()?;
+      Ok(paused)
+   }
+
+   ///
+   /// Lists the download operations whose status was changed by [`init`](Self::init)
+   /// when recovering from an interrupted session.
+   ///
+   /// # Returns
+   /// The reconciled download operations, as emitted at the time.
+   pub fn recovered(&self) -> Vec<DownloadItem> {
+      self
+         .recovered
+         .lock()
+         .map(|recovered| recovered.clone())
+         .unwrap_or_default()
    }
 
    ///
@@ -72,6 +502,148 @@ impl DownloadManager {
       self.store.list()
    }
 
+   ///
+   /// Lists the download operations with the given status.
+   ///
+   /// # Arguments
+   /// - `status` - The status to match.
+   ///
+   /// # Returns
+   /// The matching download operations.
+   pub fn list_by_status(&self, status: DownloadStatus) -> crate::Result<Vec<DownloadItem>> {
+      self.store.list_by_status(&status)
+   }
+
+   ///
+   /// Lists the download operations with a tag.
+   ///
+   /// # Arguments
+   /// - `tag` - The tag to match, one of the `tags` the downloads were created with.
+   ///
+   /// # Returns
+   /// The matching download operations.
+   pub fn list_by_tag(&self, tag: &str) -> crate::Result<Vec<DownloadItem>> {
+      self.store.list_by_tag(tag)
+   }
+
+   ///
+   /// Counts the download operations in each status.
+   ///
+   /// # Returns
+   /// The number of download operations per status. Statuses without any download
+   /// operation are omitted.
+   pub fn count_by_status(&self) -> crate::Result<HashMap<DownloadStatus, usize>> {
+      self.store.count_by_status()
+   }
+
+   ///
+   /// Combines the progress of the in progress and paused download operations, e.g. for
+   /// a single progress bar. Operations of unknown size are counted, but don't
+   /// contribute to the bytes or the percentage.
+   ///
+   /// # Returns
+   /// The combined progress.
+   pub fn aggregate_progress(&self) -> crate::Result<AggregateProgress> {
+      let mut aggregate = AggregateProgress::default();
+      for status in [DownloadStatus::InProgress, DownloadStatus::Paused] {
+         for item in self.store.list_by_status(&status)? {
+            aggregate.active_count += 1;
+            let Some(total_size) = item.total_size.filter(|&size| size > 0) else {
+               continue;
+            };
+            // Entries stored before their bytes were tracked only have the temp file.
+            let downloaded_bytes = if item.downloaded_bytes > 0 {
+               item.downloaded_bytes
+            } else {
+               let temp_path = self.temp_path(&item.path);
+               fs::metadata(temp_path).map_or(0, |metadata| metadata.len())
+            };
+            aggregate.total_bytes += total_size;
+            aggregate.downloaded_bytes += downloaded_bytes.min(total_size);
+         }
+      }
+      aggregate.combined_percent = progress_of(aggregate.downloaded_bytes, aggregate.total_bytes);
+      Ok(aggregate)
+   }
+
+   ///
+   /// Gets the number of bytes on disk for a download operation, without reading them:
+   /// the size of the temp file while the download isn't completed, and of the final file
+   /// once completed, including a download no longer in the store.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The size of the file, or 0 if there is none.
+   pub fn downloaded_bytes(&self, path: &str) -> crate::Result<u64> {
+      validate::path(path)?;
+
+      let file = match self.store.find_by_path(path)? {
+         Some(item) if item.status != DownloadStatus::Completed => self.temp_path(path),
+         _ => path.to_string(),
+      };
+      Ok(fs::metadata(file).map_or(0, |metadata| metadata.len()))
+   }
+
+   ///
+   /// Checks the file at a download path against its SHA-256 digest, without any network
+   /// activity, e.g. to skip downloading a file that shipped with the app. On a match,
+   /// the download operation at the path, if any, completes without downloading: its
+   /// running task is stopped and its partial data discarded.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `expected_sha256` - The expected digest, in hex.
+   ///
+   /// # Returns
+   /// Whether the file exists and matches the digest.
+   pub async fn verify(&self, path: &str, expected_sha256: &str) -> crate::Result<bool> {
+      validate::path(path)?;
+
+      // Hash on a blocking thread, as a large file takes a while to read.
+      let file = PathBuf::from(path);
+      let digest = tokio::task::spawn_blocking(move || sha256_file(&file))
+         .await
+         .map_err(|e| Error::File(format!("Failed to hash file: {}", e)))?
+         .map_err(|e| Error::File(format!("Failed to hash file: {}", e)))?;
+      if !digest.is_some_and(|digest| digest.eq_ignore_ascii_case(expected_sha256.trim())) {
+         return Ok(false);
+      }
+
+      if let Some(item) = self.store.find_by_path(path)?
+         && item.status != DownloadStatus::Completed
+      {
+         self.abort_task(path);
+         self.discard_temp_file(path)?;
+         let size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+         let completed = DownloadItem {
+            total_size: Some(size),
+            downloaded_bytes: size,
+            error: None,
+            scheduled_at: None,
+            ..item.with_status(DownloadStatus::Completed)
+         };
+         self.store_completed(&completed)?;
+         info!(file = %filename(path), "Completed download with a verified existing file");
+         self.emit_changed(completed);
+         self.start_dependents(path);
+      }
+      Ok(true)
+   }
+
+   ///
+   /// Finds the download operations for a URL.
+   ///
+   /// # Arguments
+   /// - `url` - The download URL.
+   ///
+   /// # Returns
+   /// The download operations using the URL, whatever their path.
+   pub fn find_by_url(&self, url: &str) -> crate::Result<Vec<DownloadItem>> {
+      self.store.find_by_url(url)
+   }
+
    ///
    /// Gets a download operation.
    ///
@@ -92,8 +664,9 @@ impl DownloadManager {
          None => Ok(DownloadItem {
             url: String::new(),
             path: path.to_string(),
-            progress: 0.0,
+            progress: Some(0.0),
             status: DownloadStatus::Pending,
+            ..Default::default()
          }),
       }
    }
@@ -101,6 +674,9 @@ impl DownloadManager {
    ///
    /// Creates a download operation.
    ///
+   /// The URL is stored normalized: without its fragment, with the scheme and host in
+   /// lowercase, and without the default port.
+   ///
    /// # Arguments
    /// - `path` - The download path.
    /// - `url` - The download URL for the resource.
@@ -108,155 +684,737 @@ impl DownloadManager {
    /// # Returns
    /// The download operation.
    pub fn create(&self, path: &str, url: &str) -> crate::Result<DownloadActionResponse> {
-      validate::path(path)?;
-      validate::url(url)?;
-
-      // Check if item already exists
-      if let Some(existing) = self.store.find_by_path(path)? {
-         return Ok(DownloadActionResponse::with_expected_status(
-            existing,
-            DownloadStatus::Idle,
-         ));
-      }
-
-      let item = self.store.create(DownloadItem {
-         url: url.to_string(),
-         path: path.to_string(),
-         progress: 0.0,
-         status: DownloadStatus::Idle,
-      })?;
-
-      self.emit_changed(item.clone());
-      Ok(DownloadActionResponse::new(item))
+      self.create_with_options(path, url, DownloadOptions::default())
    }
 
    ///
-   /// Starts a download operation.
+   /// Creates a download operation with options, e.g. mirror URLs.
    ///
    /// # Arguments
    /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - The download options.
    ///
    /// # Returns
    /// The download operation.
-   pub fn start(&self, path: &str) -> crate::Result<DownloadActionResponse> {
-      validate::path(path)?;
-
-      let item = self
-         .store
-         .find_by_path(path)?
-         .ok_or_else(|| Error::NotFound(path.to_string()))?;
-      match item.status {
-         // Allow download to be started when idle.
-         DownloadStatus::Idle => self.spawn_download(item, "failed to start"),
+   pub fn create_with_options(
+      &self,
+      path: &str,
+      url: &str,
+      options: DownloadOptions,
+   ) -> crate::Result<DownloadActionResponse> {
+      let url = self.validate_new(path, url, &options)?;
+      self.add_cookies(&url, &options.cookies);
 
-         // Return current state if in any other state.
-         _ => Ok(DownloadActionResponse::with_expected_status(
-            item,
-            DownloadStatus::InProgress,
-         )),
-      }
+      let path = match self.store.find_by_path(path)? {
+         Some(_) => path.to_string(),
+         None => self.destination(path, options.overwrite, &HashSet::new())?,
+      };
+      self.create_item(DownloadItem {
+         url,
+         path,
+         progress: Some(0.0),
+         status: DownloadStatus::Idle,
+         options,
+         ..Default::default()
+      })
    }
 
    ///
-   /// Resumes a download operation.
+   /// Gets a download operation, creating it if it doesn't exist. Unlike [`get`](Self::get)
+   /// followed by [`create`](Self::create), callers racing to create the same path all
+   /// get the one item stored.
    ///
    /// # Arguments
    /// - `path` - The download path.
+   /// - `url` - The download URL for the resource, if the download is created.
    ///
    /// # Returns
-   /// The download operation.
-   pub fn resume(&self, path: &str) -> crate::Result<DownloadActionResponse> {
-      validate::path(path)?;
-
-      let item = self
-         .store
-         .find_by_path(path)?
-         .ok_or_else(|| Error::NotFound(path.to_string()))?;
-      match item.status {
-         // Allow download to be resumed when paused.
-         DownloadStatus::Paused => self.spawn_download(item, "failed to resume"),
-
-         // Return current state if in any other state.
-         _ => Ok(DownloadActionResponse::with_expected_status(
-            item,
-            DownloadStatus::InProgress,
-         )),
+   /// The existing download operation, even if it has another URL, or the created one in
+   /// `Idle` state.
+   pub fn get_or_create(&self, path: &str, url: &str) -> crate::Result<DownloadItem> {
+      let url = self.validate_new(path, url, &DownloadOptions::default())?;
+      if let Some(existing) = self.store.find_by_path(path)? {
+         return Ok(existing);
       }
-   }
-
-   fn spawn_download(
-      &self,
-      item: DownloadItem,
-      err_msg: &'static str,
-   ) -> crate::Result<DownloadActionResponse> {
-      let item_in_progress = item.with_status(DownloadStatus::InProgress);
-      self.store.update(item_in_progress.clone())?;
-
-      let manager = self.clone();
-      let path = item.path.clone();
-      let item_in_progress_response = item_in_progress.clone();
-      tokio::spawn(async move {
-         if let Err(e) = downloader::download(&manager, item_in_progress).await {
-            error!(file = %filename(&path), "Download {}: {}", err_msg, e);
+      self.check_duplicate_url(path, &url)?;
 
-            // Revert unless already paused or canceled.
-            if let Ok(Some(current)) = manager.store.find_by_path(&path)
-               && current.status == DownloadStatus::InProgress
-            {
-               match manager.revert_in_progress(&current) {
-                  Ok(reverted) => {
-                     info!(file = %filename(&reverted.path), status = %reverted.status, "Reverted download item")
-                  }
-                  Err(e) => warn!(file = %filename(&path), "Failed to revert download item: {}", e),
-               }
-            }
+      // The store inserts the item only if none was stored at the path in the meantime,
+      // under the same lock as the check.
+      let item = DownloadItem {
+         url,
+         path: path.to_string(),
+         progress: Some(0.0),
+         status: DownloadStatus::Idle,
+         ..Default::default()
+      };
+      match self.store.create_many(vec![item.clone()])?.pop().flatten() {
+         Some(existing) => Ok(existing),
+         None => {
+            self.emit_changed(item.clone());
+            Ok(item)
          }
-      });
-
-      Ok(DownloadActionResponse::new(item_in_progress_response))
+      }
    }
 
    ///
-   /// Pauses a download operation.
+   /// Checks whether a download operation could be created, without creating it, e.g.
+   /// to validate a form before it is submitted.
    ///
    /// # Arguments
    /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
    ///
    /// # Returns
-   /// The download operation.
-   pub fn pause(&self, path: &str) -> crate::Result<DownloadActionResponse> {
-      validate::path(path)?;
+   /// The error [`create`](Self::create) would fail with, if any.
+   pub fn validate_create(&self, path: &str, url: &str) -> crate::Result<()> {
+      self.validate_create_with_options(path, url, &DownloadOptions::default())
+   }
 
-      let item = self
+   ///
+   /// Checks whether a download operation with options could be created, without
+   /// creating it.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - The download options.
+   ///
+   /// # Returns
+   /// The error [`create_with_options`](Self::create_with_options) would fail with, if
+   /// any.
+   pub fn validate_create_with_options(
+      &self,
+      path: &str,
+      url: &str,
+      options: &DownloadOptions,
+   ) -> crate::Result<()> {
+      let url = self.validate_new(path, url, options)?;
+
+      // Creating an existing download returns it rather than failing.
+      if self.store.find_by_path(path)?.is_some() {
+         return Ok(());
+      }
+      self.destination(path, options.overwrite, &HashSet::new())?;
+      self.check_duplicate_url(path, &url)
+   }
+
+   ///
+   /// Creates multiple download operations with a single store write.
+   ///
+   /// All items are validated first; the valid ones are then persisted together. Like
+   /// [`create`](Self::create), an item whose path already exists in the store returns
+   /// the existing download, while a path repeated within the batch is an error. The
+   /// [`DuplicateUrlPolicy`] is checked against the downloads already in the store.
+   ///
+   /// # Arguments
+   /// - `items` - The downloads to create.
+   ///
+   /// # Returns
+   /// The outcome for each item, in order.
+   pub fn create_many(&self, items: Vec<BatchCreateItem>) -> crate::Result<Vec<BatchCreateResult>> {
+      let mut seen = HashSet::new();
+      let validated: Vec<(String, crate::Result<DownloadItem>)> = items
+         .into_iter()
+         .map(|item| {
+            let result = self
+               .validate_new(&item.path, &item.url, &item.options)
+               .and_then(|url| match self.store.find_by_path(&item.path)? {
+                  Some(_) => Ok((url, item.path.clone())),
+                  None => {
+                     self.check_duplicate_url(&item.path, &url)?;
+                     let path = self.destination(&item.path, item.options.overwrite, &seen)?;
+                     Ok((url, path))
+                  }
+               })
+               .and_then(|(url, path)| {
+                  if seen.insert(item.path.clone())
+                     && (path == item.path || seen.insert(path.clone()))
+                  {
+                     self.add_cookies(&url, &item.options.cookies);
+                     Ok(DownloadItem {
+                        url,
+                        path,
+                        progress: Some(0.0),
+                        status: DownloadStatus::Idle,
+                        options: item.options,
+                        ..Default::default()
+                     })
+                  } else {
+                     Err(Error::Path(
+                        PathError::Duplicate,
+                        format!("duplicate path in batch: {}", item.path),
+                     ))
+                  }
+               });
+            (item.path, result)
+         })
+         .collect();
+
+      let new_items: Vec<DownloadItem> = validated
+         .iter()
+         .filter_map(|(_, result)| result.as_ref().ok().cloned())
+         .collect();
+      let mut existing = self.store.create_many(new_items)?.into_iter();
+
+      Ok(validated
+         .into_iter()
+         .map(|(path, result)| match result {
+            Ok(item) => {
+               let response = match existing.next().flatten() {
+                  Some(existing) => {
+                     DownloadActionResponse::with_expected_status(existing, DownloadStatus::Idle)
+                  }
+                  None => {
+                     self.emit_changed(item.clone());
+                     DownloadActionResponse::new(item)
+                  }
+               };
+               BatchCreateResult {
+                  path,
+                  response: Some(response),
+                  error: None,
+               }
+            }
+            Err(e) => BatchCreateResult {
+               path,
+               response: None,
+               error: Some(e.to_string()),
+            },
+         })
+         .collect())
+   }
+
+   ///
+   /// Creates a download operation whose filename is provided by the server.
+   ///
+   /// The download is stored under a placeholder path in `dir`, named after the last
+   /// segment of the URL (or `download` if it has none). When the download starts, the
+   /// filename from the response's `Content-Disposition` header, if any, replaces the
+   /// placeholder and a change event with the resolved path is emitted before any bytes
+   /// are written.
+   ///
+   /// # Arguments
+   /// - `dir` - The directory to download into.
+   /// - `url` - The download URL for the resource.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn create_in_dir(&self, dir: &str, url: &str) -> crate::Result<DownloadActionResponse> {
+      validate::directory(dir)?;
+      validate::url_against(&self.config.hosts, url)?;
+      let url = validate::normalize_url(url);
+
+      let name = filename::from_url(&url).unwrap_or_else(|| filename::DEFAULT_FILENAME.to_string());
+      let path = Path::new(dir).join(name).to_string_lossy().into_owned();
+      self.validate_within_root(&path)?;
+      self.create_item(DownloadItem {
+         url,
+         path,
+         progress: Some(0.0),
+         status: DownloadStatus::Idle,
+         resolve_filename: true,
+         ..Default::default()
+      })
+   }
+
+   /// Validates the arguments of a new download, returning its URL normalized to store.
+   fn validate_new(
+      &self,
+      path: &str,
+      url: &str,
+      options: &DownloadOptions,
+   ) -> crate::Result<String> {
+      validate::path(path)?;
+      self.validate_within_root(path)?;
+      validate::url_against(&self.config.hosts, url)?;
+      validate::options(options)?;
+      for mirror in &options.mirrors {
+         validate::url_against(&self.config.hosts, mirror)?;
+      }
+      if options.depends_on.as_deref() == Some(path) {
+         return Err(Error::Path(
+            PathError::SelfDependency,
+            "download cannot depend on itself".to_string(),
+         ));
+      }
+      Ok(validate::normalize_url(url))
+   }
+
+   /// Applies the [`OverwritePolicy`] of a new download at `path` to an existing file,
+   /// returning the path to download to. Paths in `reserved` are treated as taken.
+   fn destination(
+      &self,
+      path: &str,
+      policy: OverwritePolicy,
+      reserved: &HashSet<String>,
+   ) -> crate::Result<String> {
+      match policy {
+         OverwritePolicy::Fail if Path::new(path).exists() => {
+            Err(Error::File(format!("Destination already exists: {}", path)))
+         }
+         OverwritePolicy::Rename if Path::new(path).exists() => self.free_path(path, reserved),
+         _ => Ok(path.to_string()),
+      }
+   }
+
+   /// Returns the first of `path (1)`, `path (2)`, … that is neither a file, a
+   /// download in the store, nor in `reserved`.
+   pub(crate) fn free_path(&self, path: &str, reserved: &HashSet<String>) -> crate::Result<String> {
+      for n in 1..=u32::MAX {
+         let candidate = filename::numbered(path, n);
+         if !Path::new(&candidate).exists()
+            && !reserved.contains(&candidate)
+            && self.store.find_by_path(&candidate)?.is_none()
+         {
+            return Ok(candidate);
+         }
+      }
+      Err(Error::File(format!("No free path for {}", path)))
+   }
+
+   /// Adds the cookies of a new download to the cookie store, for the host of `url`.
+   pub(crate) fn add_cookies(&self, url: &str, cookies: &BTreeMap<String, String>) {
+      let Ok(url) = url::Url::parse(url) else {
+         return;
+      };
+      for (name, value) in cookies {
+         self
+            .cookies
+            .add_cookie_str(&format!("{}={}; Path=/", name, value), &url);
+      }
+   }
+
+   /// Checks that `path` is within the configured download root, if any.
+   fn validate_within_root(&self, path: &str) -> crate::Result<()> {
+      match &self.config.download_root {
+         Some(root) => validate::path_within(root, path),
+         None => Ok(()),
+      }
+   }
+
+   /// Returns the completed download at `path` in the store. Fails with
+   /// [`Error::NotFound`] if there is none, and with [`Error::InvalidState`] if it isn't
+   /// completed.
+   fn find_completed(&self, path: &str) -> crate::Result<DownloadItem> {
+      match self.store.find_by_path(path)? {
+         Some(item) if item.status == DownloadStatus::Completed => Ok(item),
+         Some(_) => Err(Error::InvalidState),
+         None => Err(Error::NotFound(path.to_string())),
+      }
+   }
+
+   /// Applies the configured [`DuplicateUrlPolicy`] to a new download at `path`.
+   fn check_duplicate_url(&self, path: &str, url: &str) -> crate::Result<()> {
+      if self.config.duplicate_urls == DuplicateUrlPolicy::Allow {
+         return Ok(());
+      }
+      let duplicate = self.store.find_by_url(url)?.into_iter().find(|item| {
+         item.path != path
+            && matches!(
+               item.status,
+               DownloadStatus::Idle
+                  | DownloadStatus::InProgress
+                  | DownloadStatus::Paused
+                  | DownloadStatus::Queued
+            )
+      });
+      match (duplicate, self.config.duplicate_urls) {
+         (Some(duplicate), DuplicateUrlPolicy::Block) => {
+            info!(
+               "Refusing duplicate download of {} (already downloading to {})",
+               url, duplicate.path
+            );
+            Err(Error::DuplicateUrl(url.to_string()))
+         }
+         (Some(duplicate), _) => {
+            warn!(
+               "Creating duplicate download of {} (already downloading to {})",
+               url, duplicate.path
+            );
+            Ok(())
+         }
+         (None, _) => Ok(()),
+      }
+   }
+
+   /// Persists a new `Idle` item, or returns the existing item at the same path.
+   fn create_item(&self, item: DownloadItem) -> crate::Result<DownloadActionResponse> {
+      // Check if item already exists
+      if let Some(existing) = self.store.find_by_path(&item.path)? {
+         return Ok(DownloadActionResponse::with_expected_status(
+            existing,
+            DownloadStatus::Idle,
+         ));
+      }
+
+      self.check_duplicate_url(&item.path, &item.url)?;
+      let item = self.store.create(item)?;
+
+      self.emit_changed(item.clone());
+      Ok(DownloadActionResponse::new(item))
+   }
+
+   ///
+   /// Learns the size of a resource, whether a download of it could be resumed, its
+   /// type and filename, without downloading it, e.g. to warn before starting that a
+   /// download can't be resumed.
+   ///
+   /// # Arguments
+   /// - `url` - The URL of the resource.
+   ///
+   /// # Returns
+   /// What downloading the resource would involve.
+   pub async fn probe(&self, url: &str) -> crate::Result<ProbeResult> {
+      validate::url_against(&self.config.hosts, url)?;
+      downloader::probe(self, url).await
+   }
+
+   ///
+   /// Downloads a resource into a writer rather than a file, e.g. to decrypt or transform
+   /// it as it arrives, or to keep it in memory. The request is sent like that of a
+   /// download, with the configured headers, token and limits, but no download
+   /// operation is stored: the download runs while the returned future is polled, and
+   /// its progress goes to `on_progress` only, throttled as configured.
+   ///
+   /// # Arguments
+   /// - `url` - The URL of the resource.
+   /// - `writer` - The writer to write the resource to, from its first byte.
+   /// - `on_progress` - Called with the bytes written and the size of the resource, if
+   ///   known.
+   ///
+   /// # Returns
+   /// The number of bytes written. The writer keeps those written before an error.
+   pub async fn download_to_writer<W, F>(
+      &self,
+      url: &str,
+      writer: &mut W,
+      on_progress: F,
+   ) -> crate::Result<u64>
+   where
+      W: AsyncWrite + Unpin,
+      F: FnMut(u64, Option<u64>),
+   {
+      validate::url_against(&self.config.hosts, url)?;
+      downloader::to_writer(self, url, writer, 0, on_progress).await
+   }
+
+   ///
+   /// Resumes a download into a writer, like [`download_to_writer`](Self::download_to_writer),
+   /// after the bytes already in it, e.g. after a failed attempt. Resuming needs a
+   /// seekable writer, as the bytes in it are counted by seeking to its end, where the
+   /// rest of the resource is written. It fails if the server doesn't answer with the
+   /// rest of the resource, e.g. as it doesn't accept range requests.
+   ///
+   /// # Arguments
+   /// - `url` - The URL of the resource.
+   /// - `writer` - The writer holding the first bytes of the resource.
+   /// - `on_progress` - Called with the bytes in the writer and the size of the
+   ///   resource, if known.
+   ///
+   /// # Returns
+   /// The number of bytes written after those already in the writer.
+   pub async fn resume_to_writer<W, F>(
+      &self,
+      url: &str,
+      writer: &mut W,
+      on_progress: F,
+   ) -> crate::Result<u64>
+   where
+      W: AsyncWrite + AsyncSeek + Unpin,
+      F: FnMut(u64, Option<u64>),
+   {
+      validate::url_against(&self.config.hosts, url)?;
+      let offset = writer
+         .seek(SeekFrom::End(0))
+         .await
+         .map_err(|e| Error::File(format!("Failed to seek writer: {}", e)))?;
+      downloader::to_writer(self, url, writer, offset, on_progress).await
+   }
+
+   ///
+   /// Downloads a resource in a single call: creates the download operation, starts it
+   /// and waits for it to finish.
+   ///
+   /// An existing download operation at `path` is started or resumed as needed, or
+   /// waited on if it is already running.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - Options for the download.
+   ///
+   /// # Returns
+   /// The completed download operation, with its final path. Fails with
+   /// [`Error::Failed`] if the download fails or is canceled.
+   pub async fn download_now(
+      &self,
+      path: &str,
+      url: &str,
+      options: DownloadOptions,
+   ) -> crate::Result<DownloadItem> {
+      let created = self.create_with_options(path, url, options)?.download;
+
+      // The path differs from the requested one if the overwrite policy renamed it.
+      let path = created.path.as_str();
+      let outcome = self.register_waiter(path)?;
+      match created.status {
+         DownloadStatus::Idle | DownloadStatus::Failed => {
+            self.start(path)?;
+         }
+         DownloadStatus::Paused => {
+            self.resume(path)?;
+         }
+         _ => {}
+      }
+      self.wait_on(path, outcome).await
+   }
+
+   ///
+   /// Waits for a download operation to finish.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The completed download operation, with its final path. Fails with
+   /// [`Error::Failed`] if the download fails or is canceled, and with
+   /// [`Error::NotFound`] if it isn't in the store, e.g. because it already completed.
+   pub async fn wait_for(&self, path: &str) -> crate::Result<DownloadItem> {
+      validate::path(path)?;
+      let outcome = self.register_waiter(path)?;
+      self.wait_on(path, outcome).await
+   }
+
+   ///
+   /// Subscribes to the changes of all download operations, as an alternative to the
+   /// [`OnChanged`] callback for async code. Every change is sent, even when the
+   /// callback gets them in batches. A stream that falls behind by more than 256
+   /// changes skips the oldest.
+   ///
+   /// # Returns
+   /// A stream of the download operations as they change, from now on.
+   pub fn subscribe(&self) -> impl Stream<Item = DownloadItem> + Send + Unpin + 'static {
+      Box::pin(futures::stream::unfold(
+         self.changes.subscribe(),
+         |mut receiver| async move {
+            loop {
+               match receiver.recv().await {
+                  Ok(item) => return Some((item, receiver)),
+                  Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                     warn!("Change stream fell behind, skipped {} changes", skipped)
+                  }
+                  Err(broadcast::error::RecvError::Closed) => return None,
+               }
+            }
+         },
+      ))
+   }
+
+   /// Registers a waiter for the outcome of the download at `path`.
+   fn register_waiter(&self, path: &str) -> crate::Result<oneshot::Receiver<Outcome>> {
+      let (sender, receiver) = oneshot::channel();
+      let mut waiters = self
+         .waiters
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      let senders = waiters.entry(path.to_string()).or_default();
+      senders.retain(|sender| !sender.is_closed());
+      senders.push(sender);
+      Ok(receiver)
+   }
+
+   /// Waits for `outcome`, unless the download at `path` has already finished. The
+   /// store is checked after subscribing so that an outcome in between isn't missed.
+   async fn wait_on(
+      &self,
+      path: &str,
+      mut outcome: oneshot::Receiver<Outcome>,
+   ) -> crate::Result<DownloadItem> {
+      let Some(current) = self.store.find_by_path(path)? else {
+         // Canceled downloads, and completed ones unless kept, are removed from the store
+         // before their outcome is sent.
+         return match outcome.try_recv() {
+            Ok(outcome) => outcome.map_err(Error::Failed),
+            Err(_) => Err(Error::NotFound(path.to_string())),
+         };
+      };
+      let outcome = match current.status {
+         DownloadStatus::Completed => Ok(current),
+         DownloadStatus::Failed => Err(failure_message(&current)),
+         _ => outcome
+            .await
+            .unwrap_or_else(|_| Err(format!("Download canceled: {}", path))),
+      };
+      outcome.map_err(Error::Failed)
+   }
+
+   /// Sends `outcome` to the tasks waiting on the download at `path`.
+   pub(crate) fn notify_waiters(&self, path: &str, outcome: Outcome) {
+      let senders = match self.waiters.lock() {
+         Ok(mut waiters) => waiters.remove(path),
+         Err(_) => None,
+      };
+      for sender in senders.into_iter().flatten() {
+         let _ = sender.send(outcome.clone());
+      }
+   }
+
+   /// Moves the running task of the download at `from`, and the tasks waiting on it,
+   /// to `to`, after its path changed.
+   pub(crate) fn rename_in_flight(&self, from: &str, to: &str) {
+      if let Ok(mut waiters) = self.waiters.lock()
+         && let Some(senders) = waiters.remove(from)
+      {
+         waiters.entry(to.to_string()).or_default().extend(senders);
+      }
+      if let Ok(mut tasks) = self.tasks.lock()
+         && let Some(task) = tasks.remove(from)
+      {
+         tasks.insert(to.to_string(), task);
+      }
+   }
+
+   /// Aborts the running task of the download at `path`, if any, which closes its
+   /// connection and temp file without waiting for the next chunk.
+   fn abort_task(&self, path: &str) {
+      if let Ok(mut tasks) = self.tasks.lock()
+         && let Some(task) = tasks.remove(path)
+      {
+         task.supersede().abort();
+      }
+   }
+
+   /// Whether the calling task runs a download that was superseded, i.e. that is no
+   /// longer the task registered for its path, e.g. after a cancel and a new start of
+   /// the same path. The downloader checks this for every chunk, so it reads the flag
+   /// of the task rather than locking `tasks`. Code not run by a download task is never
+   /// superseded.
+   pub(crate) fn is_superseded(&self) -> bool {
+      SUPERSEDED
+         .try_with(|superseded| superseded.load(Ordering::Relaxed))
+         .unwrap_or(false)
+   }
+
+   /// Aborts the running task of the download at `path`, if any, like
+   /// [`abort_task`](Self::abort_task), but keeps its handle so that the download, once
+   /// resumed, waits for the task to write its buffered bytes to the temp file.
+   fn stop_task(&self, path: &str) {
+      if let Ok(tasks) = self.tasks.lock()
+         && let Some(task) = tasks.get(path)
+      {
+         task.handle.abort();
+      }
+   }
+
+   ///
+   /// Performs an action on a download, dispatching to [`start`](Self::start),
+   /// [`pause`](Self::pause), [`resume`](Self::resume), [`cancel`](Self::cancel) or
+   /// [`retry`](Self::retry).
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `action` - The action to perform.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn action(
+      &self,
+      path: &str,
+      action: DownloadAction,
+   ) -> crate::Result<DownloadActionResponse> {
+      match action {
+         DownloadAction::Start => self.start(path),
+         DownloadAction::Pause => self.pause(path),
+         DownloadAction::Resume => self.resume(path),
+         DownloadAction::Cancel => self.cancel(path),
+         DownloadAction::Retry => self.retry(path),
+      }
+   }
+
+   ///
+   /// Starts a download operation.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn start(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      self.start_inner(path, false)
+   }
+
+   /// Starts a download operation. The temp file of an earlier attempt is handled as
+   /// the [`PartialFilePolicy`] says, unless `keep_partial` is set, e.g. for a retry,
+   /// in which case the downloader always resumes it.
+   fn start_inner(&self, path: &str, keep_partial: bool) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
          .store
          .find_by_path(path)?
          .ok_or_else(|| Error::NotFound(path.to_string()))?;
       match item.status {
-         // Allow download to be paused when in progress.
-         DownloadStatus::InProgress => {
-            let paused = item.with_status(DownloadStatus::Paused);
-            self.store.update(paused.clone())?;
-            self.emit_changed(paused.clone());
-            Ok(DownloadActionResponse::new(paused))
+         // Allow download to be started when idle, or again after failing. Wait for an
+         // incomplete prerequisite, or fail right away if it already failed.
+         DownloadStatus::Idle | DownloadStatus::Failed => {
+            // The temp file of an earlier attempt is either resumed by the downloader
+            // with a range request, or discarded here.
+            let progress = match self.config.partial_files {
+               PartialFilePolicy::Restart if !keep_partial => {
+                  self.discard_temp_file(&item.path)?;
+                  Some(0.0)
+               }
+               _ => self.resumed_progress(&item).or(item.progress),
+            };
+            let item = DownloadItem {
+               error: None,
+               progress,
+               scheduled_at: None,
+               deadline_at: item.deadline_at.filter(|&at| at > now_ms()),
+               ..item
+            };
+            match self.find_prerequisite(&item)? {
+               Some(prerequisite) if prerequisite.status == DownloadStatus::Failed => {
+                  let failed = item.with_error(prerequisite_failed(&prerequisite.path));
+                  self.store.update(failed.clone())?;
+                  self.emit_changed(failed.clone());
+                  Ok(DownloadActionResponse::with_expected_status(
+                     failed,
+                     DownloadStatus::InProgress,
+                  ))
+               }
+               Some(_) => {
+                  let queued = item.with_status(DownloadStatus::Queued);
+                  self.store.update(queued.clone())?;
+                  self.emit_changed(queued.clone());
+                  Ok(DownloadActionResponse::new(queued))
+               }
+               None => self.spawn_download(item, "failed to start"),
+            }
          }
 
          // Return current state if in any other state.
          _ => Ok(DownloadActionResponse::with_expected_status(
             item,
-            DownloadStatus::Paused,
+            DownloadStatus::InProgress,
          )),
       }
    }
 
    ///
-   /// Cancels a download operation.
+   /// Schedules an idle download operation to start at a given time, e.g. at night. It
+   /// then starts like [`start`](Self::start), right away if the time already passed.
+   /// Scheduling it again replaces the time, and starting it earlier drops the
+   /// schedule. Schedules survive a restart: [`init`](Self::init) arms them again.
    ///
    /// # Arguments
    /// - `path` - The download path.
+   /// - `at` - When to start, in milliseconds since the Unix epoch.
    ///
    /// # Returns
    /// The download operation.
-   pub fn cancel(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+   pub fn schedule(&self, path: &str, at: u64) -> crate::Result<DownloadActionResponse> {
       validate::path(path)?;
 
       let item = self
@@ -264,245 +1422,3510 @@ impl DownloadManager {
          .find_by_path(path)?
          .ok_or_else(|| Error::NotFound(path.to_string()))?;
       match item.status {
-         // Allow download to be canceled when created, in progress or paused.
-         DownloadStatus::Idle | DownloadStatus::InProgress | DownloadStatus::Paused => {
-            self.store.delete(&item.path)?;
-            let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
-            if fs::remove_file(&temp_path).is_err() {
-               debug!(file = %filename(&item.path), "Temp file was not found or could not be deleted");
+         DownloadStatus::Idle => {
+            let scheduled = DownloadItem {
+               scheduled_at: Some(at),
+               ..item
+            };
+            self.store.update(scheduled.clone())?;
+            self.emit_changed(scheduled.clone());
+            self.arm_schedule(&scheduled.path, at);
+            Ok(DownloadActionResponse::new(scheduled))
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            DownloadStatus::Idle,
+         )),
+      }
+   }
+
+   /// Starts the download at `path` once `at` arrives, unless it was started, removed or
+   /// scheduled for another time in between.
+   fn arm_schedule(&self, path: &str, at: u64) {
+      let Some(runtime) = self.runtime.clone().or_else(|| Handle::try_current().ok()) else {
+         warn!(file = %filename(path), "Scheduled download: no Tokio runtime to wait on");
+         return;
+      };
+      let now = now_ms();
+      let manager = self.clone();
+      let path = path.to_string();
+      runtime.spawn(async move {
+         tokio::time::sleep(Duration::from_millis(at.saturating_sub(now))).await;
+         let due = manager.store.find_by_path(&path).is_ok_and(|item| {
+            item.is_some_and(|item| {
+               item.status == DownloadStatus::Idle && item.scheduled_at == Some(at)
+            })
+         });
+         if due {
+            info!(file = %filename(&path), "Starting scheduled download");
+            if let Err(e) = manager.start(&path) {
+               warn!(file = %filename(&path), "Failed to start scheduled download: {}", e);
             }
+         }
+      });
+   }
+
+   ///
+   /// Resumes a download operation.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn resume(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      match item.status {
+         // Allow download to be resumed when paused, picking up progress from the
+         // partial file so the first event reflects where the download resumes.
+         DownloadStatus::Paused => {
+            let progress = self.resumed_progress(&item).or(item.progress);
+            self.spawn_download(DownloadItem { progress, ..item }, "failed to resume")
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            DownloadStatus::InProgress,
+         )),
+      }
+   }
+
+   ///
+   /// Retries a failed download operation. The download resumes from its partial file,
+   /// or restarts from zero if the file is gone or the resource changed since.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn retry(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      match item.status {
+         // Reset to idle and start again. A changed resource is detected by the
+         // downloader through `If-Range`.
+         DownloadStatus::Failed => {
+            let idle = DownloadItem {
+               error: None,
+               progress: self.resumed_progress(&item).or(Some(0.0)),
+               ..item.with_status(DownloadStatus::Idle)
+            };
+            self.store.update(idle)?;
+            self.start_inner(path, true)
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            DownloadStatus::InProgress,
+         )),
+      }
+   }
+
+   ///
+   /// Restarts a download operation from zero. Unlike `retry`, the partial file is
+   /// deleted and the stored validators are cleared, so nothing of an earlier attempt
+   /// is reused. Useful when the partial data is known to be bad.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `start` - Whether to start the download once it is reset.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn restart(&self, path: &str, start: bool) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      let expected_status = if start {
+         DownloadStatus::InProgress
+      } else {
+         DownloadStatus::Idle
+      };
+      match item.status {
+         // An active download writes to the temp file, so it must be paused first.
+         DownloadStatus::Idle | DownloadStatus::Paused | DownloadStatus::Failed => {
+            self.discard_temp_file(&item.path)?;
+            let idle = DownloadItem {
+               progress: Some(0.0),
+               downloaded_bytes: 0,
+               etag: None,
+               last_modified: None,
+               content_type: None,
+               active_mirror: None,
+               error: None,
+               truncated: false,
+               deadline_at: None,
+               ..item.with_status(DownloadStatus::Idle)
+            };
+            self.store.update(idle.clone())?;
+            if start {
+               return self.start(path);
+            }
+            self.emit_changed(idle.clone());
+            Ok(DownloadActionResponse::new(idle))
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            expected_status,
+         )),
+      }
+   }
+
+   fn spawn_download(
+      &self,
+      item: DownloadItem,
+      err_msg: &'static str,
+   ) -> crate::Result<DownloadActionResponse> {
+      let slots = self
+         .host_slots
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner);
+      if self.host_is_full(&item.url)? {
+         let queued = item.with_status(DownloadStatus::Queued);
+         self.store.update(queued.clone())?;
+         info!(file = %filename(&queued.path), "Queued download until its host has a free slot");
+         self.emit_changed(queued.clone());
+         return Ok(DownloadActionResponse::new(queued));
+      }
+      let item_in_progress = item.with_status(DownloadStatus::InProgress);
+      self.store.update(item_in_progress.clone())?;
+      drop(slots);
+      self.run_download(item_in_progress.clone(), err_msg);
+      Ok(DownloadActionResponse::new(item_in_progress))
+   }
+
+   /// Returns whether the host of `url` has as many downloads in progress as
+   /// [`max_downloads_per_host`](DownloadManagerConfig::max_downloads_per_host) allows.
+   /// Call with `host_slots` held.
+   fn host_is_full(&self, url: &str) -> crate::Result<bool> {
+      let Some(max) = self.config.max_downloads_per_host else {
+         return Ok(false);
+      };
+      let host = host_of(url);
+      let in_progress = self
+         .store
+         .list_by_status(&DownloadStatus::InProgress)?
+         .into_iter()
+         .filter(|item| host_of(&item.url) == host)
+         .count();
+      Ok(in_progress >= max as usize)
+   }
+
+   /// Returns the paths of the downloads queued for a free slot on their host, rather
+   /// than on a prerequisite.
+   fn waiting_for_slot(&self) -> crate::Result<HashSet<String>> {
+      if self.config.max_downloads_per_host.is_none() {
+         return Ok(HashSet::new());
+      }
+      let mut waiting = HashSet::new();
+      for item in self.store.list_by_status(&DownloadStatus::Queued)? {
+         if self.find_prerequisite(&item)?.is_none() {
+            waiting.insert(item.path);
+         }
+      }
+      Ok(waiting)
+   }
+
+   /// Starts the downloads queued for a free slot on their host, in order, while their
+   /// host has one. Downloads queued on an incomplete prerequisite keep waiting.
+   fn start_queued(&self) {
+      let slots = self
+         .host_slots
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner);
+      let mut queued = match self.store.list_by_status(&DownloadStatus::Queued) {
+         Ok(queued) => queued,
+         Err(e) => {
+            warn!("Failed to start queued downloads: {}", e);
+            return;
+         }
+      };
+      // The sort is stable, so downloads of equal priority keep their order.
+      queued.sort_by_key(|item| std::cmp::Reverse(item.priority));
+      let mut started = Vec::new();
+      for item in queued {
+         if !matches!(self.find_prerequisite(&item), Ok(None))
+            || self.host_is_full(&item.url).unwrap_or(true)
+         {
+            continue;
+         }
+         let item_in_progress = item.with_status(DownloadStatus::InProgress);
+         match self.store.update(item_in_progress.clone()) {
+            Ok(()) => started.push(item_in_progress),
+            Err(e) => self.store_failed(&item.path, "Failed to start queued download", &e),
+         }
+      }
+      drop(slots);
+
+      for item in started {
+         info!(file = %filename(&item.path), "Starting queued download");
+         self.emit_changed(item.clone());
+         self.run_download(item, "failed to start");
+      }
+   }
+
+   /// Runs the download of an item already marked `InProgress` in the store on a
+   /// background task, reverting it to a resumable state if the download fails.
+   fn run_download(&self, item: DownloadItem, err_msg: &'static str) {
+      // Report resumed progress right away rather than after the first new chunk.
+      if item.progress.is_some_and(|progress| progress > 0.0) {
+         self.emit_changed(item.clone());
+      }
+
+      let Some(runtime) = self.runtime.clone().or_else(|| Handle::try_current().ok()) else {
+         error!(file = %filename(&item.path), "Download {}: no Tokio runtime to run on", err_msg);
+         if let Err(e) = self.revert_in_progress(&item) {
+            self.store_failed(&item.path, "Failed to revert download item", &e);
+         }
+         self.notify_waiters(&item.path, Err("No Tokio runtime to run on".to_string()));
+         return;
+      };
+
+      // Hold the lock until the task is registered, so that it can't finish before.
+      let mut tasks = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+      let path = item.path.clone();
+      let previous = tasks.remove(&path).map(DownloadTask::supersede);
+      let superseded = Arc::new(AtomicBool::new(false));
+      let manager = self.clone();
+      let mut item = item;
+      // However the task ends, even aborted, its slot on the host goes to a queued
+      // download.
+      let slot = (self.config.max_downloads_per_host.is_some()).then(|| HostSlot(self.clone()));
+      let task = runtime.spawn(SUPERSEDED.scope(superseded.clone(), async move {
+         let _slot = slot;
+         // The task of a download paused before has to let go of the temp file first.
+         if let Some(previous) = previous {
+            let _ = previous.await;
+         }
+
+         // Bytes already in the temp file are not received by this run.
+         let started = Instant::now();
+         let resumed_bytes =
+            fs::metadata(manager.temp_path(&item.path)).map_or(0, |metadata| metadata.len());
+         if let Some(observer) = &manager.observer {
+            observer.on_start(&item);
+         }
+
+         // The downloader updates `item.path` if the filename is resolved from the response,
+         // and marks `item` completed once it completes. The deadline runs from the first
+         // start, so a download resumed past it fails right away; otherwise the download is
+         // dropped when it passes, which keeps its temp file.
+         let deadline_at = manager.arm_deadline(&mut item);
+         let download = downloader::download(&manager, &mut item);
+         let mut timed_out = false;
+         let result = match deadline_at.map(|at| at.saturating_sub(now_ms())) {
+            Some(0) => {
+               drop(download);
+               timed_out = true;
+               Err(deadline_exceeded())
+            }
+            Some(remaining) => tokio::time::timeout(Duration::from_millis(remaining), download)
+               .await
+               .unwrap_or_else(|_| {
+                  timed_out = true;
+                  Err(deadline_exceeded())
+               }),
+            None => download.await,
+         };
+         if let Ok(mut tasks) = manager.tasks.lock()
+            && tasks
+               .get(&item.path)
+               .is_some_and(|task| task.handle.id() == tokio::task::id())
+         {
+            tasks.remove(&item.path);
+         }
+         let failed = result.is_err()
+            || matches!(
+               manager.store.find_by_path(&item.path),
+               Ok(Some(current)) if current.status == DownloadStatus::Failed
+            );
+         manager.record_stats(
+            u64::from(item.status == DownloadStatus::Completed),
+            u64::from(failed),
+         );
+         if let Some(observer) = &manager.observer {
+            match &result {
+               Ok(()) if item.status == DownloadStatus::Completed => observer.on_complete(
+                  &item,
+                  item.downloaded_bytes.saturating_sub(resumed_bytes),
+                  started.elapsed(),
+               ),
+               Ok(()) => {}
+               Err(e) => observer.on_fail(&item, e),
+            }
+         }
+         if let Err(e) = result {
+            let path = item.path;
+            error!(file = %filename(&path), "Download {}: {}", err_msg, e);
+            if matches!(e, Error::Store(_)) {
+               manager.emit_event(DownloadEvent::StoreError(format!(
+                  "Download {}: {}",
+                  err_msg, e
+               )));
+            }
+
+            // Revert unless already paused or canceled. A write error the user can fix,
+            // e.g. by freeing space, or an exceeded deadline fails the download with its
+            // reason instead, keeping the temp file so that a retry resumes from it.
+            if let Ok(Some(current)) = manager.store.find_by_path(&path)
+               && current.status == DownloadStatus::InProgress
+            {
+               let reverted = if timed_out || matches!(e, Error::Write(_)) {
+                  manager.fail_resumable(&current, &e)
+               } else {
+                  manager.revert_in_progress(&current)
+               };
+               match reverted {
+                  Ok(reverted) => {
+                     info!(file = %filename(&reverted.path), status = %reverted.status, "Reverted download item")
+                  }
+                  Err(e) => manager.store_failed(&path, "Failed to revert download item", &e),
+               }
+               manager.notify_waiters(&path, Err(e.to_string()));
+            }
+            manager.fail_dependents(&path, &prerequisite_failed(&path));
+         }
+      }));
+      tasks.insert(
+         path,
+         DownloadTask {
+            handle: task,
+            superseded,
+         },
+      );
+   }
+
+   ///
+   /// Pauses a download operation.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn pause(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      match item.status {
+         // Allow download to be paused when in progress.
+         DownloadStatus::InProgress => {
+            let paused = item.with_status(DownloadStatus::Paused);
+            self.store.update(paused.clone())?;
+            self.stop_task(path);
+            self.emit_changed(paused.clone());
+            Ok(DownloadActionResponse::new(paused))
+         }
+
+         // Also when waiting for a free slot on its host.
+         DownloadStatus::Queued if self.waiting_for_slot()?.contains(path) => {
+            let paused = item.with_status(DownloadStatus::Paused);
+            self.store.update(paused.clone())?;
+            self.emit_changed(paused.clone());
+            Ok(DownloadActionResponse::new(paused))
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            DownloadStatus::Paused,
+         )),
+      }
+   }
+
+   ///
+   /// Pauses all in progress download operations in a single store update. Downloads in
+   /// any other state are left untouched.
+   ///
+   /// # Returns
+   /// The paused download operations.
+   pub fn pause_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      // Downloads waiting for a free slot on their host are paused too, so that none
+      // starts once the others stop.
+      let waiting = self.waiting_for_slot()?;
+      let paused = self.store.update_where(&mut |item| {
+         (item.status == DownloadStatus::InProgress || waiting.contains(&item.path))
+            .then(|| item.with_status(DownloadStatus::Paused))
+      })?;
+
+      Ok(paused
+         .into_iter()
+         .map(|item| {
+            self.stop_task(&item.path);
+            self.emit_changed(item.clone());
+            DownloadActionResponse::new(item)
+         })
+         .collect())
+   }
+
+   ///
+   /// Reports whether the connection is metered, if
+   /// [`pause_on_metered`](DownloadManagerConfig::pause_on_metered) is set. On a metered
+   /// connection, the downloads in progress are paused; once it isn't metered, the
+   /// downloads paused that way are resumed. Metering is detected on Windows; elsewhere
+   /// the app reports it, e.g. from the network APIs of the platform.
+   ///
+   /// # Arguments
+   /// - `metered` - Whether the connection is metered.
+   ///
+   /// # Returns
+   /// The paused or resumed download operations.
+   pub fn set_metered(&self, metered: bool) -> crate::Result<Vec<DownloadActionResponse>> {
+      if !self.config.pause_on_metered {
+         return Ok(Vec::new());
+      }
+      let mut metered_paused = self
+         .metered_paused
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner);
+      if metered {
+         let paused = self.pause_all()?;
+         metered_paused.extend(paused.iter().map(|paused| paused.download.path.clone()));
+         info!(
+            count = paused.len(),
+            "Paused downloads on a metered connection"
+         );
+         return Ok(paused);
+      }
+
+      // Downloads resumed, canceled or removed in the meantime are left alone.
+      let mut resumed = Vec::new();
+      for path in metered_paused.drain() {
+         if let Some(item) = self.store.find_by_path(&path)?
+            && item.status == DownloadStatus::Paused
+         {
+            resumed.push(self.resume(&path)?);
+         }
+      }
+      info!(
+         count = resumed.len(),
+         "Resumed downloads on an unmetered connection"
+      );
+      Ok(resumed)
+   }
+
+   /// Polls the connection for metering, pausing and resuming downloads as it changes.
+   /// Does nothing where metering isn't detected.
+   fn watch_metering(&self) {
+      if !cfg!(windows) {
+         debug!("Metered connections aren't detected on this platform");
+         return;
+      }
+      let Some(runtime) = self.runtime.clone().or_else(|| Handle::try_current().ok()) else {
+         warn!("Metered connections: no Tokio runtime to poll on");
+         return;
+      };
+      let manager = self.clone();
+      runtime.spawn(async move {
+         let mut last = None;
+         loop {
+            let metered = metering::is_metered();
+            if metered.is_some() && metered != last {
+               last = metered;
+               if let Err(e) = manager.set_metered(metered == Some(true)) {
+                  warn!("Failed to apply the metering of the connection: {}", e);
+               }
+            }
+            tokio::time::sleep(METERED_POLL_INTERVAL).await;
+         }
+      });
+   }
+
+   ///
+   /// Resumes all paused download operations in a single store update.
+   ///
+   /// # Returns
+   /// The resumed download operations.
+   pub fn resume_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      // With a limit per host, the downloads are queued, then start as their host has
+      // room.
+      let limited = self.config.max_downloads_per_host.is_some();
+      let status = if limited {
+         DownloadStatus::Queued
+      } else {
+         DownloadStatus::InProgress
+      };
+      let resumed = self.store.update_where(&mut |item| {
+         (item.status == DownloadStatus::Paused).then(|| {
+            let progress = self.resumed_progress(item).or(item.progress);
+            DownloadItem {
+               progress,
+               ..item.with_status(status.clone())
+            }
+         })
+      })?;
+
+      if limited {
+         self.start_queued();
+         return Ok(resumed
+            .into_iter()
+            .map(|item| {
+               // Those started were reported as they started.
+               let current = self.store.find_by_path(&item.path).ok().flatten();
+               let current = current.unwrap_or(item);
+               if current.status == DownloadStatus::Queued {
+                  self.emit_changed(current.clone());
+               }
+               DownloadActionResponse::new(current)
+            })
+            .collect());
+      }
+      Ok(resumed
+         .into_iter()
+         .map(|item| {
+            self.run_download(item.clone(), "failed to resume");
+            DownloadActionResponse::new(item)
+         })
+         .collect())
+   }
+
+   ///
+   /// Pauses the given download operations, each like [`pause`](Self::pause).
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order. A download that can't be paused, e.g.
+   /// because it isn't found, reports its error without affecting the others.
+   pub fn pause_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(for_each_path(paths, |path| self.pause(path)))
+   }
+
+   ///
+   /// Resumes the given download operations, each like [`resume`](Self::resume).
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order.
+   pub fn resume_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(for_each_path(paths, |path| self.resume(path)))
+   }
+
+   ///
+   /// Cancels the given download operations, each like [`cancel`](Self::cancel).
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order.
+   pub fn cancel_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(for_each_path(paths, |path| self.cancel(path)))
+   }
+
+   ///
+   /// Cancels all created, in progress, paused, queued and failed download operations in
+   /// a single store update, removing their temp files.
+   ///
+   /// # Returns
+   /// The canceled download operations.
+   pub fn cancel_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      let removed = self.store.delete_where(&mut |item| {
+         matches!(
+            item.status,
+            DownloadStatus::Idle
+               | DownloadStatus::InProgress
+               | DownloadStatus::Paused
+               | DownloadStatus::Queued
+               | DownloadStatus::Failed
+         )
+      })?;
+
+      Ok(removed
+         .into_iter()
+         .map(|item| {
+            self.abort_task(&item.path);
+            let temp_path = self.temp_path(&item.path);
+            if fs::remove_file(&temp_path).is_err() {
+               debug!(file = %filename(&item.path), "Temp file was not found or could not be deleted");
+            }
+
+            let canceled = item.with_status(DownloadStatus::Canceled);
+            self.emit_changed(canceled.clone());
+            DownloadActionResponse::new(canceled)
+         })
+         .collect())
+   }
+
+   ///
+   /// Cancels a download operation. A running download stops at once, closing its
+   /// connection, and its temp file is removed.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn cancel(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      validate::path(path)?;
+
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      match item.status {
+         // Allow download to be canceled when created, in progress, paused, queued or failed.
+         DownloadStatus::Idle
+         | DownloadStatus::InProgress
+         | DownloadStatus::Paused
+         | DownloadStatus::Queued
+         | DownloadStatus::Failed => {
+            self.store.delete(&item.path)?;
+            self.abort_task(&item.path);
+            let temp_path = self.temp_path(&item.path);
+            if fs::remove_file(&temp_path).is_err() {
+               debug!(file = %filename(&item.path), "Temp file was not found or could not be deleted");
+            }
+
+            self.emit_changed(item.with_status(DownloadStatus::Canceled));
+            self.fail_dependents(&item.path, &prerequisite_canceled(&item.path));
+            Ok(DownloadActionResponse::new(
+               item.with_status(DownloadStatus::Canceled),
+            ))
+         }
+
+         // Return current state if in any other state.
+         _ => Ok(DownloadActionResponse::with_expected_status(
+            item,
+            DownloadStatus::Canceled,
+         )),
+      }
+   }
+
+   ///
+   /// Removes all completed download operations from the store in a single write. They
+   /// are only kept in the store with
+   /// [`keep_completed`](DownloadManagerConfig::keep_completed).
+   ///
+   /// Only the store records are removed; the downloaded files are left on disk. No
+   /// change events are emitted for the removed items.
+   ///
+   /// # Returns
+   /// The number of download operations removed.
+   pub fn clear_completed(&self) -> crate::Result<usize> {
+      let removed = self
+         .store
+         .delete_where(&mut |item| item.status == DownloadStatus::Completed)?;
+      info!("Cleared {} completed downloads", removed.len());
+      Ok(removed.len())
+   }
+
+   ///
+   /// Moves a completed download to another path, e.g. from a temporary directory to a
+   /// folder chosen by the user.
+   ///
+   /// The file is renamed, or copied and then deleted when the new path is on another
+   /// volume, and the store entry of the download is moved to the new path. Only
+   /// completed downloads in the store can be moved, so they must be kept with
+   /// [`keep_completed`](DownloadManagerConfig::keep_completed).
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `new_path` - The path to move the download to, which must not exist yet.
+   ///
+   /// # Returns
+   /// The download at its new path.
+   pub fn move_to(&self, path: &str, new_path: &str) -> crate::Result<DownloadItem> {
+      validate::path(path)?;
+      validate::path(new_path)?;
+      self.validate_within_root(path)?;
+      self.validate_within_root(new_path)?;
+
+      let item = self.find_completed(path)?;
+      if Path::new(new_path).exists() || self.store.find_by_path(new_path)?.is_some() {
+         return Err(Error::File(format!(
+            "Destination already exists: {}",
+            new_path
+         )));
+      }
+
+      if let Some(folder) = Path::new(new_path).parent() {
+         fs::create_dir_all(folder)
+            .map_err(|e| Error::File(format!("Failed to create directory: {}", e)))?;
+      }
+      move_file(Path::new(path), Path::new(new_path))?;
+
+      let moved = DownloadItem {
+         path: new_path.to_string(),
+         ..item
+      };
+      self.store.rename(path, moved.clone())?;
+      info!(from = %path, to = %new_path, "Moved download");
+      self.emit_changed(moved.clone());
+      Ok(moved)
+   }
+
+   ///
+   /// Sets the priority of a download. Among the downloads queued for a free slot on
+   /// their host, those of higher priority start first; a download in progress keeps
+   /// running. The priority of a queued download takes effect the next time a slot
+   /// frees up.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `priority` - The priority, higher starting sooner. Downloads default to 0.
+   ///
+   /// # Returns
+   /// The download with its new priority.
+   pub fn set_priority(&self, path: &str, priority: i32) -> crate::Result<DownloadItem> {
+      validate::path(path)?;
+      let item = self
+         .store
+         .find_by_path(path)?
+         .ok_or_else(|| Error::NotFound(path.to_string()))?;
+      if item.priority == priority {
+         return Ok(item);
+      }
+      let updated = DownloadItem { priority, ..item };
+      self.store.update(updated.clone())?;
+      info!(file = %filename(path), priority, "Set download priority");
+      self.emit_changed(updated.clone());
+      Ok(updated)
+   }
+
+   ///
+   /// Shows a completed download in the file manager of the OS: Explorer and Finder
+   /// open its folder with the file selected, while on Linux its folder is opened with
+   /// `xdg-open`.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// Nothing once the file manager is launched. Fails with [`Error::InvalidState`] if
+   /// the download isn't completed, and with [`Error::NotFound`] if it isn't in the
+   /// store, as completed downloads are only kept with
+   /// [`keep_completed`](DownloadManagerConfig::keep_completed), or if its file is gone.
+   pub fn reveal(&self, path: &str) -> crate::Result<()> {
+      validate::path(path)?;
+      self.validate_within_root(path)?;
+      self.find_completed(path)?;
+      if !Path::new(path).is_file() {
+         return Err(Error::NotFound(path.to_string()));
+      }
+      reveal::reveal(Path::new(path))
+   }
+
+   ///
+   /// Cancels all downloads and clears the store.
+   ///
+   /// Every store entry is removed in a single write and the temp file of each entry is
+   /// deleted from disk. Running downloads stop at once, the same as after `cancel`. A
+   /// single `reset` event is emitted instead of a change event per item.
+   ///
+   /// # Returns
+   /// A summary of what was removed.
+   pub fn reset(&self) -> crate::Result<ResetSummary> {
+      let items = self.store.clear()?;
+
+      let mut summary = ResetSummary {
+         removed: items.len(),
+         ..Default::default()
+      };
+      for item in &items {
+         if matches!(
+            item.status,
+            DownloadStatus::Idle
+               | DownloadStatus::InProgress
+               | DownloadStatus::Paused
+               | DownloadStatus::Queued
+               | DownloadStatus::Failed
+         ) {
+            summary.canceled += 1;
+         }
+         self.notify_waiters(&item.path, Err(format!("Download canceled: {}", item.path)));
+         self.abort_task(&item.path);
+
+         let temp_path = self.temp_path(&item.path);
+         if fs::remove_file(&temp_path).is_ok() {
+            summary.temp_files_removed += 1;
+         }
+      }
+
+      info!(
+         removed = summary.removed,
+         canceled = summary.canceled,
+         temp_files_removed = summary.temp_files_removed,
+         "Reset download store"
+      );
+      self.emit_event(DownloadEvent::Reset(summary.clone()));
+      Ok(summary)
+   }
+
+   /// Returns the incomplete prerequisite of `item`, if any. A prerequisite that is no
+   /// longer in the store has completed, as failed and canceled ones fail `item` first.
+   fn find_prerequisite(&self, item: &DownloadItem) -> crate::Result<Option<DownloadItem>> {
+      match &item.options.depends_on {
+         Some(depends_on) => Ok(self
+            .store
+            .find_by_path(depends_on)?
+            .filter(|prerequisite| prerequisite.status != DownloadStatus::Completed)),
+         None => Ok(None),
+      }
+   }
+
+   /// Returns the time the download of `item` has to complete by, if a deadline is
+   /// configured, first setting it on `item` and in the store if it has none yet.
+   fn arm_deadline(&self, item: &mut DownloadItem) -> Option<u64> {
+      let deadline_ms = self.config.deadline_ms?;
+      if item.deadline_at.is_none() {
+         let at = now_ms().saturating_add(deadline_ms);
+         item.deadline_at = Some(at);
+         let stored = match self.store.find_by_path(&item.path) {
+            Ok(Some(current)) => self.store.update(DownloadItem {
+               deadline_at: Some(at),
+               ..current
+            }),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+         };
+         if let Err(e) = stored {
+            self.store_failed(&item.path, "Failed to store download deadline", &e);
+         }
+      }
+      item.deadline_at
+   }
+
+   /// Stores `completed`, or removes it from the store unless
+   /// [`keep_completed`](DownloadManagerConfig::keep_completed) is set. Its `ETag` is
+   /// kept either way, for a later download to the same path.
+   pub(crate) fn store_completed(&self, completed: &DownloadItem) -> crate::Result<()> {
+      self
+         .store
+         .set_etag(&completed.path, completed.etag.as_deref())?;
+      if self.config.keep_completed {
+         self.store.update(completed.clone())
+      } else {
+         self.store.delete(&completed.path)
+      }
+   }
+
+   /// Starts the downloads queued on the prerequisite at `path`, which has completed.
+   pub(crate) fn start_dependents(&self, path: &str) {
+      // With a limit per host, they start as their host has room.
+      if self.config.max_downloads_per_host.is_some() {
+         self.start_queued();
+         return;
+      }
+      let started = self.store.update_where(&mut |item| {
+         (item.status == DownloadStatus::Queued && item.options.depends_on.as_deref() == Some(path))
+            .then(|| item.with_status(DownloadStatus::InProgress))
+      });
+      match started {
+         Ok(started) => {
+            for item in started {
+               info!(file = %filename(&item.path), "Starting queued download");
+               self.emit_changed(item.clone());
+               self.run_download(item, "failed to start");
+            }
+         }
+         Err(e) => self.store_failed(path, "Failed to start queued downloads", &e),
+      }
+   }
+
+   /// Fails the downloads queued on the prerequisite at `path`, and in turn the
+   /// downloads queued on those.
+   pub(crate) fn fail_dependents(&self, path: &str, error: &str) {
+      let failed = self.store.update_where(&mut |item| {
+         (item.status == DownloadStatus::Queued && item.options.depends_on.as_deref() == Some(path))
+            .then(|| item.with_error(error.to_string()))
+      });
+      match failed {
+         Ok(failed) => {
+            for item in failed {
+               warn!(file = %filename(&item.path), "Queued download failed: {}", error);
+               self.emit_changed(item.clone());
+               self.fail_dependents(&item.path, &prerequisite_failed(&item.path));
+            }
+         }
+         Err(e) => self.store_failed(path, "Failed to fail queued downloads", &e),
+      }
+   }
+
+   /// Reverts an `InProgress` download item to `Paused` or `Idle` based on
+   /// whether a temp file exists on disk. No-op for other statuses.
+   fn revert_in_progress(&self, item: &DownloadItem) -> crate::Result<DownloadItem> {
+      if item.status != DownloadStatus::InProgress {
+         return Ok(item.clone());
+      }
+
+      let temp_path = self.temp_path(&item.path);
+      let reverted = if Path::new(&temp_path).exists() {
+         item.with_status(DownloadStatus::Paused)
+      } else {
+         item.with_status(DownloadStatus::Idle)
+      };
+
+      self.store.update(reverted.clone())?;
+      self.emit_changed(reverted.clone());
+      Ok(reverted)
+   }
+
+   /// Fails an `InProgress` download item with `error`, keeping its temp file and the
+   /// bytes in it, so that [`retry`](Self::retry) resumes from them.
+   fn fail_resumable(&self, item: &DownloadItem, error: &Error) -> crate::Result<DownloadItem> {
+      let temp_path = self.temp_path(&item.path);
+      let failed = DownloadItem {
+         downloaded_bytes: fs::metadata(temp_path).map_or(0, |metadata| metadata.len()),
+         ..item.with_error(error.to_string())
+      };
+      self.store.update(failed.clone())?;
+      self.emit_changed(failed.clone());
+      Ok(failed)
+   }
+
+   /// Path of the temp file a download to `path` is written to until it completes:
+   /// next to `path`, or in the configured [`temp_dir`](DownloadManagerConfig::temp_dir)
+   /// under a name derived from the whole of `path` so downloads of files with the same
+   /// name don't collide. Either way, it ends with the configured
+   /// [`temp_suffix`](DownloadManagerConfig::temp_suffix).
+   pub(crate) fn temp_path(&self, path: &str) -> String {
+      let suffix = self
+         .config
+         .temp_suffix
+         .as_deref()
+         .filter(|suffix| !suffix.is_empty())
+         .unwrap_or(DOWNLOAD_SUFFIX);
+      match &self.config.temp_dir {
+         Some(dir) => {
+            let name = format!("{:016x}-{}{}", stable_hash(path), filename(path), suffix);
+            dir.join(name).to_string_lossy().into_owned()
+         }
+         None => format!("{}{}", path, suffix),
+      }
+   }
+
+   /// Deletes the temp file an earlier attempt of the download to `path` left behind,
+   /// if any.
+   fn discard_temp_file(&self, path: &str) -> crate::Result<()> {
+      let temp_path = self.temp_path(path);
+      if Path::new(&temp_path).exists() {
+         fs::remove_file(&temp_path)
+            .map_err(|e| Error::File(format!("Failed to delete temp file: {}", e)))?;
+         info!(file = %path, "Discarded temp file of an earlier attempt");
+      }
+      Ok(())
+   }
+
+   /// Progress of a paused download derived from the size of its temp file and the
+   /// stored total size, or `None` if either is unknown. A download the server doesn't
+   /// accept range requests for resumes from zero.
+   fn resumed_progress(&self, item: &DownloadItem) -> Option<f64> {
+      if item.accepts_ranges == Some(false) {
+         return Some(0.0);
+      }
+      let total_size = item.total_size.filter(|&size| size > 0)?;
+      let downloaded_size = fs::metadata(self.temp_path(&item.path)).ok()?.len();
+      Some(progress_of(downloaded_size, total_size))
+   }
+
+   pub(crate) fn emit_changed(&self, item: DownloadItem) {
+      debug!(file = %filename(&item.path), status = %item.status, progress = ?item.progress);
+      match item.status {
+         DownloadStatus::Completed => self.notify_waiters(&item.path, Ok(item.clone())),
+         DownloadStatus::Canceled => {
+            self.notify_waiters(&item.path, Err(format!("Download canceled: {}", item.path)))
+         }
+         DownloadStatus::Failed => self.notify_waiters(&item.path, Err(failure_message(&item))),
+         _ => {}
+      }
+      if self.changes.receiver_count() > 0 {
+         let _ = self.changes.send(item.clone());
+      }
+      match self.config.change_batch_ms {
+         Some(window) if window > 0 => self.batch_change(item, Duration::from_millis(window)),
+         _ => (self.on_changed)(item),
+      }
+   }
+
+   /// Adds `item` to the changes reported once `window` elapsed since the first of them,
+   /// replacing an earlier change of the same download.
+   fn batch_change(&self, item: DownloadItem, window: Duration) {
+      let mut pending = self
+         .pending_changes
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner);
+      let first = pending.is_empty();
+      match pending.iter_mut().find(|pending| pending.path == item.path) {
+         Some(pending) => *pending = item,
+         None => pending.push(item),
+      }
+      drop(pending);
+      if !first {
+         return;
+      }
+
+      // Without a runtime to wait on, the change is reported right away.
+      let Some(runtime) = self.runtime.clone().or_else(|| Handle::try_current().ok()) else {
+         self.flush_changes();
+         return;
+      };
+      let manager = self.clone();
+      runtime.spawn(async move {
+         tokio::time::sleep(window).await;
+         manager.flush_changes();
+      });
+   }
+
+   /// Returns the bearer token requests are sent with, asking the provider for one if
+   /// none was fetched yet or if `refresh` is set. `None` without a provider.
+   pub(crate) async fn bearer_token(&self, refresh: bool) -> Option<String> {
+      let provider = self.token_provider.as_ref()?;
+      if !refresh
+         && let Some(token) = self
+            .token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+      {
+         return Some(token);
+      }
+      let token = provider().await;
+      *self.token.lock().unwrap_or_else(PoisonError::into_inner) = Some(token.clone());
+      Some(token)
+   }
+
+   /// Counts bytes received by a download towards the lifetime statistics. They are
+   /// added to the store by [`flush_stats`](Self::flush_stats).
+   pub(crate) fn count_bytes(&self, bytes: u64) {
+      self.received_bytes.fetch_add(bytes, Ordering::Relaxed);
+   }
+
+   /// Adds the bytes counted since the last flush to the lifetime statistics in the
+   /// store. Called with every progress update, as a download completes or fails, and on
+   /// [`flush`](Self::flush). The store persists them along with progress, so this
+   /// doesn't write to disk for every call.
+   pub(crate) fn flush_stats(&self) {
+      self.add_stats(DownloadStats::default());
+   }
+
+   /// Adds `completed` and `failed` downloads to the lifetime statistics in the store,
+   /// flushing the bytes counted along with them.
+   pub(crate) fn record_stats(&self, completed: u64, failed: u64) {
+      self.add_stats(DownloadStats {
+         total_bytes: 0,
+         total_completed: completed,
+         total_failed: failed,
+      });
+   }
+
+   /// Adds `stats` and the bytes counted since the last flush to the store.
+   fn add_stats(&self, mut stats: DownloadStats) {
+      stats.total_bytes += self.received_bytes.swap(0, Ordering::Relaxed);
+      if stats == DownloadStats::default() {
+         return;
+      }
+      if let Err(e) = self.store.add_stats(&stats) {
+         // Keep the bytes for the next attempt.
+         self.count_bytes(stats.total_bytes);
+         warn!("Failed to record download statistics: {}", e);
+      }
+   }
+
+   /// Logs a store failure that can't be returned to a caller, and reports it with a
+   /// [`DownloadEvent::StoreError`] event so the app can warn that the state of the
+   /// downloads may not survive a restart.
+   fn store_failed(&self, path: &str, context: &str, e: &Error) {
+      warn!(file = %filename(path), "{}: {}", context, e);
+      self.emit_event(DownloadEvent::StoreError(format!(
+         "{} '{}': {}",
+         context, path, e
+      )));
+   }
+
+   /// Reports the changes collected in the current batch, if any.
+   fn flush_changes(&self) {
+      let changes = std::mem::take(
+         &mut *self
+            .pending_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+      );
+      if !changes.is_empty() {
+         self.emit_event(DownloadEvent::ChangedBatch(changes));
+      }
+   }
+
+   /// Returns the HTTP client downloads are sent with, building it if that failed with
+   /// the manager.
+   pub(crate) fn client(&self) -> crate::Result<ClientWithMiddleware> {
+      if let Some(client) = self.client.get() {
+         return Ok(client.clone());
+      }
+      // Concurrent downloads may each build a client; all use the one stored.
+      let client = downloader::build_client(&self.config, self.cookies.clone())?;
+      Ok(self.client.get_or_init(|| client).clone())
+   }
+
+   /// Runs the completion hooks on `item`, returning the error of the first hook that
+   /// fails the download, if any. Every hook runs regardless.
+   pub(crate) fn run_completion_hooks(&self, item: &DownloadItem) -> Option<String> {
+      let hooks = self
+         .completion_hooks
+         .lock()
+         .unwrap_or_else(PoisonError::into_inner)
+         .clone();
+      let mut failure = None;
+      for (hook, fail_on_error) in hooks {
+         if let Err(e) = hook(item) {
+            error!(file = %item.path, "Completion hook failed: {}", e);
+            if fail_on_error && failure.is_none() {
+               failure = Some(e);
+            }
+         }
+      }
+      failure
+   }
+
+   pub(crate) fn emit_event(&self, event: DownloadEvent) {
+      debug!(event = event.name());
+      (self.on_event)(event);
+   }
+}
+
+tokio::task_local! {
+   /// Superseded flag of the download the current task runs.
+   static SUPERSEDED: Arc<AtomicBool>;
+}
+
+/// Task running a download, and the flag set once it is superseded.
+struct DownloadTask {
+   handle: JoinHandle<()>,
+   superseded: Arc<AtomicBool>,
+}
+
+impl DownloadTask {
+   /// Marks the task superseded, as it is no longer registered for its path.
+   fn supersede(self) -> JoinHandle<()> {
+      self.superseded.store(true, Ordering::Relaxed);
+      self.handle
+   }
+}
+
+/// Slot of a running download on its host, which starts the next queued download once
+/// dropped, see [`max_downloads_per_host`](DownloadManagerConfig::max_downloads_per_host).
+struct HostSlot(DownloadManager);
+
+impl Drop for HostSlot {
+   fn drop(&mut self) {
+      self.0.start_queued();
+   }
+}
+
+/// Returns the host of `url`, by which downloads are limited per host.
+fn host_of(url: &str) -> Option<String> {
+   url::Url::parse(url)
+      .ok()
+      .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Returns the path of the store of the download set `name` in `data_dir`, falling back
+/// to the default store if `name` is empty once sanitized.
+fn named_store_path(data_dir: &Path, name: &str) -> PathBuf {
+   match filename::sanitize(name) {
+      Some(name) => data_dir.join(format!("downloads-{}.json", name)),
+      None => {
+         warn!(
+            "Invalid download set name '{}', using the default store",
+            name
+         );
+         data_dir.join(STORE_FILE_NAME)
+      }
+   }
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+   SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Error of a download that didn't complete by its deadline.
+fn deadline_exceeded() -> Error {
+   Error::Http("deadline exceeded".to_string())
+}
+
+fn failure_message(item: &DownloadItem) -> String {
+   item
+      .error
+      .clone()
+      .unwrap_or_else(|| format!("Download failed: {}", item.path))
+}
+
+pub(crate) fn prerequisite_failed(path: &str) -> String {
+   format!("Prerequisite download failed: {}", path)
+}
+
+fn prerequisite_canceled(path: &str) -> String {
+   format!("Prerequisite download was canceled: {}", path)
+}
+
+/// Stable FNV-1a hash of `value`, which names staged temp files. Unlike the std
+/// hashers it doesn't change between releases, so a staged download still resumes
+/// after an update.
+fn stable_hash(value: &str) -> u64 {
+   value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+      (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+   })
+}
+
+/// Moves a file, falling back to copying and deleting it when `from` and `to` are on
+/// different volumes.
+pub(crate) fn move_file(from: &Path, to: &Path) -> crate::Result<()> {
+   let (from, to) = (validate::long_path(from), validate::long_path(to));
+   match fs::rename(&from, &to) {
+      Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+         fs::copy(&from, &to).map_err(|e| Error::File(format!("Failed to copy file: {}", e)))?;
+         fs::remove_file(&from)
+            .map_err(|e| Error::File(format!("Failed to remove moved file: {}", e)))
+      }
+      result => result.map_err(|e| Error::File(format!("Failed to move file: {}", e))),
+   }
+}
+
+/// Runs `action` on each path, collecting its outcome.
+fn for_each_path(
+   paths: &[String],
+   action: impl Fn(&str) -> crate::Result<DownloadActionResponse>,
+) -> Vec<BatchActionResult> {
+   paths
+      .iter()
+      .map(|path| match action(path) {
+         Ok(response) => BatchActionResult {
+            path: path.clone(),
+            response: Some(response),
+            error: None,
+         },
+         Err(e) => BatchActionResult {
+            path: path.clone(),
+            response: None,
+            error: Some(e.to_string()),
+         },
+      })
+      .collect()
+}
+
+/// Returns the SHA-256 digest of the file at `path` in lowercase hex, reading it in
+/// chunks, or `None` if there is no such file.
+fn sha256_file(path: &Path) -> std::io::Result<Option<String>> {
+   use sha2::{Digest, Sha256};
+   use std::io::Read;
+
+   let mut file = match fs::File::open(path) {
+      Ok(file) => file,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e),
+   };
+   let mut hasher = Sha256::new();
+   let mut buf = vec![0u8; 64 * 1024];
+   loop {
+      match file.read(&mut buf)? {
+         0 => break,
+         n => hasher.update(&buf[..n]),
+      }
+   }
+   Ok(Some(
+      hasher
+         .finalize()
+         .iter()
+         .map(|byte| format!("{:02x}", byte))
+         .collect(),
+   ))
+}
+
+fn filename(path: &str) -> &str {
+   Path::new(path)
+      .file_name()
+      .and_then(|s| s.to_str())
+      .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::config::HostPolicy;
+   use crate::error::UrlError;
+   use crate::store::InMemoryStore;
+   use std::sync::Mutex;
+   use tempfile::TempDir;
+
+   const VALID_URL: &str = "https://example.com/file.mp4";
+
+   type EventLog = Arc<Mutex<Vec<DownloadItem>>>;
+
+   fn make_manager() -> (DownloadManager, TempDir, EventLog) {
+      let dir = TempDir::new().unwrap();
+      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+      let captured = events.clone();
+      let on_changed: OnChanged = Arc::new(move |item| {
+         captured.lock().unwrap().push(item);
+      });
+      let manager = DownloadManager::new(dir.path().to_path_buf(), on_changed);
+      (manager, dir, events)
+   }
+
+   fn event_log(events: &EventLog) -> Vec<DownloadItem> {
+      events.lock().unwrap().clone()
+   }
+
+   fn clear_events(events: &EventLog) {
+      events.lock().unwrap().clear();
+   }
+
+   fn seed(manager: &DownloadManager, path: &str, status: DownloadStatus) {
+      manager
+         .store
+         .create(DownloadItem {
+            url: VALID_URL.to_string(),
+            path: path.to_string(),
+            progress: Some(0.0),
+            status,
+            ..Default::default()
+         })
+         .unwrap();
+   }
+
+   // ---------- list ----------
+
+   #[test]
+   fn test_list_and_count_by_status() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Idle);
+      seed(&manager, "/tmp/b.mp4", DownloadStatus::Completed);
+      seed(&manager, "/tmp/c.mp4", DownloadStatus::Completed);
+
+      let completed = manager.list_by_status(DownloadStatus::Completed).unwrap();
+      assert_eq!(completed.len(), 2);
+      assert!(
+         completed
+            .iter()
+            .all(|i| i.status == DownloadStatus::Completed)
+      );
+      assert!(
+         manager
+            .list_by_status(DownloadStatus::Paused)
+            .unwrap()
+            .is_empty()
+      );
+
+      let counts = manager.count_by_status().unwrap();
+      assert_eq!(counts.len(), 2);
+      assert_eq!(counts[&DownloadStatus::Idle], 1);
+      assert_eq!(counts[&DownloadStatus::Completed], 2);
+   }
+
+   #[test]
+   fn test_list_by_tag_returns_tagged_downloads() {
+      let (manager, _dir, _events) = make_manager();
+      let options = |tags: &[&str]| DownloadOptions {
+         tags: tags.iter().map(|tag| tag.to_string()).collect(),
+         ..Default::default()
+      };
+      let created = manager
+         .create_with_options("/tmp/a.mp3", VALID_URL, options(&["podcasts"]))
+         .unwrap();
+      assert_eq!(created.download.options.tags, ["podcasts"]);
+      manager
+         .create_with_options(
+            "/tmp/b.mp3",
+            "https://example.com/b.mp3",
+            options(&["podcasts", "favorites"]),
+         )
+         .unwrap();
+      manager
+         .create("/tmp/c.mp3", "https://example.com/c.mp3")
+         .unwrap();
+
+      let podcasts = manager.list_by_tag("podcasts").unwrap();
+      assert_eq!(podcasts.len(), 2);
+      assert_eq!(
+         manager.list_by_tag("favorites").unwrap()[0].path,
+         "/tmp/b.mp3"
+      );
+      assert!(manager.list_by_tag("attachments").unwrap().is_empty());
+   }
+
+   #[test]
+   fn test_aggregate_progress_of_active_downloads() {
+      let (manager, dir, _events) = make_manager();
+      let active = [
+         ("/tmp/a.mp4", DownloadStatus::InProgress, 250, Some(1000)),
+         ("/tmp/b.mp4", DownloadStatus::Paused, 500, Some(1000)),
+         // Unknown size: counted, but excluded from the bytes and the percentage.
+         ("/tmp/c.mp4", DownloadStatus::InProgress, 4096, None),
+         // Not active.
+         ("/tmp/d.mp4", DownloadStatus::Idle, 0, Some(1000)),
+      ];
+      for (path, status, downloaded_bytes, total_size) in active {
+         seed(&manager, path, status);
+         let item = manager.store.find_by_path(path).unwrap().unwrap();
+         manager
+            .store
+            .update(DownloadItem {
+               downloaded_bytes,
+               total_size,
+               ..item
+            })
+            .unwrap();
+      }
+      // A paused entry without tracked bytes falls back to its temp file.
+      let path = dir.path().join("e.mp4").to_string_lossy().to_string();
+      fs::write(format!("{}{}", path, DOWNLOAD_SUFFIX), [0u8; 250]).unwrap();
+      seed(&manager, &path, DownloadStatus::Paused);
+      let item = manager.store.find_by_path(&path).unwrap().unwrap();
+      manager
+         .store
+         .update(DownloadItem {
+            total_size: Some(1000),
+            ..item
+         })
+         .unwrap();
+
+      let aggregate = manager.aggregate_progress().unwrap();
+      assert_eq!(
+         aggregate,
+         AggregateProgress {
+            total_bytes: 3000,
+            downloaded_bytes: 1000,
+            active_count: 4,
+            combined_percent: 1000.0 / 3000.0 * 100.0,
+         }
+      );
+   }
+
+   #[test]
+   fn test_aggregate_progress_without_active_downloads() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Completed);
+      assert_eq!(
+         manager.aggregate_progress().unwrap(),
+         AggregateProgress::default()
+      );
+   }
+
+   #[tokio::test]
+   async fn test_downloaded_bytes_of_temp_or_final_file() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let (manager, dir, _events) = make_manager();
+      let partial = dir.path().join("a.mp4").to_string_lossy().to_string();
+      seed(&manager, &partial, DownloadStatus::Paused);
+      fs::write(manager.temp_path(&partial), [0u8; 250]).unwrap();
+      assert_eq!(manager.downloaded_bytes(&partial).unwrap(), 250);
+
+      // A completed download is no longer in the store; its final file counts.
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/b.mp4"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1000]))
+         .mount(&server)
+         .await;
+      let completed = dir.path().join("b.mp4").to_string_lossy().to_string();
+      let url = format!("{}/b.mp4", server.uri());
+      manager
+         .download_now(&completed, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(manager.downloaded_bytes(&completed).unwrap(), 1000);
+
+      // Nothing on disk, whether or not the download is in the store.
+      let idle = dir.path().join("c.mp4").to_string_lossy().to_string();
+      seed(&manager, &idle, DownloadStatus::Idle);
+      assert_eq!(manager.downloaded_bytes(&idle).unwrap(), 0);
+      let unknown = dir.path().join("d.mp4").to_string_lossy().to_string();
+      assert_eq!(manager.downloaded_bytes(&unknown).unwrap(), 0);
+   }
+
+   fn make_manager_with_duplicate_urls(
+      policy: DuplicateUrlPolicy,
+   ) -> (DownloadManager, TempDir, EventLog) {
+      let dir = TempDir::new().unwrap();
+      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+      let captured = events.clone();
+      let on_changed: OnChanged = Arc::new(move |item| {
+         captured.lock().unwrap().push(item);
+      });
+      let config = DownloadManagerConfig {
+         duplicate_urls: policy,
+         ..Default::default()
+      };
+      let manager = DownloadManager::with_config(dir.path().to_path_buf(), config, on_changed);
+      (manager, dir, events)
+   }
+
+   #[test]
+   fn test_find_by_url_returns_all_paths() {
+      let (manager, _dir, _events) = make_manager();
+      manager.create("/tmp/a.mp4", VALID_URL).unwrap();
+      manager.create("/tmp/b.mp4", VALID_URL).unwrap();
+      manager
+         .create("/tmp/c.mp4", "https://example.com/other.mp4")
+         .unwrap();
+
+      let found = manager.find_by_url(VALID_URL).unwrap();
+      assert_eq!(found.len(), 2);
+   }
+
+   #[test]
+   fn test_duplicate_url_block_refuses_unfinished_duplicate() {
+      let (manager, _dir, _events) = make_manager_with_duplicate_urls(DuplicateUrlPolicy::Block);
+      manager.create("/tmp/a.mp4", VALID_URL).unwrap();
+
+      let result = manager.create("/tmp/b.mp4", VALID_URL);
+      assert!(matches!(result, Err(Error::DuplicateUrl(_))));
+      // Creating the same path again is not a duplicate.
+      assert!(manager.create("/tmp/a.mp4", VALID_URL).is_ok());
+      assert_eq!(manager.list().unwrap().len(), 1);
+   }
+
+   #[test]
+   fn test_validate_create_reports_errors_without_writing() {
+      let (manager, dir, events) = make_manager_with_duplicate_urls(DuplicateUrlPolicy::Block);
+      manager.create("/tmp/a.mp4", VALID_URL).unwrap();
+      clear_events(&events);
+
+      assert!(
+         manager
+            .validate_create("/tmp/b.mp4", "https://example.com/b.mp4")
+            .is_ok()
+      );
+      // The existing download would be returned.
+      assert!(manager.validate_create("/tmp/a.mp4", VALID_URL).is_ok());
+      assert!(matches!(
+         manager.validate_create("/tmp/b.mp4", VALID_URL),
+         Err(Error::DuplicateUrl(_))
+      ));
+      assert!(matches!(
+         manager.validate_create("relative.mp4", VALID_URL),
+         Err(Error::Path(PathError::NotAbsolute, _))
+      ));
+      assert!(matches!(
+         manager.validate_create("/tmp/b.mp4", "ftp://example.com/b.mp4"),
+         Err(Error::Url(UrlError::BadScheme, _))
+      ));
+      let taken = dir.path().join("taken.mp4");
+      fs::write(&taken, b"file").unwrap();
+      let options = DownloadOptions {
+         overwrite: OverwritePolicy::Fail,
+         ..Default::default()
+      };
+      assert!(matches!(
+         manager.validate_create_with_options(
+            &taken.to_string_lossy(),
+            "https://example.com/taken.mp4",
+            &options
+         ),
+         Err(Error::File(_))
+      ));
+
+      assert_eq!(manager.list().unwrap().len(), 1);
+      assert!(event_log(&events).is_empty());
+   }
+
+   #[test]
+   fn test_create_stores_normalized_url() {
+      let (manager, _dir, _events) = make_manager_with_duplicate_urls(DuplicateUrlPolicy::Block);
+      let created = manager
+         .create("/tmp/a.mp4", "HTTPS://Example.COM:443/a.mp4#chapter-2")
+         .unwrap();
+      assert_eq!(created.download.url, "https://example.com/a.mp4");
+      assert_eq!(
+         manager.get("/tmp/a.mp4").unwrap().url,
+         "https://example.com/a.mp4"
+      );
+
+      // An equivalent URL is the same resource.
+      assert!(matches!(
+         manager.create("/tmp/b.mp4", "https://example.com/a.mp4#chapter-3"),
+         Err(Error::DuplicateUrl(_))
+      ));
+   }
+
+   #[test]
+   fn test_duplicate_url_block_ignores_completed_downloads() {
+      let (manager, _dir, _events) = make_manager_with_duplicate_urls(DuplicateUrlPolicy::Block);
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Completed);
+
+      assert!(manager.create("/tmp/b.mp4", VALID_URL).is_ok());
+   }
+
+   #[test]
+   fn test_duplicate_url_warn_and_allow_create() {
+      for policy in [DuplicateUrlPolicy::Warn, DuplicateUrlPolicy::Allow] {
+         let (manager, _dir, _events) = make_manager_with_duplicate_urls(policy);
+         manager.create("/tmp/a.mp4", VALID_URL).unwrap();
+         assert!(manager.create("/tmp/b.mp4", VALID_URL).is_ok());
+         assert_eq!(manager.list().unwrap().len(), 2);
+      }
+   }
+
+   #[test]
+   fn test_named_managers_keep_separate_stores() {
+      let dir = TempDir::new().unwrap();
+      let on_changed: OnChanged = Arc::new(|_| {});
+      let alice = DownloadManager::new_named(dir.path().to_path_buf(), "alice", on_changed.clone());
+      let bob = DownloadManager::new_named(dir.path().to_path_buf(), "bob", on_changed.clone());
+      alice.create("/tmp/a.mp4", VALID_URL).unwrap();
+      bob.create("/tmp/b.mp4", VALID_URL).unwrap();
+
+      assert!(dir.path().join("downloads-alice.json").exists());
+      assert!(dir.path().join("downloads-bob.json").exists());
+      assert!(!dir.path().join("downloads.json").exists());
+
+      let reloaded = DownloadManager::new_named(dir.path().to_path_buf(), "alice", on_changed);
+      let paths: Vec<_> = reloaded
+         .list()
+         .unwrap()
+         .into_iter()
+         .map(|i| i.path)
+         .collect();
+      assert_eq!(paths, vec!["/tmp/a.mp4"]);
+   }
+
+   #[test]
+   fn test_named_store_path_strips_separators() {
+      let dir = Path::new("/data");
+      assert_eq!(
+         named_store_path(dir, "../profile"),
+         dir.join("downloads-profile.json")
+      );
+      assert_eq!(named_store_path(dir, ".."), dir.join("downloads.json"));
+   }
+
+   #[test]
+   fn test_in_memory_store_needs_no_directory() {
+      let manager = DownloadManager::new_with_store(InMemoryStore::new(), Arc::new(|_| {}));
+      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+      assert_eq!(manager.list().unwrap().len(), 1);
+      assert_eq!(manager.get("/tmp/file.mp4").unwrap().url, VALID_URL);
+   }
+
+   // ---------- get ----------
+
+   #[test]
+   fn test_get_returns_pending_for_unknown_path() {
+      let (manager, _dir, _events) = make_manager();
+      let item = manager.get("/tmp/unknown.mp4").unwrap();
+      assert_eq!(item.path, "/tmp/unknown.mp4");
+      assert_eq!(item.status, DownloadStatus::Pending);
+      assert_eq!(item.url, "");
+      assert_eq!(item.progress, Some(0.0));
+   }
+
+   #[test]
+   fn test_get_returns_persisted_item() {
+      let (manager, _dir, _events) = make_manager();
+      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+      let item = manager.get("/tmp/file.mp4").unwrap();
+      assert_eq!(item.status, DownloadStatus::Idle);
+      assert_eq!(item.url, VALID_URL);
+   }
+
+   #[test]
+   fn test_get_rejects_invalid_path() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.get("").is_err());
+   }
+
+   // ---------- create ----------
+
+   #[test]
+   fn test_create_persists_idle_item_and_emits() {
+      let (manager, _dir, events) = make_manager();
+      let response = manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Idle);
+      assert!(response.is_expected_status);
+
+      let stored = manager
+         .store
+         .find_by_path("/tmp/file.mp4")
+         .unwrap()
+         .unwrap();
+      assert_eq!(stored.status, DownloadStatus::Idle);
+      assert_eq!(stored.url, VALID_URL);
+
+      let log = event_log(&events);
+      assert_eq!(log.len(), 1);
+      assert_eq!(log[0].path, "/tmp/file.mp4");
+      assert_eq!(log[0].status, DownloadStatus::Idle);
+   }
+
+   #[test]
+   fn test_create_existing_does_not_overwrite_url() {
+      let (manager, _dir, events) = make_manager();
+      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+
+      let other_url = "https://example.com/other.mp4";
+      let response = manager.create("/tmp/file.mp4", other_url).unwrap();
+      assert_eq!(response.download.url, VALID_URL);
+
+      let stored = manager
+         .store
+         .find_by_path("/tmp/file.mp4")
+         .unwrap()
+         .unwrap();
+      assert_eq!(stored.url, VALID_URL);
+
+      // Only the first create emitted a change event.
+      assert_eq!(event_log(&events).len(), 1);
+   }
+
+   #[test]
+   fn test_create_rejects_invalid_path() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.create("", VALID_URL).is_err());
+   }
+
+   #[test]
+   fn test_create_rejects_invalid_url() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.create("/tmp/file.mp4", "not-a-url").is_err());
+   }
+
+   #[test]
+   fn test_create_rejects_path_outside_download_root() {
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         download_root: Some(dir.path().to_path_buf()),
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let root = dir.path().to_string_lossy();
+
+      assert!(
+         manager
+            .create(&format!("{}/file.mp4", root), VALID_URL)
+            .is_ok()
+      );
+      assert!(matches!(
+         manager.create(&format!("{}/../file.mp4", root), VALID_URL),
+         Err(Error::Path(PathError::OutsideRoot, _))
+      ));
+      assert!(matches!(
+         manager.create("/etc/cron.d/x", VALID_URL),
+         Err(Error::Path(PathError::OutsideRoot, _))
+      ));
+      assert!(manager.create_in_dir("/etc/cron.d", VALID_URL).is_err());
+   }
+
+   #[test]
+   fn test_create_rejects_hosts_not_allowed() {
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         hosts: HostPolicy {
+            allow: vec!["example.com".to_string()],
+            deny: Vec::new(),
+         },
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+
+      assert!(manager.create("/tmp/a.mp4", VALID_URL).is_ok());
+      assert!(matches!(
+         manager.create("/tmp/b.mp4", "https://other.example.org/file.mp4"),
+         Err(Error::Url(UrlError::HostNotAllowed, _))
+      ));
+      let options = DownloadOptions {
+         mirrors: vec!["https://mirror.example.org/file.mp4".to_string()],
+         ..Default::default()
+      };
+      assert!(
+         manager
+            .create_with_options("/tmp/c.mp4", VALID_URL, options)
+            .is_err()
+      );
+      assert!(manager.store.find_by_path("/tmp/c.mp4").unwrap().is_none());
+   }
+
+   #[test]
+   fn test_create_with_options_persists_mirrors() {
+      let (manager, _dir, _events) = make_manager();
+      let options = DownloadOptions {
+         mirrors: vec!["https://mirror.example.com/file.mp4".to_string()],
+         ..Default::default()
+      };
+
+      let response = manager
+         .create_with_options("/tmp/file.mp4", VALID_URL, options.clone())
+         .unwrap();
+      assert_eq!(response.download.options, options);
+
+      let stored = manager
+         .store
+         .find_by_path("/tmp/file.mp4")
+         .unwrap()
+         .unwrap();
+      assert_eq!(stored.options, options);
+   }
+
+   #[test]
+   fn test_create_with_options_rejects_invalid_mirror() {
+      let (manager, _dir, _events) = make_manager();
+      let options = DownloadOptions {
+         mirrors: vec!["not-a-url".to_string()],
+         ..Default::default()
+      };
+      assert!(matches!(
+         manager.create_with_options("/tmp/file.mp4", VALID_URL, options),
+         Err(Error::Url(UrlError::Malformed, _))
+      ));
+      assert!(
+         manager
+            .store
+            .find_by_path("/tmp/file.mp4")
+            .unwrap()
+            .is_none()
+      );
+   }
+
+   #[test]
+   fn test_create_applies_overwrite_policy_to_existing_file() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(&path, b"existing").unwrap();
+      let create = |overwrite| {
+         manager.create_with_options(
+            &path,
+            VALID_URL,
+            DownloadOptions {
+               overwrite,
+               ..Default::default()
+            },
+         )
+      };
+
+      assert!(
+         matches!(create(OverwritePolicy::Fail), Err(Error::File(msg)) if msg.contains("already exists"))
+      );
+
+      let renamed = create(OverwritePolicy::Rename).unwrap().download;
+      assert_eq!(renamed.path, filename::numbered(&path, 1));
+      // The next free path skips the one now taken in the store.
+      let renamed = create(OverwritePolicy::Rename).unwrap().download;
+      assert_eq!(renamed.path, filename::numbered(&path, 2));
+
+      let overwritten = create(OverwritePolicy::Overwrite).unwrap().download;
+      assert_eq!(overwritten.path, path);
+   }
+
+   #[test]
+   fn test_get_or_create_returns_one_item_to_concurrent_callers() {
+      let (manager, _dir, events) = make_manager();
+      let path = "/tmp/downloads/shared.zip";
+
+      let items: Vec<DownloadItem> = std::thread::scope(|scope| {
+         let handles: Vec<_> = (0..8)
+            .map(|_| scope.spawn(|| manager.get_or_create(path, VALID_URL).unwrap()))
+            .collect();
+         handles.into_iter().map(|h| h.join().unwrap()).collect()
+      });
+      assert!(items.iter().all(|item| item.status == DownloadStatus::Idle));
+      assert_eq!(manager.list().unwrap().len(), 1);
+      assert_eq!(event_log(&events).len(), 1);
+
+      // An existing item is returned as is.
+      let other = manager
+         .get_or_create(path, "https://example.com/other.zip")
+         .unwrap();
+      assert_eq!(other.url, VALID_URL);
+   }
+
+   #[test]
+   fn test_create_many_applies_overwrite_policy() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(&path, b"existing").unwrap();
+      let item = |overwrite| BatchCreateItem {
+         path: path.clone(),
+         url: VALID_URL.to_string(),
+         options: DownloadOptions {
+            overwrite,
+            ..Default::default()
+         },
+      };
+
+      let results = manager
+         .create_many(vec![item(OverwritePolicy::Fail)])
+         .unwrap();
+      assert!(
+         results[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("already exists")
+      );
+
+      let results = manager
+         .create_many(vec![item(OverwritePolicy::Rename)])
+         .unwrap();
+      assert_eq!(
+         results[0].response.as_ref().unwrap().download.path,
+         filename::numbered(&path, 1)
+      );
+   }
+
+   // ---------- create_in_dir ----------
+
+   #[test]
+   fn test_create_many_reports_each_item() {
+      let (manager, _dir, events) = make_manager();
+      manager.create("/tmp/existing.mp4", VALID_URL).unwrap();
+      clear_events(&events);
+
+      let item = |path: &str, url: &str| BatchCreateItem {
+         path: path.to_string(),
+         url: url.to_string(),
+         ..Default::default()
+      };
+      let results = manager
+         .create_many(vec![
+            item("/tmp/a.mp4", VALID_URL),
+            item("/tmp/existing.mp4", "https://example.com/other.mp4"),
+            item("/tmp/b.mp4", "not-a-url"),
+            item("/tmp/a.mp4", VALID_URL),
+         ])
+         .unwrap();
+
+      assert_eq!(results.len(), 4);
+      let created = results[0].response.as_ref().unwrap();
+      assert!(created.is_expected_status);
+      assert_eq!(created.download.path, "/tmp/a.mp4");
+      let existing = results[1].response.as_ref().unwrap();
+      assert_eq!(existing.download.url, VALID_URL);
+      assert!(results[2].response.is_none());
+      assert!(results[2].error.is_some());
+      assert_eq!(results[3].path, "/tmp/a.mp4");
+      assert!(results[3].error.as_ref().unwrap().contains("duplicate"));
+
+      assert_eq!(manager.store.list().unwrap().len(), 2);
+      let log = event_log(&events);
+      assert_eq!(log.len(), 1);
+      assert_eq!(log[0].path, "/tmp/a.mp4");
+   }
+
+   #[test]
+   fn test_create_in_dir_uses_url_filename_as_placeholder() {
+      let (manager, _dir, events) = make_manager();
+      let response = manager
+         .create_in_dir("/tmp/downloads", "https://example.com/files/report.pdf?x=1")
+         .unwrap();
+      assert_eq!(response.download.path, "/tmp/downloads/report.pdf");
+      assert!(response.download.resolve_filename);
+      assert_eq!(response.download.status, DownloadStatus::Idle);
+
+      let stored = manager
+         .store
+         .find_by_path("/tmp/downloads/report.pdf")
+         .unwrap()
+         .unwrap();
+      assert!(stored.resolve_filename);
+      assert_eq!(event_log(&events).len(), 1);
+   }
+
+   #[test]
+   fn test_create_in_dir_falls_back_to_default_filename() {
+      let (manager, _dir, _events) = make_manager();
+      let response = manager
+         .create_in_dir("/tmp/downloads", "https://example.com/")
+         .unwrap();
+      assert_eq!(response.download.path, "/tmp/downloads/download");
+   }
+
+   #[test]
+   fn test_create_in_dir_rejects_invalid_input() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.create_in_dir("relative", VALID_URL).is_err());
+      assert!(manager.create_in_dir("/tmp", "not-a-url").is_err());
+   }
+
+   // ---------- download_now / wait_for ----------
+
+   #[tokio::test]
+   async fn test_download_now_resolves_with_completed_item() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+
+      let (manager, dir, _events) = make_manager();
+      let dest = dir.path().join("file.bin");
+      let dest = dest.to_str().unwrap();
+      let url = format!("{}/file.bin", server.uri());
+
+      let item = manager
+         .download_now(dest, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(item.status, DownloadStatus::Completed);
+      assert_eq!(item.path, dest);
+      assert_eq!(fs::read(dest).unwrap(), b"hello");
+   }
+
+   #[test]
+   fn test_with_runtime_runs_downloads_started_outside_of_it() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let runtime = tokio::runtime::Builder::new_current_thread()
+         .enable_all()
+         .build()
+         .unwrap();
+      let server = runtime.block_on(async {
+         let server = MockServer::start().await;
+         Mock::given(method("GET"))
+            .and(wm_path("/file.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+            .mount(&server)
+            .await;
+         server
+      });
+
+      let (manager, dir, events) = make_manager();
+      let manager = manager.with_runtime(runtime.handle().clone());
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      manager
+         .create(&dest, &format!("{}/file.bin", server.uri()))
+         .unwrap();
+
+      // Started from this thread, which is outside of any runtime.
+      manager.start(&dest).unwrap();
+      runtime.block_on(async {
+         while !event_log(&events)
+            .iter()
+            .any(|item| item.status == DownloadStatus::Completed)
+         {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+         }
+      });
+      assert_eq!(fs::read(&dest).unwrap(), b"hello");
+   }
+
+   #[tokio::test]
+   async fn test_observer_is_notified_of_start_completion_and_failure() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      #[derive(Default)]
+      struct Recorder(Mutex<Vec<String>>);
+      impl DownloadObserver for Recorder {
+         fn on_start(&self, item: &DownloadItem) {
+            self
+               .0
+               .lock()
+               .unwrap()
+               .push(format!("start {}", filename(&item.path)));
+         }
+         fn on_complete(&self, item: &DownloadItem, bytes: u64, _duration: Duration) {
+            let name = filename(&item.path);
+            self
+               .0
+               .lock()
+               .unwrap()
+               .push(format!("complete {} {}", name, bytes));
+         }
+         fn on_fail(&self, item: &DownloadItem, _error: &Error) {
+            self
+               .0
+               .lock()
+               .unwrap()
+               .push(format!("fail {}", filename(&item.path)));
+         }
+      }
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/missing.bin"))
+         .respond_with(ResponseTemplate::new(404))
+         .mount(&server)
+         .await;
+
+      let recorder = Arc::new(Recorder::default());
+      let (manager, dir, _events) = make_manager();
+      let manager = manager.with_observer(recorder.clone());
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      let missing = dir.path().join("missing.bin").to_string_lossy().to_string();
+      manager
+         .download_now(
+            &dest,
+            &format!("{}/file.bin", server.uri()),
+            DownloadOptions::default(),
+         )
+         .await
+         .unwrap();
+      let url = format!("{}/missing.bin", server.uri());
+      assert!(
+         manager
+            .download_now(&missing, &url, DownloadOptions::default())
+            .await
+            .is_err()
+      );
+
+      // The observer is notified once the task finishes, after waiters are.
+      let expected = [
+         "start file.bin",
+         "complete file.bin 5",
+         "start missing.bin",
+         "fail missing.bin",
+      ];
+      for _ in 0..100 {
+         if recorder.0.lock().unwrap().len() == expected.len() {
+            break;
+         }
+         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+      }
+      assert_eq!(*recorder.0.lock().unwrap(), expected);
+   }
+
+   #[cfg(target_os = "linux")]
+   #[tokio::test]
+   async fn test_write_error_fails_resumably() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+
+      // Writes to `/dev/full` fail as if the disk were full.
+      let (manager, dir, _events) = make_manager();
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      let url = format!("{}/file.bin", server.uri());
+      let temp_path = manager.temp_path(&dest);
+      std::os::unix::fs::symlink("/dev/full", &temp_path).unwrap();
+
+      let err = manager
+         .download_now(&dest, &url, DownloadOptions::default())
+         .await
+         .unwrap_err();
+      assert!(err.to_string().contains("Write Error"), "{}", err);
+      let failed = manager.get(&dest).unwrap();
+      assert_eq!(failed.status, DownloadStatus::Failed);
+      assert!(failed.error.unwrap().contains("Write Error"));
+      assert!(Path::new(&temp_path).exists());
+
+      // Once space is freed, the download picks up again.
+      fs::remove_file(&temp_path).unwrap();
+      fs::write(&temp_path, b"he").unwrap();
+      manager
+         .download_now(&dest, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"hello");
+   }
+
+   #[tokio::test]
+   async fn test_download_to_writer_and_resume() {
+      use wiremock::matchers::{header, method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .and(header("range", "bytes=6-"))
+         .respond_with(
+            ResponseTemplate::new(206)
+               .insert_header("Content-Range", "bytes 6-10/11")
+               .set_body_bytes(b"world".to_vec()),
+         )
+         .with_priority(1)
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello world".to_vec()))
+         .mount(&server)
+         .await;
+      let (manager, _dir, events) = make_manager();
+      let url = format!("{}/file.bin", server.uri());
+
+      let mut buffer = Vec::new();
+      let mut progress = Vec::new();
+      let written = manager
+         .download_to_writer(&url, &mut buffer, |bytes, total| {
+            progress.push((bytes, total))
+         })
+         .await
+         .unwrap();
+      assert_eq!(written, 11);
+      assert_eq!(buffer, b"hello world");
+      assert_eq!(progress.last(), Some(&(11, Some(11))));
+
+      // A seekable writer resumes after the bytes it holds.
+      let mut partial = std::io::Cursor::new(b"hello ".to_vec());
+      let mut progress = Vec::new();
+      let written = manager
+         .resume_to_writer(&url, &mut partial, |bytes, total| {
+            progress.push((bytes, total))
+         })
+         .await
+         .unwrap();
+      assert_eq!(written, 5);
+      assert_eq!(partial.into_inner(), b"hello world");
+      assert_eq!(progress.last(), Some(&(11, Some(11))));
+
+      // The whole resource can't follow the bytes in the writer.
+      let mut partial = std::io::Cursor::new(b"hel".to_vec());
+      let result = manager
+         .resume_to_writer(&url, &mut partial, |_, _| {})
+         .await;
+      assert!(matches!(result, Err(Error::Http(_))));
+      assert_eq!(partial.into_inner(), b"hel");
+
+      // Nothing was stored.
+      assert!(manager.list().unwrap().is_empty());
+      assert!(event_log(&events).is_empty());
+   }
+
+   #[tokio::test]
+   async fn test_token_provider_refreshes_rejected_token() {
+      use wiremock::matchers::{header, method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .and(header("authorization", "Bearer token-2"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .with_priority(1)
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(401))
+         .mount(&server)
+         .await;
+
+      // Hands out a new token on every call, of which only the second is accepted.
+      let calls = Arc::new(AtomicU64::new(0));
+      let counter = calls.clone();
+      let provider: TokenProvider = Arc::new(move || {
+         let call = counter.fetch_add(1, Ordering::Relaxed) + 1;
+         Box::pin(async move { format!("token-{}", call) })
+      });
+      let (manager, dir, _events) = make_manager();
+      let manager = manager.with_token_provider(provider);
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      let url = format!("{}/file.bin", server.uri());
+      manager
+         .download_now(&dest, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(fs::read(&dest).unwrap(), b"hello");
+      assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+      // The refreshed token is kept for the next downloads.
+      let other = dir.path().join("other.bin").to_string_lossy().to_string();
+      manager
+         .download_now(&other, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(calls.load(Ordering::Relaxed), 2);
+   }
+
+   #[tokio::test]
+   async fn test_subscribe_streams_changes() {
+      use futures::StreamExt;
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+
+      let (manager, dir, events) = make_manager();
+      let changes = manager.subscribe();
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      manager
+         .create(&dest, &format!("{}/file.bin", server.uri()))
+         .unwrap();
+      manager.start(&dest).unwrap();
+
+      // The stream ends with the completion, and sees what the callback does.
+      let streamed: Vec<DownloadItem> = tokio::time::timeout(
+         Duration::from_secs(5),
+         changes
+            .take_while(|item| futures::future::ready(item.status != DownloadStatus::Completed))
+            .collect(),
+      )
+      .await
+      .unwrap();
+      assert_eq!(streamed[0].status, DownloadStatus::Idle);
+
+      // The callback gets the completion right after the stream.
+      while event_log(&events)
+         .last()
+         .is_none_or(|item| item.status != DownloadStatus::Completed)
+      {
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+      let statuses = |items: &[DownloadItem]| {
+         items
+            .iter()
+            .map(|item| item.status.clone())
+            .collect::<Vec<_>>()
+      };
+      let mut expected = statuses(&event_log(&events));
+      assert_eq!(expected.pop(), Some(DownloadStatus::Completed));
+      assert_eq!(statuses(&streamed), expected);
+   }
+
+   #[test]
+   fn test_flush_stats_moves_counted_bytes_to_store() {
+      let (manager, _dir, _events) = make_manager();
+      manager.count_bytes(42);
+      assert_eq!(manager.store.stats().unwrap().total_bytes, 0);
+
+      manager.flush_stats();
+      assert_eq!(manager.store.stats().unwrap().total_bytes, 42);
+      assert_eq!(manager.stats().unwrap().total_bytes, 42);
+   }
+
+   #[tokio::test]
+   async fn test_stats_count_downloads_across_restarts() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .and(wm_path("/missing.bin"))
+         .respond_with(ResponseTemplate::new(404))
+         .mount(&server)
+         .await;
+
+      let (manager, dir, _events) = make_manager();
+      let dest = dir.path().join("file.bin").to_string_lossy().to_string();
+      let missing = dir.path().join("missing.bin").to_string_lossy().to_string();
+      let url = format!("{}/file.bin", server.uri());
+      manager
+         .download_now(&dest, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      let url = format!("{}/missing.bin", server.uri());
+      let _ = manager
+         .download_now(&missing, &url, DownloadOptions::default())
+         .await;
+
+      // Statistics are recorded once the task finishes, after waiters are notified.
+      let expected = DownloadStats {
+         total_bytes: 5,
+         total_completed: 1,
+         total_failed: 1,
+      };
+      for _ in 0..100 {
+         if manager.stats().unwrap() == expected {
+            break;
+         }
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+      assert_eq!(manager.stats().unwrap(), expected);
+
+      manager.flush().unwrap();
+      let reloaded = DownloadManager::new(dir.path().to_path_buf(), Arc::new(|_| {}));
+      assert_eq!(reloaded.stats().unwrap(), expected);
+      reloaded.reset_stats().unwrap();
+      assert_eq!(reloaded.stats().unwrap(), DownloadStats::default());
+   }
+
+   #[tokio::test]
+   async fn test_cancel_interrupts_stalled_stream() {
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      // Sends part of the body, then stalls until the client closes the connection.
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      let (closed_tx, closed_rx) = oneshot::channel();
+      tokio::spawn(async move {
+         let (mut socket, _) = listener.accept().await.unwrap();
+         let mut request = [0u8; 1024];
+         let _ = socket.read(&mut request).await;
+         let _ = socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\npartial")
+            .await;
+         while socket.read(&mut request).await.is_ok_and(|read| read > 0) {}
+         let _ = closed_tx.send(());
+      });
+
+      let (manager, dir, _events) = make_manager();
+      let dest = dir.path().join("slow.bin").to_string_lossy().to_string();
+      let temp_path = format!("{}{}", dest, DOWNLOAD_SUFFIX);
+      manager
+         .create(&dest, &format!("http://{}/slow", addr))
+         .unwrap();
+      manager.start(&dest).unwrap();
+      while !Path::new(&temp_path).exists() {
+         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+      }
+
+      manager.cancel(&dest).unwrap();
+
+      tokio::time::timeout(std::time::Duration::from_secs(2), closed_rx)
+         .await
+         .expect("connection should close promptly after cancel")
+         .unwrap();
+      assert!(!Path::new(&temp_path).exists());
+      assert!(manager.tasks.lock().unwrap().is_empty());
+   }
+
+   #[test]
+   fn test_start_outside_of_runtime_reverts_item() {
+      let (manager, _dir, _events) = make_manager();
+      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+
+      manager.start("/tmp/file.mp4").unwrap();
+
+      let item = manager.get("/tmp/file.mp4").unwrap();
+      assert_eq!(item.status, DownloadStatus::Idle);
+   }
+
+   #[tokio::test]
+   async fn test_download_now_rejects_on_failure() {
+      use wiremock::matchers::method;
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .respond_with(ResponseTemplate::new(404))
+         .mount(&server)
+         .await;
+
+      let (manager, dir, _events) = make_manager();
+      let dest = dir.path().join("missing.bin");
+      let url = format!("{}/missing.bin", server.uri());
+
+      let result = manager
+         .download_now(dest.to_str().unwrap(), &url, DownloadOptions::default())
+         .await;
+      assert!(matches!(result, Err(Error::Failed(_))));
+   }
+
+   #[tokio::test]
+   async fn test_wait_for_rejects_when_canceled() {
+      let (manager, _dir, _events) = make_manager();
+      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+
+      let (result, _) = tokio::join!(manager.wait_for("/tmp/file.mp4"), async {
+         tokio::task::yield_now().await;
+         manager.cancel("/tmp/file.mp4").unwrap();
+      });
+      assert!(matches!(result, Err(Error::Failed(_))));
+   }
+
+   #[tokio::test]
+   async fn test_wait_for_unknown_path_returns_not_found() {
+      let (manager, _dir, _events) = make_manager();
+      let result = manager.wait_for("/tmp/unknown.mp4").await;
+      assert!(matches!(result, Err(Error::NotFound(_))));
+   }
+
+   // ---------- start ----------
+
+   #[test]
+   fn test_start_unknown_path_returns_not_found() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(matches!(
+         manager.start("/tmp/unknown.mp4"),
+         Err(Error::NotFound(_))
+      ));
+   }
+
+   #[test]
+   fn test_start_rejects_invalid_path() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.start("").is_err());
+   }
+
+   #[test]
+   fn test_start_from_non_idle_does_not_change_state() {
+      let (manager, _dir, _events) = make_manager();
+      let path = "/tmp/file.mp4";
+      seed(&manager, path, DownloadStatus::InProgress);
+
+      let response = manager.start(path).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+      assert_eq!(response.expected_status, DownloadStatus::InProgress);
+      assert!(response.is_expected_status);
+
+      let stored = manager.store.find_by_path(path).unwrap().unwrap();
+      assert_eq!(stored.status, DownloadStatus::InProgress);
+   }
+
+   // ---------- resume ----------
+
+   #[test]
+   fn test_resume_unknown_path_returns_not_found() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(matches!(
+         manager.resume("/tmp/unknown.mp4"),
+         Err(Error::NotFound(_))
+      ));
+   }
+
+   #[test]
+   fn test_resume_rejects_invalid_path() {
+      let (manager, _dir, _events) = make_manager();
+      assert!(manager.resume("").is_err());
+   }
+
+   #[test]
+   fn test_resumed_progress_from_temp_file_and_total_size() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(format!("{}{}", path, DOWNLOAD_SUFFIX), [0u8; 62]).unwrap();
+
+      let item = DownloadItem {
+         path,
+         total_size: Some(100),
+         ..Default::default()
+      };
+      assert_eq!(manager.resumed_progress(&item), Some(62.0));
+
+      // Unknown total size or missing temp file can't be resolved.
+      let unknown = DownloadItem {
+         total_size: None,
+         ..item.clone()
+      };
+      assert_eq!(manager.resumed_progress(&unknown), None);
+      let missing = DownloadItem {
+         path: dir.path().join("missing.mp4").to_string_lossy().to_string(),
+         ..item
+      };
+      assert_eq!(manager.resumed_progress(&missing), None);
+   }
+
+   #[test]
+   fn test_resume_from_non_paused_does_not_change_state() {
+      let (manager, _dir, _events) = make_manager();
+      let path = "/tmp/file.mp4";
+      seed(&manager, path, DownloadStatus::Idle);
+
+      let response = manager.resume(path).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Idle);
+      assert_eq!(response.expected_status, DownloadStatus::InProgress);
+      assert!(!response.is_expected_status);
+
+      let stored = manager.store.find_by_path(path).unwrap().unwrap();
+      assert_eq!(stored.status, DownloadStatus::Idle);
+   }
+
+   /// Starts an `Idle` download whose temp file holds `partial`, as left behind when
+   /// the store entry was recreated, from a server that honors ranges. With `retry`, the
+   /// download is failed and retried instead. Returns the final file and the `Range`
+   /// header of the request, if any.
+   async fn start_with_partial_file(
+      policy: PartialFilePolicy,
+      partial: &[u8],
+      body: &[u8],
+      retry: bool,
+   ) -> (Vec<u8>, Option<String>) {
+      use wiremock::matchers::{header, method};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      let range = format!("bytes={}-", partial.len());
+      Mock::given(method("GET"))
+         .and(header("Range", range.as_str()))
+         .respond_with(ResponseTemplate::new(206).set_body_bytes(&body[partial.len()..]))
+         .mount(&server)
+         .await;
+      Mock::given(method("GET"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+         .with_priority(2)
+         .mount(&server)
+         .await;
+
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         partial_files: policy,
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let path = dir.path().join("file.bin").to_string_lossy().to_string();
+      fs::write(format!("{}{}", path, DOWNLOAD_SUFFIX), partial).unwrap();
+      manager
+         .create(&path, &format!("{}/file.bin", server.uri()))
+         .unwrap();
+
+      let item = if retry {
+         let item = manager.store.find_by_path(&path).unwrap().unwrap();
+         manager
+            .store
+            .update(item.with_error("Connection reset".to_string()))
+            .unwrap();
+         // The download only runs once the test yields, so the wait starts first.
+         manager.retry(&path).unwrap();
+         manager.wait_for(&path).await
+      } else {
+         let (item, _) = tokio::join!(manager.wait_for(&path), async {
+            manager.start(&path).unwrap();
+         });
+         item
+      };
+      assert_eq!(item.unwrap().status, DownloadStatus::Completed);
+
+      let requests = server.received_requests().await.unwrap();
+      assert_eq!(requests.len(), 1);
+      let range = requests[0]
+         .headers
+         .get("Range")
+         .map(|value| value.to_str().unwrap().to_string());
+      (fs::read(&path).unwrap(), range)
+   }
+
+   #[tokio::test]
+   async fn test_start_resumes_from_existing_temp_file() {
+      let body = b"0123456789abcdef";
+      let (file, range) =
+         start_with_partial_file(PartialFilePolicy::Resume, &body[..6], body, false).await;
+      assert_eq!(file, body);
+      assert_eq!(range.as_deref(), Some("bytes=6-"));
+   }
+
+   #[tokio::test]
+   async fn test_start_restart_policy_discards_existing_temp_file() {
+      // Stale bytes that don't belong to the resource must not end up in the file.
+      let body = b"0123456789abcdef";
+      let (file, range) =
+         start_with_partial_file(PartialFilePolicy::Restart, b"stale", body, false).await;
+      assert_eq!(file, body);
+      assert_eq!(range, None);
+   }
+
+   #[tokio::test]
+   async fn test_retry_restart_policy_resumes_existing_temp_file() {
+      // The policy only applies to a start; a retry continues the failed attempt.
+      let body = b"0123456789abcdef";
+      let (file, range) =
+         start_with_partial_file(PartialFilePolicy::Restart, &body[..6], body, true).await;
+      assert_eq!(file, body);
+      assert_eq!(range.as_deref(), Some("bytes=6-"));
+   }
+
+   #[test]
+   fn test_temp_path_in_temp_dir_is_unique_per_path() {
+      let staging = TempDir::new().unwrap();
+      let manager = DownloadManager::with_config(
+         staging.path().to_path_buf(),
+         DownloadManagerConfig {
+            temp_dir: Some(staging.path().join("partial")),
+            ..Default::default()
+         },
+         Arc::new(|_| {}),
+      );
+
+      let music = manager.temp_path("/downloads/music/file.mp3");
+      let podcasts = manager.temp_path("/downloads/podcasts/file.mp3");
+      assert_ne!(music, podcasts);
+      assert_eq!(music, manager.temp_path("/downloads/music/file.mp3"));
+      for temp_path in [&music, &podcasts] {
+         let temp_path = Path::new(temp_path);
+         assert_eq!(
+            temp_path.parent(),
+            Some(staging.path().join("partial").as_path())
+         );
+         assert!(temp_path.to_string_lossy().ends_with("-file.mp3.download"));
+      }
+
+      // Without a temp directory, the temp file is next to the download.
+      let (manager, _dir, _events) = make_manager();
+      assert_eq!(
+         manager.temp_path("/downloads/file.mp3"),
+         "/downloads/file.mp3.download"
+      );
+   }
+
+   // ---------- retry ----------
+
+   #[tokio::test]
+   async fn test_retry_failed_resumes_from_temp_file() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(format!("{}{}", path, DOWNLOAD_SUFFIX), [0u8; 40]).unwrap();
+      manager
+         .store
+         .create(
+            DownloadItem {
+               url: VALID_URL.to_string(),
+               path: path.clone(),
+               progress: Some(10.0),
+               total_size: Some(100),
+               ..Default::default()
+            }
+            .with_error("Connection reset".to_string()),
+         )
+         .unwrap();
+
+      let response = manager.retry(&path).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+      assert_eq!(response.download.error, None);
+      assert_eq!(response.download.progress, Some(40.0));
+   }
+
+   #[tokio::test]
+   async fn test_retry_without_temp_file_starts_from_zero() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      manager
+         .store
+         .create(
+            DownloadItem {
+               url: VALID_URL.to_string(),
+               path: path.clone(),
+               progress: Some(55.0),
+               total_size: Some(100),
+               ..Default::default()
+            }
+            .with_error("Connection reset".to_string()),
+         )
+         .unwrap();
+
+      let response = manager.retry(&path).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+      assert_eq!(response.download.progress, Some(0.0));
+   }
+
+   #[test]
+   fn test_retry_from_non_failed_does_not_change_state() {
+      let (manager, _dir, _events) = make_manager();
+      let path = "/tmp/file.mp4";
+      seed(&manager, path, DownloadStatus::Paused);
+
+      let response = manager.retry(path).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Paused);
+      assert!(!response.is_expected_status);
+      assert!(matches!(
+         manager.retry("/tmp/unknown.mp4"),
+         Err(Error::NotFound(_))
+      ));
+   }
+
+   // ---------- restart ----------
+
+   #[tokio::test]
+   async fn test_restart_deletes_temp_file_and_resets_state() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      let temp_path = format!("{}{}", path, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, [0u8; 40]).unwrap();
+      manager
+         .store
+         .create(
+            DownloadItem {
+               url: VALID_URL.to_string(),
+               path: path.clone(),
+               progress: Some(40.0),
+               total_size: Some(100),
+               downloaded_bytes: 40,
+               etag: Some("\"v1\"".to_string()),
+               ..Default::default()
+            }
+            .with_error("Corrupt data".to_string()),
+         )
+         .unwrap();
+
+      let response = manager.restart(&path, false).unwrap();
+      assert!(response.is_expected_status);
+      assert_eq!(response.download.status, DownloadStatus::Idle);
+      assert_eq!(response.download.progress, Some(0.0));
+      assert_eq!(response.download.downloaded_bytes, 0);
+      assert_eq!(response.download.etag, None);
+      assert_eq!(response.download.error, None);
+      assert!(!Path::new(&temp_path).exists());
+
+      let stored = manager.get(&path).unwrap();
+      assert_eq!(stored.status, DownloadStatus::Idle);
+      assert_eq!(stored.progress, Some(0.0));
+   }
+
+   #[tokio::test]
+   async fn test_restart_can_start_the_download() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(format!("{}{}", path, DOWNLOAD_SUFFIX), [0u8; 40]).unwrap();
+      seed(&manager, &path, DownloadStatus::Paused);
+
+      let response = manager.restart(&path, true).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+      assert_eq!(response.download.progress, Some(0.0));
+   }
+
+   #[test]
+   fn test_restart_in_progress_does_not_change_state() {
+      let (manager, _dir, _events) = make_manager();
+      let path = "/tmp/file.mp4";
+      seed(&manager, path, DownloadStatus::InProgress);
+
+      let response = manager.restart(path, false).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+      assert!(!response.is_expected_status);
+      assert!(matches!(
+         manager.restart("/tmp/unknown.mp4", false),
+         Err(Error::NotFound(_))
+      ));
+   }
+
+   #[tokio::test]
+   async fn test_deadline_stops_download_and_keeps_temp_file() {
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         deadline_ms: Some(200),
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
+
+      let err = tokio::time::timeout(Duration::from_secs(5), manager.wait_for(&path))
+         .await
+         .expect("deadline should stop the download")
+         .unwrap_err();
+      assert!(err.to_string().contains("deadline exceeded"), "{}", err);
 
-            self.emit_changed(item.with_status(DownloadStatus::Canceled));
-            Ok(DownloadActionResponse::new(
-               item.with_status(DownloadStatus::Canceled),
-            ))
+      // The bytes received are kept with the error, and a retry resumes from them.
+      assert_eq!(fs::read(manager.temp_path(&path)).unwrap(), b"0123456789");
+      let failed = manager.get(&path).unwrap();
+      assert_eq!(failed.status, DownloadStatus::Failed);
+      assert!(failed.error.unwrap().contains("deadline exceeded"));
+      assert_eq!(failed.downloaded_bytes, 10);
+      assert!(manager.tasks.lock().unwrap().is_empty());
+   }
+
+   #[tokio::test]
+   async fn test_deadline_runs_from_first_start() {
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         deadline_ms: Some(60_000),
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
+      let wait_for_deadline = || async {
+         let until = Instant::now() + Duration::from_secs(5);
+         loop {
+            if let Some(at) = manager.get(&path).unwrap().deadline_at {
+               return at;
+            }
+            assert!(Instant::now() < until, "deadline should be set");
+            tokio::time::sleep(Duration::from_millis(10)).await;
          }
+      };
+      let deadline_at = wait_for_deadline().await;
 
-         // Return current state if in any other state.
-         _ => Ok(DownloadActionResponse::with_expected_status(
-            item,
-            DownloadStatus::Canceled,
-         )),
-      }
+      // Pausing and resuming keeps the deadline.
+      manager.pause(&path).unwrap();
+      manager.resume(&path).unwrap();
+      tokio::time::sleep(Duration::from_millis(50)).await;
+      assert_eq!(manager.get(&path).unwrap().deadline_at, Some(deadline_at));
+
+      // A download resumed past its deadline fails right away.
+      manager.pause(&path).unwrap();
+      let paused = manager.get(&path).unwrap();
+      manager
+         .store
+         .update(DownloadItem {
+            deadline_at: Some(1),
+            ..paused
+         })
+         .unwrap();
+      let outcome = manager.register_waiter(&path).unwrap();
+      manager.resume(&path).unwrap();
+      let err = tokio::time::timeout(Duration::from_secs(1), manager.wait_on(&path, outcome))
+         .await
+         .expect("the download should fail right away")
+         .unwrap_err();
+      assert!(err.to_string().contains("deadline exceeded"), "{}", err);
+      assert_eq!(manager.get(&path).unwrap().status, DownloadStatus::Failed);
+
+      // A retry gets a new deadline.
+      manager.retry(&path).unwrap();
+      assert!(wait_for_deadline().await > 1);
+      manager.cancel(&path).unwrap();
    }
 
-   /// Reverts an `InProgress` download item to `Paused` or `Idle` based on
-   /// whether a temp file exists on disk. No-op for other statuses.
-   fn revert_in_progress(&self, item: &DownloadItem) -> crate::Result<DownloadItem> {
-      if item.status != DownloadStatus::InProgress {
-         return Ok(item.clone());
+   // ---------- shutdown ----------
+
+   /// Serves the first 10 of 100 bytes, then stalls with the connection open. Returns
+   /// the URL.
+   #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+   async fn test_superseded_download_leaves_the_path_to_its_successor() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let body: Vec<u8> = (0..512 * 1024).map(|i| (i % 251) as u8).collect();
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+         .mount(&server)
+         .await;
+
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.bin").to_string_lossy().to_string();
+      let url = format!("{}/file.bin", server.uri());
+      for i in 0..50 {
+         let _ = manager.create(&path, &url);
+         let _ = manager.start(&path);
+         if i % 2 == 0 {
+            tokio::task::yield_now().await;
+         }
+         let _ = manager.cancel(&path);
       }
 
-      let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
-      let reverted = if Path::new(&temp_path).exists() {
-         item.with_status(DownloadStatus::Paused)
-      } else {
-         item.with_status(DownloadStatus::Idle)
-      };
+      let completed = manager
+         .download_now(&path, &url, DownloadOptions::default())
+         .await
+         .unwrap();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(fs::read(&path).unwrap(), body);
 
-      self.store.update(reverted.clone())?;
-      self.emit_changed(reverted.clone());
-      Ok(reverted)
+      // No task of a canceled download outlived it to write to the path again.
+      tokio::time::sleep(Duration::from_millis(100)).await;
+      assert_eq!(fs::read(&path).unwrap(), body);
+      assert!(manager.list().unwrap().is_empty());
+      assert!(!Path::new(&manager.temp_path(&path)).exists());
    }
 
-   pub(crate) fn emit_changed(&self, item: DownloadItem) {
-      debug!(file = %filename(&item.path), status = %item.status, progress = item.progress);
-      (self.on_changed)(item);
-   }
-}
+   #[tokio::test]
+   async fn test_set_metered_pauses_and_resumes_downloads() {
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         pause_on_metered: true,
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let running = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      let paused = dir.path().join("paused.bin").to_string_lossy().to_string();
+      manager.create(&running, &serve_stalled().await).unwrap();
+      manager.start(&running).unwrap();
+      seed(&manager, &paused, DownloadStatus::Paused);
 
-fn filename(path: &str) -> &str {
-   Path::new(path)
-      .file_name()
-      .and_then(|s| s.to_str())
-      .unwrap_or(path)
-}
+      let changed = manager.set_metered(true).unwrap();
+      assert_eq!(changed.len(), 1);
+      assert_eq!(
+         manager.get(&running).unwrap().status,
+         DownloadStatus::Paused
+      );
 
-#[cfg(test)]
-mod tests {
-   use super::*;
-   use std::sync::Mutex;
-   use tempfile::TempDir;
+      // Only the download paused for metering resumes.
+      let changed = manager.set_metered(false).unwrap();
+      assert_eq!(changed.len(), 1);
+      assert_eq!(changed[0].download.path, running);
+      assert_eq!(
+         manager.get(&running).unwrap().status,
+         DownloadStatus::InProgress
+      );
+      assert_eq!(manager.get(&paused).unwrap().status, DownloadStatus::Paused);
+      manager.cancel(&running).unwrap();
+   }
 
-   const VALID_URL: &str = "https://example.com/file.mp4";
+   #[tokio::test]
+   async fn test_set_metered_does_nothing_unless_configured() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
 
-   type EventLog = Arc<Mutex<Vec<DownloadItem>>>;
+      assert!(manager.set_metered(true).unwrap().is_empty());
+      assert_eq!(
+         manager.get(&path).unwrap().status,
+         DownloadStatus::InProgress
+      );
+      manager.cancel(&path).unwrap();
+   }
 
-   fn make_manager() -> (DownloadManager, TempDir, EventLog) {
-      let dir = TempDir::new().unwrap();
-      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
-      let captured = events.clone();
-      let on_changed: OnChanged = Arc::new(move |item| {
-         captured.lock().unwrap().push(item);
+   async fn serve_stalled() -> String {
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap();
+      tokio::spawn(async move {
+         let (mut socket, _) = listener.accept().await.unwrap();
+         let mut request = [0u8; 1024];
+         let _ = socket.read(&mut request).await;
+         let _ = socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n0123456789")
+            .await;
+         std::future::pending::<()>().await;
+         drop(socket);
       });
-      let manager = DownloadManager::new(dir.path().to_path_buf(), on_changed);
-      (manager, dir, events)
+      format!("http://{}/stalled.bin", addr)
    }
 
-   fn event_log(events: &EventLog) -> Vec<DownloadItem> {
-      events.lock().unwrap().clone()
-   }
+   #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+   async fn test_chunks_are_written_without_locking_tasks() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
 
-   fn clear_events(events: &EventLog) {
-      events.lock().unwrap().clear();
+      // The running download receives and reports its bytes while the tasks are locked.
+      let tasks = manager.tasks.lock().unwrap();
+      let deadline = Instant::now() + Duration::from_secs(5);
+      while manager.get(&path).unwrap().downloaded_bytes == 0 && Instant::now() < deadline {
+         std::thread::sleep(Duration::from_millis(10));
+      }
+      drop(tasks);
+      assert_eq!(manager.get(&path).unwrap().downloaded_bytes, 10);
+      manager.pause(&path).unwrap();
    }
 
-   fn seed(manager: &DownloadManager, path: &str, status: DownloadStatus) {
+   #[tokio::test]
+   async fn test_max_downloads_per_host_queues_downloads_from_a_busy_host() {
+      let (manager, dir, _events) = make_manager();
+      let manager = DownloadManager::with_config(
+         dir.path().to_path_buf(),
+         DownloadManagerConfig {
+            max_downloads_per_host: Some(1),
+            ..Default::default()
+         },
+         manager.on_changed.clone(),
+      );
+      let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+      let status = |name: &str| manager.get(&path(name)).unwrap().status;
+
+      // Two downloads from 127.0.0.1, and one from localhost.
       manager
-         .store
-         .create(DownloadItem {
-            url: VALID_URL.to_string(),
-            path: path.to_string(),
-            progress: 0.0,
-            status,
-         })
+         .create(&path("a.bin"), &serve_stalled().await)
+         .unwrap();
+      manager
+         .create(&path("b.bin"), &serve_stalled().await)
          .unwrap();
+      let other_host = serve_stalled().await.replace("127.0.0.1", "localhost");
+      manager.create(&path("c.bin"), &other_host).unwrap();
+      for name in ["a.bin", "b.bin", "c.bin"] {
+         manager.start(&path(name)).unwrap();
+      }
+      assert_eq!(status("a.bin"), DownloadStatus::InProgress);
+      assert_eq!(status("b.bin"), DownloadStatus::Queued);
+      assert_eq!(status("c.bin"), DownloadStatus::InProgress);
+
+      // Once the download holding the slot stops, the queued one takes it.
+      manager.pause(&path("a.bin")).unwrap();
+      for _ in 0..100 {
+         if status("b.bin") == DownloadStatus::InProgress {
+            break;
+         }
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+      assert_eq!(status("b.bin"), DownloadStatus::InProgress);
+      assert_eq!(
+         manager.resume(&path("a.bin")).unwrap().download.status,
+         DownloadStatus::Queued
+      );
+
+      let paused = manager.pause_all().unwrap();
+      assert_eq!(paused.len(), 3);
    }
 
-   // ---------- get ----------
+   #[tokio::test]
+   async fn test_action_dispatches_to_matching_operation() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
 
-   #[test]
-   fn test_get_returns_pending_for_unknown_path() {
-      let (manager, _dir, _events) = make_manager();
-      let item = manager.get("/tmp/unknown.mp4").unwrap();
-      assert_eq!(item.path, "/tmp/unknown.mp4");
-      assert_eq!(item.status, DownloadStatus::Pending);
-      assert_eq!(item.url, "");
-      assert_eq!(item.progress, 0.0);
+      let steps = [
+         (DownloadAction::Start, DownloadStatus::InProgress),
+         (DownloadAction::Pause, DownloadStatus::Paused),
+         (DownloadAction::Resume, DownloadStatus::InProgress),
+         (DownloadAction::Cancel, DownloadStatus::Canceled),
+      ];
+      for (action, status) in steps {
+         let response = manager.action(&path, action).unwrap();
+         assert_eq!(response.expected_status, status, "{:?}", action);
+         assert!(response.is_expected_status, "{:?}", action);
+      }
+      assert!(matches!(
+         manager.action(&path, DownloadAction::Retry),
+         Err(Error::NotFound(_))
+      ));
    }
 
-   #[test]
-   fn test_get_returns_persisted_item() {
-      let (manager, _dir, _events) = make_manager();
-      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
-      let item = manager.get("/tmp/file.mp4").unwrap();
-      assert_eq!(item.status, DownloadStatus::Idle);
-      assert_eq!(item.url, VALID_URL);
+   #[tokio::test]
+   async fn test_queued_downloads_start_by_priority() {
+      let (manager, dir, _events) = make_manager();
+      let manager = DownloadManager::with_config(
+         dir.path().to_path_buf(),
+         DownloadManagerConfig {
+            max_downloads_per_host: Some(1),
+            ..Default::default()
+         },
+         manager.on_changed.clone(),
+      );
+      let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+      let status = |name: &str| manager.get(&path(name)).unwrap().status;
+
+      for name in ["a.bin", "b.bin", "c.bin"] {
+         manager.create(&path(name), &serve_stalled().await).unwrap();
+         manager.start(&path(name)).unwrap();
+      }
+      assert_eq!(status("b.bin"), DownloadStatus::Queued);
+      assert_eq!(status("c.bin"), DownloadStatus::Queued);
+
+      // The download queued last jumps ahead once its priority is raised.
+      let raised = manager.set_priority(&path("c.bin"), 5).unwrap();
+      assert_eq!(raised.priority, 5);
+      assert_eq!(manager.get(&path("c.bin")).unwrap().priority, 5);
+
+      manager.pause(&path("a.bin")).unwrap();
+      for _ in 0..100 {
+         if status("c.bin") == DownloadStatus::InProgress {
+            break;
+         }
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+      assert_eq!(status("c.bin"), DownloadStatus::InProgress);
+      assert_eq!(status("b.bin"), DownloadStatus::Queued);
+
+      assert!(matches!(
+         manager.set_priority(&path("missing.bin"), 1),
+         Err(Error::NotFound(_))
+      ));
+      manager.pause_all().unwrap();
    }
 
-   #[test]
-   fn test_get_rejects_invalid_path() {
-      let (manager, _dir, _events) = make_manager();
-      assert!(manager.get("").is_err());
+   #[tokio::test]
+   async fn test_shutdown_pauses_running_download_and_keeps_its_bytes() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
+
+      // Wait for the first bytes to arrive.
+      while manager.get(&path).unwrap().downloaded_bytes == 0 {
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+
+      let paused = manager.shutdown(Duration::from_millis(100)).await.unwrap();
+      assert_eq!(paused.len(), 1);
+      assert_eq!(paused[0].download.status, DownloadStatus::Paused);
+      assert!(manager.tasks.lock().unwrap().is_empty());
+
+      // The buffered bytes were written, so the download resumes from them.
+      assert_eq!(fs::read(manager.temp_path(&path)).unwrap(), b"0123456789");
+      let reloaded = DownloadManager::new(dir.path().to_path_buf(), Arc::new(|_| {}));
+      assert_eq!(reloaded.get(&path).unwrap().status, DownloadStatus::Paused);
    }
 
-   // ---------- create ----------
+   #[tokio::test]
+   async fn test_pause_stops_stalled_download_and_keeps_its_bytes() {
+      let (manager, dir, events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
+      while manager.get(&path).unwrap().downloaded_bytes == 0 {
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
 
-   #[test]
-   fn test_create_persists_idle_item_and_emits() {
-      let (manager, _dir, events) = make_manager();
-      let response = manager.create("/tmp/file.mp4", VALID_URL).unwrap();
-      assert_eq!(response.download.status, DownloadStatus::Idle);
-      assert!(response.is_expected_status);
+      manager.pause(&path).unwrap();
+      let emitted = event_log(&events).len();
 
-      let stored = manager
-         .store
-         .find_by_path("/tmp/file.mp4")
-         .unwrap()
-         .unwrap();
-      assert_eq!(stored.status, DownloadStatus::Idle);
-      assert_eq!(stored.url, VALID_URL);
+      // The task stops without waiting for another chunk.
+      tokio::time::timeout(Duration::from_secs(2), async {
+         while !manager.tasks.lock().unwrap()[&path].handle.is_finished() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+         }
+      })
+      .await
+      .expect("download should stop promptly after pause");
+      assert_eq!(event_log(&events).len(), emitted);
+      assert_eq!(
+         event_log(&events).last().unwrap().status,
+         DownloadStatus::Paused
+      );
+      assert_eq!(fs::read(manager.temp_path(&path)).unwrap(), b"0123456789");
+   }
 
-      let log = event_log(&events);
-      assert_eq!(log.len(), 1);
-      assert_eq!(log[0].path, "/tmp/file.mp4");
-      assert_eq!(log[0].status, DownloadStatus::Idle);
+   /// SHA-256 digest of `b"hello"`.
+   const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+   #[tokio::test]
+   async fn test_verify_completes_download_with_matching_file() {
+      let (manager, dir, events) = make_manager();
+      let path = dir.path().join("bundled.bin").to_string_lossy().to_string();
+      seed(&manager, &path, DownloadStatus::Paused);
+      fs::write(manager.temp_path(&path), b"hel").unwrap();
+      fs::write(&path, b"hello").unwrap();
+
+      assert!(!manager.verify(&path, &"0".repeat(64)).await.unwrap());
+      assert!(event_log(&events).is_empty());
+
+      assert!(
+         manager
+            .verify(&path, &HELLO_SHA256.to_uppercase())
+            .await
+            .unwrap()
+      );
+      let completed = event_log(&events).pop().unwrap();
+      assert_eq!(completed.status, DownloadStatus::Completed);
+      assert_eq!(completed.downloaded_bytes, 5);
+      assert!(manager.store.find_by_path(&path).unwrap().is_none());
+      assert!(!Path::new(&manager.temp_path(&path)).exists());
+      assert_eq!(fs::read(&path).unwrap(), b"hello");
    }
 
-   #[test]
-   fn test_create_existing_does_not_overwrite_url() {
-      let (manager, _dir, events) = make_manager();
-      manager.create("/tmp/file.mp4", VALID_URL).unwrap();
+   #[tokio::test]
+   async fn test_verify_missing_file_or_without_download() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.bin").to_string_lossy().to_string();
+      assert!(!manager.verify(&path, HELLO_SHA256).await.unwrap());
 
-      let other_url = "https://example.com/other.mp4";
-      let response = manager.create("/tmp/file.mp4", other_url).unwrap();
-      assert_eq!(response.download.url, VALID_URL);
+      // A file without a download operation is only checked.
+      fs::write(&path, b"hello").unwrap();
+      assert!(manager.verify(&path, HELLO_SHA256).await.unwrap());
+   }
 
-      let stored = manager
-         .store
-         .find_by_path("/tmp/file.mp4")
-         .unwrap()
-         .unwrap();
-      assert_eq!(stored.url, VALID_URL);
+   #[tokio::test]
+   async fn test_schedule_starts_download_when_due() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager.create(&path, &serve_stalled().await).unwrap();
 
-      // Only the first create emitted a change event.
-      assert_eq!(event_log(&events).len(), 1);
+      let at = now_ms() + 100;
+      let response = manager.schedule(&path, at).unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Idle);
+      assert_eq!(manager.get(&path).unwrap().scheduled_at, Some(at));
+
+      tokio::time::timeout(Duration::from_secs(2), async {
+         while manager.get(&path).unwrap().downloaded_bytes == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+         }
+      })
+      .await
+      .expect("scheduled download should start");
+      let item = manager.get(&path).unwrap();
+      assert_eq!(item.status, DownloadStatus::InProgress);
+      assert_eq!(item.scheduled_at, None);
+
+      // Only idle downloads can be scheduled.
+      assert!(!manager.schedule(&path, at).unwrap().is_expected_status);
    }
 
-   #[test]
-   fn test_create_rejects_invalid_path() {
+   #[tokio::test]
+   async fn test_rescheduled_download_waits_for_the_new_time() {
       let (manager, _dir, _events) = make_manager();
-      assert!(manager.create("", VALID_URL).is_err());
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Idle);
+
+      manager.schedule("/tmp/a.mp4", now_ms() + 20).unwrap();
+      manager.schedule("/tmp/a.mp4", now_ms() + 60_000).unwrap();
+      tokio::time::sleep(Duration::from_millis(100)).await;
+
+      let item = manager.get("/tmp/a.mp4").unwrap();
+      assert_eq!(item.status, DownloadStatus::Idle);
+      assert!(item.scheduled_at.is_some());
    }
 
-   #[test]
-   fn test_create_rejects_invalid_url() {
-      let (manager, _dir, _events) = make_manager();
-      assert!(manager.create("/tmp/file.mp4", "not-a-url").is_err());
+   #[tokio::test]
+   async fn test_init_starts_overdue_scheduled_downloads() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      manager
+         .store
+         .create(DownloadItem {
+            url: serve_stalled().await,
+            path: path.clone(),
+            status: DownloadStatus::Idle,
+            scheduled_at: Some(1),
+            ..Default::default()
+         })
+         .unwrap();
+
+      manager.init();
+      tokio::time::timeout(Duration::from_secs(2), async {
+         while manager.get(&path).unwrap().status != DownloadStatus::InProgress {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+         }
+      })
+      .await
+      .expect("overdue scheduled download should start on init");
    }
 
-   // ---------- start ----------
+   #[tokio::test]
+   async fn test_custom_temp_suffix_names_the_temp_file() {
+      let dir = TempDir::new().unwrap();
+      let manager = DownloadManager::with_config(
+         dir.path().to_path_buf(),
+         DownloadManagerConfig {
+            temp_suffix: Some(".part".to_string()),
+            ..Default::default()
+         },
+         Arc::new(|_| {}),
+      );
+      let path = dir.path().join("stalled.bin").to_string_lossy().to_string();
+      assert_eq!(manager.temp_path(&path), format!("{}.part", path));
 
-   #[test]
-   fn test_start_unknown_path_returns_not_found() {
-      let (manager, _dir, _events) = make_manager();
-      assert!(matches!(
-         manager.start("/tmp/unknown.mp4"),
-         Err(Error::NotFound(_))
-      ));
+      manager.create(&path, &serve_stalled().await).unwrap();
+      manager.start(&path).unwrap();
+      while manager.get(&path).unwrap().downloaded_bytes == 0 {
+         tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+      manager.pause(&path).unwrap();
+      assert!(Path::new(&format!("{}.part", path)).exists());
+      assert!(!Path::new(&format!("{}{}", path, DOWNLOAD_SUFFIX)).exists());
+
+      // Restarting discards the temp file with the custom suffix too.
+      manager.restart(&path, false).unwrap();
+      assert!(!Path::new(&format!("{}.part", path)).exists());
    }
 
-   #[test]
-   fn test_start_rejects_invalid_path() {
+   #[tokio::test]
+   async fn test_shutdown_without_running_downloads() {
       let (manager, _dir, _events) = make_manager();
-      assert!(manager.start("").is_err());
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+
+      let paused = manager.shutdown(Duration::from_secs(1)).await.unwrap();
+      assert!(paused.is_empty());
+      assert_eq!(
+         manager.get("/tmp/idle.mp4").unwrap().status,
+         DownloadStatus::Idle
+      );
    }
 
    #[test]
-   fn test_start_from_non_idle_does_not_change_state() {
-      let (manager, _dir, _events) = make_manager();
-      let path = "/tmp/file.mp4";
-      seed(&manager, path, DownloadStatus::InProgress);
+   fn test_store_failure_outside_of_a_call_is_reported() {
+      let (manager, dir, _events) = make_manager();
+      let reported: Arc<Mutex<Vec<DownloadEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let captured = reported.clone();
+      let manager = manager.with_event_handler(Arc::new(move |event| {
+         captured.lock().unwrap().push(event);
+      }));
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::InProgress);
 
-      let response = manager.start(path).unwrap();
-      assert_eq!(response.download.status, DownloadStatus::InProgress);
-      assert_eq!(response.expected_status, DownloadStatus::InProgress);
-      assert!(response.is_expected_status);
+      // Block the store's temp file, so that saving it fails.
+      fs::create_dir(dir.path().join("downloads.json.tmp")).unwrap();
+      manager.init();
+
+      let reported = reported.lock().unwrap();
+      let errors: Vec<_> = reported
+         .iter()
+         .filter_map(|event| match event {
+            DownloadEvent::StoreError(message) => Some(message),
+            _ => None,
+         })
+         .collect();
+      assert_eq!(errors.len(), 1);
+      assert!(errors[0].contains("Failed to revert download item '/tmp/a.mp4'"));
+      assert!(errors[0].contains("Failed to write store"));
+   }
 
-      let stored = manager.store.find_by_path(path).unwrap().unwrap();
-      assert_eq!(stored.status, DownloadStatus::InProgress);
+   fn make_manager_with_change_batch(
+      window_ms: u64,
+   ) -> (
+      DownloadManager,
+      TempDir,
+      EventLog,
+      Arc<Mutex<Vec<DownloadEvent>>>,
+   ) {
+      let (manager, dir, events) = make_manager();
+      let manager = DownloadManager::with_config(
+         dir.path().to_path_buf(),
+         DownloadManagerConfig {
+            change_batch_ms: Some(window_ms),
+            ..Default::default()
+         },
+         manager.on_changed.clone(),
+      );
+      let batches: Arc<Mutex<Vec<DownloadEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let captured = batches.clone();
+      let manager = manager.with_event_handler(Arc::new(move |event| {
+         captured.lock().unwrap().push(event);
+      }));
+      (manager, dir, events, batches)
    }
 
-   // ---------- resume ----------
+   #[tokio::test]
+   async fn test_change_batch_reports_latest_state_of_each_download_once() {
+      let (manager, _dir, events, batches) = make_manager_with_change_batch(50);
+      manager.create("/tmp/a.mp4", VALID_URL).unwrap();
+      manager.create("/tmp/b.mp4", VALID_URL).unwrap();
+      let a = manager.get("/tmp/a.mp4").unwrap();
+      manager.emit_changed(a.with_progress(10.0));
+      manager.emit_changed(a.with_progress(20.0));
 
-   #[test]
-   fn test_resume_unknown_path_returns_not_found() {
-      let (manager, _dir, _events) = make_manager();
-      assert!(matches!(
-         manager.resume("/tmp/unknown.mp4"),
-         Err(Error::NotFound(_))
-      ));
-   }
+      // Nothing is reported until the window elapsed.
+      assert!(batches.lock().unwrap().is_empty());
+      tokio::time::sleep(Duration::from_millis(200)).await;
 
-   #[test]
-   fn test_resume_rejects_invalid_path() {
-      let (manager, _dir, _events) = make_manager();
-      assert!(manager.resume("").is_err());
+      assert!(event_log(&events).is_empty());
+      let batches = batches.lock().unwrap();
+      let [DownloadEvent::ChangedBatch(items)] = batches.as_slice() else {
+         panic!("expected a single batch, got {:?}", batches);
+      };
+      let reported: Vec<_> = items
+         .iter()
+         .map(|item| (item.path.as_str(), item.progress))
+         .collect();
+      assert_eq!(
+         reported,
+         [("/tmp/a.mp4", Some(20.0)), ("/tmp/b.mp4", Some(0.0))]
+      );
    }
 
    #[test]
-   fn test_resume_from_non_paused_does_not_change_state() {
-      let (manager, _dir, _events) = make_manager();
-      let path = "/tmp/file.mp4";
-      seed(&manager, path, DownloadStatus::Idle);
-
-      let response = manager.resume(path).unwrap();
-      assert_eq!(response.download.status, DownloadStatus::Idle);
-      assert_eq!(response.expected_status, DownloadStatus::InProgress);
-      assert!(!response.is_expected_status);
+   fn test_change_batch_without_runtime_reports_right_away() {
+      let (manager, _dir, events, batches) = make_manager_with_change_batch(50);
+      manager.create("/tmp/a.mp4", VALID_URL).unwrap();
 
-      let stored = manager.store.find_by_path(path).unwrap().unwrap();
-      assert_eq!(stored.status, DownloadStatus::Idle);
+      assert!(event_log(&events).is_empty());
+      assert!(matches!(
+         batches.lock().unwrap().as_slice(),
+         [DownloadEvent::ChangedBatch(items)] if items[0].path == "/tmp/a.mp4"
+      ));
    }
 
    // ---------- pause ----------
@@ -647,6 +5070,493 @@ mod tests {
       assert!(manager.cancel("").is_err());
    }
 
+   // ---------- dependencies ----------
+
+   fn seed_dependent(manager: &DownloadManager, path: &str, depends_on: &str) {
+      manager
+         .create_with_options(
+            path,
+            VALID_URL,
+            DownloadOptions {
+               depends_on: Some(depends_on.to_string()),
+               ..Default::default()
+            },
+         )
+         .unwrap();
+   }
+
+   #[test]
+   fn test_create_rejects_self_dependency() {
+      let (manager, _dir, _events) = make_manager();
+      let options = DownloadOptions {
+         depends_on: Some("/tmp/file.mp4".to_string()),
+         ..Default::default()
+      };
+      assert!(matches!(
+         manager.create_with_options("/tmp/file.mp4", VALID_URL, options),
+         Err(Error::Path(PathError::SelfDependency, _))
+      ));
+   }
+
+   #[test]
+   fn test_start_with_incomplete_prerequisite_queues() {
+      let (manager, _dir, events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Paused);
+      seed_dependent(&manager, "/tmp/b.mp4", "/tmp/a.mp4");
+
+      let response = manager.start("/tmp/b.mp4").unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Queued);
+      assert!(response.is_expected_status);
+
+      let stored = manager.store.find_by_path("/tmp/b.mp4").unwrap().unwrap();
+      assert_eq!(stored.status, DownloadStatus::Queued);
+      assert_eq!(
+         event_log(&events).last().map(|e| e.status.clone()),
+         Some(DownloadStatus::Queued)
+      );
+   }
+
+   #[test]
+   fn test_start_with_failed_prerequisite_fails() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Failed);
+      seed_dependent(&manager, "/tmp/b.mp4", "/tmp/a.mp4");
+
+      let response = manager.start("/tmp/b.mp4").unwrap();
+      assert_eq!(response.download.status, DownloadStatus::Failed);
+      assert!(!response.is_expected_status);
+      assert!(
+         response
+            .download
+            .error
+            .unwrap()
+            .contains("Prerequisite download failed")
+      );
+   }
+
+   #[tokio::test]
+   async fn test_start_with_completed_prerequisite_starts() {
+      let (manager, _dir, _events) = make_manager();
+      // A completed prerequisite is no longer in the store.
+      seed_dependent(&manager, "/tmp/b.mp4", "/tmp/a.mp4");
+
+      let response = manager.start("/tmp/b.mp4").unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+
+      // A completed prerequisite kept in the store doesn't hold it back either.
+      seed(&manager, "/tmp/c.mp4", DownloadStatus::Completed);
+      seed_dependent(&manager, "/tmp/d.mp4", "/tmp/c.mp4");
+      let response = manager.start("/tmp/d.mp4").unwrap();
+      assert_eq!(response.download.status, DownloadStatus::InProgress);
+   }
+
+   #[test]
+   fn test_cancel_prerequisite_fails_queued_dependents_transitively() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Paused);
+      seed_dependent(&manager, "/tmp/b.mp4", "/tmp/a.mp4");
+      seed_dependent(&manager, "/tmp/c.mp4", "/tmp/b.mp4");
+      manager.start("/tmp/b.mp4").unwrap();
+      manager.start("/tmp/c.mp4").unwrap();
+
+      manager.cancel("/tmp/a.mp4").unwrap();
+
+      let b = manager.store.find_by_path("/tmp/b.mp4").unwrap().unwrap();
+      let c = manager.store.find_by_path("/tmp/c.mp4").unwrap().unwrap();
+      assert_eq!(b.status, DownloadStatus::Failed);
+      assert!(b.error.unwrap().contains("was canceled: /tmp/a.mp4"));
+      assert_eq!(c.status, DownloadStatus::Failed);
+      assert!(c.error.unwrap().contains("failed: /tmp/b.mp4"));
+   }
+
+   #[tokio::test]
+   async fn test_init_verifies_completed_files_if_configured() {
+      use wiremock::matchers::method;
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         keep_completed: true,
+         verify_completed: true,
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config.clone(), Arc::new(|_| {}));
+      let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+      for name in ["intact.bin", "missing.bin", "changed.bin"] {
+         let url = format!("{}/{}", server.uri(), name);
+         manager
+            .download_now(&path(name), &url, DownloadOptions::default())
+            .await
+            .unwrap();
+      }
+      manager.flush().unwrap();
+      fs::remove_file(path("missing.bin")).unwrap();
+      fs::write(path("changed.bin"), b"hello again").unwrap();
+
+      // The next run finds the completed downloads in the store and checks their files.
+      let events: EventLog = Arc::new(Mutex::new(Vec::new()));
+      let captured = events.clone();
+      let manager = DownloadManager::with_config(
+         dir.path().to_path_buf(),
+         config,
+         Arc::new(move |item| captured.lock().unwrap().push(item)),
+      );
+      manager.init();
+
+      let status = |name: &str| manager.get(&path(name)).unwrap().status;
+      assert_eq!(status("intact.bin"), DownloadStatus::Completed);
+      assert_eq!(status("missing.bin"), DownloadStatus::Idle);
+      assert_eq!(status("changed.bin"), DownloadStatus::Idle);
+      let demoted: Vec<_> = event_log(&events)
+         .into_iter()
+         .filter(|item| item.status == DownloadStatus::Idle)
+         .map(|item| item.path)
+         .collect();
+      assert_eq!(demoted, [path("missing.bin"), path("changed.bin")]);
+      assert_eq!(manager.recovered().len(), 2);
+   }
+
+   #[test]
+   fn test_init_reverts_queued_without_prerequisite_to_idle() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Paused);
+      seed_dependent(&manager, "/tmp/b.mp4", "/tmp/a.mp4");
+      seed_dependent(&manager, "/tmp/c.mp4", "/tmp/gone.mp4");
+      for path in ["/tmp/b.mp4", "/tmp/c.mp4"] {
+         let item = manager.store.find_by_path(path).unwrap().unwrap();
+         manager
+            .store
+            .update(item.with_status(DownloadStatus::Queued))
+            .unwrap();
+      }
+
+      manager.init();
+
+      let status = |path| manager.store.find_by_path(path).unwrap().unwrap().status;
+      assert_eq!(status("/tmp/b.mp4"), DownloadStatus::Queued);
+      assert_eq!(status("/tmp/c.mp4"), DownloadStatus::Idle);
+   }
+
+   // ---------- pause_all / resume_all / cancel_all ----------
+
+   #[test]
+   fn test_pause_all_only_pauses_in_progress() {
+      let (manager, _dir, events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::InProgress);
+      seed(&manager, "/tmp/b.mp4", DownloadStatus::InProgress);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+
+      let responses = manager.pause_all().unwrap();
+      assert_eq!(responses.len(), 2);
+      assert!(
+         responses
+            .iter()
+            .all(|r| r.download.status == DownloadStatus::Paused && r.is_expected_status)
+      );
+
+      let stored = |path| manager.store.find_by_path(path).unwrap().unwrap().status;
+      assert_eq!(stored("/tmp/a.mp4"), DownloadStatus::Paused);
+      assert_eq!(stored("/tmp/b.mp4"), DownloadStatus::Paused);
+      assert_eq!(stored("/tmp/idle.mp4"), DownloadStatus::Idle);
+      assert_eq!(event_log(&events).len(), 2);
+   }
+
+   #[tokio::test]
+   async fn test_resume_all_only_resumes_paused() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::Paused);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+
+      let responses = manager.resume_all().unwrap();
+      assert_eq!(responses.len(), 1);
+      assert_eq!(responses[0].download.path, "/tmp/a.mp4");
+      assert_eq!(responses[0].download.status, DownloadStatus::InProgress);
+
+      let stored = manager
+         .store
+         .find_by_path("/tmp/idle.mp4")
+         .unwrap()
+         .unwrap();
+      assert_eq!(stored.status, DownloadStatus::Idle);
+   }
+
+   #[test]
+   fn test_pause_paths_reports_each_outcome_in_order() {
+      let (manager, _dir, events) = make_manager();
+      seed(&manager, "/tmp/a.mp4", DownloadStatus::InProgress);
+      seed(&manager, "/tmp/b.mp4", DownloadStatus::InProgress);
+      seed(&manager, "/tmp/c.mp4", DownloadStatus::InProgress);
+
+      let paths = ["/tmp/b.mp4", "/tmp/missing.mp4", "/tmp/a.mp4"].map(String::from);
+      let results = manager.pause_paths(&paths).unwrap();
+      let outcome: Vec<_> = results
+         .iter()
+         .map(|r| (r.path.as_str(), r.response.is_some(), r.error.is_some()))
+         .collect();
+      assert_eq!(
+         outcome,
+         [
+            ("/tmp/b.mp4", true, false),
+            ("/tmp/missing.mp4", false, true),
+            ("/tmp/a.mp4", true, false),
+         ]
+      );
+
+      let stored = |path| manager.store.find_by_path(path).unwrap().unwrap().status;
+      assert_eq!(stored("/tmp/a.mp4"), DownloadStatus::Paused);
+      assert_eq!(stored("/tmp/b.mp4"), DownloadStatus::Paused);
+      assert_eq!(stored("/tmp/c.mp4"), DownloadStatus::InProgress);
+      assert_eq!(event_log(&events).len(), 2);
+
+      let results = manager.cancel_paths(&["/tmp/c.mp4".to_string()]).unwrap();
+      assert_eq!(
+         results[0].response.as_ref().unwrap().download.status,
+         DownloadStatus::Canceled
+      );
+   }
+
+   #[test]
+   fn test_cancel_all_removes_active_and_keeps_others() {
+      let (manager, dir, events) = make_manager();
+      let paused = dir.path().join("paused.mp4").to_string_lossy().to_string();
+      let temp_path = format!("{}{}", paused, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"partial").unwrap();
+      seed(&manager, &paused, DownloadStatus::Paused);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+      seed(&manager, "/tmp/done.mp4", DownloadStatus::Completed);
+
+      let responses = manager.cancel_all().unwrap();
+      assert_eq!(responses.len(), 2);
+      assert!(
+         responses
+            .iter()
+            .all(|r| r.download.status == DownloadStatus::Canceled)
+      );
+
+      let remaining: Vec<_> = manager
+         .list()
+         .unwrap()
+         .into_iter()
+         .map(|i| i.path)
+         .collect();
+      assert_eq!(remaining, vec!["/tmp/done.mp4"]);
+      assert!(!Path::new(&temp_path).exists());
+      assert_eq!(event_log(&events).len(), 2);
+   }
+
+   // ---------- reset ----------
+
+   #[test]
+   fn test_reset_clears_store_and_temp_files() {
+      let (manager, dir, events) = make_manager();
+      let paused = dir.path().join("paused.mp4").to_string_lossy().to_string();
+      let temp_path = format!("{}{}", paused, DOWNLOAD_SUFFIX);
+      fs::write(&temp_path, b"partial").unwrap();
+      seed(&manager, &paused, DownloadStatus::Paused);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+      seed(&manager, "/tmp/done.mp4", DownloadStatus::Completed);
+
+      let reset_events: Arc<Mutex<Vec<DownloadEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let captured = reset_events.clone();
+      let manager = manager.with_event_handler(Arc::new(move |event| {
+         captured.lock().unwrap().push(event);
+      }));
+
+      let summary = manager.reset().unwrap();
+      assert_eq!(
+         summary,
+         ResetSummary {
+            canceled: 2,
+            removed: 3,
+            temp_files_removed: 1,
+         }
+      );
+
+      assert!(manager.list().unwrap().is_empty());
+      assert!(!Path::new(&temp_path).exists());
+
+      // A single reset event, no per-item change events.
+      assert!(event_log(&events).is_empty());
+      let reset_events = reset_events.lock().unwrap();
+      assert_eq!(reset_events.len(), 1);
+      assert!(matches!(&reset_events[0], DownloadEvent::Reset(s) if *s == summary));
+   }
+
+   #[test]
+   fn test_clear_completed_keeps_files_and_other_items() {
+      let (manager, dir, events) = make_manager();
+      let file = dir.path().join("done.mp4");
+      fs::write(&file, b"data").unwrap();
+      seed(&manager, file.to_str().unwrap(), DownloadStatus::Completed);
+      seed(&manager, "/tmp/other.mp4", DownloadStatus::Completed);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+
+      assert_eq!(manager.clear_completed().unwrap(), 2);
+
+      let remaining = manager.list().unwrap();
+      assert_eq!(remaining.len(), 1);
+      assert_eq!(remaining[0].path, "/tmp/idle.mp4");
+      assert!(file.exists());
+      assert!(event_log(&events).is_empty());
+      assert_eq!(manager.clear_completed().unwrap(), 0);
+   }
+
+   #[tokio::test]
+   async fn test_keep_completed_stores_completed_downloads_until_cleared() {
+      use wiremock::matchers::{method, path as wm_path};
+      use wiremock::{Mock, MockServer, ResponseTemplate};
+
+      let server = MockServer::start().await;
+      Mock::given(method("GET"))
+         .and(wm_path("/file.bin"))
+         .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello".to_vec()))
+         .mount(&server)
+         .await;
+      let dir = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         keep_completed: true,
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let path = dir.path().join("file.bin").to_string_lossy().to_string();
+      manager
+         .download_now(
+            &path,
+            &format!("{}/file.bin", server.uri()),
+            DownloadOptions::default(),
+         )
+         .await
+         .unwrap();
+
+      let kept = manager.get(&path).unwrap();
+      assert_eq!(kept.status, DownloadStatus::Completed);
+      assert_eq!(kept.downloaded_bytes, 5);
+      assert_eq!(manager.wait_for(&path).await.unwrap().path, path);
+
+      assert_eq!(manager.clear_completed().unwrap(), 1);
+      assert!(manager.list().unwrap().is_empty());
+      assert_eq!(fs::read(&path).unwrap(), b"hello");
+   }
+
+   // ---------- move_to ----------
+
+   #[test]
+   fn test_move_to_moves_completed_file_and_store_entry() {
+      let (manager, dir, events) = make_manager();
+      let path = dir.path().join("done.mp4").to_string_lossy().to_string();
+      let new_path = dir
+         .path()
+         .join("library/done.mp4")
+         .to_string_lossy()
+         .to_string();
+      fs::write(&path, b"data").unwrap();
+      seed(&manager, &path, DownloadStatus::Completed);
+
+      let moved = manager.move_to(&path, &new_path).unwrap();
+
+      assert_eq!(moved.path, new_path);
+      assert_eq!(moved.status, DownloadStatus::Completed);
+      assert!(!Path::new(&path).exists());
+      assert_eq!(fs::read(&new_path).unwrap(), b"data");
+      assert!(manager.store.find_by_path(&path).unwrap().is_none());
+      assert!(manager.store.find_by_path(&new_path).unwrap().is_some());
+      assert_eq!(event_log(&events).last().unwrap().path, new_path);
+   }
+
+   #[test]
+   fn test_move_to_rejects_file_without_store_entry() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("done.mp4").to_string_lossy().to_string();
+      let new_path = dir.path().join("moved.mp4").to_string_lossy().to_string();
+      fs::write(&path, b"data").unwrap();
+
+      assert!(matches!(
+         manager.move_to(&path, &new_path),
+         Err(Error::NotFound(_))
+      ));
+      assert!(matches!(manager.reveal(&path), Err(Error::NotFound(_))));
+      assert_eq!(fs::read(&path).unwrap(), b"data");
+      assert!(!Path::new(&new_path).exists());
+   }
+
+   #[test]
+   fn test_move_to_rejects_source_outside_download_root() {
+      let dir = TempDir::new().unwrap();
+      let outside = TempDir::new().unwrap();
+      let config = DownloadManagerConfig {
+         download_root: Some(dir.path().to_path_buf()),
+         ..Default::default()
+      };
+      let manager =
+         DownloadManager::with_config(dir.path().to_path_buf(), config, Arc::new(|_| {}));
+      let path = outside
+         .path()
+         .join("done.mp4")
+         .to_string_lossy()
+         .to_string();
+      let new_path = dir.path().join("moved.mp4").to_string_lossy().to_string();
+      fs::write(&path, b"data").unwrap();
+      seed(&manager, &path, DownloadStatus::Completed);
+
+      assert!(manager.move_to(&path, &new_path).is_err());
+      assert!(manager.reveal(&path).is_err());
+      assert_eq!(fs::read(&path).unwrap(), b"data");
+      assert!(!Path::new(&new_path).exists());
+   }
+
+   #[test]
+   fn test_move_to_rejects_incomplete_missing_and_existing() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      let new_path = dir.path().join("moved.mp4").to_string_lossy().to_string();
+      seed(&manager, &path, DownloadStatus::InProgress);
+      assert!(matches!(
+         manager.move_to(&path, &new_path),
+         Err(Error::InvalidState)
+      ));
+
+      assert!(matches!(
+         manager.move_to("/tmp/missing-download.mp4", &new_path),
+         Err(Error::NotFound(_))
+      ));
+
+      let done = dir.path().join("done.mp4").to_string_lossy().to_string();
+      fs::write(&done, b"data").unwrap();
+      seed(&manager, &done, DownloadStatus::Completed);
+      fs::write(&new_path, b"other").unwrap();
+      assert!(matches!(
+         manager.move_to(&done, &new_path),
+         Err(Error::File(_))
+      ));
+      assert_eq!(fs::read(&done).unwrap(), b"data");
+   }
+
+   #[test]
+   fn test_reveal_rejects_incomplete_and_missing() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      fs::write(&path, b"data").unwrap();
+      seed(&manager, &path, DownloadStatus::Paused);
+      assert!(matches!(manager.reveal(&path), Err(Error::InvalidState)));
+
+      let missing = dir.path().join("missing.mp4").to_string_lossy().to_string();
+      seed(&manager, &missing, DownloadStatus::Completed);
+      assert!(matches!(manager.reveal(&missing), Err(Error::NotFound(_))));
+   }
+
+   #[test]
+   fn test_reset_empty_store() {
+      let (manager, _dir, _events) = make_manager();
+      assert_eq!(manager.reset().unwrap(), ResetSummary::default());
+   }
+
    // ---------- init / revert_in_progress ----------
 
    #[test]
@@ -681,6 +5591,75 @@ mod tests {
       assert_eq!(stored.status, DownloadStatus::Idle);
    }
 
+   #[test]
+   fn test_init_keeps_reconciled_items_for_replay() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      seed(&manager, &path, DownloadStatus::InProgress);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+      assert!(manager.recovered().is_empty());
+
+      manager.init();
+
+      let recovered = manager.recovered();
+      assert_eq!(recovered.len(), 1);
+      assert_eq!(recovered[0].path, path);
+      assert_eq!(recovered[0].status, DownloadStatus::Idle);
+   }
+
+   #[test]
+   fn test_init_emits_ready_event_once_reconciled() {
+      let (manager, dir, _events) = make_manager();
+      let path = dir.path().join("file.mp4").to_string_lossy().to_string();
+      seed(&manager, &path, DownloadStatus::InProgress);
+      seed(&manager, "/tmp/idle.mp4", DownloadStatus::Idle);
+      let ready_events: Arc<Mutex<Vec<DownloadEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let captured = ready_events.clone();
+      let manager = manager.with_event_handler(Arc::new(move |event| {
+         captured.lock().unwrap().push(event);
+      }));
+
+      manager.init();
+
+      let ready_events = ready_events.lock().unwrap();
+      assert_eq!(ready_events.len(), 1);
+      assert_eq!(ready_events[0].name(), "ready");
+      let DownloadEvent::Ready(summary) = &ready_events[0] else {
+         panic!("expected a ready event");
+      };
+      assert_eq!(summary.downloads.len(), 2);
+      assert!(
+         summary
+            .downloads
+            .iter()
+            .all(|item| item.status == DownloadStatus::Idle)
+      );
+      assert_eq!(summary.recovered.len(), 1);
+      assert_eq!(summary.recovered[0].path, path);
+      assert!(summary.demoted_in_progress);
+   }
+
+   #[test]
+   fn test_init_ready_event_without_interrupted_downloads() {
+      let (manager, _dir, _events) = make_manager();
+      seed(&manager, "/tmp/paused.mp4", DownloadStatus::Paused);
+      let ready_events: Arc<Mutex<Vec<DownloadEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let captured = ready_events.clone();
+      let manager = manager.with_event_handler(Arc::new(move |event| {
+         captured.lock().unwrap().push(event);
+      }));
+
+      manager.init();
+
+      let ready_events = ready_events.lock().unwrap();
+      let [DownloadEvent::Ready(summary)] = ready_events.as_slice() else {
+         panic!("expected a single ready event");
+      };
+      assert_eq!(summary.downloads.len(), 1);
+      assert!(summary.recovered.is_empty());
+      assert!(!summary.demoted_in_progress);
+   }
+
    #[test]
    fn test_init_leaves_non_in_progress_unchanged() {
       let (manager, _dir, _events) = make_manager();
@@ -0,0 +1,23 @@
+/// Whether the connection to the internet is metered, e.g. a Wi-Fi network marked as
+/// metered or a cellular one, or `None` if unknown.
+#[cfg(windows)]
+pub(crate) fn is_metered() -> Option<bool> {
+   use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+   // Fails while offline, when there is no connection profile.
+   let profile = NetworkInformation::GetInternetConnectionProfile().ok()?;
+   let cost = profile.GetConnectionCost().ok()?;
+   let cost_type = cost.NetworkCostType().ok()?;
+   Some(
+      cost_type == NetworkCostType::Fixed
+         || cost_type == NetworkCostType::Variable
+         || cost.Roaming().unwrap_or(false)
+         || cost.OverDataLimit().unwrap_or(false),
+   )
+}
+
+/// Whether the connection to the internet is metered. Not detected on this platform.
+#[cfg(not(windows))]
+pub(crate) fn is_metered() -> Option<bool> {
+   None
+}
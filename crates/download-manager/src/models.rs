@@ -1,16 +1,185 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadItem {
    pub url: String,
    pub path: String,
-   pub progress: f64,
+   /// Percentage of the download received, from 0 to 100. `None` while bytes are
+   /// received of a resource whose size is unknown, see `downloaded_bytes`.
+   pub progress: Option<f64>,
    pub status: DownloadStatus,
+   /// `ETag` of the remote resource, captured from the first response. Sent as
+   /// `If-Range` on resume so a changed resource restarts instead of being appended to.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub etag: Option<String>,
+   /// `Last-Modified` of the remote resource, used for `If-Range` when no strong
+   /// `ETag` is available.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub last_modified: Option<String>,
+   /// Total size of the download in bytes, if known: the size of the remote resource,
+   /// capped at `max_bytes_truncate`. Lets a resume report its progress before any new
+   /// bytes arrive.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub total_size: Option<u64>,
+   /// Bytes received so far, updated with `progress` while the download runs. Unlike
+   /// `progress`, it is meaningful when `total_size` is unknown.
+   #[serde(default, skip_serializing_if = "is_zero")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub downloaded_bytes: u64,
+   /// Options the download was created with.
+   #[serde(flatten)]
+   pub options: DownloadOptions,
+   /// Mirror currently used in place of `url` after a failover, if any.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub active_mirror: Option<String>,
+   /// `Content-Type` of the resource, captured from the response, e.g. to categorize
+   /// finished files without sniffing them.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub content_type: Option<String>,
+   /// Whether the server accepts range requests for the resource, from its first
+   /// response. A download it doesn't accept them for restarts from zero on resume.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub accepts_ranges: Option<bool>,
+   /// Priority of the download among those `Queued` for a free slot on their host:
+   /// downloads of higher priority start first, those of equal priority in the order
+   /// they were created. Defaults to 0.
+   #[serde(default, skip_serializing_if = "is_default")]
+   pub priority: i32,
+   /// Milliseconds the download waits before its next attempt because the server
+   /// answered with `Retry-After`, while `status` is `InProgress`. Never persisted.
+   #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(optional, type = "number"))]
+   pub retry_after_ms: Option<u64>,
+   /// Reason the download failed, if `status` is `Failed`.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub error: Option<String>,
+   /// Time the download is scheduled to start at, in milliseconds since the Unix epoch,
+   /// while `status` is `Idle`. Cleared once it starts.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub scheduled_at: Option<u64>,
+   /// Time the download has to complete by, in milliseconds since the Unix epoch, set
+   /// when it first starts if `deadline_ms` is configured. It is kept when the download
+   /// is paused and resumed, and replaced when a download that missed it is started
+   /// again.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub deadline_at: Option<u64>,
+   /// Whether the download completed early because it reached `max_bytes_truncate`.
+   #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+   pub truncated: bool,
+   /// Whether the filename in `path` is a placeholder derived from the URL, to be
+   /// replaced by the `Content-Disposition` filename once the download starts.
+   #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+   pub resolve_filename: bool,
+   /// Time the item was last written to the store, in milliseconds since the Unix
+   /// epoch, or 0 if unknown. Set by the store.
+   #[serde(default, skip_serializing_if = "is_zero")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub updated_at: u64,
 }
 
+/// Per-download options provided on creation.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadOptions {
+   /// Fallback URLs tried in order, from the current offset, when `url` can't be
+   /// reached or returns a server error.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub mirrors: Vec<String>,
+   /// Download only the first N bytes of the resource, then stop and complete the
+   /// download with `truncated` set, e.g. to preview a large file. Unlike a range
+   /// request this doesn't need the server to support ranges.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub max_bytes_truncate: Option<u64>,
+   /// First byte of the resource to download, for a download of only part of it. The
+   /// file then holds the bytes from `range_start` to `range_end`, and progress is
+   /// relative to their number. Needs the server to support range requests.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub range_start: Option<u64>,
+   /// Last byte of the resource to download, inclusive, like the end of an HTTP `Range`.
+   /// Without it, the download runs to the end of the resource.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   #[cfg_attr(feature = "schema", ts(type = "number"))]
+   pub range_end: Option<u64>,
+   /// Path of a download that must complete before this one starts. Starting this
+   /// download while the prerequisite is incomplete queues it; it then starts when the
+   /// prerequisite completes, or fails if the prerequisite fails or is canceled.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub depends_on: Option<String>,
+   /// Expected `Content-Type` of the response, either exact (`application/zip`) or a
+   /// prefix ending in `/` (`image/`). A response of another type fails the download
+   /// before anything is written.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub expected_content_type: Option<String>,
+   /// `Content-Type`s the response may have, each like `expected_content_type` or with
+   /// a wildcard subtype (`video/*`). A response matching none of them, nor
+   /// `expected_content_type`, fails the download before anything is written.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub allowed_content_types: Vec<String>,
+   /// Headers sent with every request of the download. They take precedence over the
+   /// configured default headers of the same name.
+   #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+   pub headers: BTreeMap<String, String>,
+   /// Cookies sent with the requests of the download, by name, e.g. a session copied
+   /// from the webview after logging in. They are added to the manager's cookie store
+   /// when the download is created, where the cookies the host sets update them, so a
+   /// resumed download carries the current session. The store is kept in memory only:
+   /// cookies are neither persisted nor sent in change events, and are lost when the
+   /// app exits. Only the download's host gets them, and a `Cookie` header replaces them.
+   #[serde(default, skip_serializing)]
+   pub cookies: BTreeMap<String, String>,
+   /// `ETag` of the file already at the download path, e.g. the `etag` of the completed
+   /// item that downloaded it. Sent as `If-None-Match` while the file exists, so that if
+   /// the resource is unchanged (`304 Not Modified`) the download completes with the
+   /// existing file instead of downloading it again. Defaults to the `ETag` the store
+   /// kept for the path from the last download to it.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub if_none_match: Option<String>,
+   /// Unix permissions the completed file is given, e.g. `0o755` for a helper binary.
+   /// Otherwise it keeps those it was created with. Ignored on Windows.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub mode: Option<u32>,
+   /// What to do when a file already exists at the download path, checked on creation
+   /// and again before the completed download is moved into place.
+   #[serde(default, skip_serializing_if = "OverwritePolicy::is_overwrite")]
+   pub overwrite: OverwritePolicy,
+   /// Labels to group and filter downloads by, e.g. the feature of the app that created
+   /// them. They don't affect the download.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub tags: Vec<String>,
+}
+
+/// Policy for a download whose path is taken by an existing file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub enum OverwritePolicy {
+   /// Replace the existing file.
+   #[default]
+   Overwrite,
+   /// Refuse to create the download, or fail it on completion.
+   Fail,
+   /// Download to the first free path with ` (1)`, ` (2)`, … appended to the file stem.
+   Rename,
+}
+
+impl OverwritePolicy {
+   fn is_overwrite(&self) -> bool {
+      *self == OverwritePolicy::Overwrite
+   }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
 #[serde(rename_all = "camelCase")]
 pub enum DownloadStatus {
    /// Status could not be determined.
@@ -24,13 +193,33 @@ pub enum DownloadStatus {
    InProgress,
    /// Download was in progress but has been paused.
    Paused,
+   /// Download was started and waits for its prerequisite to complete, or for a free
+   /// slot on its host.
+   Queued,
+   /// Download failed and can be started again.
+   Failed,
    /// Download was canceled by the user.
    Canceled,
    /// Download completed.
    Completed,
 }
 
+/// Action on a single download, dispatched by [`DownloadManager::action`].
+///
+/// [`DownloadManager::action`]: crate::DownloadManager::action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadAction {
+   Start,
+   Pause,
+   Resume,
+   Cancel,
+   Retry,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadActionResponse {
    pub download: DownloadItem,
@@ -38,6 +227,158 @@ pub struct DownloadActionResponse {
    pub is_expected_status: bool,
 }
 
+/// A download to create with [`create_many`](crate::DownloadManager::create_many).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchCreateItem {
+   pub path: String,
+   pub url: String,
+   #[serde(default)]
+   pub options: DownloadOptions,
+}
+
+/// Outcome of creating one download of a batch: either the download operation, or the
+/// reason it could not be created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchCreateResult {
+   pub path: String,
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub response: Option<DownloadActionResponse>,
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub error: Option<String>,
+}
+
+/// Outcome of an action on one download of a batch, e.g. of
+/// [`pause_paths`](crate::DownloadManager::pause_paths): either the action's response, or
+/// the reason it failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchActionResult {
+   pub path: String,
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub response: Option<DownloadActionResponse>,
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub error: Option<String>,
+}
+
+/// Combined progress of the active downloads, see
+/// [`aggregate_progress`](crate::DownloadManager::aggregate_progress).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateProgress {
+   /// Total size in bytes of the active downloads of known size.
+   pub total_bytes: u64,
+   /// Bytes received so far of the active downloads of known size.
+   pub downloaded_bytes: u64,
+   /// Number of in progress and paused downloads, including those of unknown size.
+   pub active_count: usize,
+   /// Percentage of `total_bytes` received, from 0 to 100.
+   pub combined_percent: f64,
+}
+
+/// What downloading a resource would involve, see
+/// [`probe`](crate::DownloadManager::probe).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResult {
+   /// Size of the resource in bytes, if the server reports it.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub size: Option<u64>,
+   /// Whether the server supports range requests, so that a download can be resumed.
+   pub accepts_ranges: bool,
+   /// `Content-Type` of the resource, if any.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub content_type: Option<String>,
+   /// Filename from the `Content-Disposition` header, or else from the URL.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub filename: Option<String>,
+}
+
+/// State of the downloads once [`init`](crate::DownloadManager::init) reconciled them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadySummary {
+   /// All downloads, after reconciliation.
+   pub downloads: Vec<DownloadItem>,
+   /// Downloads whose status was changed, as returned by
+   /// [`recovered`](crate::DownloadManager::recovered).
+   pub recovered: Vec<DownloadItem>,
+   /// Whether any download was interrupted while in progress and reverted to `Paused`
+   /// or `Idle`.
+   pub demoted_in_progress: bool,
+}
+
+/// Summary of a [`reset`](crate::DownloadManager::reset) operation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetSummary {
+   /// Number of idle, in progress or paused downloads that were canceled.
+   pub canceled: usize,
+   /// Total number of store entries removed.
+   pub removed: usize,
+   /// Number of temp files deleted from disk.
+   pub temp_files_removed: usize,
+}
+
+/// Lifetime statistics of the downloads of a manager, e.g. to report data usage. They
+/// are persisted with the store, and survive the downloads they count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DownloadStats {
+   /// Bytes received by all downloads, including downloads later canceled.
+   pub total_bytes: u64,
+   /// Number of downloads that completed.
+   pub total_completed: u64,
+   /// Number of downloads that stopped with an error, counting each attempt.
+   pub total_failed: u64,
+}
+
+impl DownloadStats {
+   /// Adds the counts of `other` to these.
+   pub(crate) fn add(&mut self, other: &DownloadStats) {
+      self.total_bytes += other.total_bytes;
+      self.total_completed += other.total_completed;
+      self.total_failed += other.total_failed;
+   }
+}
+
+/// Manager-wide events that don't concern a single download item.
+///
+/// Serialized untagged so the payload of each event is just its inner value; use
+/// [`DownloadEvent::name`] to distinguish them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum DownloadEvent {
+   /// [`init`](crate::DownloadManager::init) reconciled the persisted downloads.
+   Ready(ReadySummary),
+   /// All downloads were canceled and the store was cleared.
+   Reset(ResetSummary),
+   /// The downloads that changed during a
+   /// [`change_batch_ms`](crate::DownloadManagerConfig::change_batch_ms) window, each in
+   /// its latest state, in order of their first change.
+   ChangedBatch(Vec<DownloadItem>),
+   /// The store failed outside of a call that could return the error, e.g. while a
+   /// download ran in the background, so the stored state may not survive a restart.
+   StoreError(String),
+   /// A download that was resumed started over from zero, as the server doesn't accept
+   /// range requests or the resource changed, so the bytes received before are lost.
+   Restarted(Box<DownloadItem>),
+}
+
+impl DownloadEvent {
+   /// The event name, e.g. `reset`.
+   pub fn name(&self) -> &'static str {
+      match self {
+         DownloadEvent::Ready(_) => "ready",
+         DownloadEvent::Reset(_) => "reset",
+         DownloadEvent::ChangedBatch(_) => "changed-batch",
+         DownloadEvent::StoreError(_) => "store-error",
+         DownloadEvent::Restarted(_) => "restarted",
+      }
+   }
+}
+
 impl DownloadActionResponse {
    pub fn new(download: DownloadItem) -> Self {
       let expected_status = download.status.clone();
@@ -61,25 +402,66 @@ impl DownloadActionResponse {
 impl DownloadItem {
    pub fn with_progress(&self, new_progress: f64) -> DownloadItem {
       DownloadItem {
-         progress: new_progress,
+         progress: Some(new_progress),
+         status: DownloadStatus::InProgress,
+         ..self.clone()
+      }
+   }
+
+   /// Returns the item in progress with `downloaded_bytes` received of `total_size`, if
+   /// known, and `progress` derived from them, `None` if the total size is unknown.
+   pub fn with_bytes(&self, downloaded_bytes: u64, total_size: Option<u64>) -> DownloadItem {
+      let total_size = total_size.filter(|&size| size > 0).or(self.total_size);
+      DownloadItem {
+         progress: total_size.map(|size| progress_of(downloaded_bytes, size)),
+         downloaded_bytes,
+         total_size,
          status: DownloadStatus::InProgress,
          ..self.clone()
       }
    }
 
+   pub fn with_error(&self, error: String) -> DownloadItem {
+      DownloadItem {
+         status: DownloadStatus::Failed,
+         error: Some(error),
+         retry_after_ms: None,
+         ..self.clone()
+      }
+   }
+
    pub fn with_status(&self, new_status: DownloadStatus) -> DownloadItem {
       DownloadItem {
          progress: if new_status == DownloadStatus::Completed {
-            100.0
+            Some(100.0)
          } else {
             self.progress
          },
          status: new_status,
+         retry_after_ms: None,
          ..self.clone()
       }
    }
 }
 
+/// Percentage of `total_size` that `downloaded` bytes make up, or 0 if the total size
+/// is unknown (0).
+pub(crate) fn progress_of(downloaded: u64, total_size: u64) -> f64 {
+   if total_size > 0 {
+      (downloaded as f64 / total_size as f64 * 100.0).min(100.0)
+   } else {
+      0.0
+   }
+}
+
+fn is_zero(n: &u64) -> bool {
+   *n == 0
+}
+
+fn is_default(n: &i32) -> bool {
+   *n == 0
+}
+
 impl fmt::Display for DownloadStatus {
    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       let text = match self {
@@ -88,6 +470,8 @@ impl fmt::Display for DownloadStatus {
          DownloadStatus::Idle => "Idle",
          DownloadStatus::InProgress => "InProgress",
          DownloadStatus::Paused => "Paused",
+         DownloadStatus::Queued => "Queued",
+         DownloadStatus::Failed => "Failed",
          DownloadStatus::Canceled => "Canceled",
          DownloadStatus::Completed => "Completed",
       };
@@ -103,8 +487,9 @@ mod tests {
       DownloadItem {
          url: "http://example.com/file.mp4".to_string(),
          path: "/tmp/file.mp4".to_string(),
-         progress: 0.0,
+         progress: Some(0.0),
          status: DownloadStatus::Idle,
+         ..Default::default()
       }
    }
 
@@ -112,25 +497,43 @@ mod tests {
    fn test_download_item_with_progress() {
       let item = sample_item();
       let updated = item.with_progress(50.0);
-      assert_eq!(updated.progress, 50.0);
+      assert_eq!(updated.progress, Some(50.0));
       assert_eq!(updated.status, DownloadStatus::InProgress);
       assert_eq!(updated.url, item.url);
       assert_eq!(updated.path, item.path);
    }
 
+   #[test]
+   fn test_download_item_with_bytes() {
+      let item = sample_item();
+      let updated = item.with_bytes(512, Some(2048));
+      assert_eq!(updated.progress, Some(25.0));
+      assert_eq!(updated.downloaded_bytes, 512);
+      assert_eq!(updated.total_size, Some(2048));
+      assert_eq!(updated.status, DownloadStatus::InProgress);
+
+      // An unknown total keeps the known one, if any.
+      assert_eq!(updated.with_bytes(1024, None).progress, Some(50.0));
+      let unknown = item.with_bytes(1024, Some(0));
+      assert_eq!(unknown.progress, None);
+      assert!(serde_json::to_value(&unknown).unwrap()["progress"].is_null());
+      assert_eq!(unknown.downloaded_bytes, 1024);
+      assert_eq!(unknown.total_size, None);
+   }
+
    #[test]
    fn test_download_item_with_status() {
       let mut item = sample_item();
-      item.progress = 50.0;
+      item.progress = Some(50.0);
 
       // Preserves progress for non-completed status
       let paused = item.with_status(DownloadStatus::Paused);
-      assert_eq!(paused.progress, 50.0);
+      assert_eq!(paused.progress, Some(50.0));
       assert_eq!(paused.status, DownloadStatus::Paused);
 
       // Sets progress to 100 for completed status
       let completed = item.with_status(DownloadStatus::Completed);
-      assert_eq!(completed.progress, 100.0);
+      assert_eq!(completed.progress, Some(100.0));
       assert_eq!(completed.status, DownloadStatus::Completed);
    }
 
@@ -154,6 +557,31 @@ mod tests {
       assert!(!mismatch_response.is_expected_status);
    }
 
+   #[test]
+   fn test_download_event_serializes_inner_payload() {
+      let event = DownloadEvent::Reset(ResetSummary {
+         canceled: 1,
+         removed: 2,
+         temp_files_removed: 1,
+      });
+      assert_eq!(event.name(), "reset");
+      assert_eq!(
+         serde_json::to_string(&event).unwrap(),
+         r#"{"canceled":1,"removed":2,"tempFilesRemoved":1}"#
+      );
+
+      let event = DownloadEvent::ChangedBatch(Vec::new());
+      assert_eq!(event.name(), "changed-batch");
+      assert_eq!(serde_json::to_string(&event).unwrap(), "[]");
+
+      let event = DownloadEvent::StoreError("Failed to write store".to_string());
+      assert_eq!(event.name(), "store-error");
+      assert_eq!(
+         serde_json::to_string(&event).unwrap(),
+         r#""Failed to write store""#
+      );
+   }
+
    #[test]
    fn test_download_status() {
       // Default
@@ -165,4 +593,72 @@ mod tests {
       assert_eq!(format!("{}", DownloadStatus::InProgress), "InProgress");
       assert_eq!(format!("{}", DownloadStatus::Completed), "Completed");
    }
+
+   #[test]
+   fn test_download_action_uses_command_names() {
+      let action: DownloadAction = serde_json::from_str(r#""resume""#).unwrap();
+      assert_eq!(action, DownloadAction::Resume);
+      assert_eq!(
+         serde_json::to_string(&DownloadAction::Cancel).unwrap(),
+         r#""cancel""#
+      );
+      assert!(serde_json::from_str::<DownloadAction>(r#""restart""#).is_err());
+   }
+
+   #[cfg(feature = "schema")]
+   #[test]
+   fn test_schema_uses_serialized_names() {
+      let schema = serde_json::to_value(schemars::schema_for!(DownloadActionResponse)).unwrap();
+      let defs = &schema["$defs"];
+
+      // Every field of a serialized item is described, under the name it is sent with.
+      let item = serde_json::to_value(sample_item()).unwrap();
+      for name in item.as_object().unwrap().keys() {
+         assert!(
+            defs["DownloadItem"]["properties"].get(name).is_some(),
+            "{}",
+            name
+         );
+      }
+      let statuses = serde_json::to_string(&defs["DownloadStatus"]).unwrap();
+      assert!(statuses.contains("\"inProgress\""));
+      assert!(schema["properties"].get("isExpectedStatus").is_some());
+   }
+
+   /// Fails once the checked-in TypeScript declarations no longer match the models.
+   /// Regenerate them with `UPDATE_BINDINGS=1 cargo test -p download-manager --features
+   /// schema typescript_bindings`.
+   #[cfg(feature = "schema")]
+   #[test]
+   fn test_typescript_bindings_are_up_to_date() {
+      use std::path::Path;
+      use ts_rs::TS;
+
+      let declarations = [
+         DownloadActionResponse::decl(),
+         DownloadItem::decl(),
+         DownloadOptions::decl(),
+         DownloadStatus::decl(),
+         OverwritePolicy::decl(),
+         DownloadAction::decl(),
+      ];
+      let generated = format!(
+         "// Generated from the download-manager models by ts-rs. Don't edit by hand; see\n\
+          // `test_typescript_bindings_are_up_to_date`.\n\n{}",
+         declarations
+            .iter()
+            .map(|declaration| format!("export {}\n", declaration))
+            .collect::<String>()
+      );
+      let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("bindings/download-manager.d.ts");
+      if std::env::var_os("UPDATE_BINDINGS").is_some() {
+         std::fs::write(&path, &generated).unwrap();
+      }
+      let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+      assert!(
+         checked_in == generated,
+         "{} is out of date; regenerate it with UPDATE_BINDINGS=1",
+         path.display()
+      );
+   }
 }
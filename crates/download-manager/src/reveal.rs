@@ -0,0 +1,65 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::Error;
+
+/// Opens the file manager of the OS showing the file at `path`, selected where the file
+/// manager supports it, without waiting for it to close.
+pub(crate) fn reveal(path: &Path) -> crate::Result<()> {
+   command(path)
+      .spawn()
+      .map_err(|e| Error::File(format!("Failed to open the file manager: {}", e)))?;
+   Ok(())
+}
+
+/// Explorer, with the file selected.
+#[cfg(windows)]
+fn command(path: &Path) -> Command {
+   use std::os::windows::process::CommandExt;
+
+   // Explorer parses its own command line, which needs the path quoted as is.
+   let mut command = Command::new("explorer");
+   command.raw_arg(format!("/select,\"{}\"", path.display()));
+   command
+}
+
+/// Finder, with the file selected.
+#[cfg(target_os = "macos")]
+fn command(path: &Path) -> Command {
+   let mut command = Command::new("open");
+   command.arg("-R").arg(path);
+   command
+}
+
+/// The default file manager, showing the folder of the file as it can't be told to
+/// select it.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn command(path: &Path) -> Command {
+   let mut command = Command::new("xdg-open");
+   command.arg(path.parent().unwrap_or(path));
+   command
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[cfg(not(any(windows, target_os = "macos")))]
+   #[test]
+   fn test_command_opens_folder_of_file() {
+      let command = command(Path::new("/downloads/file.zip"));
+      assert_eq!(command.get_program(), "xdg-open");
+      assert_eq!(command.get_args().collect::<Vec<_>>(), ["/downloads"]);
+   }
+
+   #[cfg(target_os = "macos")]
+   #[test]
+   fn test_command_selects_file() {
+      let command = command(Path::new("/downloads/file.zip"));
+      assert_eq!(command.get_program(), "open");
+      assert_eq!(
+         command.get_args().collect::<Vec<_>>(),
+         ["-R", "/downloads/file.zip"]
+      );
+   }
+}
@@ -1,10 +1,134 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::{DownloadItem, Error};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{DownloadItem, DownloadStats, DownloadStatus, Error};
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+pub use memory::InMemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Persistence of download items. Items are kept in insertion order and are unique by
+/// path.
+///
+/// Implementations must be thread-safe, as the manager and its running downloads share
+/// the store.
+pub trait Store: Send + Sync + std::fmt::Debug {
+   /// Loads the persisted items. Called once at startup.
+   fn load(&self) -> crate::Result<()>;
+
+   fn list(&self) -> crate::Result<Vec<DownloadItem>>;
+
+   fn find_by_url(&self, url: &str) -> crate::Result<Vec<DownloadItem>> {
+      Ok(self.list()?.into_iter().filter(|i| i.url == url).collect())
+   }
+
+   fn list_by_status(&self, status: &DownloadStatus) -> crate::Result<Vec<DownloadItem>> {
+      Ok(self
+         .list()?
+         .into_iter()
+         .filter(|i| &i.status == status)
+         .collect())
+   }
+
+   fn list_by_tag(&self, tag: &str) -> crate::Result<Vec<DownloadItem>> {
+      Ok(self
+         .list()?
+         .into_iter()
+         .filter(|i| i.options.tags.iter().any(|t| t == tag))
+         .collect())
+   }
+
+   fn count_by_status(&self) -> crate::Result<HashMap<DownloadStatus, usize>> {
+      let mut counts = HashMap::new();
+      for item in self.list()? {
+         *counts.entry(item.status).or_insert(0) += 1;
+      }
+      Ok(counts)
+   }
+
+   fn find_by_path(&self, path: &str) -> crate::Result<Option<DownloadItem>>;
+
+   /// Inserts an item, failing if an item already exists at its path.
+   fn create(&self, item: DownloadItem) -> crate::Result<DownloadItem>;
+
+   /// Inserts the items in a single write. For each item, returns the item already
+   /// stored at its path, if any, in which case the item is not inserted.
+   fn create_many(&self, items: Vec<DownloadItem>) -> crate::Result<Vec<Option<DownloadItem>>>;
+
+   /// Replaces the item at the same path, if any.
+   fn update(&self, item: DownloadItem) -> crate::Result<()>;
+
+   /// Like [`update`](Self::update), but the change may only be persisted by a later
+   /// write or [`flush`](Self::flush). Used for frequent progress updates.
+   fn update_no_persist(&self, item: DownloadItem) -> crate::Result<()>;
+
+   /// Persists any change not yet written by [`update_no_persist`](Self::update_no_persist).
+   fn flush(&self) -> crate::Result<()> {
+      Ok(())
+   }
+
+   /// Replaces the item stored at `old_path` with `item`, which may have a different path.
+   fn rename(&self, old_path: &str, item: DownloadItem) -> crate::Result<()>;
+
+   fn delete(&self, path: &str) -> crate::Result<()>;
+
+   /// Replaces each item for which `f` returns an updated item, in a single write.
+   /// Returns the updated items.
+   fn update_where(
+      &self,
+      f: &mut dyn FnMut(&DownloadItem) -> Option<DownloadItem>,
+   ) -> crate::Result<Vec<DownloadItem>>;
+
+   /// Removes every item matching `predicate` in a single write, returning the removed
+   /// items.
+   fn delete_where(
+      &self,
+      predicate: &mut dyn FnMut(&DownloadItem) -> bool,
+   ) -> crate::Result<Vec<DownloadItem>>;
+
+   /// Removes every item in a single write, returning the removed items.
+   fn clear(&self) -> crate::Result<Vec<DownloadItem>>;
+
+   /// Gets the lifetime statistics of the downloads.
+   fn stats(&self) -> crate::Result<DownloadStats>;
+
+   /// Adds to the lifetime statistics. Like [`update_no_persist`](Self::update_no_persist),
+   /// the change may only be persisted by a later write or [`flush`](Self::flush).
+   fn add_stats(&self, stats: &DownloadStats) -> crate::Result<()>;
+
+   /// Resets the lifetime statistics to zero.
+   fn reset_stats(&self) -> crate::Result<()>;
+
+   /// Gets the `ETag` of the file last downloaded to `path`. It is kept after the
+   /// download leaves the store, so a later download to the path can ask for the
+   /// resource only if it changed.
+   fn etag(&self, path: &str) -> crate::Result<Option<String>>;
+
+   /// Records the `ETag` of the file downloaded to `path`, or forgets it if `None`. Like
+   /// [`add_stats`](Self::add_stats), the change may only be persisted by a later write
+   /// or [`flush`](Self::flush).
+   fn set_etag(&self, path: &str, etag: Option<&str>) -> crate::Result<()>;
+}
+
+/// Minimum time between two writes of progress-only changes, see
+/// [`update_no_persist`](Store::update_no_persist).
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Thread-safe JSON file store for download items, mirroring iOS `DownloadStore`.
+///
+/// Every change rewrites the whole file, except progress updates, which are written at
+/// most once per [`FLUSH_INTERVAL`]. Pending progress is written by the next change, by
+/// [`flush`](Store::flush), or when the last clone of the store is dropped.
 #[derive(Clone, Debug)]
 pub struct DownloadStore {
    inner: Arc<Mutex<StoreInner>>,
@@ -13,7 +137,14 @@ pub struct DownloadStore {
 #[derive(Debug)]
 struct StoreInner {
    downloads: Vec<DownloadItem>,
+   stats: DownloadStats,
+   /// `ETag`s of the files downloaded, by path.
+   etags: BTreeMap<String, String>,
    path: PathBuf,
+   /// Whether `downloads`, `stats` or `etags` have changes that aren't written yet.
+   dirty: bool,
+   last_saved: Option<Instant>,
+   flush_interval: Duration,
 }
 
 impl DownloadStore {
@@ -22,12 +153,35 @@ impl DownloadStore {
       Self {
          inner: Arc::new(Mutex::new(StoreInner {
             downloads: Vec::new(),
+            stats: DownloadStats::default(),
+            etags: BTreeMap::new(),
             path,
+            dirty: false,
+            last_saved: None,
+            flush_interval: FLUSH_INTERVAL,
          })),
       }
    }
 
-   pub fn list(&self) -> crate::Result<Vec<DownloadItem>> {
+   #[cfg(test)]
+   fn with_flush_interval(self, flush_interval: Duration) -> Self {
+      self.inner.lock().unwrap().flush_interval = flush_interval;
+      self
+   }
+}
+
+impl Drop for StoreInner {
+   fn drop(&mut self) {
+      if self.dirty
+         && let Err(e) = save_inner(self)
+      {
+         tracing::warn!(path = %self.path.display(), "Failed to flush download store: {}", e);
+      }
+   }
+}
+
+impl Store for DownloadStore {
+   fn list(&self) -> crate::Result<Vec<DownloadItem>> {
       let inner = self
          .inner
          .lock()
@@ -35,7 +189,45 @@ impl DownloadStore {
       Ok(inner.downloads.clone())
    }
 
-   pub fn find_by_path(&self, path: &str) -> crate::Result<Option<DownloadItem>> {
+   fn find_by_url(&self, url: &str) -> crate::Result<Vec<DownloadItem>> {
+      let inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      Ok(inner
+         .downloads
+         .iter()
+         .filter(|i| i.url == url)
+         .cloned()
+         .collect())
+   }
+
+   fn list_by_status(&self, status: &DownloadStatus) -> crate::Result<Vec<DownloadItem>> {
+      let inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      Ok(inner
+         .downloads
+         .iter()
+         .filter(|i| &i.status == status)
+         .cloned()
+         .collect())
+   }
+
+   fn count_by_status(&self) -> crate::Result<HashMap<DownloadStatus, usize>> {
+      let inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      let mut counts = HashMap::new();
+      for item in &inner.downloads {
+         *counts.entry(item.status.clone()).or_insert(0) += 1;
+      }
+      Ok(counts)
+   }
+
+   fn find_by_path(&self, path: &str) -> crate::Result<Option<DownloadItem>> {
       let inner = self
          .inner
          .lock()
@@ -43,7 +235,7 @@ impl DownloadStore {
       Ok(inner.downloads.iter().find(|i| i.path == path).cloned())
    }
 
-   pub fn create(&self, item: DownloadItem) -> crate::Result<DownloadItem> {
+   fn create(&self, item: DownloadItem) -> crate::Result<DownloadItem> {
       let mut inner = self
          .inner
          .lock()
@@ -56,73 +248,422 @@ impl DownloadStore {
          )));
       }
 
+      let item = touched(item);
       inner.downloads.push(item.clone());
-      save_inner(&inner)?;
+      save_inner(&mut inner)?;
       Ok(item)
    }
 
-   pub fn update(&self, item: DownloadItem) -> crate::Result<()> {
+   fn create_many(&self, items: Vec<DownloadItem>) -> crate::Result<Vec<Option<DownloadItem>>> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      let mut existing = Vec::with_capacity(items.len());
+      for item in items {
+         match inner.downloads.iter().find(|i| i.path == item.path) {
+            Some(found) => existing.push(Some(found.clone())),
+            None => {
+               inner.downloads.push(touched(item));
+               existing.push(None);
+            }
+         }
+      }
+      if existing.iter().any(Option::is_none) {
+         save_inner(&mut inner)?;
+      }
+      Ok(existing)
+   }
+
+   fn update(&self, item: DownloadItem) -> crate::Result<()> {
       let mut inner = self
          .inner
          .lock()
          .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
 
       if let Some(existing) = inner.downloads.iter_mut().find(|i| i.path == item.path) {
-         *existing = item;
+         *existing = touched(item);
       }
-      save_inner(&inner)?;
+      save_inner(&mut inner)?;
       Ok(())
    }
 
-   pub fn update_no_persist(&self, item: DownloadItem) -> crate::Result<()> {
+   fn update_no_persist(&self, item: DownloadItem) -> crate::Result<()> {
       let mut inner = self
          .inner
          .lock()
          .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
 
       if let Some(existing) = inner.downloads.iter_mut().find(|i| i.path == item.path) {
-         *existing = item;
+         *existing = touched(item);
+         inner.dirty = true;
+      }
+      let due = inner
+         .last_saved
+         .is_none_or(|last_saved| last_saved.elapsed() >= inner.flush_interval);
+      if inner.dirty && due {
+         save_inner(&mut inner)?;
+      }
+      Ok(())
+   }
+
+   fn flush(&self) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      if inner.dirty {
+         save_inner(&mut inner)?;
       }
       Ok(())
    }
 
-   pub fn delete(&self, path: &str) -> crate::Result<()> {
+   fn rename(&self, old_path: &str, item: DownloadItem) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      if item.path != old_path && inner.downloads.iter().any(|i| i.path == item.path) {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &item.path
+         )));
+      }
+
+      let existing = inner
+         .downloads
+         .iter_mut()
+         .find(|i| i.path == old_path)
+         .ok_or_else(|| Error::NotFound(old_path.to_string()))?;
+      *existing = touched(item);
+      save_inner(&mut inner)?;
+      Ok(())
+   }
+
+   fn delete(&self, path: &str) -> crate::Result<()> {
       let mut inner = self
          .inner
          .lock()
          .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
 
       inner.downloads.retain(|i| i.path != path);
-      save_inner(&inner)?;
+      save_inner(&mut inner)?;
       Ok(())
    }
 
-   /// Loads the store from disk. Should be called once at startup.
-   pub fn load(&self) -> crate::Result<()> {
+   fn update_where(
+      &self,
+      f: &mut dyn FnMut(&DownloadItem) -> Option<DownloadItem>,
+   ) -> crate::Result<Vec<DownloadItem>> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      let mut updated = Vec::new();
+      for existing in inner.downloads.iter_mut() {
+         if let Some(item) = f(existing) {
+            let item = touched(item);
+            *existing = item.clone();
+            updated.push(item);
+         }
+      }
+      if !updated.is_empty() {
+         save_inner(&mut inner)?;
+      }
+      Ok(updated)
+   }
+
+   fn delete_where(
+      &self,
+      predicate: &mut dyn FnMut(&DownloadItem) -> bool,
+   ) -> crate::Result<Vec<DownloadItem>> {
       let mut inner = self
          .inner
          .lock()
          .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
 
-      if !inner.path.exists() {
-         return Ok(());
+      let (removed, kept) = std::mem::take(&mut inner.downloads)
+         .into_iter()
+         .partition(|item| predicate(item));
+      inner.downloads = kept;
+      if !removed.is_empty() {
+         save_inner(&mut inner)?;
       }
+      Ok(removed)
+   }
+
+   fn clear(&self) -> crate::Result<Vec<DownloadItem>> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      let removed = std::mem::take(&mut inner.downloads);
+      save_inner(&mut inner)?;
+      Ok(removed)
+   }
+
+   fn stats(&self) -> crate::Result<DownloadStats> {
+      let inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      Ok(inner.stats)
+   }
+
+   fn add_stats(&self, stats: &DownloadStats) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      inner.stats.add(stats);
+      inner.dirty = true;
+      let due = inner
+         .last_saved
+         .is_none_or(|last_saved| last_saved.elapsed() >= inner.flush_interval);
+      if due {
+         save_inner(&mut inner)?;
+      }
+      Ok(())
+   }
 
-      let data =
-         fs::read(&inner.path).map_err(|e| Error::Store(format!("Failed to read store: {}", e)))?;
-      inner.downloads = serde_json::from_slice(&data)
-         .map_err(|e| Error::Store(format!("Failed to parse store: {}", e)))?;
+   fn reset_stats(&self) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
 
+      inner.stats = DownloadStats::default();
+      save_inner(&mut inner)?;
       Ok(())
    }
+
+   fn etag(&self, path: &str) -> crate::Result<Option<String>> {
+      let inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+      Ok(inner.etags.get(path).cloned())
+   }
+
+   fn set_etag(&self, path: &str, etag: Option<&str>) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      let changed = match etag {
+         Some(etag) => {
+            inner
+               .etags
+               .insert(path.to_string(), etag.to_string())
+               .as_deref()
+               != Some(etag)
+         }
+         None => inner.etags.remove(path).is_some(),
+      };
+      if changed {
+         inner.dirty = true;
+      }
+      Ok(())
+   }
+
+   fn load(&self) -> crate::Result<()> {
+      let mut inner = self
+         .inner
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))?;
+
+      let primary = match read_downloads(&inner.path) {
+         Ok(Some(contents)) => {
+            inner.downloads = normalize(contents.downloads);
+            inner.stats = contents.stats;
+            inner.etags = contents.etags;
+            return Ok(());
+         }
+         other => other,
+      };
+
+      // The store is missing or corrupt, e.g. after a crash while it was being replaced:
+      // fall back to the previous version.
+      match read_downloads(&sibling(&inner.path, BACKUP_SUFFIX)) {
+         Ok(Some(contents)) => {
+            tracing::warn!(path = %inner.path.display(), "Recovered download store from backup");
+            inner.downloads = normalize(contents.downloads);
+            inner.stats = contents.stats;
+            inner.etags = contents.etags;
+            Ok(())
+         }
+         _ => primary.map(|_| ()),
+      }
+   }
+}
+
+/// Suffix of the file a new version of the store is written to before replacing it.
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Suffix of the previous version of the store, used to recover from a corrupt store.
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// Returns `path` with `suffix` appended, e.g. `downloads.json.tmp`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+   let mut sibling = path.as_os_str().to_owned();
+   sibling.push(suffix);
+   PathBuf::from(sibling)
+}
+
+/// Version of the store file format, written as `{ "version": N, "downloads": [...] }`.
+/// Version 0 is the bare array of items written before the format was versioned.
+const STORE_VERSION: u64 = 1;
+
+/// Upgrades a persisted item, as a JSON object, by one version: `MIGRATIONS[n]` upgrades
+/// an item of version `n` to `n + 1`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); STORE_VERSION as usize] = [
+   // 0 -> 1: only the envelope changed.
+   |_| {},
+];
+
+#[derive(Serialize)]
+struct StoreFile<'a> {
+   version: u64,
+   downloads: &'a [DownloadItem],
+   stats: &'a DownloadStats,
+   #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+   etags: &'a BTreeMap<String, String>,
+}
+
+/// Contents of a store file.
+struct StoreContents {
+   downloads: Vec<DownloadItem>,
+   stats: DownloadStats,
+   etags: BTreeMap<String, String>,
+}
+
+/// Reads the items, statistics and `ETag`s stored at `path`, or `None` if the file
+/// doesn't exist. Statistics and `ETag`s are missing from stores written before they
+/// were kept.
+fn read_downloads(path: &Path) -> crate::Result<Option<StoreContents>> {
+   if !path.exists() {
+      return Ok(None);
+   }
+   let data = fs::read(path).map_err(|e| Error::Store(format!("Failed to read store: {}", e)))?;
+   let value: Value = serde_json::from_slice(&data)
+      .map_err(|e| Error::Store(format!("Failed to parse store: {}", e)))?;
+   let stats = value
+      .get("stats")
+      .and_then(|stats| serde_json::from_value(stats.clone()).ok())
+      .unwrap_or_default();
+   let etags = value
+      .get("etags")
+      .and_then(|etags| serde_json::from_value(etags.clone()).ok())
+      .unwrap_or_default();
+   migrate(value).map(|downloads| {
+      Some(StoreContents {
+         downloads,
+         stats,
+         etags,
+      })
+   })
+}
+
+/// Parses a store file of any supported version, upgrading its items to the current one.
+fn migrate(value: Value) -> crate::Result<Vec<DownloadItem>> {
+   let (version, downloads) = match value {
+      Value::Array(downloads) => (0, downloads),
+      Value::Object(mut file) => {
+         let version = file
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| Error::Store("Failed to parse store: missing version".to_string()))?;
+         match file.remove("downloads") {
+            Some(Value::Array(downloads)) => (version, downloads),
+            _ => {
+               return Err(Error::Store(
+                  "Failed to parse store: missing downloads".to_string(),
+               ));
+            }
+         }
+      }
+      _ => {
+         return Err(Error::Store(
+            "Failed to parse store: unexpected format".to_string(),
+         ));
+      }
+   };
+   if version > STORE_VERSION {
+      return Err(Error::Store(format!(
+         "Store version {} is newer than the supported version {}",
+         version, STORE_VERSION
+      )));
+   }
+   if version < STORE_VERSION {
+      tracing::info!(
+         from = version,
+         to = STORE_VERSION,
+         "Migrating download store"
+      );
+   }
+
+   downloads
+      .into_iter()
+      .map(|mut item| {
+         if let Value::Object(fields) = &mut item {
+            for migration in &MIGRATIONS[version as usize..] {
+               migration(fields);
+            }
+         }
+         serde_json::from_value(item)
+            .map_err(|e| Error::Store(format!("Failed to parse store: {}", e)))
+      })
+      .collect()
+}
+
+/// Repairs items loaded from a store file that may have been edited by hand or merged:
+/// of several items with the same path, only the most recently updated is kept, and
+/// `progress` is clamped to 0–100.
+fn normalize(downloads: Vec<DownloadItem>) -> Vec<DownloadItem> {
+   let mut normalized: Vec<DownloadItem> = Vec::with_capacity(downloads.len());
+   let mut indices: HashMap<String, usize> = HashMap::new();
+   for mut item in downloads {
+      item.progress = item.progress.map(|progress| progress.clamp(0.0, 100.0));
+      match indices.get(&item.path) {
+         Some(&index) => {
+            tracing::warn!(path = %item.path, "Dropping duplicate download store entry");
+            if item.updated_at >= normalized[index].updated_at {
+               normalized[index] = item;
+            }
+         }
+         None => {
+            indices.insert(item.path.clone(), normalized.len());
+            normalized.push(item);
+         }
+      }
+   }
+   normalized
+}
+
+/// Returns `item` with `updated_at` set to the current time.
+fn touched(item: DownloadItem) -> DownloadItem {
+   let updated_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |since| since.as_millis() as u64);
+   DownloadItem { updated_at, ..item }
 }
 
 /// Serializes and writes the store to disk.
 ///
+/// The data is written to a temp file which then replaces the store, so a crash never
+/// leaves a partially written store behind. The previous store is kept as a backup.
+///
 /// Accepts `&StoreInner` directly rather than `&self` because callers already hold the
 /// `MutexGuard` when they call this. Taking `&self` would attempt to re-acquire the lock
 /// on the same thread, causing a deadlock since `Mutex` is not re-entrant.
-fn save_inner(inner: &StoreInner) -> crate::Result<()> {
+fn save_inner(inner: &mut StoreInner) -> crate::Result<()> {
    if let Some(parent) = Path::new(&inner.path).parent()
       && !parent.exists()
    {
@@ -130,21 +671,59 @@ fn save_inner(inner: &StoreInner) -> crate::Result<()> {
          .map_err(|e| Error::Store(format!("Failed to create store directory: {}", e)))?;
    }
 
-   let data = serde_json::to_vec(&inner.downloads)
-      .map_err(|e| Error::Store(format!("Failed to serialize store: {}", e)))?;
-   fs::write(&inner.path, &data)
+   let data = serde_json::to_vec(&StoreFile {
+      version: STORE_VERSION,
+      downloads: &inner.downloads,
+      stats: &inner.stats,
+      etags: &inner.etags,
+   })
+   .map_err(|e| Error::Store(format!("Failed to serialize store: {}", e)))?;
+   let temp_path = sibling(&inner.path, TEMP_SUFFIX);
+   fs::File::create(&temp_path)
+      .and_then(|mut file| {
+         file.write_all(&data)?;
+         file.sync_all()
+      })
       .map_err(|e| Error::Store(format!("Failed to write store: {}", e)))?;
+
+   if inner.path.exists() {
+      fs::rename(&inner.path, sibling(&inner.path, BACKUP_SUFFIX))
+         .map_err(|e| Error::Store(format!("Failed to back up store: {}", e)))?;
+   }
+   fs::rename(&temp_path, &inner.path)
+      .map_err(|e| Error::Store(format!("Failed to replace store: {}", e)))?;
+   inner.dirty = false;
+   inner.last_saved = Some(Instant::now());
    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
    use super::*;
-   use crate::models::DownloadStatus;
    use std::fs;
    use tempfile::TempDir;
 
-   fn temp_store() -> (DownloadStore, TempDir) {
+   /// Opens a store of one backend in `dir`. Opening it again loads the persisted items.
+   type Open = fn(&Path) -> Box<dyn Store>;
+
+   fn open_json(dir: &Path) -> Box<dyn Store> {
+      let store = DownloadStore::new(dir.join("downloads.json"));
+      store.load().unwrap();
+      Box::new(store)
+   }
+
+   #[cfg(feature = "sqlite")]
+   fn open_sqlite(dir: &Path) -> Box<dyn Store> {
+      Box::new(SqliteStore::open(dir.join("downloads.db")).unwrap())
+   }
+
+   fn temp_store(open: Open) -> (Box<dyn Store>, TempDir) {
+      let dir = TempDir::new().unwrap();
+      let store = open(dir.path());
+      (store, dir)
+   }
+
+   fn temp_json_store() -> (DownloadStore, TempDir) {
       let dir = TempDir::new().unwrap();
       let store = DownloadStore::new(dir.path().join("downloads.json"));
       (store, dir)
@@ -154,144 +733,480 @@ mod tests {
       DownloadItem {
          url: "https://example.com/file.mp4".to_string(),
          path: path.to_string(),
-         progress: 0.0,
+         progress: Some(0.0),
          status: DownloadStatus::Idle,
+         ..Default::default()
       }
    }
 
-   #[test]
-   fn test_list_empty() {
-      let (store, _dir) = temp_store();
+   /// Runs each shared test against every store backend.
+   macro_rules! backend_tests {
+      ($($name:ident),* $(,)?) => {
+         mod json {
+            $(#[test]
+            fn $name() {
+               super::$name(super::open_json)
+            })*
+         }
+
+         #[cfg(feature = "sqlite")]
+         mod sqlite {
+            $(#[test]
+            fn $name() {
+               super::$name(super::open_sqlite)
+            })*
+         }
+      };
+   }
+
+   backend_tests!(
+      test_list_empty,
+      test_list_after_create,
+      test_find_by_path_found,
+      test_find_by_path_not_found,
+      test_create_success,
+      test_create_duplicate_returns_error,
+      test_create_many_skips_existing_and_writes_once,
+      test_update_persists_to_disk,
+      test_update_no_op_on_unknown_path,
+      test_rename_moves_item_and_persists,
+      test_rename_rejects_existing_target,
+      test_rename_unknown_path_returns_not_found,
+      test_delete_removes_item_and_persists,
+      test_delete_unknown_path_is_ok,
+      test_clear_removes_all_and_persists,
+      test_update_where_updates_matching_and_persists,
+      test_delete_where_removes_matching_and_persists,
+      test_queries_by_url_and_status,
+      test_list_by_tag_after_reload,
+      test_writes_set_updated_at,
+      test_stats_survive_reload_and_reset,
+      test_etags_survive_reload_and_deletion,
+   );
+
+   fn test_etags_survive_reload_and_deletion(open: Open) {
+      let (store, dir) = temp_store(open);
+      assert_eq!(store.etag("/tmp/file.mp4").unwrap(), None);
+      store.create(sample_item("/tmp/file.mp4")).unwrap();
+      store.set_etag("/tmp/file.mp4", Some("\"v1\"")).unwrap();
+      store.set_etag("/tmp/other.mp4", Some("\"v2\"")).unwrap();
+
+      // The ETag is kept once the item is deleted.
+      store.delete("/tmp/file.mp4").unwrap();
+      store.set_etag("/tmp/other.mp4", None).unwrap();
+      store.flush().unwrap();
+
+      let reloaded = open(dir.path());
+      assert_eq!(
+         reloaded.etag("/tmp/file.mp4").unwrap().as_deref(),
+         Some("\"v1\"")
+      );
+      assert_eq!(reloaded.etag("/tmp/other.mp4").unwrap(), None);
+   }
+
+   fn test_stats_survive_reload_and_reset(open: Open) {
+      let (store, dir) = temp_store(open);
+      assert_eq!(store.stats().unwrap(), DownloadStats::default());
+      let delta = DownloadStats {
+         total_bytes: 100,
+         total_completed: 1,
+         total_failed: 2,
+      };
+      store.add_stats(&delta).unwrap();
+      store.add_stats(&delta).unwrap();
+      store.flush().unwrap();
+
+      let reloaded = open(dir.path());
+      let expected = DownloadStats {
+         total_bytes: 200,
+         total_completed: 2,
+         total_failed: 4,
+      };
+      assert_eq!(reloaded.stats().unwrap(), expected);
+
+      // Statistics are kept when the items are cleared, unless reset.
+      store.clear().unwrap();
+      assert_eq!(open(dir.path()).stats().unwrap(), expected);
+      store.reset_stats().unwrap();
+      assert_eq!(open(dir.path()).stats().unwrap(), DownloadStats::default());
+   }
+
+   fn test_writes_set_updated_at(open: Open) {
+      let (store, _dir) = temp_store(open);
+      let created = store.create(sample_item("/tmp/file.mp4")).unwrap();
+      assert!(created.updated_at > 0);
+
+      store
+         .update(DownloadItem {
+            updated_at: 0,
+            ..created.clone()
+         })
+         .unwrap();
+      let updated = store.find_by_path("/tmp/file.mp4").unwrap().unwrap();
+      assert!(updated.updated_at >= created.updated_at);
+   }
+
+   fn test_list_empty(open: Open) {
+      let (store, _dir) = temp_store(open);
       assert!(store.list().unwrap().is_empty());
    }
 
-   #[test]
-   fn test_list_after_create() {
-      let (store, _dir) = temp_store();
+   fn test_list_after_create(open: Open) {
+      let (store, _dir) = temp_store(open);
       store.create(sample_item("/tmp/a.mp4")).unwrap();
       store.create(sample_item("/tmp/b.mp4")).unwrap();
       assert_eq!(store.list().unwrap().len(), 2);
    }
 
-   #[test]
-   fn test_find_by_path_found() {
-      let (store, _dir) = temp_store();
+   fn test_find_by_path_found(open: Open) {
+      let (store, _dir) = temp_store(open);
       store.create(sample_item("/tmp/file.mp4")).unwrap();
       let result = store.find_by_path("/tmp/file.mp4").unwrap();
       assert_eq!(result.unwrap().path, "/tmp/file.mp4");
    }
 
-   #[test]
-   fn test_find_by_path_not_found() {
-      let (store, _dir) = temp_store();
+   fn test_find_by_path_not_found(open: Open) {
+      let (store, _dir) = temp_store(open);
       assert!(store.find_by_path("/tmp/missing.mp4").unwrap().is_none());
    }
 
-   #[test]
-   fn test_create_success() {
-      let (store, _dir) = temp_store();
+   fn test_create_success(open: Open) {
+      let (store, _dir) = temp_store(open);
       let item = store.create(sample_item("/tmp/file.mp4")).unwrap();
       assert_eq!(item.path, "/tmp/file.mp4");
    }
 
-   #[test]
-   fn test_create_persists_to_disk() {
-      let (store, dir) = temp_store();
-      store.create(sample_item("/tmp/file.mp4")).unwrap();
-      assert!(dir.path().join("downloads.json").exists());
-   }
-
-   #[test]
-   fn test_create_duplicate_returns_error() {
-      let (store, _dir) = temp_store();
+   fn test_create_duplicate_returns_error(open: Open) {
+      let (store, _dir) = temp_store(open);
       store.create(sample_item("/tmp/file.mp4")).unwrap();
       let result = store.create(sample_item("/tmp/file.mp4"));
       assert!(result.is_err());
    }
 
-   #[test]
-   fn test_update_persists_to_disk() {
-      let (store, dir) = temp_store();
+   fn test_create_many_skips_existing_and_writes_once(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+
+      let existing = store
+         .create_many(vec![sample_item("/tmp/a.mp4"), sample_item("/tmp/b.mp4")])
+         .unwrap();
+      assert_eq!(existing[0].as_ref().unwrap().path, "/tmp/a.mp4");
+      assert!(existing[1].is_none());
+
+      let reloaded = open(dir.path());
+      assert_eq!(reloaded.list().unwrap().len(), 2);
+   }
+
+   fn test_update_persists_to_disk(open: Open) {
+      let (store, dir) = temp_store(open);
       let item = store.create(sample_item("/tmp/file.mp4")).unwrap();
       let updated = DownloadItem {
-         progress: 50.0,
+         progress: Some(50.0),
          status: DownloadStatus::InProgress,
          ..item
       };
       store.update(updated).unwrap();
 
-      let reloaded = DownloadStore::new(dir.path().join("downloads.json"));
-      reloaded.load().unwrap();
+      let reloaded = open(dir.path());
       let found = reloaded.find_by_path("/tmp/file.mp4").unwrap().unwrap();
-      assert_eq!(found.progress, 50.0);
+      assert_eq!(found.progress, Some(50.0));
    }
 
-   #[test]
-   fn test_update_no_op_on_unknown_path() {
-      let (store, _dir) = temp_store();
+   fn test_update_no_op_on_unknown_path(open: Open) {
+      let (store, _dir) = temp_store(open);
       store.create(sample_item("/tmp/file.mp4")).unwrap();
       let unknown = sample_item("/tmp/unknown.mp4");
       assert!(store.update(unknown).is_ok());
       assert_eq!(store.list().unwrap().len(), 1);
    }
 
+   fn test_rename_moves_item_and_persists(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/old.mp4")).unwrap();
+      store
+         .rename("/tmp/old.mp4", sample_item("/tmp/new.mp4"))
+         .unwrap();
+
+      assert!(store.find_by_path("/tmp/old.mp4").unwrap().is_none());
+      assert!(store.find_by_path("/tmp/new.mp4").unwrap().is_some());
+
+      let reloaded = open(dir.path());
+      assert!(reloaded.find_by_path("/tmp/new.mp4").unwrap().is_some());
+   }
+
+   fn test_rename_rejects_existing_target(open: Open) {
+      let (store, _dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+      assert!(
+         store
+            .rename("/tmp/a.mp4", sample_item("/tmp/b.mp4"))
+            .is_err()
+      );
+      assert_eq!(store.list().unwrap().len(), 2);
+   }
+
+   fn test_rename_unknown_path_returns_not_found(open: Open) {
+      let (store, _dir) = temp_store(open);
+      assert!(matches!(
+         store.rename("/tmp/missing.mp4", sample_item("/tmp/new.mp4")),
+         Err(Error::NotFound(_))
+      ));
+   }
+
+   fn test_delete_removes_item_and_persists(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/file.mp4")).unwrap();
+      store.delete("/tmp/file.mp4").unwrap();
+
+      assert!(store.list().unwrap().is_empty());
+
+      let reloaded = open(dir.path());
+      assert!(reloaded.list().unwrap().is_empty());
+   }
+
+   fn test_delete_unknown_path_is_ok(open: Open) {
+      let (store, _dir) = temp_store(open);
+      assert!(store.delete("/tmp/nonexistent.mp4").is_ok());
+   }
+
+   fn test_clear_removes_all_and_persists(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+
+      let removed = store.clear().unwrap();
+      assert_eq!(removed.len(), 2);
+      assert!(store.list().unwrap().is_empty());
+
+      let reloaded = open(dir.path());
+      assert!(reloaded.list().unwrap().is_empty());
+   }
+
+   fn test_update_where_updates_matching_and_persists(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+
+      let updated = store
+         .update_where(&mut |item| {
+            (item.path == "/tmp/a.mp4").then(|| item.with_status(DownloadStatus::Paused))
+         })
+         .unwrap();
+      assert_eq!(updated.len(), 1);
+      assert_eq!(updated[0].path, "/tmp/a.mp4");
+
+      let reloaded = open(dir.path());
+      let a = reloaded.find_by_path("/tmp/a.mp4").unwrap().unwrap();
+      let b = reloaded.find_by_path("/tmp/b.mp4").unwrap().unwrap();
+      assert_eq!(a.status, DownloadStatus::Paused);
+      assert_eq!(b.status, sample_item("/tmp/b.mp4").status);
+   }
+
+   fn test_delete_where_removes_matching_and_persists(open: Open) {
+      let (store, dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+
+      let removed = store
+         .delete_where(&mut |item| item.path == "/tmp/b.mp4")
+         .unwrap();
+      assert_eq!(removed.len(), 1);
+      assert_eq!(removed[0].path, "/tmp/b.mp4");
+
+      let reloaded = open(dir.path());
+      let paths: Vec<_> = reloaded
+         .list()
+         .unwrap()
+         .into_iter()
+         .map(|i| i.path)
+         .collect();
+      assert_eq!(paths, vec!["/tmp/a.mp4"]);
+   }
+
+   fn test_queries_by_url_and_status(open: Open) {
+      let (store, _dir) = temp_store(open);
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store
+         .create(DownloadItem {
+            url: "https://example.com/other.mp4".to_string(),
+            status: DownloadStatus::Paused,
+            ..sample_item("/tmp/b.mp4")
+         })
+         .unwrap();
+
+      let by_url = store.find_by_url("https://example.com/other.mp4").unwrap();
+      assert_eq!(by_url.len(), 1);
+      assert_eq!(by_url[0].path, "/tmp/b.mp4");
+
+      let paused = store.list_by_status(&DownloadStatus::Paused).unwrap();
+      assert_eq!(paused.len(), 1);
+      assert_eq!(paused[0].path, "/tmp/b.mp4");
+
+      let counts = store.count_by_status().unwrap();
+      assert_eq!(counts[&DownloadStatus::Idle], 1);
+      assert_eq!(counts[&DownloadStatus::Paused], 1);
+   }
+
+   fn test_list_by_tag_after_reload(open: Open) {
+      let (store, dir) = temp_store(open);
+      let tagged = |path: &str, tags: &[&str]| DownloadItem {
+         options: crate::models::DownloadOptions {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+         },
+         ..sample_item(path)
+      };
+      store.create(tagged("/tmp/a.mp3", &["podcasts"])).unwrap();
+      store
+         .create(tagged("/tmp/b.pdf", &["attachments", "inbox"]))
+         .unwrap();
+      store.create(sample_item("/tmp/c.mp4")).unwrap();
+
+      let reloaded = open(dir.path());
+      let paths = |tag| {
+         reloaded
+            .list_by_tag(tag)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.path)
+            .collect::<Vec<_>>()
+      };
+      assert_eq!(paths("podcasts"), ["/tmp/a.mp3"]);
+      assert_eq!(paths("inbox"), ["/tmp/b.pdf"]);
+      assert!(paths("podcast").is_empty());
+   }
+
+   // ---------- JSON store ----------
+
+   #[test]
+   fn test_create_persists_to_disk() {
+      let (store, dir) = temp_json_store();
+      store.create(sample_item("/tmp/file.mp4")).unwrap();
+      assert!(dir.path().join("downloads.json").exists());
+   }
+
    #[test]
    fn test_update_no_persist_does_not_write_disk() {
-      let (store, dir) = temp_store();
+      let (store, dir) = temp_json_store();
       let item = store.create(sample_item("/tmp/file.mp4")).unwrap();
       let updated = DownloadItem {
-         progress: 75.0,
+         progress: Some(75.0),
          ..item
       };
       store.update_no_persist(updated).unwrap();
 
       // In-memory reflects the change.
       let in_memory = store.find_by_path("/tmp/file.mp4").unwrap().unwrap();
-      assert_eq!(in_memory.progress, 75.0);
+      assert_eq!(in_memory.progress, Some(75.0));
 
       // Disk still has the original value.
       let reloaded = DownloadStore::new(dir.path().join("downloads.json"));
       reloaded.load().unwrap();
       let on_disk = reloaded.find_by_path("/tmp/file.mp4").unwrap().unwrap();
-      assert_eq!(on_disk.progress, 0.0);
+      assert_eq!(on_disk.progress, Some(0.0));
    }
 
    #[test]
-   fn test_delete_removes_item_and_persists() {
-      let (store, dir) = temp_store();
-      store.create(sample_item("/tmp/file.mp4")).unwrap();
-      store.delete("/tmp/file.mp4").unwrap();
-
+   fn test_load_missing_file_is_ok() {
+      let (store, _dir) = temp_json_store();
+      assert!(store.load().is_ok());
       assert!(store.list().unwrap().is_empty());
+   }
 
-      let reloaded = DownloadStore::new(dir.path().join("downloads.json"));
-      reloaded.load().unwrap();
-      assert!(reloaded.list().unwrap().is_empty());
+   #[test]
+   fn test_load_from_valid_json() {
+      let dir = TempDir::new().unwrap();
+      let path = dir.path().join("downloads.json");
+      let items = vec![sample_item("/tmp/file.mp4")];
+      fs::write(&path, serde_json::to_vec(&items).unwrap()).unwrap();
+
+      let store = DownloadStore::new(path);
+      store.load().unwrap();
+      assert_eq!(store.list().unwrap().len(), 1);
    }
 
    #[test]
-   fn test_delete_unknown_path_is_ok() {
-      let (store, _dir) = temp_store();
-      assert!(store.delete("/tmp/nonexistent.mp4").is_ok());
+   fn test_load_migrates_unversioned_store() {
+      let dir = TempDir::new().unwrap();
+      let path = dir.path().join("downloads.json");
+      fs::write(
+         &path,
+         r#"[{"url":"https://example.com/a.mp4","path":"/tmp/a.mp4","progress":42.0,"status":"paused"}]"#,
+      )
+      .unwrap();
+
+      let store = DownloadStore::new(path.clone());
+      store.load().unwrap();
+      let item = store.find_by_path("/tmp/a.mp4").unwrap().unwrap();
+      assert_eq!(item.url, "https://example.com/a.mp4");
+      assert_eq!(item.progress, Some(42.0));
+      assert_eq!(item.status, DownloadStatus::Paused);
+
+      // The next write upgrades the file.
+      store.update(item).unwrap();
+      let file: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+      assert_eq!(file["version"], STORE_VERSION);
+      assert_eq!(file["downloads"][0]["path"], "/tmp/a.mp4");
    }
 
    #[test]
-   fn test_load_missing_file_is_ok() {
-      let (store, _dir) = temp_store();
-      assert!(store.load().is_ok());
-      assert!(store.list().unwrap().is_empty());
+   fn test_load_keeps_most_recent_of_duplicate_paths() {
+      let dir = TempDir::new().unwrap();
+      let path = dir.path().join("downloads.json");
+      fs::write(
+         &path,
+         r#"{"version":1,"downloads":[
+            {"url":"https://example.com/a.mp4","path":"/tmp/a.mp4","progress":10.0,"status":"paused","updatedAt":2000},
+            {"url":"https://example.com/b.mp4","path":"/tmp/b.mp4","progress":0.0,"status":"idle"},
+            {"url":"https://example.com/a.mp4","path":"/tmp/a.mp4","progress":60.0,"status":"paused","updatedAt":3000},
+            {"url":"https://example.com/a.mp4","path":"/tmp/a.mp4","progress":5.0,"status":"idle","updatedAt":1000}
+         ]}"#,
+      )
+      .unwrap();
+
+      let store = DownloadStore::new(path);
+      store.load().unwrap();
+      let items = store.list().unwrap();
+      assert_eq!(items.len(), 2);
+      assert_eq!(items[0].path, "/tmp/a.mp4");
+      assert_eq!(items[0].progress, Some(60.0));
+      assert_eq!(items[0].updated_at, 3000);
+      assert_eq!(items[1].path, "/tmp/b.mp4");
+
+      // The remaining entry can be updated and deleted like any other.
+      store.delete("/tmp/a.mp4").unwrap();
+      assert!(store.find_by_path("/tmp/a.mp4").unwrap().is_none());
    }
 
    #[test]
-   fn test_load_from_valid_json() {
+   fn test_load_clamps_progress() {
       let dir = TempDir::new().unwrap();
       let path = dir.path().join("downloads.json");
-      let items = vec![sample_item("/tmp/file.mp4")];
-      fs::write(&path, serde_json::to_vec(&items).unwrap()).unwrap();
+      fs::write(
+         &path,
+         r#"{"version":1,"downloads":[
+            {"url":"https://example.com/a.mp4","path":"/tmp/a.mp4","progress":250.0,"status":"paused"},
+            {"url":"https://example.com/b.mp4","path":"/tmp/b.mp4","progress":-3.5,"status":"paused"}
+         ]}"#,
+      )
+      .unwrap();
 
       let store = DownloadStore::new(path);
       store.load().unwrap();
-      assert_eq!(store.list().unwrap().len(), 1);
+      let items = store.list().unwrap();
+      assert_eq!(items[0].progress, Some(100.0));
+      assert_eq!(items[1].progress, Some(0.0));
+   }
+
+   #[test]
+   fn test_load_rejects_newer_version() {
+      let dir = TempDir::new().unwrap();
+      let path = dir.path().join("downloads.json");
+      fs::write(&path, br#"{"version":999,"downloads":[]}"#).unwrap();
+
+      let store = DownloadStore::new(path);
+      assert!(store.load().is_err());
    }
 
    #[test]
@@ -304,6 +1219,124 @@ mod tests {
       assert!(store.load().is_err());
    }
 
+   #[test]
+   fn test_update_no_persist_writes_once_interval_elapsed() {
+      let dir = TempDir::new().unwrap();
+      let path = dir.path().join("downloads.json");
+      let store = DownloadStore::new(path.clone()).with_flush_interval(Duration::ZERO);
+      let item = store.create(sample_item("/tmp/file.mp4")).unwrap();
+      store
+         .update_no_persist(DownloadItem {
+            progress: Some(40.0),
+            ..item
+         })
+         .unwrap();
+
+      let reloaded = DownloadStore::new(path);
+      reloaded.load().unwrap();
+      let on_disk = reloaded.find_by_path("/tmp/file.mp4").unwrap().unwrap();
+      assert_eq!(on_disk.progress, Some(40.0));
+   }
+
+   #[test]
+   fn test_flush_and_drop_write_pending_progress() {
+      let (store, dir) = temp_json_store();
+      let path = dir.path().join("downloads.json");
+      let item = store.create(sample_item("/tmp/file.mp4")).unwrap();
+      let reload_progress = || {
+         let reloaded = DownloadStore::new(path.clone());
+         reloaded.load().unwrap();
+         reloaded
+            .find_by_path("/tmp/file.mp4")
+            .unwrap()
+            .unwrap()
+            .progress
+      };
+
+      store
+         .update_no_persist(DownloadItem {
+            progress: Some(10.0),
+            ..item.clone()
+         })
+         .unwrap();
+      assert_eq!(reload_progress(), Some(0.0));
+      store.flush().unwrap();
+      assert_eq!(reload_progress(), Some(10.0));
+
+      store
+         .update_no_persist(DownloadItem {
+            progress: Some(20.0),
+            ..item
+         })
+         .unwrap();
+      assert_eq!(reload_progress(), Some(10.0));
+      drop(store);
+      assert_eq!(reload_progress(), Some(20.0));
+   }
+
+   #[test]
+   fn test_save_replaces_store_and_keeps_backup() {
+      let (store, dir) = temp_json_store();
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+
+      let path = dir.path().join("downloads.json");
+      assert!(!sibling(&path, TEMP_SUFFIX).exists());
+      let backup = migrate(
+         serde_json::from_slice(&fs::read(sibling(&path, BACKUP_SUFFIX)).unwrap()).unwrap(),
+      )
+      .unwrap();
+      assert_eq!(backup.len(), 1);
+   }
+
+   #[test]
+   fn test_load_recovers_from_backup_after_partial_write() {
+      let (store, dir) = temp_json_store();
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+
+      // Simulate a store truncated mid-write.
+      let path = dir.path().join("downloads.json");
+      let data = fs::read(&path).unwrap();
+      fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+      let reloaded = DownloadStore::new(path);
+      reloaded.load().unwrap();
+      let paths: Vec<_> = reloaded
+         .list()
+         .unwrap()
+         .into_iter()
+         .map(|i| i.path)
+         .collect();
+      assert_eq!(paths, vec!["/tmp/a.mp4"]);
+   }
+
+   #[test]
+   fn test_load_recovers_from_backup_when_store_missing() {
+      // A crash between backing up the store and moving the new one in place.
+      let (store, dir) = temp_json_store();
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+      let path = dir.path().join("downloads.json");
+      fs::rename(&path, sibling(&path, TEMP_SUFFIX)).unwrap();
+
+      let reloaded = DownloadStore::new(path);
+      reloaded.load().unwrap();
+      assert_eq!(reloaded.list().unwrap().len(), 1);
+   }
+
+   #[test]
+   fn test_load_ignores_partial_temp_file() {
+      let (store, dir) = temp_json_store();
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      let path = dir.path().join("downloads.json");
+      fs::write(sibling(&path, TEMP_SUFFIX), b"[{\"url\":").unwrap();
+
+      let reloaded = DownloadStore::new(path);
+      reloaded.load().unwrap();
+      assert_eq!(reloaded.list().unwrap().len(), 1);
+   }
+
    #[test]
    fn test_save_creates_parent_directory() {
       let dir = TempDir::new().unwrap();
@@ -311,4 +1344,26 @@ mod tests {
       store.create(sample_item("/tmp/file.mp4")).unwrap();
       assert!(dir.path().join("nested/dir/downloads.json").exists());
    }
+
+   // ---------- In-memory store ----------
+
+   #[test]
+   fn test_in_memory_store_keeps_items_without_disk() {
+      let store = InMemoryStore::new();
+      store.load().unwrap();
+      store.create(sample_item("/tmp/a.mp4")).unwrap();
+      store.create(sample_item("/tmp/b.mp4")).unwrap();
+      assert!(store.create(sample_item("/tmp/a.mp4")).is_err());
+
+      store
+         .rename("/tmp/a.mp4", sample_item("/tmp/c.mp4"))
+         .unwrap();
+      let removed = store
+         .delete_where(&mut |item| item.path == "/tmp/b.mp4")
+         .unwrap();
+      assert_eq!(removed.len(), 1);
+
+      let paths: Vec<_> = store.list().unwrap().into_iter().map(|i| i.path).collect();
+      assert_eq!(paths, vec!["/tmp/c.mp4"]);
+   }
 }
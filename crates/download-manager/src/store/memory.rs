@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use super::{Store, touched};
+use crate::{DownloadItem, DownloadStats, Error};
+
+/// Store that keeps download items in memory only, e.g. for tests or downloads that
+/// shouldn't outlive the session. Nothing is read from or written to disk.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+   downloads: Mutex<Vec<DownloadItem>>,
+   stats: Mutex<DownloadStats>,
+   etags: Mutex<HashMap<String, String>>,
+}
+
+impl InMemoryStore {
+   /// Creates an empty store.
+   pub fn new() -> Self {
+      Self::default()
+   }
+
+   fn downloads(&self) -> crate::Result<MutexGuard<'_, Vec<DownloadItem>>> {
+      self
+         .downloads
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))
+   }
+
+   fn stats_guard(&self) -> crate::Result<MutexGuard<'_, DownloadStats>> {
+      self
+         .stats
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))
+   }
+
+   fn etags(&self) -> crate::Result<MutexGuard<'_, HashMap<String, String>>> {
+      self
+         .etags
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))
+   }
+}
+
+impl Store for InMemoryStore {
+   fn load(&self) -> crate::Result<()> {
+      Ok(())
+   }
+
+   fn list(&self) -> crate::Result<Vec<DownloadItem>> {
+      Ok(self.downloads()?.clone())
+   }
+
+   fn find_by_path(&self, path: &str) -> crate::Result<Option<DownloadItem>> {
+      Ok(self.downloads()?.iter().find(|i| i.path == path).cloned())
+   }
+
+   fn create(&self, item: DownloadItem) -> crate::Result<DownloadItem> {
+      let mut downloads = self.downloads()?;
+      if downloads.iter().any(|i| i.path == item.path) {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &item.path
+         )));
+      }
+      let item = touched(item);
+      downloads.push(item.clone());
+      Ok(item)
+   }
+
+   fn create_many(&self, items: Vec<DownloadItem>) -> crate::Result<Vec<Option<DownloadItem>>> {
+      let mut downloads = self.downloads()?;
+      let mut existing = Vec::with_capacity(items.len());
+      for item in items {
+         match downloads.iter().find(|i| i.path == item.path) {
+            Some(found) => existing.push(Some(found.clone())),
+            None => {
+               downloads.push(touched(item));
+               existing.push(None);
+            }
+         }
+      }
+      Ok(existing)
+   }
+
+   fn update(&self, item: DownloadItem) -> crate::Result<()> {
+      if let Some(existing) = self.downloads()?.iter_mut().find(|i| i.path == item.path) {
+         *existing = touched(item);
+      }
+      Ok(())
+   }
+
+   fn update_no_persist(&self, item: DownloadItem) -> crate::Result<()> {
+      self.update(item)
+   }
+
+   fn rename(&self, old_path: &str, item: DownloadItem) -> crate::Result<()> {
+      let mut downloads = self.downloads()?;
+      if item.path != old_path && downloads.iter().any(|i| i.path == item.path) {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &item.path
+         )));
+      }
+      let existing = downloads
+         .iter_mut()
+         .find(|i| i.path == old_path)
+         .ok_or_else(|| Error::NotFound(old_path.to_string()))?;
+      *existing = touched(item);
+      Ok(())
+   }
+
+   fn delete(&self, path: &str) -> crate::Result<()> {
+      self.downloads()?.retain(|i| i.path != path);
+      Ok(())
+   }
+
+   fn update_where(
+      &self,
+      f: &mut dyn FnMut(&DownloadItem) -> Option<DownloadItem>,
+   ) -> crate::Result<Vec<DownloadItem>> {
+      let mut updated = Vec::new();
+      for existing in self.downloads()?.iter_mut() {
+         if let Some(item) = f(existing) {
+            let item = touched(item);
+            *existing = item.clone();
+            updated.push(item);
+         }
+      }
+      Ok(updated)
+   }
+
+   fn delete_where(
+      &self,
+      predicate: &mut dyn FnMut(&DownloadItem) -> bool,
+   ) -> crate::Result<Vec<DownloadItem>> {
+      let mut downloads = self.downloads()?;
+      let (removed, kept) = std::mem::take(&mut *downloads)
+         .into_iter()
+         .partition(|item| predicate(item));
+      *downloads = kept;
+      Ok(removed)
+   }
+
+   fn clear(&self) -> crate::Result<Vec<DownloadItem>> {
+      Ok(std::mem::take(&mut *self.downloads()?))
+   }
+
+   fn stats(&self) -> crate::Result<DownloadStats> {
+      Ok(*self.stats_guard()?)
+   }
+
+   fn add_stats(&self, stats: &DownloadStats) -> crate::Result<()> {
+      self.stats_guard()?.add(stats);
+      Ok(())
+   }
+
+   fn reset_stats(&self) -> crate::Result<()> {
+      *self.stats_guard()? = DownloadStats::default();
+      Ok(())
+   }
+
+   fn etag(&self, path: &str) -> crate::Result<Option<String>> {
+      Ok(self.etags()?.get(path).cloned())
+   }
+
+   fn set_etag(&self, path: &str, etag: Option<&str>) -> crate::Result<()> {
+      let mut etags = self.etags()?;
+      match etag {
+         Some(etag) => etags.insert(path.to_string(), etag.to_string()),
+         None => etags.remove(path),
+      };
+      Ok(())
+   }
+}
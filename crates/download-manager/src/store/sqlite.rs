@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{Connection, OptionalExtension, params};
+
+use super::{Store, touched};
+use crate::{DownloadItem, DownloadStats, DownloadStatus, Error};
+
+/// SQLite store for download items.
+///
+/// Unlike the JSON [`DownloadStore`](super::DownloadStore), which rewrites the whole file
+/// on every change, each change only writes the affected rows. Items are stored as JSON,
+/// alongside the columns used for lookups.
+#[derive(Debug)]
+pub struct SqliteStore {
+   conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+   /// Opens the database at `path`, creating it and its parent directory if needed.
+   pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+      let path = path.as_ref();
+      if let Some(parent) = path.parent()
+         && !parent.exists()
+      {
+         std::fs::create_dir_all(parent)
+            .map_err(|e| Error::Store(format!("Failed to create store directory: {}", e)))?;
+      }
+      let conn = Connection::open(path).map_err(sql_error)?;
+      conn
+         .execute_batch(
+            "CREATE TABLE IF NOT EXISTS downloads (
+               id INTEGER PRIMARY KEY AUTOINCREMENT,
+               path TEXT NOT NULL UNIQUE,
+               url TEXT NOT NULL,
+               status TEXT NOT NULL,
+               item TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS downloads_url ON downloads (url);
+            CREATE INDEX IF NOT EXISTS downloads_status ON downloads (status);
+            CREATE TABLE IF NOT EXISTS stats (
+               id INTEGER PRIMARY KEY CHECK (id = 0),
+               total_bytes INTEGER NOT NULL,
+               total_completed INTEGER NOT NULL,
+               total_failed INTEGER NOT NULL
+            );
+            INSERT OR IGNORE INTO stats VALUES (0, 0, 0, 0);
+            CREATE TABLE IF NOT EXISTS etags (
+               path TEXT PRIMARY KEY,
+               etag TEXT NOT NULL
+            );",
+         )
+         .map_err(sql_error)?;
+      Ok(Self {
+         conn: Mutex::new(conn),
+      })
+   }
+
+   fn conn(&self) -> crate::Result<MutexGuard<'_, Connection>> {
+      self
+         .conn
+         .lock()
+         .map_err(|e| Error::Store(format!("Lock poisoned: {}", e)))
+   }
+}
+
+impl Store for SqliteStore {
+   fn load(&self) -> crate::Result<()> {
+      // Rows are read on demand.
+      Ok(())
+   }
+
+   fn list(&self) -> crate::Result<Vec<DownloadItem>> {
+      query_items(&*self.conn()?, "SELECT item FROM downloads ORDER BY id", [])
+   }
+
+   fn find_by_url(&self, url: &str) -> crate::Result<Vec<DownloadItem>> {
+      query_items(
+         &*self.conn()?,
+         "SELECT item FROM downloads WHERE url = ?1 ORDER BY id",
+         [url],
+      )
+   }
+
+   fn list_by_status(&self, status: &DownloadStatus) -> crate::Result<Vec<DownloadItem>> {
+      query_items(
+         &*self.conn()?,
+         "SELECT item FROM downloads WHERE status = ?1 ORDER BY id",
+         [status_key(status)?],
+      )
+   }
+
+   fn list_by_tag(&self, tag: &str) -> crate::Result<Vec<DownloadItem>> {
+      query_items(
+         &*self.conn()?,
+         "SELECT item FROM downloads \
+          WHERE EXISTS (SELECT 1 FROM json_each(item, '$.tags') WHERE value = ?1) ORDER BY id",
+         [tag],
+      )
+   }
+
+   fn count_by_status(&self) -> crate::Result<HashMap<DownloadStatus, usize>> {
+      let conn = self.conn()?;
+      let mut stmt = conn
+         .prepare("SELECT status, COUNT(*) FROM downloads GROUP BY status")
+         .map_err(sql_error)?;
+      let rows = stmt
+         .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+         })
+         .map_err(sql_error)?;
+
+      let mut counts = HashMap::new();
+      for row in rows {
+         let (status, count) = row.map_err(sql_error)?;
+         let status: DownloadStatus = serde_json::from_value(serde_json::Value::String(status))
+            .map_err(|e| Error::Store(format!("Failed to parse status: {}", e)))?;
+         counts.insert(status, count as usize);
+      }
+      Ok(counts)
+   }
+
+   fn find_by_path(&self, path: &str) -> crate::Result<Option<DownloadItem>> {
+      find(&*self.conn()?, path)
+   }
+
+   fn create(&self, item: DownloadItem) -> crate::Result<DownloadItem> {
+      let conn = self.conn()?;
+      if find(&conn, &item.path)?.is_some() {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &item.path
+         )));
+      }
+      let item = touched(item);
+      insert(&conn, &item)?;
+      Ok(item)
+   }
+
+   fn create_many(&self, items: Vec<DownloadItem>) -> crate::Result<Vec<Option<DownloadItem>>> {
+      let mut conn = self.conn()?;
+      let tx = conn.transaction().map_err(sql_error)?;
+
+      let mut existing = Vec::with_capacity(items.len());
+      for item in items {
+         match find(&tx, &item.path)? {
+            Some(found) => existing.push(Some(found)),
+            None => {
+               insert(&tx, &touched(item))?;
+               existing.push(None);
+            }
+         }
+      }
+      tx.commit().map_err(sql_error)?;
+      Ok(existing)
+   }
+
+   fn update(&self, item: DownloadItem) -> crate::Result<()> {
+      let item = touched(item);
+      replace(&*self.conn()?, &item.path, &item)?;
+      Ok(())
+   }
+
+   fn update_no_persist(&self, item: DownloadItem) -> crate::Result<()> {
+      // A row update is cheap, so progress is persisted right away.
+      self.update(item)
+   }
+
+   fn rename(&self, old_path: &str, item: DownloadItem) -> crate::Result<()> {
+      let conn = self.conn()?;
+      if item.path != old_path && find(&conn, &item.path)?.is_some() {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &item.path
+         )));
+      }
+      if replace(&conn, old_path, &touched(item))? == 0 {
+         return Err(Error::NotFound(old_path.to_string()));
+      }
+      Ok(())
+   }
+
+   fn delete(&self, path: &str) -> crate::Result<()> {
+      self
+         .conn()?
+         .execute("DELETE FROM downloads WHERE path = ?1", [path])
+         .map_err(sql_error)?;
+      Ok(())
+   }
+
+   fn update_where(
+      &self,
+      f: &mut dyn FnMut(&DownloadItem) -> Option<DownloadItem>,
+   ) -> crate::Result<Vec<DownloadItem>> {
+      let mut conn = self.conn()?;
+      let tx = conn.transaction().map_err(sql_error)?;
+
+      let mut updated = Vec::new();
+      for existing in query_items(&tx, "SELECT item FROM downloads ORDER BY id", [])? {
+         if let Some(item) = f(&existing) {
+            let item = touched(item);
+            replace(&tx, &existing.path, &item)?;
+            updated.push(item);
+         }
+      }
+      tx.commit().map_err(sql_error)?;
+      Ok(updated)
+   }
+
+   fn delete_where(
+      &self,
+      predicate: &mut dyn FnMut(&DownloadItem) -> bool,
+   ) -> crate::Result<Vec<DownloadItem>> {
+      let mut conn = self.conn()?;
+      let tx = conn.transaction().map_err(sql_error)?;
+
+      let removed: Vec<DownloadItem> =
+         query_items(&tx, "SELECT item FROM downloads ORDER BY id", [])?
+            .into_iter()
+            .filter(|item| predicate(item))
+            .collect();
+      for item in &removed {
+         tx.execute("DELETE FROM downloads WHERE path = ?1", [&item.path])
+            .map_err(sql_error)?;
+      }
+      tx.commit().map_err(sql_error)?;
+      Ok(removed)
+   }
+
+   fn clear(&self) -> crate::Result<Vec<DownloadItem>> {
+      let mut conn = self.conn()?;
+      let tx = conn.transaction().map_err(sql_error)?;
+      let removed = query_items(&tx, "SELECT item FROM downloads ORDER BY id", [])?;
+      tx.execute("DELETE FROM downloads", []).map_err(sql_error)?;
+      tx.commit().map_err(sql_error)?;
+      Ok(removed)
+   }
+
+   fn stats(&self) -> crate::Result<DownloadStats> {
+      self
+         .conn()?
+         .query_row(
+            "SELECT total_bytes, total_completed, total_failed FROM stats",
+            [],
+            |row| {
+               Ok(DownloadStats {
+                  total_bytes: row.get::<_, i64>(0)? as u64,
+                  total_completed: row.get::<_, i64>(1)? as u64,
+                  total_failed: row.get::<_, i64>(2)? as u64,
+               })
+            },
+         )
+         .map_err(sql_error)
+   }
+
+   fn add_stats(&self, stats: &DownloadStats) -> crate::Result<()> {
+      // A row update is cheap, so the change is persisted right away.
+      self
+         .conn()?
+         .execute(
+            "UPDATE stats SET total_bytes = total_bytes + ?1, \
+             total_completed = total_completed + ?2, total_failed = total_failed + ?3",
+            params![
+               stats.total_bytes as i64,
+               stats.total_completed as i64,
+               stats.total_failed as i64
+            ],
+         )
+         .map_err(sql_error)?;
+      Ok(())
+   }
+
+   fn reset_stats(&self) -> crate::Result<()> {
+      self
+         .conn()?
+         .execute(
+            "UPDATE stats SET total_bytes = 0, total_completed = 0, total_failed = 0",
+            [],
+         )
+         .map_err(sql_error)?;
+      Ok(())
+   }
+
+   fn etag(&self, path: &str) -> crate::Result<Option<String>> {
+      self
+         .conn()?
+         .query_row("SELECT etag FROM etags WHERE path = ?1", [path], |row| {
+            row.get(0)
+         })
+         .optional()
+         .map_err(sql_error)
+   }
+
+   fn set_etag(&self, path: &str, etag: Option<&str>) -> crate::Result<()> {
+      // A row update is cheap, so the change is persisted right away.
+      let conn = self.conn()?;
+      match etag {
+         Some(etag) => conn.execute(
+            "INSERT OR REPLACE INTO etags (path, etag) VALUES (?1, ?2)",
+            params![path, etag],
+         ),
+         None => conn.execute("DELETE FROM etags WHERE path = ?1", [path]),
+      }
+      .map_err(sql_error)?;
+      Ok(())
+   }
+}
+
+fn query_items<P: rusqlite::Params>(
+   conn: &Connection,
+   sql: &str,
+   params: P,
+) -> crate::Result<Vec<DownloadItem>> {
+   let mut stmt = conn.prepare(sql).map_err(sql_error)?;
+   let rows = stmt
+      .query_map(params, |row| row.get::<_, String>(0))
+      .map_err(sql_error)?;
+   rows
+      .map(|row| parse_item(&row.map_err(sql_error)?))
+      .collect()
+}
+
+fn find(conn: &Connection, path: &str) -> crate::Result<Option<DownloadItem>> {
+   conn
+      .query_row(
+         "SELECT item FROM downloads WHERE path = ?1",
+         [path],
+         |row| row.get::<_, String>(0),
+      )
+      .optional()
+      .map_err(sql_error)?
+      .map(|json| parse_item(&json))
+      .transpose()
+}
+
+fn insert(conn: &Connection, item: &DownloadItem) -> crate::Result<()> {
+   conn
+      .execute(
+         "INSERT INTO downloads (path, url, status, item) VALUES (?1, ?2, ?3, ?4)",
+         params![
+            item.path,
+            item.url,
+            status_key(&item.status)?,
+            to_json(item)?
+         ],
+      )
+      .map_err(sql_error)?;
+   Ok(())
+}
+
+/// Replaces the row at `path` with `item`, returning the number of rows changed.
+fn replace(conn: &Connection, path: &str, item: &DownloadItem) -> crate::Result<usize> {
+   conn
+      .execute(
+         "UPDATE downloads SET path = ?1, url = ?2, status = ?3, item = ?4 WHERE path = ?5",
+         params![
+            item.path,
+            item.url,
+            status_key(&item.status)?,
+            to_json(item)?,
+            path
+         ],
+      )
+      .map_err(sql_error)
+}
+
+/// Returns the serialized name of `status`, e.g. `inProgress`.
+fn status_key(status: &DownloadStatus) -> crate::Result<String> {
+   match serde_json::to_value(status) {
+      Ok(serde_json::Value::String(key)) => Ok(key),
+      _ => Err(Error::Store(format!(
+         "Failed to serialize status: {}",
+         status
+      ))),
+   }
+}
+
+fn to_json(item: &DownloadItem) -> crate::Result<String> {
+   serde_json::to_string(item).map_err(|e| Error::Store(format!("Failed to serialize item: {}", e)))
+}
+
+fn parse_item(json: &str) -> crate::Result<DownloadItem> {
+   serde_json::from_str(json).map_err(|e| Error::Store(format!("Failed to parse item: {}", e)))
+}
+
+fn sql_error(e: rusqlite::Error) -> Error {
+   Error::Store(format!("SQLite error: {}", e))
+}
@@ -1,6 +1,13 @@
-use std::path::Path;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use reqwest::header::{HeaderName, HeaderValue};
 
 use crate::Error;
+use crate::config::HostPolicy;
+use crate::error::{PathError, UrlError};
+use crate::models::DownloadOptions;
 
 /// Validates a download path.
 ///
@@ -10,17 +17,150 @@ use crate::Error;
 /// - Has a filename component
 pub fn path(path: &str) -> crate::Result<()> {
    if path.is_empty() {
-      return Err(Error::Path("path cannot be empty".to_string()));
+      return Err(Error::Path(
+         PathError::Empty,
+         "path cannot be empty".to_string(),
+      ));
    }
 
    let p = Path::new(path);
 
    if !p.is_absolute() {
-      return Err(Error::Path("path must be absolute".to_string()));
+      return Err(Error::Path(
+         PathError::NotAbsolute,
+         "path must be absolute".to_string(),
+      ));
    }
 
    if p.file_name().is_none() {
-      return Err(Error::Path("path must have a filename".to_string()));
+      return Err(Error::Path(
+         PathError::NoFilename,
+         "path must have a filename".to_string(),
+      ));
+   }
+
+   Ok(())
+}
+
+/// Validates that a download path stays within `root` once resolved.
+///
+/// Checks that the path:
+/// - Is a valid download path, see [`path`]
+/// - Is within `root` after resolving symlinks and `..` segments. Segments past the
+///   deepest existing ancestor can't be resolved, so `..` isn't allowed among them.
+pub fn path_within(root: &Path, path: &str) -> crate::Result<()> {
+   self::path(path)?;
+
+   let root = root.canonicalize().map_err(|e| {
+      Error::Path(
+         PathError::InvalidRoot,
+         format!("Invalid download root '{}': {}", root.display(), e),
+      )
+   })?;
+   match resolve(Path::new(path)) {
+      Some(resolved) if resolved.starts_with(&root) => Ok(()),
+      _ => Err(Error::Path(
+         PathError::OutsideRoot,
+         format!("path escapes the download root: {}", path),
+      )),
+   }
+}
+
+/// Resolves an absolute path like [`Path::canonicalize`], except that it doesn't need
+/// to exist: its deepest existing ancestor is canonicalized and the remaining segments
+/// are appended. Returns `None` if a remaining segment is `..`.
+fn resolve(path: &Path) -> Option<PathBuf> {
+   let mut ancestor = path;
+   let mut rest = Vec::new();
+   let mut resolved = loop {
+      if let Ok(canonical) = ancestor.canonicalize() {
+         break canonical;
+      }
+      let (parent, last) = (ancestor.parent()?, ancestor.components().next_back()?);
+      rest.push(last);
+      ancestor = parent;
+   };
+   for component in rest.into_iter().rev() {
+      match component {
+         Component::Normal(segment) => resolved.push(segment),
+         Component::CurDir => {}
+         _ => return None,
+      }
+   }
+   Some(resolved)
+}
+
+/// Returns the form of an absolute path to hand to the file system.
+///
+/// On Windows, drive and UNC paths are turned into `\\?\` paths, which aren't limited
+/// to `MAX_PATH` (260) characters, so downloads into deep directory trees or network
+/// shares can be written. `/` separators and `.` and `..` segments are resolved first,
+/// since the file system takes these paths literally. Other paths are left as is.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+   use std::ffi::OsString;
+   use std::path::Prefix;
+
+   if !path.has_root() {
+      return Cow::Borrowed(path);
+   }
+   let mut components = path.components();
+   let mut long = match components.next() {
+      Some(Component::Prefix(prefix)) => match prefix.kind() {
+         Prefix::Disk(letter) => OsString::from(format!(r"\\?\{}:", char::from(letter))),
+         Prefix::UNC(server, share) => {
+            let mut long = OsString::from(r"\\?\UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+            long
+         }
+         _ => return Cow::Borrowed(path),
+      },
+      _ => return Cow::Borrowed(path),
+   };
+   let mut segments = Vec::new();
+   for component in components {
+      match component {
+         Component::Normal(segment) => segments.push(segment),
+         Component::ParentDir => {
+            segments.pop();
+         }
+         Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+      }
+   }
+   for segment in segments {
+      long.push(r"\");
+      long.push(segment);
+   }
+   Cow::Owned(PathBuf::from(long))
+}
+
+/// Returns the form of an absolute path to hand to the file system, which is the path
+/// itself outside Windows.
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+   Cow::Borrowed(path)
+}
+
+/// Validates a download directory.
+///
+/// Checks that the path:
+/// - Is not empty
+/// - Is an absolute path
+pub fn directory(path: &str) -> crate::Result<()> {
+   if path.is_empty() {
+      return Err(Error::Path(
+         PathError::Empty,
+         "directory cannot be empty".to_string(),
+      ));
+   }
+
+   if !Path::new(path).is_absolute() {
+      return Err(Error::Path(
+         PathError::NotAbsolute,
+         "directory must be absolute".to_string(),
+      ));
    }
 
    Ok(())
@@ -34,31 +174,153 @@ pub fn path(path: &str) -> crate::Result<()> {
 /// - Has a valid host
 pub fn url(url: &str) -> crate::Result<()> {
    if url.is_empty() {
-      return Err(Error::Url("URL cannot be empty".to_string()));
+      return Err(Error::Url(
+         UrlError::Empty,
+         "URL cannot be empty".to_string(),
+      ));
    }
 
    // Parse and validate URL structure
-   let parsed = url::Url::parse(url).map_err(|e| Error::Url(format!("Invalid URL: {}", e)))?;
+   let parsed = url::Url::parse(url)
+      .map_err(|e| Error::Url(UrlError::Malformed, format!("Invalid URL: {}", e)))?;
 
    // Check scheme
    match parsed.scheme() {
       "http" | "https" => {}
       scheme => {
-         return Err(Error::Url(format!(
-            "Invalid URL scheme '{}': must be http or https",
-            scheme
-         )));
+         return Err(Error::Url(
+            UrlError::BadScheme,
+            format!("Invalid URL scheme '{}': must be http or https", scheme),
+         ));
       }
    }
 
    // Check host
    if parsed.host().is_none() {
-      return Err(Error::Url("URL must have a host".to_string()));
+      return Err(Error::Url(
+         UrlError::NoHost,
+         "URL must have a host".to_string(),
+      ));
    }
 
    Ok(())
 }
 
+/// Returns the form of a valid download URL that is stored: without a fragment, which
+/// isn't part of the resource requested, and with the scheme and host in lowercase and
+/// no default port, so that equivalent URLs are stored alike.
+pub fn normalize_url(url: &str) -> String {
+   match url::Url::parse(url) {
+      Ok(mut parsed) => {
+         parsed.set_fragment(None);
+         parsed.into()
+      }
+      Err(_) => url.to_string(),
+   }
+}
+
+/// Validates a download URL against a host policy.
+///
+/// Checks that the URL:
+/// - Is a valid download URL, see [`url`]
+/// - Has a host allowed by `policy`
+pub fn url_against(policy: &HostPolicy, url: &str) -> crate::Result<()> {
+   self::url(url)?;
+
+   let parsed = url::Url::parse(url)
+      .map_err(|e| Error::Url(UrlError::Malformed, format!("Invalid URL: {}", e)))?;
+   let host = parsed.host_str().unwrap_or_default();
+   if !policy.allows(host) {
+      return Err(Error::Url(
+         UrlError::HostNotAllowed,
+         format!("Host '{}' is not allowed", host),
+      ));
+   }
+
+   Ok(())
+}
+
+/// Validates the options of a download.
+///
+/// Checks that:
+/// - Each mirror is a valid download URL
+/// - The prerequisite, if any, is a valid download path
+/// - The headers, cookies and `If-None-Match` value are valid
+/// - The file mode, if any, only has permission bits
+/// - The byte range, if any, doesn't end before it starts
+pub fn options(options: &DownloadOptions) -> crate::Result<()> {
+   for mirror in &options.mirrors {
+      url(mirror).map_err(|e| match e {
+         Error::Url(code, message) => {
+            Error::Url(code, format!("Invalid mirror '{}': {}", mirror, message))
+         }
+         e => e,
+      })?;
+   }
+
+   if let Some(depends_on) = &options.depends_on {
+      path(depends_on).map_err(|e| match e {
+         Error::Path(code, message) => {
+            Error::Path(code, format!("Invalid prerequisite: {}", message))
+         }
+         e => e,
+      })?;
+   }
+
+   if let Some(etag) = &options.if_none_match {
+      HeaderValue::from_str(etag)
+         .map_err(|e| Error::Http(format!("Invalid If-None-Match value: {}", e)))?;
+   }
+
+   if let Some(end) = options.range_end
+      && end < options.range_start.unwrap_or(0)
+   {
+      return Err(Error::Http(format!(
+         "Invalid range: ends at byte {} before it starts at byte {}",
+         end,
+         options.range_start.unwrap_or(0)
+      )));
+   }
+
+   if let Some(mode) = options.mode
+      && mode > 0o7777
+   {
+      return Err(Error::File(format!("Invalid file mode {:o}", mode)));
+   }
+
+   headers(&options.headers)?;
+   cookies(&options.cookies)
+}
+
+/// Validates HTTP headers, checking that each name and value can be sent.
+pub fn headers(headers: &BTreeMap<String, String>) -> crate::Result<()> {
+   for (name, value) in headers {
+      HeaderName::from_bytes(name.as_bytes())
+         .map_err(|e| Error::Http(format!("Invalid header name '{}': {}", name, e)))?;
+      HeaderValue::from_str(value)
+         .map_err(|e| Error::Http(format!("Invalid value of header '{}': {}", name, e)))?;
+   }
+   Ok(())
+}
+
+/// Validates cookies, checking that each name is a token and that each name and value
+/// can be sent in a `Cookie` header.
+pub fn cookies(cookies: &BTreeMap<String, String>) -> crate::Result<()> {
+   for (name, value) in cookies {
+      let valid_name = !name.is_empty()
+         && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b));
+      if !valid_name {
+         return Err(Error::Http(format!("Invalid cookie name '{}'", name)));
+      }
+      if value.contains(';') || HeaderValue::from_str(value).is_err() {
+         return Err(Error::Http(format!("Invalid value of cookie '{}'", name)));
+      }
+   }
+   Ok(())
+}
+
 #[cfg(test)]
 mod tests {
    use super::*;
@@ -69,23 +331,159 @@ mod tests {
       assert!(path("/file.txt").is_ok());
    }
 
+   /// Returns the code of a path validation error.
+   fn path_code(result: crate::Result<()>) -> Option<PathError> {
+      match result {
+         Err(Error::Path(code, _)) => Some(code),
+         _ => None,
+      }
+   }
+
+   /// Returns the code of a URL validation error.
+   fn url_code(result: crate::Result<()>) -> Option<UrlError> {
+      match result {
+         Err(Error::Url(code, _)) => Some(code),
+         _ => None,
+      }
+   }
+
    #[test]
    fn test_empty_path() {
       let result = path("");
       assert!(result.is_err());
       assert!(result.unwrap_err().to_string().contains("empty"));
+      assert_eq!(path_code(path("")), Some(PathError::Empty));
    }
 
    #[test]
    fn test_relative_path() {
-      assert!(path("relative/path.txt").is_err());
-      assert!(path("file.txt").is_err());
+      assert_eq!(
+         path_code(path("relative/path.txt")),
+         Some(PathError::NotAbsolute)
+      );
+      assert_eq!(path_code(path("file.txt")), Some(PathError::NotAbsolute));
+   }
+
+   #[cfg(windows)]
+   #[test]
+   fn test_windows_paths() {
+      assert!(path(r"C:\Downloads\file.mp4").is_ok());
+      assert!(path(r"\\server\share\file.mp4").is_ok());
+      assert!(path(r"\\?\C:\Downloads\file.mp4").is_ok());
+      let deep = format!(r"C:\{}\file.mp4", ["segment"; 40].join(r"\"));
+      assert!(deep.len() > 260);
+      assert!(path(&deep).is_ok());
+
+      // Drive-relative and rootless paths depend on the current directory.
+      assert_eq!(path_code(path(r"C:file.mp4")), Some(PathError::NotAbsolute));
+      assert_eq!(path_code(path(r"\file.mp4")), Some(PathError::NotAbsolute));
+   }
+
+   #[cfg(windows)]
+   #[test]
+   fn test_long_path() {
+      let long = |p: &str| long_path(Path::new(p)).into_owned();
+      assert_eq!(
+         long(r"C:\Downloads\a\..\.\file.mp4"),
+         Path::new(r"\\?\C:\Downloads\file.mp4")
+      );
+      assert_eq!(
+         long("C:/Downloads/file.mp4"),
+         Path::new(r"\\?\C:\Downloads\file.mp4")
+      );
+      assert_eq!(
+         long(r"\\server\share\dir\file.mp4"),
+         Path::new(r"\\?\UNC\server\share\dir\file.mp4")
+      );
+      // Already verbatim, or not absolute.
+      assert_eq!(long(r"\\?\C:\file.mp4"), Path::new(r"\\?\C:\file.mp4"));
+      assert_eq!(long(r"C:file.mp4"), Path::new(r"C:file.mp4"));
+   }
+
+   #[cfg(not(windows))]
+   #[test]
+   fn test_long_path_is_unchanged() {
+      assert!(matches!(
+         long_path(Path::new("/downloads/../file.mp4")),
+         Cow::Borrowed(p) if p == Path::new("/downloads/../file.mp4")
+      ));
    }
 
    #[test]
    fn test_path_without_filename() {
       // Root path has no filename component.
-      assert!(path("/").is_err());
+      assert_eq!(path_code(path("/")), Some(PathError::NoFilename));
+   }
+
+   #[test]
+   fn test_path_within_root() {
+      let dir = tempfile::TempDir::new().unwrap();
+      let root = dir.path().join("downloads");
+      std::fs::create_dir(&root).unwrap();
+      let within = |path: &Path| path_within(&root, &path.to_string_lossy());
+
+      assert!(within(&root.join("file.mp4")).is_ok());
+      // Missing directories are created on download.
+      assert!(within(&root.join("new/dir/file.mp4")).is_ok());
+      // `..` after an existing directory resolves; after a missing one it can't.
+      std::fs::create_dir(root.join("sub")).unwrap();
+      assert!(within(&root.join("sub/../file.mp4")).is_ok());
+      assert!(within(&root.join("new/../file.mp4")).is_err());
+
+      let err = within(&root.join("../outside.mp4"))
+         .unwrap_err()
+         .to_string();
+      assert!(err.contains("escapes the download root"));
+      assert_eq!(
+         path_code(within(&root.join("sub/../../outside.mp4"))),
+         Some(PathError::OutsideRoot)
+      );
+      assert!(within(Path::new("/etc/cron.d/x")).is_err());
+   }
+
+   #[cfg(unix)]
+   #[test]
+   fn test_path_within_rejects_symlink_escape() {
+      let dir = tempfile::TempDir::new().unwrap();
+      let root = dir.path().join("downloads");
+      let outside = dir.path().join("outside");
+      std::fs::create_dir(&root).unwrap();
+      std::fs::create_dir(&outside).unwrap();
+      std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+      let path = root.join("link/file.mp4");
+      assert!(path_within(&root, &path.to_string_lossy()).is_err());
+   }
+
+   #[test]
+   fn test_path_within_missing_root() {
+      let err = path_within(Path::new("/nonexistent/root"), "/nonexistent/root/file.mp4")
+         .unwrap_err()
+         .to_string();
+      assert!(err.contains("Invalid download root"));
+      assert_eq!(
+         path_code(path_within(
+            Path::new("/nonexistent/root"),
+            "/nonexistent/root/file.mp4"
+         )),
+         Some(PathError::InvalidRoot)
+      );
+   }
+
+   #[test]
+   fn test_valid_directory() {
+      assert!(directory("/downloads").is_ok());
+      assert!(directory("/").is_ok());
+   }
+
+   #[test]
+   fn test_invalid_directory() {
+      assert!(directory("").unwrap_err().to_string().contains("empty"));
+      assert_eq!(path_code(directory("")), Some(PathError::Empty));
+      assert_eq!(
+         path_code(directory("relative/dir")),
+         Some(PathError::NotAbsolute)
+      );
    }
 
    #[test]
@@ -103,25 +501,181 @@ mod tests {
       let result = url("");
       assert!(result.is_err());
       assert!(result.unwrap_err().to_string().contains("empty"));
+      assert_eq!(url_code(url("")), Some(UrlError::Empty));
    }
 
    #[test]
    fn test_invalid_scheme() {
-      assert!(url("ftp://example.com/file.mp4").is_err());
-      assert!(url("file:///path/to/file.mp4").is_err());
-      assert!(url("ws://example.com/socket").is_err());
-      assert!(url("data:text/plain,hello").is_err());
+      for invalid in [
+         "ftp://example.com/file.mp4",
+         "file:///path/to/file.mp4",
+         "ws://example.com/socket",
+         "data:text/plain,hello",
+      ] {
+         assert_eq!(url_code(url(invalid)), Some(UrlError::BadScheme));
+      }
    }
 
    #[test]
    fn test_missing_host() {
       assert!(url("https://:8080/file.mp4").is_err());
+      assert_eq!(
+         url_code(url("https://:8080/file.mp4")),
+         Some(UrlError::Malformed)
+      );
    }
 
    #[test]
    fn test_invalid_url_format() {
-      assert!(url("not a valid url").is_err());
+      assert_eq!(url_code(url("not a valid url")), Some(UrlError::Malformed));
       // Protocol-relative URL with no scheme.
-      assert!(url("//example.com/file.mp4").is_err());
+      assert_eq!(
+         url_code(url("//example.com/file.mp4")),
+         Some(UrlError::Malformed)
+      );
+   }
+
+   #[test]
+   fn test_normalize_url() {
+      assert_eq!(
+         normalize_url("https://example.com/file.mp4#t=30"),
+         "https://example.com/file.mp4"
+      );
+      assert_eq!(
+         normalize_url("HTTPS://CDN.Example.com/File.mp4?a=B"),
+         "https://cdn.example.com/File.mp4?a=B"
+      );
+      assert_eq!(
+         normalize_url("http://example.com:80/file.mp4"),
+         "http://example.com/file.mp4"
+      );
+      assert_eq!(
+         normalize_url("https://example.com:8443/file.mp4"),
+         "https://example.com:8443/file.mp4"
+      );
+   }
+
+   #[test]
+   fn test_url_against_host_policy() {
+      let policy = HostPolicy {
+         allow: vec!["*.example.com".to_string()],
+         deny: Vec::new(),
+      };
+      assert!(url_against(&policy, "https://cdn.example.com/file.mp4").is_ok());
+      assert!(url_against(&policy, "https://CDN.Example.com./file.mp4").is_ok());
+      let err = url_against(&policy, "https://example.org/file.mp4")
+         .unwrap_err()
+         .to_string();
+      assert!(err.contains("Host 'example.org' is not allowed"));
+      assert_eq!(
+         url_code(url_against(&policy, "https://example.org/file.mp4")),
+         Some(UrlError::HostNotAllowed)
+      );
+      // Punycode in the error, as in the request.
+      let err = url_against(&policy, "https://bücher.example/file.mp4")
+         .unwrap_err()
+         .to_string();
+      assert!(err.contains("xn--bcher-kva.example"));
+      assert!(url_against(&policy, "ftp://cdn.example.com/file.mp4").is_err());
+   }
+
+   #[test]
+   fn test_options_validates_mirrors() {
+      let mut opts = DownloadOptions {
+         mirrors: vec!["https://mirror.example.com/file.mp4".to_string()],
+         ..Default::default()
+      };
+      assert!(options(&opts).is_ok());
+
+      opts
+         .mirrors
+         .push("ftp://mirror.example.com/file.mp4".to_string());
+      let err = options(&opts).unwrap_err().to_string();
+      assert!(err.contains("Invalid mirror 'ftp://mirror.example.com/file.mp4'"));
+      // The code of the mirror's error is kept.
+      assert_eq!(url_code(options(&opts)), Some(UrlError::BadScheme));
+   }
+
+   #[test]
+   fn test_options_validates_headers() {
+      let mut opts = DownloadOptions::default();
+      opts
+         .headers
+         .insert("Authorization".to_string(), "Bearer abc".to_string());
+      assert!(options(&opts).is_ok());
+
+      opts
+         .headers
+         .insert("Bad Name".to_string(), "value".to_string());
+      let err = options(&opts).unwrap_err().to_string();
+      assert!(err.contains("Invalid header name 'Bad Name'"));
+
+      let mut opts = DownloadOptions::default();
+      opts
+         .headers
+         .insert("X-Token".to_string(), "line\nbreak".to_string());
+      assert!(options(&opts).is_err());
+   }
+
+   #[test]
+   fn test_options_validates_cookies() {
+      let mut opts = DownloadOptions::default();
+      opts
+         .cookies
+         .insert("session".to_string(), "abc123".to_string());
+      assert!(options(&opts).is_ok());
+
+      for name in ["", "bad name", "a=b", "a;b"] {
+         let mut opts = DownloadOptions::default();
+         opts.cookies.insert(name.to_string(), "value".to_string());
+         let err = options(&opts).unwrap_err().to_string();
+         assert!(err.contains("Invalid cookie name"), "{}", name);
+      }
+
+      let mut opts = DownloadOptions::default();
+      opts
+         .cookies
+         .insert("session".to_string(), "a; admin=1".to_string());
+      assert!(options(&opts).is_err());
+   }
+
+   #[test]
+   fn test_options_validates_if_none_match() {
+      let opts = DownloadOptions {
+         if_none_match: Some("\"v1\"".to_string()),
+         ..Default::default()
+      };
+      assert!(options(&opts).is_ok());
+
+      let opts = DownloadOptions {
+         if_none_match: Some("\"v1\"\r\n".to_string()),
+         ..Default::default()
+      };
+      let err = options(&opts).unwrap_err().to_string();
+      assert!(err.contains("Invalid If-None-Match value"));
+   }
+
+   #[test]
+   fn test_options_validates_range() {
+      let mut opts = DownloadOptions {
+         range_start: Some(10),
+         range_end: Some(10),
+         ..Default::default()
+      };
+      assert!(options(&opts).is_ok());
+      opts.range_end = Some(9);
+      let err = options(&opts).unwrap_err().to_string();
+      assert!(err.contains("ends at byte 9 before it starts at byte 10"));
+   }
+
+   #[test]
+   fn test_options_validates_mode() {
+      let mut opts = DownloadOptions {
+         mode: Some(0o755),
+         ..Default::default()
+      };
+      assert!(options(&opts).is_ok());
+      opts.mode = Some(0o100755);
+      assert!(options(&opts).is_err());
    }
 }
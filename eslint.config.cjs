@@ -8,6 +8,7 @@ module.exports = [
          'src-tauri/**',
          'examples/tauri-app/src-tauri/**',
          'android/**/build/**',
+         'crates/download-manager/bindings/**',
       ],
    },
    ...config,
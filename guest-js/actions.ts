@@ -1,8 +1,9 @@
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { addPluginListener, invoke } from '@tauri-apps/api/core';
 import {
-   AllDownloadActions, allowedActions, Download, DownloadAction, DownloadActionResponse, DownloadState,
-   DownloadStatus, DownloadWithAnyStatus, isTerminal, ListenOptions,
+   AllDownloadActions, allowedActions, Download, DownloadAction, DownloadActionResponse, DownloadOptions,
+   DownloadState, DownloadStatus, DownloadWithAnyStatus, isTerminal, ListenOptions,
+   RestartOptions,
 } from './types';
 
 /**
@@ -68,9 +69,19 @@ class DownloadEventManager {
             this._notifyListeners(event.path, event);
          });
       } else {
-         this._eventUnlistenFn = await listen<DownloadState<DownloadStatus>>('tauri-plugin-download:changed', (event) => {
+         const unlistenChanged = await listen<DownloadState<DownloadStatus>>('tauri-plugin-download:changed', (event) => {
             this._notifyListeners(event.payload.path, event.payload);
          });
+
+         // Emitted instead of the individual events when `changeBatchMs` is configured.
+         const unlistenBatch = await listen<DownloadState<DownloadStatus>[]>('tauri-plugin-download:changed-batch', (event) => {
+            event.payload.forEach((download) => { this._notifyListeners(download.path, download); });
+         });
+
+         this._eventUnlistenFn = () => {
+            unlistenChanged();
+            unlistenBatch();
+         };
       }
    }
 
@@ -153,8 +164,8 @@ const actions = {
       return unlisten;
    },
 
-   async create(url: string) {
-      return sendAction(DownloadAction.Create, { path: this.path, url });
+   async create(url: string, options?: DownloadOptions) {
+      return sendAction(DownloadAction.Create, { path: this.path, url, options });
    },
 
    async start() {
@@ -165,6 +176,14 @@ const actions = {
       return sendAction(DownloadAction.Resume, { path: this.path });
    },
 
+   async retry() {
+      return sendAction(DownloadAction.Retry, { path: this.path });
+   },
+
+   async restart(options?: RestartOptions) {
+      return sendAction(DownloadAction.Restart, { path: this.path, start: options?.start ?? false });
+   },
+
    async pause() {
       return sendAction(DownloadAction.Pause, { path: this.path });
    },
@@ -180,12 +199,8 @@ const actions = {
  * @param state The de-serialized download state from the plugin
  */
 export function attachDownload<S extends DownloadStatus>(state: DownloadState<S>): Download<S> {
-   const download = {
-      url: state.url,
-      path: state.path,
-      progress: state.progress,
-      status: state.status,
-   } satisfies DownloadState<S>;
+   // Copy every field, including optional ones such as `error` or `tags`.
+   const download = { ...state } satisfies DownloadState<S>;
 
    const actionsForDownload = allowedActions[state.status];
 
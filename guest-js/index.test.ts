@@ -3,12 +3,13 @@
  */
 import { describe, it, expect, beforeEach, afterEach, vi } from 'vitest';
 import { mockIPC, clearMocks } from '@tauri-apps/api/mocks';
-import { list, get } from './index';
+import { action, onReady, onStoreError, onRestarted, list, listByStatus, listByTag, countByStatus, aggregateProgress, stats, resetStats, get, getOrCreate, downloadedBytes, moveTo, setPriority, reveal, reset, clearCompleted, config, capabilities, downloadNow, probe, verify, schedule, validateCreate, createInDir, createBatch, recovered, pauseAll, resumeAll, cancelAll, pausePaths, resumePaths, cancelPaths } from './index';
 import {
    DownloadStatus,
    DownloadAction,
    hasAction,
    hasAnyAction,
+   isValidationError,
 } from './types';
 import { attachDownload, wrapListenerWithAutoUnlisten } from './actions';
 
@@ -48,6 +49,13 @@ const PAUSED_STATE = {
    status: DownloadStatus.Paused,
 };
 
+const COMPLETED_STATE = {
+   url: 'https://example.com/file.zip',
+   path: '/tmp/file.zip',
+   progress: 100,
+   status: DownloadStatus.Completed,
+};
+
 const ACTION_RESPONSE_BASE = {
    isExpectedStatus: true,
 };
@@ -62,6 +70,48 @@ beforeEach(() => {
       if (cmd === 'plugin:download|list') {
          return [ IDLE_STATE ];
       }
+      if (cmd === 'plugin:download|list_by_status') {
+         return [ { ...IDLE_STATE, status: (args as { status: DownloadStatus }).status } ];
+      }
+      if (cmd === 'plugin:download|list_by_tag') {
+         return [ { ...IDLE_STATE, tags: [ (args as { tag: string }).tag ] } ];
+      }
+      if (cmd === 'plugin:download|count_by_status') {
+         return { [DownloadStatus.Idle]: 1, [DownloadStatus.Completed]: 2 };
+      }
+      if (cmd === 'plugin:download|aggregate_progress') {
+         return { totalBytes: 2000, downloadedBytes: 500, activeCount: 3, combinedPercent: 25 };
+      }
+      if (cmd === 'plugin:download|stats') {
+         return { totalBytes: 4096, totalCompleted: 2, totalFailed: 1 };
+      }
+      if (cmd === 'plugin:download|reset_stats') {
+         return null;
+      }
+      if (cmd === 'plugin:download|probe') {
+         return { size: 5000, acceptsRanges: false, filename: 'file.zip' };
+      }
+      if (cmd === 'plugin:download|verify') {
+         return true;
+      }
+      if (cmd === 'plugin:download|schedule') {
+         return { ...ACTION_RESPONSE_BASE, expectedStatus: DownloadStatus.Idle, download: { ...IDLE_STATE, scheduledAt: (args as { at: number }).at } };
+      }
+      if (cmd === 'plugin:download|validate_create') {
+         return null;
+      }
+      if (cmd === 'plugin:download|downloaded_bytes') {
+         return 250;
+      }
+      if (cmd === 'plugin:download|recovered') {
+         return [ { ...IDLE_STATE, path: '/tmp/recovered.zip' } ];
+      }
+      if (cmd === 'plugin:download|set_priority') {
+         return { ...IDLE_STATE, status: DownloadStatus.Queued, priority: (args as { priority: number }).priority };
+      }
+      if (cmd === 'plugin:download|move_to') {
+         return { ...COMPLETED_STATE, path: (args as { newPath: string }).newPath };
+      }
       if (cmd === 'plugin:download|get') {
          const path = (args as { path: string }).path;
 
@@ -75,6 +125,28 @@ beforeEach(() => {
             status: DownloadStatus.Pending,
          };
       }
+      if (cmd === 'plugin:download|get_or_create') {
+         return { ...IDLE_STATE, path: (args as { path: string }).path, url: (args as { url: string }).url };
+      }
+      if (cmd === 'plugin:download|download_now') {
+         return { ...IDLE_STATE, status: DownloadStatus.Completed, progress: 100 };
+      }
+      if (cmd === 'plugin:download|create_in_dir') {
+         return {
+            ...ACTION_RESPONSE_BASE,
+            expectedStatus: DownloadStatus.Idle,
+            download: { ...IDLE_STATE, path: `${(args as { dir: string }).dir}/file.zip` },
+         };
+      }
+      if (cmd === 'plugin:download|create_batch') {
+         return [
+            {
+               path: '/tmp/file.zip',
+               response: { ...ACTION_RESPONSE_BASE, expectedStatus: DownloadStatus.Idle, download: IDLE_STATE },
+            },
+            { path: '', error: 'Invalid path' },
+         ];
+      }
       if (cmd === 'plugin:download|create') {
          return {
             ...ACTION_RESPONSE_BASE,
@@ -89,6 +161,13 @@ beforeEach(() => {
             download: IN_PROGRESS_STATE,
          };
       }
+      if (cmd === 'plugin:download|action') {
+         return {
+            ...ACTION_RESPONSE_BASE,
+            expectedStatus: DownloadStatus.Paused,
+            download: PAUSED_STATE,
+         };
+      }
       if (cmd === 'plugin:download|pause') {
          return {
             ...ACTION_RESPONSE_BASE,
@@ -103,6 +182,20 @@ beforeEach(() => {
             download: IN_PROGRESS_STATE,
          };
       }
+      if (cmd === 'plugin:download|retry') {
+         return {
+            ...ACTION_RESPONSE_BASE,
+            expectedStatus: DownloadStatus.InProgress,
+            download: IN_PROGRESS_STATE,
+         };
+      }
+      if (cmd === 'plugin:download|restart') {
+         return {
+            ...ACTION_RESPONSE_BASE,
+            expectedStatus: DownloadStatus.Idle,
+            download: IDLE_STATE,
+         };
+      }
       if (cmd === 'plugin:download|cancel') {
          return {
             ...ACTION_RESPONSE_BASE,
@@ -110,6 +203,58 @@ beforeEach(() => {
             download: { ...IDLE_STATE, status: DownloadStatus.Canceled },
          };
       }
+      if (cmd === 'plugin:download|pause_all') {
+         return [ { ...ACTION_RESPONSE_BASE, expectedStatus: DownloadStatus.Paused, download: PAUSED_STATE } ];
+      }
+      if (cmd === 'plugin:download|resume_all') {
+         return [ { ...ACTION_RESPONSE_BASE, expectedStatus: DownloadStatus.InProgress, download: IN_PROGRESS_STATE } ];
+      }
+      if (cmd === 'plugin:download|cancel_all') {
+         return [];
+      }
+      if (cmd === 'plugin:download|pause_paths') {
+         return [
+            { path: PAUSED_STATE.path, response: { ...ACTION_RESPONSE_BASE, expectedStatus: DownloadStatus.Paused, download: PAUSED_STATE } },
+            { path: '/tmp/missing.zip', error: 'Download not found' },
+         ];
+      }
+      if (cmd === 'plugin:download|resume_paths' || cmd === 'plugin:download|cancel_paths') {
+         return [];
+      }
+      if (cmd === 'plugin:download|config') {
+         return {
+            redirect: { crossHost: 'allow', maxRedirects: null },
+            maxSizeBytes: null,
+            duplicateUrls: 'allow',
+            segments: null,
+            maxDownloadsPerHost: null,
+            progress: { threshold: 1, intervalMs: null, heartbeatMs: null },
+            proxy: null,
+            userAgent: null,
+            headers: {},
+            downloadRoot: null,
+            hosts: { allow: [], deny: [] },
+            writeBufferBytes: null,
+            partialFiles: 'resume',
+            tempDir: null,
+            tempSuffix: null,
+            deadlineMs: null,
+            tls: { clientIdentity: null, rootCertificates: [], minVersion: null },
+            changeBatchMs: null,
+            pauseOnMetered: false,
+            verifyCompleted: false,
+            keepCompleted: false,
+         };
+      }
+      if (cmd === 'plugin:download|clear_completed') {
+         return 3;
+      }
+      if (cmd === 'plugin:download|reset') {
+         return { canceled: 1, removed: 2, tempFilesRemoved: 1 };
+      }
+      if (cmd === 'plugin:download|capabilities') {
+         return { native: false, supportsResume: true, supportsBackground: false, supportsRateLimit: false };
+      }
       if (cmd === 'plugin:download|is_native') {
          return false;
       }
@@ -123,6 +268,66 @@ afterEach(() => {
    return clearMocks();
 });
 
+describe('onReady', () => {
+   it('listens for the ready event and attaches actions to the downloads', async () => {
+      const unlisten = vi.fn(),
+            listener = vi.fn();
+
+      eventListenMock.mockResolvedValue(unlisten);
+
+      expect(await onReady(listener)).toBe(unlisten);
+      expect(eventListenMock).toHaveBeenCalledWith('tauri-plugin-download:ready', expect.any(Function));
+
+      const handler = eventListenMock.mock.calls[0][1];
+
+      handler({ payload: { downloads: [ IDLE_STATE ], recovered: [], demotedInProgress: true } });
+
+      const summary = listener.mock.calls[0][0];
+
+      expect(summary.demotedInProgress).toBe(true);
+      expect(hasAction(summary.downloads[0], DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('onStoreError', () => {
+   it('listens for the store-error event and passes the message', async () => {
+      const unlisten = vi.fn(),
+            listener = vi.fn();
+
+      eventListenMock.mockResolvedValue(unlisten);
+
+      expect(await onStoreError(listener)).toBe(unlisten);
+      expect(eventListenMock).toHaveBeenCalledWith('tauri-plugin-download:store-error', expect.any(Function));
+
+      const handler = eventListenMock.mock.calls[0][1];
+
+      handler({ payload: 'Failed to write store' });
+
+      expect(listener).toHaveBeenCalledWith('Failed to write store');
+   });
+});
+
+describe('onRestarted', () => {
+   it('listens for the restarted event and attaches actions to the download', async () => {
+      const unlisten = vi.fn(),
+            listener = vi.fn();
+
+      eventListenMock.mockResolvedValue(unlisten);
+
+      expect(await onRestarted(listener)).toBe(unlisten);
+      expect(eventListenMock).toHaveBeenCalledWith('tauri-plugin-download:restarted', expect.any(Function));
+
+      const handler = eventListenMock.mock.calls[0][1];
+
+      handler({ payload: { ...PAUSED_STATE, status: DownloadStatus.InProgress, progress: 0 } });
+
+      const download = listener.mock.calls[0][0];
+
+      expect(download.progress).toBe(0);
+      expect(typeof download.pause).toBe('function');
+   });
+});
+
 describe('list', () => {
    it('invokes the correct command and returns downloads with actions attached', async () => {
       const downloads = await list();
@@ -134,6 +339,63 @@ describe('list', () => {
    });
 });
 
+describe('listByStatus', () => {
+   it('sends the status and returns downloads with actions attached', async () => {
+      const downloads = await listByStatus(DownloadStatus.Paused);
+
+      expect(lastCmd).toBe('plugin:download|list_by_status');
+      expect(lastArgs).toEqual({ status: DownloadStatus.Paused });
+      expect(downloads).toHaveLength(1);
+      expect(hasAction(downloads[0], DownloadAction.Resume)).toBe(true);
+   });
+});
+
+describe('listByTag', () => {
+   it('passes the tag and attaches actions', async () => {
+      const downloads = await listByTag('podcasts');
+
+      expect(lastCmd).toBe('plugin:download|list_by_tag');
+      expect(lastArgs).toEqual({ tag: 'podcasts' });
+      expect(downloads[0].tags).toEqual([ 'podcasts' ]);
+      expect(hasAction(downloads[0], DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('countByStatus', () => {
+   it('returns the count per status', async () => {
+      const counts = await countByStatus();
+
+      expect(lastCmd).toBe('plugin:download|count_by_status');
+      expect(counts[DownloadStatus.Completed]).toBe(2);
+      expect(counts[DownloadStatus.Paused]).toBeUndefined();
+   });
+});
+
+describe('aggregateProgress', () => {
+   it('returns the combined progress of the active downloads', async () => {
+      const aggregate = await aggregateProgress();
+
+      expect(lastCmd).toBe('plugin:download|aggregate_progress');
+      expect(aggregate.activeCount).toBe(3);
+      expect(aggregate.combinedPercent).toBe(25);
+   });
+});
+
+describe('stats', () => {
+   it('returns the lifetime statistics of the downloads', async () => {
+      const result = await stats();
+
+      expect(lastCmd).toBe('plugin:download|stats');
+      expect(result).toEqual({ totalBytes: 4096, totalCompleted: 2, totalFailed: 1 });
+   });
+
+   it('resets the statistics', async () => {
+      await resetStats();
+
+      expect(lastCmd).toBe('plugin:download|reset_stats');
+   });
+});
+
 describe('get', () => {
    it('invokes the correct command and returns an Idle download with actions attached', async () => {
       const download = await get('/tmp/file.zip');
@@ -155,6 +417,249 @@ describe('get', () => {
    });
 });
 
+describe('getOrCreate', () => {
+   it('sends the path and URL and returns the download with actions attached', async () => {
+      const download = await getOrCreate('/tmp/new.zip', 'https://example.com/new.zip');
+
+      expect(lastCmd).toBe('plugin:download|get_or_create');
+      expect(lastArgs).toEqual({ path: '/tmp/new.zip', url: 'https://example.com/new.zip' });
+      expect(download.status).toBe(DownloadStatus.Idle);
+      expect(hasAction(download, DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('verify', () => {
+   it('sends the path and digest and returns whether they match', async () => {
+      const matches = await verify('/tmp/file.zip', 'abc123');
+
+      expect(lastCmd).toBe('plugin:download|verify');
+      expect(lastArgs).toEqual({ path: '/tmp/file.zip', expectedSha256: 'abc123' });
+      expect(matches).toBe(true);
+   });
+});
+
+describe('schedule', () => {
+   it('sends the time in milliseconds and attaches actions', async () => {
+      const at = new Date(Date.UTC(2030, 0, 1, 2));
+
+      const response = await schedule('/tmp/file.zip', at);
+
+      expect(lastCmd).toBe('plugin:download|schedule');
+      expect(lastArgs).toEqual({ path: '/tmp/file.zip', at: at.getTime() });
+      expect(response.download.scheduledAt).toBe(at.getTime());
+      expect(hasAction(response.download, DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('validateCreate', () => {
+   it('sends the path, URL and options', async () => {
+      await validateCreate('/tmp/file.zip', 'https://example.com/file.zip', { overwrite: 'rename' });
+
+      expect(lastCmd).toBe('plugin:download|validate_create');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(lastArgs.url).toBe('https://example.com/file.zip');
+      expect(lastArgs.options).toEqual({ overwrite: 'rename' });
+   });
+});
+
+describe('probe', () => {
+   it('sends the URL and returns what the download would involve', async () => {
+      const result = await probe('https://example.com/file.zip');
+
+      expect(lastCmd).toBe('plugin:download|probe');
+      expect(lastArgs.url).toBe('https://example.com/file.zip');
+      expect(result.size).toBe(5000);
+      expect(result.acceptsRanges).toBe(false);
+   });
+});
+
+describe('downloadedBytes', () => {
+   it('sends the path and returns the size on disk', async () => {
+      const bytes = await downloadedBytes('/tmp/file.zip');
+
+      expect(lastCmd).toBe('plugin:download|downloaded_bytes');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(bytes).toBe(250);
+   });
+});
+
+describe('moveTo', () => {
+   it('sends path and newPath, returns the Completed download at its new path', async () => {
+      const download = await moveTo('/tmp/file.zip', '/downloads/file.zip');
+
+      expect(lastCmd).toBe('plugin:download|move_to');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(lastArgs.newPath).toBe('/downloads/file.zip');
+      expect(download.path).toBe('/downloads/file.zip');
+      expect(download.status).toBe(DownloadStatus.Completed);
+      expect(hasAnyAction(download)).toBe(false);
+   });
+});
+
+describe('action', () => {
+   it('sends path and action, returns the response with actions attached', async () => {
+      const response = await action('/tmp/file.zip', DownloadAction.Pause);
+
+      expect(lastCmd).toBe('plugin:download|action');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(lastArgs.action).toBe('pause');
+      expect(response.expectedStatus).toBe(DownloadStatus.Paused);
+      expect(hasAction(response.download, DownloadAction.Resume)).toBe(true);
+   });
+});
+
+describe('setPriority', () => {
+   it('sends path and priority, returns the download with its priority', async () => {
+      const download = await setPriority('/tmp/file.zip', 10);
+
+      expect(lastCmd).toBe('plugin:download|set_priority');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(lastArgs.priority).toBe(10);
+      expect(download.priority).toBe(10);
+      expect(download.status).toBe(DownloadStatus.Queued);
+   });
+});
+
+describe('reveal', () => {
+   it('sends the path', async () => {
+      await reveal('/downloads/file.zip');
+
+      expect(lastCmd).toBe('plugin:download|reveal');
+      expect(lastArgs.path).toBe('/downloads/file.zip');
+   });
+});
+
+describe('recovered', () => {
+   it('invokes the correct command and returns downloads with actions attached', async () => {
+      const downloads = await recovered();
+
+      expect(lastCmd).toBe('plugin:download|recovered');
+      expect(downloads).toHaveLength(1);
+      expect(downloads[0].path).toBe('/tmp/recovered.zip');
+      expect(hasAction(downloads[0], DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('downloadNow', () => {
+   it('sends path, url and options, returns the completed item', async () => {
+      const item = await downloadNow('/tmp/file.zip', 'https://example.com/file.zip', { mirrors: [] });
+
+      expect(lastCmd).toBe('plugin:download|download_now');
+      expect(lastArgs).toEqual({ path: '/tmp/file.zip', url: 'https://example.com/file.zip', options: { mirrors: [] } });
+      expect(item.status).toBe(DownloadStatus.Completed);
+   });
+});
+
+describe('createInDir', () => {
+   it('sends dir and url, returns Idle download with actions attached', async () => {
+      const { download } = await createInDir('/tmp', 'https://example.com/file.zip');
+
+      expect(lastCmd).toBe('plugin:download|create_in_dir');
+      expect(lastArgs).toEqual({ dir: '/tmp', url: 'https://example.com/file.zip' });
+      expect(download.path).toBe('/tmp/file.zip');
+      expect(hasAction(download, DownloadAction.Start)).toBe(true);
+   });
+});
+
+describe('createBatch', () => {
+   it('sends items and attaches actions to created downloads', async () => {
+      const items = [
+         { path: '/tmp/file.zip', url: 'https://example.com/file.zip' },
+         { path: '', url: 'https://example.com/file.zip' },
+      ];
+
+      const results = await createBatch(items);
+
+      expect(lastCmd).toBe('plugin:download|create_batch');
+      expect(lastArgs).toEqual({ items });
+      expect(results).toHaveLength(2);
+      expect(results[0].response?.download.path).toBe('/tmp/file.zip');
+      expect(results[0].response && hasAction(results[0].response.download, DownloadAction.Start)).toBe(true);
+      expect(results[1].response).toBeUndefined();
+      expect(results[1].error).toBe('Invalid path');
+   });
+});
+
+describe('bulk actions', () => {
+   it('pauseAll — returns paused downloads with actions attached', async () => {
+      const responses = await pauseAll();
+
+      expect(lastCmd).toBe('plugin:download|pause_all');
+      expect(responses).toHaveLength(1);
+      expect(hasAction(responses[0].download, DownloadAction.Resume)).toBe(true);
+   });
+
+   it('resumeAll — returns resumed downloads with actions attached', async () => {
+      const responses = await resumeAll();
+
+      expect(lastCmd).toBe('plugin:download|resume_all');
+      expect(hasAction(responses[0].download, DownloadAction.Pause)).toBe(true);
+   });
+
+   it('cancelAll — invokes the correct command', async () => {
+      const responses = await cancelAll();
+
+      expect(lastCmd).toBe('plugin:download|cancel_all');
+      expect(responses).toEqual([]);
+   });
+
+   it('pausePaths — sends the paths and reports each outcome', async () => {
+      const results = await pausePaths([ PAUSED_STATE.path, '/tmp/missing.zip' ]);
+
+      expect(lastCmd).toBe('plugin:download|pause_paths');
+      expect(lastArgs).toEqual({ paths: [ PAUSED_STATE.path, '/tmp/missing.zip' ] });
+      expect(results[0].response && hasAction(results[0].response.download, DownloadAction.Resume)).toBe(true);
+      expect(results[1].response).toBeUndefined();
+      expect(results[1].error).toBe('Download not found');
+   });
+
+   it('resumePaths and cancelPaths — invoke the correct commands', async () => {
+      await resumePaths([ '/tmp/a.zip' ]);
+      expect(lastCmd).toBe('plugin:download|resume_paths');
+
+      await cancelPaths([ '/tmp/a.zip' ]);
+      expect(lastCmd).toBe('plugin:download|cancel_paths');
+   });
+});
+
+describe('config', () => {
+   it('invokes the correct command and returns the configuration', async () => {
+      const result = await config();
+
+      expect(lastCmd).toBe('plugin:download|config');
+      expect(result.redirect.crossHost).toBe('allow');
+      expect(result.duplicateUrls).toBe('allow');
+   });
+});
+
+describe('capabilities', () => {
+   it('invokes the correct command and returns the capabilities', async () => {
+      const result = await capabilities();
+
+      expect(lastCmd).toBe('plugin:download|capabilities');
+      expect(result.native).toBe(false);
+      expect(result.supportsResume).toBe(true);
+   });
+});
+
+describe('clearCompleted', () => {
+   it('invokes the correct command and returns the removed count', async () => {
+      const removed = await clearCompleted();
+
+      expect(lastCmd).toBe('plugin:download|clear_completed');
+      expect(removed).toBe(3);
+   });
+});
+
+describe('reset', () => {
+   it('invokes the correct command and returns the summary', async () => {
+      const summary = await reset();
+
+      expect(lastCmd).toBe('plugin:download|reset');
+      expect(summary).toEqual({ canceled: 1, removed: 2, tempFilesRemoved: 1 });
+   });
+});
+
 describe('download actions', () => {
    it('create — sends path and url, returns Idle download', async () => {
       const pending = await get('/tmp/unknown.zip');
@@ -171,6 +676,17 @@ describe('download actions', () => {
       expect(response.download.status).toBe(DownloadStatus.Idle);
    });
 
+   it('create — sends options when provided', async () => {
+      const pending = await get('/tmp/unknown.zip');
+
+      if (!hasAction(pending, DownloadAction.Create)) {
+         throw new Error('expected create action');
+      }
+      await pending.create('https://example.com/file.zip', { mirrors: [ 'https://mirror.example.com/file.zip' ] });
+
+      expect(lastArgs.options).toEqual({ mirrors: [ 'https://mirror.example.com/file.zip' ] });
+   });
+
    it('start — sends path, returns InProgress download', async () => {
       const download = await get('/tmp/file.zip');
 
@@ -185,6 +701,20 @@ describe('download actions', () => {
       expect(response.download.status).toBe(DownloadStatus.InProgress);
    });
 
+   it('keeps the optional fields of the download', () => {
+      const failed = attachDownload({
+         ...IDLE_STATE,
+         status: DownloadStatus.Failed,
+         error: 'HTTP 404: Not Found',
+         tags: [ 'podcasts' ],
+         downloadedBytes: 512,
+      });
+
+      expect(failed.error).toBe('HTTP 404: Not Found');
+      expect(failed.tags).toEqual([ 'podcasts' ]);
+      expect(failed.downloadedBytes).toBe(512);
+   });
+
    it('pause — sends path, returns Paused download', async () => {
       const inProgress = attachDownload(IN_PROGRESS_STATE);
 
@@ -209,6 +739,34 @@ describe('download actions', () => {
       expect(response.download.status).toBe(DownloadStatus.InProgress);
    });
 
+   it('retry — sends path, returns InProgress download', async () => {
+      const failed = attachDownload({ ...IDLE_STATE, status: DownloadStatus.Failed });
+
+      expect(hasAction(failed, DownloadAction.Retry)).toBe(true);
+      const response = await failed.retry();
+
+      expect(lastCmd).toBe('plugin:download|retry');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(response.isExpectedStatus).toBe(true);
+      expect(response.download.status).toBe(DownloadStatus.InProgress);
+   });
+
+   it('restart — sends path and start flag, returns Idle download', async () => {
+      const failed = attachDownload({ ...IDLE_STATE, status: DownloadStatus.Failed });
+
+      expect(hasAction(failed, DownloadAction.Restart)).toBe(true);
+      const response = await failed.restart();
+
+      expect(lastCmd).toBe('plugin:download|restart');
+      expect(lastArgs.path).toBe('/tmp/file.zip');
+      expect(lastArgs.start).toBe(false);
+      expect(response.isExpectedStatus).toBe(true);
+      expect(response.download.status).toBe(DownloadStatus.Idle);
+
+      await failed.restart({ start: true });
+      expect(lastArgs.start).toBe(true);
+   });
+
    it('cancel — sends path, returns Canceled download', async () => {
       const download = await get('/tmp/file.zip');
 
@@ -286,6 +844,26 @@ describe('state machine — action availability', () => {
       expect(hasAction(download, DownloadAction.Pause)).toBe(false);
    });
 
+   it('Queued: cancel and listen are available', () => {
+      const download = attachDownload({ ...IDLE_STATE, status: DownloadStatus.Queued });
+
+      expect(hasAction(download, DownloadAction.Cancel)).toBe(true);
+      expect(hasAction(download, DownloadAction.Listen)).toBe(true);
+      expect(hasAction(download, DownloadAction.Start)).toBe(false);
+      expect(hasAction(download, DownloadAction.Pause)).toBe(false);
+   });
+
+   it('Failed: start, retry, restart, cancel, and listen are available', () => {
+      const download = attachDownload({ ...IDLE_STATE, status: DownloadStatus.Failed });
+
+      expect(hasAction(download, DownloadAction.Start)).toBe(true);
+      expect(hasAction(download, DownloadAction.Retry)).toBe(true);
+      expect(hasAction(download, DownloadAction.Restart)).toBe(true);
+      expect(hasAction(download, DownloadAction.Cancel)).toBe(true);
+      expect(hasAction(download, DownloadAction.Listen)).toBe(true);
+      expect(hasAction(download, DownloadAction.Resume)).toBe(false);
+   });
+
    it('Completed: no actions available', () => {
       const download = attachDownload({
          ...IDLE_STATE,
@@ -411,3 +989,32 @@ describe('wrapListenerWithAutoUnlisten', () => {
       expect(unlisten).toHaveBeenCalledTimes(1);
    });
 });
+
+describe('listen', () => {
+   it('notifies the listeners of each download in a changed batch', async () => {
+      const listener = vi.fn();
+
+      eventListenMock.mockResolvedValue(vi.fn());
+
+      const unlisten = await attachDownload(IDLE_STATE).listen(listener);
+
+      const batchHandler = eventListenMock.mock.calls
+         .find(([ name ]) => { return name === 'tauri-plugin-download:changed-batch'; })?.[1];
+
+      batchHandler({ payload: [ IN_PROGRESS_STATE, { ...IDLE_STATE, path: '/tmp/other.zip' } ] });
+
+      expect(listener).toHaveBeenCalledTimes(1);
+      expect(listener.mock.calls[0][0].progress).toBe(42);
+      expect(hasAction(listener.mock.calls[0][0], DownloadAction.Pause)).toBe(true);
+
+      unlisten();
+   });
+});
+
+describe('isValidationError', () => {
+   it('tells validation errors from other errors', () => {
+      expect(isValidationError({ code: 'notAbsolute', message: 'Path Error: path must be absolute' })).toBe(true);
+      expect(isValidationError('HTTP Error: timeout')).toBe(false);
+      expect(isValidationError(null)).toBe(false);
+   });
+});
@@ -1,5 +1,25 @@
-import { invoke } from '@tauri-apps/api/core';
-import { DownloadState, DownloadStatus, DownloadWithAnyStatus } from './types';
+import { addPluginListener, invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import {
+   AggregateProgress,
+   BatchActionResult,
+   BatchCreateItem,
+   BatchCreateResult,
+   Capabilities,
+   DispatchedAction,
+   Download,
+   DownloadAction,
+   DownloadActionResponse,
+   DownloadManagerConfig,
+   DownloadOptions,
+   DownloadState,
+   DownloadStats,
+   DownloadStatus,
+   DownloadWithAnyStatus,
+   ProbeResult,
+   ReadySummary,
+   ResetSummary,
+} from './types';
 import { attachDownload } from './actions';
 export { attachDownload };
 
@@ -23,6 +43,123 @@ export async function list(): Promise<DownloadWithAnyStatus[]> {
       .map((item) => { return attachDownload(item); });
 }
 
+/**
+ * Lists the persisted download operations with the given status.
+ *
+ * @param status - The status to match.
+ * @returns The downloads in that status.
+ *
+ * @example
+ * ```ts
+ * const active = await listByStatus(DownloadStatus.InProgress);
+ * ```
+ */
+export async function listByStatus<S extends DownloadStatus>(status: S): Promise<Download<S>[]> {
+   return (await invoke<DownloadState<S>[]>('plugin:download|list_by_status', { status }))
+      .map((item) => { return attachDownload(item) as Download<S>; });
+}
+
+/**
+ * Lists the persisted download operations created with the given tag. Only supported on
+ * desktop.
+ *
+ * @param tag - The tag to match.
+ * @returns The downloads with that tag.
+ *
+ * @example
+ * ```ts
+ * const podcasts = await listByTag('podcasts');
+ * ```
+ */
+export async function listByTag(tag: string): Promise<DownloadWithAnyStatus[]> {
+   return (await invoke<DownloadState<DownloadStatus>[]>('plugin:download|list_by_tag', { tag }))
+      .map((item) => { return attachDownload(item); });
+}
+
+/**
+ * Counts the persisted download operations in each status. Statuses without any download
+ * are omitted.
+ *
+ * @returns The number of downloads per status.
+ *
+ * @example
+ * ```ts
+ * const counts = await countByStatus();
+ * const badge = counts[DownloadStatus.InProgress] ?? 0;
+ * ```
+ */
+export async function countByStatus(): Promise<Partial<Record<DownloadStatus, number>>> {
+   return invoke<Partial<Record<DownloadStatus, number>>>('plugin:download|count_by_status');
+}
+
+/**
+ * Combines the progress of the in progress and paused downloads, e.g. for a single
+ * progress bar. Downloads of unknown size are counted in `activeCount`, but don't
+ * contribute to the bytes or the percentage. Only supported on desktop.
+ *
+ * @returns The combined progress.
+ *
+ * @example
+ * ```ts
+ * const { activeCount, combinedPercent } = await aggregateProgress();
+ * console.debug(`Downloading ${activeCount} files: ${combinedPercent.toFixed(0)}%`);
+ * ```
+ */
+export async function aggregateProgress(): Promise<AggregateProgress> {
+   return invoke<AggregateProgress>('plugin:download|aggregate_progress');
+}
+
+/**
+ * Returns the lifetime statistics of the downloads, e.g. for data usage reporting.
+ * They survive restarts and clearing the downloads, until {@link resetStats}. Only
+ * supported on desktop.
+ *
+ * @returns The bytes received and the downloads completed and failed.
+ *
+ * @example
+ * ```ts
+ * const { totalBytes } = await stats();
+ * console.debug(`Downloaded ${(totalBytes / 1e6).toFixed(1)} MB`);
+ * ```
+ */
+export async function stats(): Promise<DownloadStats> {
+   return invoke<DownloadStats>('plugin:download|stats');
+}
+
+/**
+ * Resets the lifetime statistics of the downloads to zero. Only supported on desktop.
+ *
+ * @example
+ * ```ts
+ * await resetStats();
+ * ```
+ */
+export async function resetStats(): Promise<void> {
+   return invoke<void>('plugin:download|reset_stats');
+}
+
+/**
+ * Lists the downloads whose status was changed on startup when recovering from an
+ * interrupted session, e.g. `InProgress` downloads reverted to `Paused` or `Idle`.
+ *
+ * The change events for these are emitted as the app starts, usually before any
+ * listener is attached, so this allows replaying them. Desktop only; always empty on
+ * mobile.
+ *
+ * @returns The recovered downloads, as emitted at the time.
+ *
+ * @example
+ * ```ts
+ * for (const download of await recovered()) {
+ *    console.debug(`${download.path} was interrupted and is now ${download.status}`);
+ * }
+ * ```
+ */
+export async function recovered(): Promise<DownloadWithAnyStatus[]> {
+   return (await invoke<DownloadState<DownloadStatus>[]>('plugin:download|recovered'))
+      .map((item) => { return attachDownload(item); });
+}
+
 /**
  * Gets a download by path.
  *
@@ -51,4 +188,550 @@ export async function get(path: string): Promise<DownloadWithAnyStatus> {
    return attachDownload(download);
 }
 
+/**
+ * Gets a download, creating it if it doesn't exist.
+ *
+ * Unlike {@link get} followed by `create`, callers racing to create the same path all get
+ * the one download stored.
+ *
+ * @param path - The download path.
+ * @param url - The download URL for the resource, if the download is created.
+ * @returns The existing download, even if it has another URL, or the created one in
+ * {@link DownloadStatus.Idle} state.
+ *
+ * @example
+ * ```ts
+ * const download = await getOrCreate('example/file.zip', 'https://example.com/file.zip');
+ * if (download.status === DownloadStatus.Idle) {
+ *    await download.start();
+ * }
+ * ```
+ */
+export async function getOrCreate(path: string, url: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|get_or_create', { path, url });
+
+   return attachDownload(download);
+}
+
+/**
+ * Gets the number of bytes on disk for a download without reading them: the size of the
+ * final file once completed, and of the partial file otherwise. Useful to reconcile the
+ * UI with the disk, e.g. after a restart. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @returns The size of the file, or 0 if there is none.
+ *
+ * @example
+ * ```ts
+ * const bytes = await downloadedBytes('example/file.zip');
+ * ```
+ */
+export async function downloadedBytes(path: string): Promise<number> {
+   return invoke<number>('plugin:download|downloaded_bytes', { path });
+}
+
+/**
+ * Moves a completed download to another path, e.g. from a temporary directory to a
+ * folder chosen by the user. The file is moved on disk and a change event is emitted
+ * with the new path. Only completed downloads kept in the store (see `keepCompleted`)
+ * can be moved.
+ *
+ * @param path - The download path.
+ * @param newPath - The path to move the download to, which must not exist yet.
+ * @returns The download at its new path.
+ * @throws If the download isn't a completed download in the store or the new path
+ * already exists.
+ *
+ * @example
+ * ```ts
+ * const moved = await moveTo('/tmp/report.pdf', '/documents/report.pdf');
+ * ```
+ */
+export async function moveTo(path: string, newPath: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|move_to', { path, newPath });
+
+   return attachDownload(download);
+}
+
+/**
+ * Sets the priority of a download. Among downloads queued for a free slot on their host
+ * (see `maxDownloadsPerHost`), those of higher priority start first. A download in
+ * progress keeps running. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @param priority - The priority, higher starting sooner. Downloads default to 0.
+ * @returns The download with its new priority.
+ *
+ * @example
+ * ```ts
+ * await setPriority('/downloads/urgent.zip', 10);
+ * ```
+ */
+export async function setPriority(path: string, priority: number): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|set_priority', { path, priority });
+
+   return attachDownload(download);
+}
+
+/**
+ * Shows a completed download in the system file manager: the file is selected in
+ * Explorer on Windows and in Finder on macOS, while on Linux its containing folder is
+ * opened. Only completed downloads kept in the store (see `keepCompleted`) can be
+ * revealed. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @throws If the download isn't a completed download in the store or its file doesn't
+ * exist.
+ *
+ * @example
+ * ```ts
+ * await reveal('/downloads/report.pdf');
+ * ```
+ */
+export async function reveal(path: string): Promise<void> {
+   return invoke<void>('plugin:download|reveal', { path });
+}
+
+/**
+ * Downloads a resource in a single call: creates the download, starts it and resolves
+ * once it completes. An existing download at `path` is started, resumed or waited on as
+ * needed.
+ *
+ * @param path - The download path.
+ * @param url - The download URL for the resource.
+ * @param options - Options for the download.
+ * @returns The completed download, with its final path.
+ * @throws If the download fails or is canceled.
+ *
+ * @example
+ * ```ts
+ * const item = await downloadNow('/downloads/file.zip', 'https://example.com/file.zip');
+ * ```
+ */
+export async function downloadNow(path: string, url: string, options?: DownloadOptions): Promise<DownloadState<DownloadStatus.Completed>> {
+   return invoke<DownloadState<DownloadStatus.Completed>>('plugin:download|download_now', { path, url, options });
+}
+
+/**
+ * Learns the size of a resource, whether a download of it could be resumed, its type and
+ * filename, without downloading it, e.g. to show the size or warn that a download can't
+ * be resumed before starting it. Only supported on desktop.
+ *
+ * @param url - The URL of the resource.
+ * @returns What downloading the resource would involve.
+ *
+ * @example
+ * ```ts
+ * const { size, acceptsRanges } = await probe('https://example.com/file.zip');
+ * ```
+ */
+export async function probe(url: string): Promise<ProbeResult> {
+   return invoke<ProbeResult>('plugin:download|probe', { url });
+}
+
+/**
+ * Checks the file at a download path against its SHA-256 digest, without downloading
+ * anything, e.g. to skip downloading a file that shipped with the app. On a match, the
+ * download at the path, if any, completes without downloading and its partial data is
+ * discarded. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @param expectedSha256 - The expected digest, in hex.
+ * @returns Whether the file exists and matches the digest.
+ *
+ * @example
+ * ```ts
+ * if (!await verify('/downloads/model.bin', sha256)) {
+ *    await download.start();
+ * }
+ * ```
+ */
+export async function verify(path: string, expectedSha256: string): Promise<boolean> {
+   return invoke<boolean>('plugin:download|verify', { path, expectedSha256 });
+}
+
+/**
+ * Performs an action on a download by name, e.g. from a generic handler of toolbar
+ * buttons. The same as calling the method of the action on the download.
+ *
+ * @param path - The download path.
+ * @param action - The action to perform.
+ * @returns The download operation.
+ *
+ * @example
+ * ```ts
+ * const response = await action('/downloads/file.zip', DownloadAction.Pause);
+ *
+ * if (!response.isExpectedStatus) {
+ *    console.warn('Download was not paused:', response.download.status);
+ * }
+ * ```
+ */
+export async function action<A extends DispatchedAction>(path: string, action: A): Promise<DownloadActionResponse<A>> {
+   const response = await invoke<DownloadActionResponse<A>>('plugin:download|action', { path, action });
+
+   return { ...response, download: attachDownload(response.download) };
+}
+
+/**
+ * Schedules an idle download to start at a given time, e.g. at night when bandwidth is
+ * cheap. It starts right away if the time already passed, including when the app wasn't
+ * running at the time. Scheduling it again replaces the time, and starting it earlier
+ * drops the schedule. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @param at - When to start.
+ * @returns The download operation, with `scheduledAt` set.
+ *
+ * @example
+ * ```ts
+ * const tonight = new Date();
+ *
+ * tonight.setHours(26, 0, 0, 0); // 2am tomorrow
+ * await schedule('/downloads/file.zip', tonight);
+ * ```
+ */
+export async function schedule(path: string, at: Date | number): Promise<DownloadActionResponse> {
+   const response = await invoke<DownloadActionResponse>('plugin:download|schedule', {
+      path,
+      at: at instanceof Date ? at.getTime() : at,
+   });
+
+   return { ...response, download: attachDownload(response.download) };
+}
+
+/**
+ * Checks whether a download could be created, without creating it, e.g. to validate a
+ * form before it is submitted. Resolves when {@link Download.create} would succeed and
+ * rejects with the same error otherwise. Only supported on desktop.
+ *
+ * @param path - The download path.
+ * @param url - The download URL for the resource.
+ * @param options - The download options.
+ *
+ * @example
+ * ```ts
+ * try {
+ *    await validateCreate('/downloads/file.zip', url);
+ * } catch(e) {
+ *    if (isValidationError(e)) {
+ *       showFieldError(e.code);
+ *    }
+ * }
+ * ```
+ */
+export async function validateCreate(path: string, url: string, options?: DownloadOptions): Promise<void> {
+   await invoke<void>('plugin:download|validate_create', { path, url, options });
+}
+
+/**
+ * Creates a download in a directory, letting the server choose the filename.
+ *
+ * The download is persisted with a placeholder path derived from the URL. When started,
+ * the filename is resolved from the response's `Content-Disposition` header and the
+ * download's `path` is updated before any data is written. Listen for changes on the
+ * returned download to observe the final path.
+ *
+ * @param dir - The absolute destination directory.
+ * @param url - The download URL for the resource.
+ * @returns The download operation, in {@link DownloadStatus.Idle} state.
+ *
+ * @example
+ * ```ts
+ * const { download } = await createInDir('/downloads', 'https://example.com/export?id=1');
+ * await download.start();
+ * ```
+ */
+export async function createInDir(dir: string, url: string): Promise<DownloadActionResponse<DownloadAction.Create>> {
+   const response = await invoke<DownloadActionResponse<DownloadAction.Create>>('plugin:download|create_in_dir', { dir, url });
+
+   response.download = attachDownload(response.download);
+
+   return response;
+}
+
+/**
+ * Creates multiple downloads at once. All items are validated up front and persisted in
+ * a single store write; each item reports its own outcome, so one invalid or duplicate
+ * path doesn't fail the others.
+ *
+ * @param items - The downloads to create.
+ * @returns The outcome for each item, in order.
+ *
+ * @example
+ * ```ts
+ * const results = await createBatch([
+ *    { path: '/downloads/a.zip', url: 'https://example.com/a.zip' },
+ *    { path: '/downloads/b.zip', url: 'https://example.com/b.zip' },
+ * ]);
+ *
+ * for (const { path, response, error } of results) {
+ *    if (error) {
+ *       console.error(path, error);
+ *    } else {
+ *       await response?.download.start();
+ *    }
+ * }
+ * ```
+ */
+export async function createBatch(items: BatchCreateItem[]): Promise<BatchCreateResult[]> {
+   const results = await invoke<BatchCreateResult[]>('plugin:download|create_batch', { items });
+
+   return results.map((result) => {
+      if (!result.response) {
+         return result;
+      }
+      return { ...result, response: { ...result.response, download: attachDownload(result.response.download) } };
+   });
+}
+
+async function sendBulkAction<A extends DownloadAction>(command: string): Promise<DownloadActionResponse<A>[]> {
+   const responses = await invoke<DownloadActionResponse<A>[]>(`plugin:download|${command}`);
+
+   return responses.map((response) => {
+      return { ...response, download: attachDownload(response.download) };
+   });
+}
+
+/**
+ * Pauses all in progress downloads. Downloads in any other state are left untouched.
+ *
+ * @returns The paused downloads.
+ *
+ * @example
+ * ```ts
+ * // e.g. when the app moves to the background:
+ * await pauseAll();
+ * ```
+ */
+export async function pauseAll(): Promise<DownloadActionResponse<DownloadAction.Pause>[]> {
+   return sendBulkAction<DownloadAction.Pause>('pause_all');
+}
+
+/**
+ * Resumes all paused downloads.
+ *
+ * @returns The resumed downloads.
+ */
+export async function resumeAll(): Promise<DownloadActionResponse<DownloadAction.Resume>[]> {
+   return sendBulkAction<DownloadAction.Resume>('resume_all');
+}
+
+/**
+ * Cancels all created, in progress and paused downloads and removes their temp files.
+ * Unlike {@link reset}, a change event is emitted per canceled download.
+ *
+ * @returns The canceled downloads.
+ */
+export async function cancelAll(): Promise<DownloadActionResponse<DownloadAction.Cancel>[]> {
+   return sendBulkAction<DownloadAction.Cancel>('cancel_all');
+}
+
+async function sendPathsAction<A extends DownloadAction>(command: string, paths: string[]): Promise<BatchActionResult<A>[]> {
+   const results = await invoke<BatchActionResult<A>[]>(`plugin:download|${command}`, { paths });
+
+   return results.map((result) => {
+      if (!result.response) {
+         return result;
+      }
+      return { ...result, response: { ...result.response, download: attachDownload(result.response.download) } };
+   });
+}
+
+/**
+ * Pauses the given downloads in a single call, e.g. those selected in a list. Each path
+ * reports its own outcome, so one that can't be paused doesn't fail the others.
+ *
+ * @param paths - The download paths.
+ * @returns The outcome for each path, in order.
+ *
+ * @example
+ * ```ts
+ * for (const { path, error } of await pausePaths(selected)) {
+ *    if (error) {
+ *       console.error(path, error);
+ *    }
+ * }
+ * ```
+ */
+export async function pausePaths(paths: string[]): Promise<BatchActionResult<DownloadAction.Pause>[]> {
+   return sendPathsAction<DownloadAction.Pause>('pause_paths', paths);
+}
+
+/**
+ * Resumes the given downloads in a single call.
+ *
+ * @param paths - The download paths.
+ * @returns The outcome for each path, in order.
+ */
+export async function resumePaths(paths: string[]): Promise<BatchActionResult<DownloadAction.Resume>[]> {
+   return sendPathsAction<DownloadAction.Resume>('resume_paths', paths);
+}
+
+/**
+ * Cancels the given downloads in a single call and removes their temp files.
+ *
+ * @param paths - The download paths.
+ * @returns The outcome for each path, in order.
+ */
+export async function cancelPaths(paths: string[]): Promise<BatchActionResult<DownloadAction.Cancel>[]> {
+   return sendPathsAction<DownloadAction.Cancel>('cancel_paths', paths);
+}
+
+/**
+ * Gets the configuration the download manager runs with, e.g. for a settings screen or
+ * to diagnose its behavior. Secrets such as credentials are redacted. Only supported on
+ * desktop, as the configuration doesn't apply on mobile.
+ *
+ * @returns A snapshot of the configuration.
+ */
+export async function config(): Promise<DownloadManagerConfig> {
+   return invoke<DownloadManagerConfig>('plugin:download|config');
+}
+
+/**
+ * Gets the features available on the current platform, to detect them rather than
+ * branch on the platform.
+ *
+ * @returns The capabilities of the platform.
+ *
+ * @example
+ * ```ts
+ * const { supportsBackground } = await capabilities();
+ * ```
+ */
+export async function capabilities(): Promise<Capabilities> {
+   return invoke<Capabilities>('plugin:download|capabilities');
+}
+
+/**
+ * Removes all completed downloads from the store, where they are kept with the
+ * `keepCompleted` option. The downloaded files are left on disk, and no change events are
+ * emitted.
+ *
+ * @returns The number of downloads removed.
+ *
+ * @example
+ * ```ts
+ * const removed = await clearCompleted();
+ * ```
+ */
+export async function clearCompleted(): Promise<number> {
+   return invoke<number>('plugin:download|clear_completed');
+}
+
+/**
+ * Cancels all downloads, removes their temp files and clears the store.
+ *
+ * Emits a single reset event (see {@link onReset}) rather than a change event per
+ * download.
+ *
+ * @returns A summary of what was removed.
+ *
+ * @example
+ * ```ts
+ * const { removed } = await reset();
+ * console.debug(`Removed ${removed} downloads`);
+ * ```
+ */
+export async function reset(): Promise<ResetSummary> {
+   return invoke<ResetSummary>('plugin:download|reset');
+}
+
+/**
+ * Listens for a manager-wide event, e.g. `reset`, from the Rust or native plugin.
+ */
+async function listenForEvent<T>(name: string, listener: (payload: T) => void): Promise<UnlistenFn> {
+   const isNative = await invoke<boolean>('plugin:download|is_native');
+
+   if (isNative) {
+      const pluginListener = await addPluginListener('download', name, listener);
+
+      return () => { return pluginListener.unregister(); };
+   }
+
+   return listen<T>(`tauri-plugin-download:${name}`, (event) => { return listener(event.payload); });
+}
+
+/**
+ * Listens for {@link reset} operations, including those triggered from other windows.
+ *
+ * @param listener Callback invoked with the reset summary.
+ * @returns A promise with a function to remove the listener.
+ */
+export async function onReset(listener: (summary: ResetSummary) => void): Promise<UnlistenFn> {
+   return listenForEvent('reset', listener);
+}
+
+/**
+ * Listens for failures to save the state of the downloads that happen in the background,
+ * e.g. while a download runs, rather than failing a call. The state shown may then not
+ * survive a restart, so the app can warn the user, e.g. that the disk is full. Only
+ * emitted on desktop.
+ *
+ * @param listener Callback invoked with the error message.
+ * @returns A promise with a function to remove the listener.
+ *
+ * @example
+ * ```ts
+ * await onStoreError((message) => {
+ *    showWarning(`Download progress may not be saved: ${message}`);
+ * });
+ * ```
+ */
+export async function onStoreError(listener: (message: string) => void): Promise<UnlistenFn> {
+   return listenForEvent('store-error', listener);
+}
+
+/**
+ * Listens for downloads that started over from zero when resumed, as the server doesn't
+ * accept range requests or the resource changed, e.g. to tell the user that the bytes
+ * received before were lost. Only emitted on desktop.
+ *
+ * @param listener Callback invoked with the download, its progress reset to zero.
+ * @returns A promise with a function to remove the listener.
+ *
+ * @example
+ * ```ts
+ * await onRestarted((download) => {
+ *    showNotice(`${download.path} could not be resumed and started over`);
+ * });
+ * ```
+ */
+export async function onRestarted(listener: (download: DownloadWithAnyStatus) => void): Promise<UnlistenFn> {
+   return listenForEvent<DownloadState<DownloadStatus>>('restarted', (item) => {
+      listener(attachDownload(item));
+   });
+}
+
+/**
+ * Listens for the one-time event emitted once the downloads of the previous session
+ * were reconciled on startup, e.g. to render the initial state without polling
+ * {@link list}. Only emitted on desktop.
+ *
+ * @param listener Callback invoked with the settled downloads.
+ * @returns A promise with a function to remove the listener.
+ *
+ * @example
+ * ```ts
+ * await onReady(({ downloads, demotedInProgress }) => {
+ *    render(downloads);
+ * });
+ * ```
+ */
+export async function onReady(listener: (summary: ReadySummary) => void): Promise<UnlistenFn> {
+   type Payload = Omit<ReadySummary, 'downloads' | 'recovered'> & {
+      downloads: DownloadState<DownloadStatus>[];
+      recovered: DownloadState<DownloadStatus>[];
+   };
+
+   return listenForEvent<Payload>('ready', (summary) => {
+      listener({
+         ...summary,
+         downloads: summary.downloads.map((item) => { return attachDownload(item); }),
+         recovered: summary.recovered.map((item) => { return attachDownload(item); }),
+      });
+   });
+}
+
 export * from './types';
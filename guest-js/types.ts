@@ -32,6 +32,15 @@ export enum DownloadStatus {
    /** Download was in progress but has been paused. */
    Paused = 'paused',
 
+   /**
+    * Download was started and waits for its prerequisite to complete, or for a free slot
+    * on its host.
+    */
+   Queued = 'queued',
+
+   /** Download failed and can be started again. See `error` for the reason. */
+   Failed = 'failed',
+
    /** Download was canceled by the user. */
    Canceled = 'canceled',
 
@@ -44,6 +53,8 @@ export enum DownloadAction {
    Create = 'create',
    Start = 'start',
    Resume = 'resume',
+   Retry = 'retry',
+   Restart = 'restart',
    Pause = 'pause',
    Cancel = 'cancel',
 }
@@ -51,8 +62,180 @@ export enum DownloadAction {
 export interface DownloadState<S extends DownloadStatus> {
    url: string;
    path: string;
-   progress: number;
+
+   /**
+    * Percentage of the download received, from 0 to 100. `null` while bytes are received
+    * of a resource whose size is unknown, see `downloadedBytes`.
+    */
+   progress: number | null;
    status: S;
+
+   /** Fallback URLs the download was created with, if any. */
+   mirrors?: string[];
+
+   /** Mirror currently used in place of `url` after a failover, if any. */
+   activeMirror?: string;
+
+   /** Tags the download was created with, if any. Desktop only. */
+   tags?: string[];
+
+   /** Whether the download completed early because it reached `maxBytesTruncate`. */
+   truncated?: boolean;
+
+   /**
+    * Bytes received so far, updated with `progress`. Unlike `progress`, it is meaningful
+    * when `totalSize` is unknown. Desktop only.
+    */
+   downloadedBytes?: number;
+
+   /** Total size of the download in bytes, if known. Desktop only. */
+   totalSize?: number;
+
+   /**
+    * `Content-Type` of the resource, as reported by the server, e.g. to categorize
+    * finished files without sniffing them. Desktop only.
+    */
+   contentType?: string;
+
+   /**
+    * `ETag` of the resource, as reported by the server, e.g. to pass as `ifNoneMatch`
+    * when refreshing the file later. Desktop only.
+    */
+   etag?: string;
+
+   /**
+    * Whether the server accepts range requests for the resource, if known. A download it
+    * doesn't accept them for restarts from zero when resumed. Desktop only.
+    */
+   acceptsRanges?: boolean;
+
+   /**
+    * Priority among downloads queued for a free slot on their host: higher starts
+    * sooner. Defaults to 0. Desktop only.
+    */
+   priority?: number;
+
+   /**
+    * Milliseconds the download waits before its next attempt because the server asked
+    * to retry later (`Retry-After`), while it is in progress. Desktop only.
+    */
+   retryAfterMs?: number;
+
+   /**
+    * Time the download is scheduled to start at, in milliseconds since the Unix epoch,
+    * while it is idle. See `schedule`. Desktop only.
+    */
+   scheduledAt?: number;
+
+   /**
+    * Time the download has to complete by, in milliseconds since the Unix epoch, set
+    * when it first starts if `deadlineMs` is configured. Desktop only.
+    */
+   deadlineAt?: number;
+
+   /** Reason the download failed, if `status` is {@link DownloadStatus.Failed}. */
+   error?: string;
+
+   /** Time the download was last stored, in milliseconds since the Unix epoch. Desktop only. */
+   updatedAt?: number;
+}
+
+/**
+ * Options for creating a download.
+ */
+export interface DownloadOptions {
+
+   /**
+    * Fallback URLs tried in order, resuming from the current offset, when `url` can't be
+    * reached or returns a server error. Desktop only.
+    */
+   mirrors?: string[];
+
+   /**
+    * Download only the first N bytes of the resource, then stop and complete the download
+    * with `truncated` set, e.g. to preview a large file. Unlike a range request this
+    * doesn't need the server to support ranges. Desktop only.
+    */
+   maxBytesTruncate?: number;
+
+   /**
+    * First byte of the resource to download, for a download of only part of it. The file
+    * then holds the bytes from `rangeStart` to `rangeEnd`, and progress is relative to their
+    * number. Needs the server to support range requests. Desktop only.
+    */
+   rangeStart?: number;
+
+   /**
+    * Last byte of the resource to download, inclusive, like the end of an HTTP `Range`.
+    * Without it, the download runs to the end of the resource. Desktop only.
+    */
+   rangeEnd?: number;
+
+   /**
+    * Path of a download that must complete before this one starts. Starting this download
+    * while the prerequisite is incomplete queues it; it then starts when the prerequisite
+    * completes, or fails if the prerequisite fails or is canceled. Desktop only.
+    */
+   dependsOn?: string;
+
+   /**
+    * Expected `Content-Type` of the response, either exact (`application/zip`) or a prefix
+    * ending in `/` (`image/`). A response of another type fails the download before
+    * anything is written. Desktop only.
+    */
+   expectedContentType?: string;
+
+   /**
+    * `Content-Type`s the response may have, each like `expectedContentType` or with a
+    * wildcard subtype (`video/*`). A response matching none of them, nor
+    * `expectedContentType`, fails the download before anything is written. Desktop only.
+    */
+   allowedContentTypes?: string[];
+
+   /**
+    * Headers sent with every request of the download. They take precedence over the
+    * configured default headers of the same name. Desktop only.
+    */
+   headers?: Record<string, string>;
+
+   /**
+    * Cookies sent with the requests of the download, by name, e.g. a session copied from
+    * the webview after logging in. Cookies the host sets update them, so a resumed download
+    * carries the current session. They are kept in memory only, so they aren't included in
+    * the download's state and are lost when the app exits. Only the download's host gets
+    * them, and a `Cookie` header replaces them. Desktop only.
+    */
+   cookies?: Record<string, string>;
+
+   /**
+    * `ETag` of the file already at the download path, e.g. the `etag` of the completed
+    * download that wrote it. Sent as `If-None-Match` while the file exists, so that if the
+    * resource is unchanged (`304 Not Modified`) the download completes with the existing
+    * file instead of downloading it again. Defaults to the `ETag` kept from the last
+    * download to the path. Desktop only.
+    */
+   ifNoneMatch?: string;
+
+   /**
+    * Unix permissions the completed file is given, e.g. `0o755` for a helper binary.
+    * Otherwise it keeps those it was created with. Ignored on Windows. Desktop only.
+    */
+   mode?: number;
+
+   /**
+    * What to do when a file already exists at the download path, checked on creation and
+    * again before the completed download is moved into place: `'overwrite'` replaces it
+    * (the default), `'fail'` refuses to create the download or fails it on completion,
+    * and `'rename'` downloads to the first free path with ` (1)`, ` (2)`, … appended to
+    * the file name. Desktop only.
+    */
+   overwrite?: 'overwrite' | 'fail' | 'rename';
+
+   /**
+    * Labels to group and filter downloads by with {@link listByTag}, e.g. the feature of
+    * the app that created them. They don't affect the download. Desktop only.
+    */
+   tags?: string[];
 }
 
 export interface DownloadActionResponse<A extends DownloadAction = DownloadAction> {
@@ -61,6 +244,188 @@ export interface DownloadActionResponse<A extends DownloadAction = DownloadActio
    isExpectedStatus: boolean;
 }
 
+/**
+ * A download to create with {@link createBatch}.
+ */
+export interface BatchCreateItem {
+   path: string;
+   url: string;
+   options?: DownloadOptions;
+}
+
+/**
+ * Outcome of creating one download of a batch: `response` when the download was created
+ * (or already existed), `error` otherwise.
+ */
+export interface BatchCreateResult {
+   path: string;
+   response?: DownloadActionResponse<DownloadAction.Create>;
+   error?: string;
+}
+
+/**
+ * Outcome of an action on one download of a batch, e.g. of `pausePaths`: either the
+ * action's response, or the reason it failed.
+ */
+export interface BatchActionResult<A extends DownloadAction> {
+   path: string;
+   response?: DownloadActionResponse<A>;
+   error?: string;
+}
+
+/**
+ * Features available on the current platform, see {@link capabilities}.
+ */
+export interface Capabilities {
+
+   /** Whether downloads are handled by the native mobile plugin. */
+   native: boolean;
+
+   /** Whether a paused or interrupted download continues from the bytes received. */
+   supportsResume: boolean;
+
+   /** Whether downloads continue while the app is in the background. */
+   supportsBackground: boolean;
+
+   /** Whether the download speed can be limited. */
+   supportsRateLimit: boolean;
+}
+
+/**
+ * Combined progress of the in progress and paused downloads, see
+ * {@link aggregateProgress}.
+ */
+export interface AggregateProgress {
+
+   /** Total size in bytes of the active downloads of known size. */
+   totalBytes: number;
+
+   /** Bytes received so far of the active downloads of known size. */
+   downloadedBytes: number;
+
+   /** Number of in progress and paused downloads, including those of unknown size. */
+   activeCount: number;
+
+   /** Percentage of `totalBytes` received, from 0 to 100. */
+   combinedPercent: number;
+}
+
+/**
+ * Lifetime statistics of the downloads, see {@link stats}.
+ */
+export interface DownloadStats {
+
+   /** Bytes received by all downloads, including those that didn't complete. */
+   totalBytes: number;
+
+   /** Number of downloads completed. */
+   totalCompleted: number;
+
+   /** Number of downloads failed. */
+   totalFailed: number;
+}
+
+/**
+ * What downloading a resource would involve, see {@link probe}.
+ */
+export interface ProbeResult {
+
+   /** Size of the resource in bytes, if the server reports it. */
+   size?: number;
+
+   /** Whether the server supports range requests, so that a download can be resumed. */
+   acceptsRanges: boolean;
+
+   /** `Content-Type` of the resource, if any. */
+   contentType?: string;
+
+   /** Filename from the `Content-Disposition` header, or else from the URL. */
+   filename?: string;
+}
+
+/**
+ * State of the downloads once the plugin reconciled them on startup, see
+ * {@link onReady}.
+ */
+export interface ReadySummary {
+
+   /** All downloads, after reconciliation. */
+   downloads: DownloadWithAnyStatus[];
+
+   /** Downloads whose status was changed, as returned by {@link recovered}. */
+   recovered: DownloadWithAnyStatus[];
+
+   /**
+    * Whether any download was interrupted while in progress and reverted to `Paused` or
+    * `Idle`.
+    */
+   demotedInProgress: boolean;
+}
+
+/**
+ * Summary of a {@link reset} operation.
+ */
+export interface ResetSummary {
+
+   /** Number of idle, in progress or paused downloads that were canceled. */
+   canceled: number;
+
+   /** Total number of store entries removed. */
+   removed: number;
+
+   /** Number of temp files deleted from disk. */
+   tempFilesRemoved: number;
+}
+
+/**
+ * The download manager configuration, as returned by {@link config}. Secrets are
+ * redacted.
+ */
+export interface DownloadManagerConfig {
+   redirect: {
+      crossHost: 'allow' | 'deny';
+      maxRedirects: number | null;
+   };
+   maxSizeBytes: number | null;
+   duplicateUrls: 'allow' | 'warn' | 'block';
+   segments: number | null;
+   maxDownloadsPerHost: number | null;
+   progress: {
+      threshold: number;
+      intervalMs: number | null;
+      heartbeatMs: number | null;
+   };
+   proxy: {
+      url: string;
+      username: string | null;
+      password: string | null;
+      noProxy: string[];
+   } | null;
+   userAgent: string | null;
+   headers: Record<string, string>;
+   downloadRoot: string | null;
+   hosts: {
+      allow: string[];
+      deny: string[];
+   };
+   writeBufferBytes: number | null;
+   partialFiles: 'resume' | 'restart';
+   tempDir: string | null;
+   tempSuffix: string | null;
+   deadlineMs: number | null;
+   tls: {
+      clientIdentity: string | null;
+      rootCertificates: string[];
+      minVersion: '1.2' | '1.3' | null;
+      /** Only present with the `danger-accept-invalid-certs` feature. */
+      dangerAcceptInvalidCerts?: boolean;
+   };
+   changeBatchMs: number | null;
+   pauseOnMetered: boolean;
+   verifyCompleted: boolean;
+   keepCompleted: boolean;
+}
+
 export interface ListenOptions {
 
    /**
@@ -76,6 +441,15 @@ export interface ListenOptions {
    autoUnlisten?: boolean;
 }
 
+export interface RestartOptions {
+
+   /**
+    * Start the download once its partial data is discarded. Default: false, which
+    * leaves the download `Idle`.
+    */
+   start?: boolean;
+}
+
 export interface AllDownloadActions {
 
    /**
@@ -106,9 +480,11 @@ export interface AllDownloadActions {
     * ```
     */
    [DownloadAction.Listen]: (listener: (download: DownloadWithAnyStatus) => void, options?: ListenOptions) => Promise<UnlistenFn>;
-   [DownloadAction.Create]: (url: string) => Promise<DownloadActionResponse<DownloadAction.Create>>;
+   [DownloadAction.Create]: (url: string, options?: DownloadOptions) => Promise<DownloadActionResponse<DownloadAction.Create>>;
    [DownloadAction.Start]: () => Promise<DownloadActionResponse<DownloadAction.Start>>;
    [DownloadAction.Resume]: () => Promise<DownloadActionResponse<DownloadAction.Resume>>;
+   [DownloadAction.Retry]: () => Promise<DownloadActionResponse<DownloadAction.Retry>>;
+   [DownloadAction.Restart]: (options?: RestartOptions) => Promise<DownloadActionResponse<DownloadAction.Restart>>;
    [DownloadAction.Pause]: () => Promise<DownloadActionResponse<DownloadAction.Pause>>;
    [DownloadAction.Cancel]: () => Promise<DownloadActionResponse<DownloadAction.Cancel>>;
 }
@@ -122,6 +498,7 @@ export const allowedActions = {
    [DownloadStatus.Idle]: [
       DownloadAction.Listen,
       DownloadAction.Start,
+      DownloadAction.Restart,
       DownloadAction.Cancel,
    ],
    [DownloadStatus.InProgress]: [
@@ -132,6 +509,18 @@ export const allowedActions = {
    [DownloadStatus.Paused]: [
       DownloadAction.Listen,
       DownloadAction.Resume,
+      DownloadAction.Restart,
+      DownloadAction.Cancel,
+   ],
+   [DownloadStatus.Queued]: [
+      DownloadAction.Listen,
+      DownloadAction.Cancel,
+   ],
+   [DownloadStatus.Failed]: [
+      DownloadAction.Listen,
+      DownloadAction.Start,
+      DownloadAction.Retry,
+      DownloadAction.Restart,
       DownloadAction.Cancel,
    ],
    [DownloadStatus.Completed]: [],
@@ -143,8 +532,10 @@ export const allowedActions = {
 
 export const expectedStatusesForAction = {
    [DownloadAction.Create]: [ DownloadStatus.Idle ],
-   [DownloadAction.Start]: [ DownloadStatus.InProgress ],
-   [DownloadAction.Resume]: [ DownloadStatus.InProgress ],
+   [DownloadAction.Start]: [ DownloadStatus.InProgress, DownloadStatus.Queued ],
+   [DownloadAction.Resume]: [ DownloadStatus.InProgress, DownloadStatus.Queued ],
+   [DownloadAction.Retry]: [ DownloadStatus.InProgress, DownloadStatus.Queued ],
+   [DownloadAction.Restart]: [ DownloadStatus.Idle, DownloadStatus.InProgress, DownloadStatus.Queued ],
    [DownloadAction.Pause]: [ DownloadStatus.Paused ],
    [DownloadAction.Cancel]: [ DownloadStatus.Canceled ],
 
@@ -154,6 +545,8 @@ export const expectedStatusesForAction = {
       DownloadStatus.Idle,
       DownloadStatus.InProgress,
       DownloadStatus.Paused,
+      DownloadStatus.Queued,
+      DownloadStatus.Failed,
       DownloadStatus.Canceled,
       DownloadStatus.Completed,
    ],
@@ -184,6 +577,9 @@ export type Download<S extends DownloadStatus> = DownloadState<S> & AllowedActio
  */
 export type DownloadWithAnyStatus = { [T in DownloadStatus]: Download<T> }[DownloadStatus];
 
+/** Actions {@link action} performs on a download by name. */
+export type DispatchedAction = DownloadAction.Start | DownloadAction.Pause | DownloadAction.Resume | DownloadAction.Cancel | DownloadAction.Retry;
+
 export type ExpectedStatusesForAction<A extends DownloadAction> = (typeof expectedStatusesForAction)[A][number];
 export type UnexpectedStatusesForAction<A extends DownloadAction> = Exclude<DownloadStatus, ExpectedStatusesForAction<A>>;
 
@@ -207,3 +603,30 @@ export function isTerminal(download: DownloadWithAnyStatus): download is Downloa
 export function hasAnyAction(download: DownloadWithAnyStatus): download is Exclude<DownloadWithAnyStatus, Download<DownloadStatus.Completed> | Download<DownloadStatus.Canceled>> {
    return !isTerminal(download);
 }
+
+/**
+ * Why a download path or directory was rejected, see {@link ValidationError}.
+ */
+export type PathErrorCode = 'empty' | 'notAbsolute' | 'noFilename' | 'outsideRoot' | 'invalidRoot' | 'duplicate' | 'selfDependency';
+
+/**
+ * Why a download URL was rejected, see {@link ValidationError}.
+ */
+export type UrlErrorCode = 'empty' | 'malformed' | 'badScheme' | 'noHost' | 'hostNotAllowed';
+
+/**
+ * Error an action rejects with on desktop when its path or URL is invalid. The `code`
+ * identifies the reason, e.g. to show a localized message; the `message` is meant for
+ * logs. Other errors are rejected with their message only.
+ */
+export interface ValidationError {
+   code: PathErrorCode | UrlErrorCode;
+   message: string;
+}
+
+/**
+ * @returns `true` if the error is a {@link ValidationError}.
+ */
+export function isValidationError(error: unknown): error is ValidationError {
+   return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
@@ -226,6 +226,35 @@ public final class DownloadManager: NSObject {
       return DownloadActionResponse(download: item)
    }
 
+   /**
+    Cancels all downloads and clears the store.
+
+    Removes every store entry in a single write and deletes any saved resume data.
+    No change events are emitted for the removed items.
+
+    - Returns: A summary of what was removed.
+    */
+   public func reset() async -> ResetSummary {
+      for task in await session.allTasks {
+         task.cancel()
+      }
+
+      let items = await store.clear()
+      var canceled = 0
+      var tempFilesRemoved = 0
+      for item in items {
+         if item.status == .idle || item.status == .inProgress || item.status == .paused {
+            canceled += 1
+         }
+         if loadResumeData(for: item) != nil {
+            deleteResumeData(for: item)
+            tempFilesRemoved += 1
+         }
+      }
+
+      return ResetSummary(canceled: canceled, removed: items.count, tempFilesRemoved: tempFilesRemoved)
+   }
+
    /**
     Handler for download progress updates. Called by DownloadSessionDelegate.
 
@@ -46,6 +46,13 @@ actor DownloadStore {
       save()
    }
    
+   func clear() -> [DownloadItem] {
+      let removed = downloads
+      downloads.removeAll()
+      save()
+      return removed
+   }
+   
    private static func load() -> [DownloadItem] {
       do {
          let data = try Data(contentsOf: savePath)
@@ -0,0 +1,16 @@
+//
+//  ResetSummary.swift
+//  DownloadManagerKit
+//
+
+import Foundation
+
+/// Summary of a `DownloadManager.reset()` operation.
+public struct ResetSummary: Codable {
+   /// Number of idle, in progress or paused downloads that were canceled.
+   public let canceled: Int
+   /// Total number of store entries removed.
+   public let removed: Int
+   /// Number of temp (resume data) files deleted from disk.
+   public let tempFilesRemoved: Int
+}
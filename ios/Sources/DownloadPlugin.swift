@@ -105,6 +105,14 @@ class DownloadPlugin: Plugin {
          }
       }
    }
+
+   @objc public func reset(_ invoke: Invoke) {
+      Task {
+         let summary = await self.downloadManager.reset()
+         try? self.trigger("reset", data: summary)
+         invoke.resolve(summary)
+      }
+   }
 }
 
 @_cdecl("init_plugin_download")
@@ -1,26 +1,150 @@
+use std::collections::HashMap;
+
 use tauri::{AppHandle, Runtime, command};
 
-use crate::DownloadExt;
 use crate::Result;
 use crate::models::*;
+use crate::{DownloadExt, DownloadManagerConfig};
 
 #[command]
 pub(crate) async fn list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<DownloadItem>> {
    app.download().list()
 }
 
+#[command]
+pub(crate) async fn aggregate_progress<R: Runtime>(app: AppHandle<R>) -> Result<AggregateProgress> {
+   app.download().aggregate_progress()
+}
+
+#[command]
+pub(crate) async fn stats<R: Runtime>(app: AppHandle<R>) -> Result<DownloadStats> {
+   app.download().stats()
+}
+
+#[command]
+pub(crate) async fn reset_stats<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+   app.download().reset_stats()
+}
+
+#[command]
+pub(crate) async fn recovered<R: Runtime>(app: AppHandle<R>) -> Result<Vec<DownloadItem>> {
+   Ok(app.download().recovered())
+}
+
+#[command]
+pub(crate) async fn list_by_status<R: Runtime>(
+   app: AppHandle<R>,
+   status: DownloadStatus,
+) -> Result<Vec<DownloadItem>> {
+   app.download().list_by_status(status)
+}
+
+#[command]
+pub(crate) async fn list_by_tag<R: Runtime>(
+   app: AppHandle<R>,
+   tag: String,
+) -> Result<Vec<DownloadItem>> {
+   app.download().list_by_tag(&tag)
+}
+
+#[command]
+pub(crate) async fn count_by_status<R: Runtime>(
+   app: AppHandle<R>,
+) -> Result<HashMap<DownloadStatus, usize>> {
+   app.download().count_by_status()
+}
+
 #[command]
 pub(crate) async fn get<R: Runtime>(app: AppHandle<R>, path: String) -> Result<DownloadItem> {
    app.download().get(&path)
 }
 
+#[command]
+pub(crate) async fn get_or_create<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   url: String,
+) -> Result<DownloadItem> {
+   app.download().get_or_create(&path, &url)
+}
+
+#[command]
+pub(crate) async fn downloaded_bytes<R: Runtime>(app: AppHandle<R>, path: String) -> Result<u64> {
+   app.download().downloaded_bytes(&path)
+}
+
 #[command]
 pub(crate) async fn create<R: Runtime>(
    app: AppHandle<R>,
    path: String,
    url: String,
+   options: Option<DownloadOptions>,
 ) -> Result<DownloadActionResponse> {
-   app.download().create(&path, &url)
+   app.download()
+      .create_with_options(&path, &url, options.unwrap_or_default())
+}
+
+#[command]
+pub(crate) async fn create_batch<R: Runtime>(
+   app: AppHandle<R>,
+   items: Vec<BatchCreateItem>,
+) -> Result<Vec<BatchCreateResult>> {
+   app.download().create_many(items)
+}
+
+#[command]
+pub(crate) async fn validate_create<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   url: String,
+   options: Option<DownloadOptions>,
+) -> Result<()> {
+   app.download()
+      .validate_create_with_options(&path, &url, &options.unwrap_or_default())
+}
+
+#[command]
+pub(crate) async fn create_in_dir<R: Runtime>(
+   app: AppHandle<R>,
+   dir: String,
+   url: String,
+) -> Result<DownloadActionResponse> {
+   app.download().create_in_dir(&dir, &url)
+}
+
+#[command]
+pub(crate) async fn download_now<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   url: String,
+   options: Option<DownloadOptions>,
+) -> Result<DownloadItem> {
+   app.download()
+      .download_now(&path, &url, options.unwrap_or_default())
+      .await
+}
+
+#[command]
+pub(crate) async fn probe<R: Runtime>(app: AppHandle<R>, url: String) -> Result<ProbeResult> {
+   app.download().probe(&url).await
+}
+
+#[command]
+pub(crate) async fn verify<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   expected_sha256: String,
+) -> Result<bool> {
+   app.download().verify(&path, &expected_sha256).await
+}
+
+#[command]
+pub(crate) async fn action<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   action: DownloadAction,
+) -> Result<DownloadActionResponse> {
+   app.download().action(&path, action)
 }
 
 #[command]
@@ -28,7 +152,7 @@ pub(crate) async fn start<R: Runtime>(
    app: AppHandle<R>,
    path: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().start(&path)
+   app.download().action(&path, DownloadAction::Start)
 }
 
 #[command]
@@ -36,7 +160,33 @@ pub(crate) async fn resume<R: Runtime>(
    app: AppHandle<R>,
    path: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().resume(&path)
+   app.download().action(&path, DownloadAction::Resume)
+}
+
+#[command]
+pub(crate) async fn retry<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+) -> Result<DownloadActionResponse> {
+   app.download().action(&path, DownloadAction::Retry)
+}
+
+#[command]
+pub(crate) async fn schedule<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   at: u64,
+) -> Result<DownloadActionResponse> {
+   app.download().schedule(&path, at)
+}
+
+#[command]
+pub(crate) async fn restart<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   start: Option<bool>,
+) -> Result<DownloadActionResponse> {
+   app.download().restart(&path, start.unwrap_or(false))
 }
 
 #[command]
@@ -44,7 +194,7 @@ pub(crate) async fn pause<R: Runtime>(
    app: AppHandle<R>,
    path: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().pause(&path)
+   app.download().action(&path, DownloadAction::Pause)
 }
 
 #[command]
@@ -52,7 +202,129 @@ pub(crate) async fn cancel<R: Runtime>(
    app: AppHandle<R>,
    path: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().cancel(&path)
+   app.download().action(&path, DownloadAction::Cancel)
+}
+
+#[command]
+pub(crate) async fn pause_all<R: Runtime>(
+   app: AppHandle<R>,
+) -> Result<Vec<DownloadActionResponse>> {
+   app.download().pause_all()
+}
+
+#[command]
+pub(crate) async fn resume_all<R: Runtime>(
+   app: AppHandle<R>,
+) -> Result<Vec<DownloadActionResponse>> {
+   app.download().resume_all()
+}
+
+#[command]
+pub(crate) async fn cancel_all<R: Runtime>(
+   app: AppHandle<R>,
+) -> Result<Vec<DownloadActionResponse>> {
+   app.download().cancel_all()
+}
+
+#[command]
+pub(crate) async fn pause_paths<R: Runtime>(
+   app: AppHandle<R>,
+   paths: Vec<String>,
+) -> Result<Vec<BatchActionResult>> {
+   app.download().pause_paths(&paths)
+}
+
+#[command]
+pub(crate) async fn resume_paths<R: Runtime>(
+   app: AppHandle<R>,
+   paths: Vec<String>,
+) -> Result<Vec<BatchActionResult>> {
+   app.download().resume_paths(&paths)
+}
+
+#[command]
+pub(crate) async fn cancel_paths<R: Runtime>(
+   app: AppHandle<R>,
+   paths: Vec<String>,
+) -> Result<Vec<BatchActionResult>> {
+   app.download().cancel_paths(&paths)
+}
+
+#[command]
+pub(crate) async fn move_to<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   new_path: String,
+) -> Result<DownloadItem> {
+   app.download().move_to(&path, &new_path)
+}
+
+#[command]
+pub(crate) async fn set_priority<R: Runtime>(
+   app: AppHandle<R>,
+   path: String,
+   priority: i32,
+) -> Result<DownloadItem> {
+   app.download().set_priority(&path, priority)
+}
+
+#[command]
+pub(crate) async fn reveal<R: Runtime>(app: AppHandle<R>, path: String) -> Result<()> {
+   app.download().reveal(&path)
+}
+
+#[command]
+pub(crate) async fn config<R: Runtime>(app: AppHandle<R>) -> Result<DownloadManagerConfig> {
+   #[cfg(desktop)]
+   {
+      Ok(app.download().config())
+   }
+   #[cfg(mobile)]
+   {
+      app.download().config()
+   }
+}
+
+#[command]
+pub(crate) async fn clear_completed<R: Runtime>(app: AppHandle<R>) -> Result<usize> {
+   app.download().clear_completed()
+}
+
+#[command]
+pub(crate) async fn reset<R: Runtime>(app: AppHandle<R>) -> Result<ResetSummary> {
+   app.download().reset()
+}
+
+#[command]
+pub(crate) async fn capabilities<R: Runtime>(_app: AppHandle<R>) -> Result<Capabilities> {
+   #[cfg(desktop)]
+   {
+      Ok(Capabilities {
+         native: false,
+         supports_resume: true,
+         supports_background: false,
+         supports_rate_limit: false,
+      })
+   }
+   // iOS downloads run in a background `URLSession`.
+   #[cfg(target_os = "ios")]
+   {
+      Ok(Capabilities {
+         native: true,
+         supports_resume: true,
+         supports_background: true,
+         supports_rate_limit: false,
+      })
+   }
+   #[cfg(target_os = "android")]
+   {
+      Ok(Capabilities {
+         native: true,
+         supports_resume: true,
+         supports_background: false,
+         supports_rate_limit: false,
+      })
+   }
 }
 
 #[tauri::command(rename_all = "snake_case")]
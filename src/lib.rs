@@ -17,6 +17,15 @@ use error::Result;
 #[cfg(desktop)]
 use download_manager::DownloadManager;
 
+/// How long running downloads get to stop when the application exits.
+#[cfg(desktop)]
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+pub use download_manager::{
+   CrossHostRedirects, DownloadManagerConfig, DuplicateUrlPolicy, HostPolicy, PartialFilePolicy,
+   ProgressConfig, ProxyConfig, RedirectConfig, TlsConfig, TlsVersion,
+};
+
 #[cfg(mobile)]
 mod mobile;
 #[cfg(mobile)]
@@ -55,17 +64,52 @@ impl<R: Runtime, T: Manager<R>> crate::DownloadExt<R> for T {
 }
 
 /// Initializes the plugin.
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-   Builder::new("download")
+///
+/// The desktop download manager is configured from the optional `plugins.download`
+/// section of `tauri.conf.json` (see [`DownloadManagerConfig`]).
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<DownloadManagerConfig>> {
+   Builder::<R, Option<DownloadManagerConfig>>::new("download")
       .invoke_handler(tauri::generate_handler![
          commands::create,
+         commands::create_in_dir,
+         commands::create_batch,
+         commands::validate_create,
          commands::list,
+         commands::list_by_status,
+         commands::list_by_tag,
+         commands::count_by_status,
+         commands::aggregate_progress,
+         commands::stats,
+         commands::reset_stats,
+         commands::recovered,
          commands::get,
+         commands::get_or_create,
+         commands::downloaded_bytes,
+         commands::download_now,
+         commands::probe,
+         commands::verify,
+         commands::action,
          commands::start,
          commands::cancel,
          commands::pause,
          commands::resume,
+         commands::retry,
+         commands::restart,
+         commands::schedule,
          commands::is_native,
+         commands::capabilities,
+         commands::config,
+         commands::move_to,
+         commands::set_priority,
+         commands::reveal,
+         commands::pause_all,
+         commands::resume_all,
+         commands::cancel_all,
+         commands::pause_paths,
+         commands::resume_paths,
+         commands::cancel_paths,
+         commands::clear_completed,
+         commands::reset,
       ])
       .setup(|app, _api| {
          #[cfg(desktop)]
@@ -76,16 +120,27 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
                std::path::PathBuf::from(".")
             });
 
-            // Wire Tauri event emission as the on_changed callback.
+            // Wire Tauri event emission as the on_changed and on_event callbacks.
             let app_handle = app.app_handle().clone();
-            let manager = DownloadManager::new(
+            let event_handle = app.app_handle().clone();
+            let config = _api.config().clone().unwrap_or_default();
+            let manager = DownloadManager::with_config(
                data_dir,
+               config,
                std::sync::Arc::new(move |item| {
                   if let Err(e) = app_handle.emit("tauri-plugin-download:changed", &item) {
                      warn!("Failed to emit change event: {}", e);
                   }
                }),
-            );
+            )
+            .with_event_handler(std::sync::Arc::new(move |event| {
+               let name = format!("tauri-plugin-download:{}", event.name());
+               if let Err(e) = event_handle.emit(&name, &event) {
+                  warn!("Failed to emit {} event: {}", event.name(), e);
+               }
+            }))
+            // Run downloads on Tauri's runtime, so they can be started from any thread.
+            .with_runtime(tauri::async_runtime::handle().inner().clone());
             app.manage(manager);
          }
 
@@ -99,10 +154,25 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
          Ok(())
       })
       .on_event(|_app_handle, event| {
-         if let RunEvent::Ready = event {
-            // Initialize the download plugin.
-            #[cfg(desktop)]
-            _app_handle.state::<DownloadManager>().init();
+         match event {
+            RunEvent::Ready => {
+               // Initialize the download plugin.
+               #[cfg(desktop)]
+               _app_handle.state::<DownloadManager>().init();
+            }
+            RunEvent::Exit => {
+               // Pause running downloads and persist their state, so the next launch
+               // finds them paused rather than interrupted.
+               #[cfg(desktop)]
+               {
+                  let manager = _app_handle.state::<DownloadManager>();
+                  if let Err(e) = tauri::async_runtime::block_on(manager.shutdown(SHUTDOWN_TIMEOUT))
+                  {
+                     warn!("Failed to shut down download manager: {}", e);
+                  }
+               }
+            }
+            _ => {}
          }
       })
       .build()
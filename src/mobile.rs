@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::de::DeserializeOwned;
 use tauri::plugin::{PluginApi, PluginHandle};
 use tauri::{AppHandle, Runtime};
@@ -60,6 +62,16 @@ impl<R: Runtime> Download<R> {
       }
    }
 
+   ///
+   /// Lists the download operations whose status was changed when recovering from an
+   /// interrupted session. The native plugins don't keep these, so this is always empty.
+   ///
+   /// # Returns
+   /// The reconciled download operations.
+   pub fn recovered(&self) -> Vec<DownloadItem> {
+      Vec::new()
+   }
+
    ///
    /// Gets a download operation.
    ///
@@ -84,6 +96,42 @@ impl<R: Runtime> Download<R> {
          .map_err(Into::into)
    }
 
+   ///
+   /// Gets a download operation, creating it if it doesn't exist.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource, if the download is created.
+   ///
+   /// # Returns
+   /// The existing download operation, or the created one.
+   pub fn get_or_create(&self, path: &str, url: &str) -> crate::Result<DownloadItem> {
+      let item = self.get(path)?;
+      if item.status != DownloadStatus::Pending {
+         return Ok(item);
+      }
+      // The native plugins return the existing item if one was created in between.
+      Ok(self.create(path, url)?.download)
+   }
+
+   ///
+   /// Gets the number of bytes on disk for a download operation.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The size of the file, or 0 if there is none.
+   pub fn downloaded_bytes(&self, _path: &str) -> crate::Result<u64> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Downloaded bytes are only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
    ///
    /// Creates a download operation.
    ///
@@ -101,11 +149,205 @@ impl<R: Runtime> Download<R> {
             CreateArgs {
                path: path.to_string(),
                url: url.to_string(),
+               options: None,
+            },
+         )
+         .map_err(Into::into)
+   }
+
+   ///
+   /// Creates a download operation with options. Mirror failover is not yet
+   /// implemented by the native plugins, which ignore unsupported options.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - The download options.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn create_with_options(
+      &self,
+      path: &str,
+      url: &str,
+      options: DownloadOptions,
+   ) -> crate::Result<DownloadActionResponse> {
+      self
+         .0
+         .run_mobile_plugin(
+            "create",
+            CreateArgs {
+               path: path.to_string(),
+               url: url.to_string(),
+               options: Some(options),
             },
          )
          .map_err(Into::into)
    }
 
+   ///
+   /// Creates a download operation in a directory. The filename is resolved from the
+   /// server's `Content-Disposition` header, falling back to the URL.
+   ///
+   /// # Arguments
+   /// - `dir` - The destination directory.
+   /// - `url` - The download URL for the resource.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn create_in_dir(&self, _dir: &str, _url: &str) -> crate::Result<DownloadActionResponse> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Creating a download in a directory is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Creates multiple download operations. The native plugins have no batch
+   /// operation, so each download is created individually.
+   ///
+   /// # Arguments
+   /// - `items` - The downloads to create.
+   ///
+   /// # Returns
+   /// The outcome for each item, in order.
+   pub fn create_many(&self, items: Vec<BatchCreateItem>) -> crate::Result<Vec<BatchCreateResult>> {
+      Ok(items
+         .into_iter()
+         .map(
+            |item| match self.create_with_options(&item.path, &item.url, item.options) {
+               Ok(response) => BatchCreateResult {
+                  path: item.path,
+                  response: Some(response),
+                  error: None,
+               },
+               Err(e) => BatchCreateResult {
+                  path: item.path,
+                  response: None,
+                  error: Some(e.to_string()),
+               },
+            },
+         )
+         .collect())
+   }
+
+   ///
+   /// Checks whether a download operation with options could be created, without
+   /// creating it.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - The download options.
+   ///
+   /// # Returns
+   /// The error creating the download operation would fail with, if any.
+   pub fn validate_create_with_options(
+      &self,
+      _path: &str,
+      _url: &str,
+      _options: &DownloadOptions,
+   ) -> crate::Result<()> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Create validation is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Learns the size of a resource, whether a download of it could be resumed, its
+   /// type and filename, without downloading it.
+   ///
+   /// # Arguments
+   /// - `url` - The URL of the resource.
+   ///
+   /// # Returns
+   /// What downloading the resource would involve.
+   pub async fn probe(&self, _url: &str) -> crate::Result<ProbeResult> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Probing is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Checks the file at a download path against its SHA-256 digest, completing the
+   /// download operation at the path on a match.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `expected_sha256` - The expected digest, in hex.
+   ///
+   /// # Returns
+   /// Whether the file exists and matches the digest.
+   pub async fn verify(&self, _path: &str, _expected_sha256: &str) -> crate::Result<bool> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "File verification is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Downloads a resource in a single call: creates the download operation, starts it
+   /// and waits for it to finish.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `url` - The download URL for the resource.
+   /// - `options` - Options for the download.
+   ///
+   /// # Returns
+   /// The completed download operation.
+   pub async fn download_now(
+      &self,
+      _path: &str,
+      _url: &str,
+      _options: DownloadOptions,
+   ) -> crate::Result<DownloadItem> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Downloading in a single call is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Performs an action on a download, dispatching to the matching operation.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `action` - The action to perform.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn action(
+      &self,
+      path: &str,
+      action: DownloadAction,
+   ) -> crate::Result<DownloadActionResponse> {
+      match action {
+         DownloadAction::Start => self.start(path),
+         DownloadAction::Pause => self.pause(path),
+         DownloadAction::Resume => self.resume(path),
+         DownloadAction::Cancel => self.cancel(path),
+         DownloadAction::Retry => self.retry(path),
+      }
+   }
+
    ///
    /// Starts a download operation.
    ///
@@ -146,6 +388,57 @@ impl<R: Runtime> Download<R> {
          .map_err(Into::into)
    }
 
+   ///
+   /// Retries a failed download operation. Native downloads don't fail into a
+   /// retryable state, so this starts the download.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn retry(&self, path: &str) -> crate::Result<DownloadActionResponse> {
+      self.start(path)
+   }
+
+   ///
+   /// Schedules an idle download operation to start at a given time.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `at` - When to start, in milliseconds since the Unix epoch.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn schedule(&self, _path: &str, _at: u64) -> crate::Result<DownloadActionResponse> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Scheduling is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Restarts a download operation from zero, discarding its partial data.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `start` - Whether to start the download once it is reset.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn restart(&self, _path: &str, _start: bool) -> crate::Result<DownloadActionResponse> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Restarting downloads is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
    ///
    /// Pauses a download operation.
    ///
@@ -185,4 +478,301 @@ impl<R: Runtime> Download<R> {
          )
          .map_err(Into::into)
    }
+
+   ///
+   /// Pauses all in progress download operations. The native plugins have no bulk
+   /// operation, so each download is paused individually.
+   ///
+   /// # Returns
+   /// The paused download operations.
+   pub fn pause_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      self.for_each_with_status(&[DownloadStatus::InProgress], |path| self.pause(path))
+   }
+
+   ///
+   /// Resumes all paused download operations, each individually.
+   ///
+   /// # Returns
+   /// The resumed download operations.
+   pub fn resume_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      self.for_each_with_status(&[DownloadStatus::Paused], |path| self.resume(path))
+   }
+
+   ///
+   /// Cancels all created, in progress and paused download operations, each
+   /// individually.
+   ///
+   /// # Returns
+   /// The canceled download operations.
+   pub fn cancel_all(&self) -> crate::Result<Vec<DownloadActionResponse>> {
+      self.for_each_with_status(
+         &[
+            DownloadStatus::Idle,
+            DownloadStatus::InProgress,
+            DownloadStatus::Paused,
+         ],
+         |path| self.cancel(path),
+      )
+   }
+
+   ///
+   /// Pauses the given download operations, each individually.
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order.
+   pub fn pause_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(Self::for_each_path(paths, |path| self.pause(path)))
+   }
+
+   ///
+   /// Resumes the given download operations, each individually.
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order.
+   pub fn resume_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(Self::for_each_path(paths, |path| self.resume(path)))
+   }
+
+   ///
+   /// Cancels the given download operations, each individually.
+   ///
+   /// # Arguments
+   /// - `paths` - The download paths.
+   ///
+   /// # Returns
+   /// The outcome for each path, in order.
+   pub fn cancel_paths(&self, paths: &[String]) -> crate::Result<Vec<BatchActionResult>> {
+      Ok(Self::for_each_path(paths, |path| self.cancel(path)))
+   }
+
+   ///
+   /// Lists the download operations with the given status.
+   ///
+   /// # Arguments
+   /// - `status` - The status to match.
+   ///
+   /// # Returns
+   /// The matching download operations.
+   pub fn list_by_status(&self, status: DownloadStatus) -> crate::Result<Vec<DownloadItem>> {
+      Ok(self
+         .list()?
+         .into_iter()
+         .filter(|item| item.status == status)
+         .collect())
+   }
+
+   ///
+   /// Lists the download operations with a tag.
+   ///
+   /// # Arguments
+   /// - `tag` - The tag to match.
+   ///
+   /// # Returns
+   /// The matching download operations.
+   pub fn list_by_tag(&self, _tag: &str) -> crate::Result<Vec<DownloadItem>> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Tags are only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Counts the download operations in each status.
+   ///
+   /// # Returns
+   /// The number of download operations per status.
+   pub fn count_by_status(&self) -> crate::Result<HashMap<DownloadStatus, usize>> {
+      let mut counts = HashMap::new();
+      for item in self.list()? {
+         *counts.entry(item.status).or_insert(0) += 1;
+      }
+      Ok(counts)
+   }
+
+   ///
+   /// Combines the progress of the in progress and paused download operations.
+   ///
+   /// # Returns
+   /// The combined progress.
+   pub fn aggregate_progress(&self) -> crate::Result<AggregateProgress> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Aggregate progress is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Returns the lifetime statistics of the downloads.
+   ///
+   /// # Returns
+   /// The bytes received and the downloads completed and failed.
+   pub fn stats(&self) -> crate::Result<DownloadStats> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Download statistics are only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Resets the lifetime statistics of the downloads to zero.
+   pub fn reset_stats(&self) -> crate::Result<()> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Download statistics are only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   fn for_each_path<F>(paths: &[String], action: F) -> Vec<BatchActionResult>
+   where
+      F: Fn(&str) -> crate::Result<DownloadActionResponse>,
+   {
+      paths
+         .iter()
+         .map(|path| match action(path) {
+            Ok(response) => BatchActionResult {
+               path: path.clone(),
+               response: Some(response),
+               error: None,
+            },
+            Err(e) => BatchActionResult {
+               path: path.clone(),
+               response: None,
+               error: Some(e.to_string()),
+            },
+         })
+         .collect()
+   }
+
+   fn for_each_with_status<F>(
+      &self,
+      statuses: &[DownloadStatus],
+      action: F,
+   ) -> crate::Result<Vec<DownloadActionResponse>>
+   where
+      F: Fn(&str) -> crate::Result<DownloadActionResponse>,
+   {
+      self
+         .list()?
+         .into_iter()
+         .filter(|item| statuses.contains(&item.status))
+         .map(|item| action(&item.path))
+         .collect()
+   }
+
+   ///
+   /// Gets the download manager configuration. Not supported by the native plugins, as
+   /// the configuration only applies to the desktop download manager.
+   ///
+   /// # Returns
+   /// An error, as reading the configuration is only supported on desktop.
+   pub fn config(&self) -> crate::Result<crate::DownloadManagerConfig> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Reading the configuration is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Moves a completed download to another path. Not supported by the native plugins.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `new_path` - The path to move the download to.
+   ///
+   /// # Returns
+   /// An error, as moving downloads is only supported on desktop.
+   pub fn move_to(&self, _path: &str, _new_path: &str) -> crate::Result<DownloadItem> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Moving downloads is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Sets the priority of a download among queued downloads. Not supported by the
+   /// native plugins.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   /// - `priority` - The priority, higher starting sooner.
+   ///
+   /// # Returns
+   /// An error, as download priorities are only supported on desktop.
+   pub fn set_priority(&self, _path: &str, _priority: i32) -> crate::Result<DownloadItem> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Download priorities are only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Shows a completed download in the file manager. Not supported by the native
+   /// plugins.
+   ///
+   /// # Arguments
+   /// - `path` - The download path.
+   ///
+   /// # Returns
+   /// An error, as revealing downloads is only supported on desktop.
+   pub fn reveal(&self, _path: &str) -> crate::Result<()> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Revealing downloads is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Removes all completed download operations from the store, leaving the downloaded
+   /// files on disk.
+   ///
+   /// # Returns
+   /// The number of download operations removed.
+   pub fn clear_completed(&self) -> crate::Result<usize> {
+      Err(
+         std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Clearing completed downloads is only supported on desktop",
+         )
+         .into(),
+      )
+   }
+
+   ///
+   /// Cancels all downloads and clears the store.
+   ///
+   /// # Returns
+   /// A summary of what was removed.
+   pub fn reset(&self) -> crate::Result<ResetSummary> {
+      self.0.run_mobile_plugin("reset", ()).map_err(Into::into)
+   }
 }
@@ -1,6 +1,30 @@
+use serde::Serialize;
+
+pub use download_manager::{
+   AggregateProgress, BatchCreateItem, DownloadAction, DownloadOptions, DownloadStats, ProbeResult,
+};
+
+/// Features available on the current platform, so callers can detect them rather
+/// than infer them from the platform.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+   /// Whether downloads are handled by the native mobile plugin.
+   pub native: bool,
+   /// Whether a paused or interrupted download continues from the bytes received.
+   pub supports_resume: bool,
+   /// Whether downloads continue while the app is in the background.
+   pub supports_background: bool,
+   /// Whether the download speed can be limited.
+   pub supports_rate_limit: bool,
+}
+
 // Desktop model types
 #[cfg(desktop)]
-pub use download_manager::{DownloadActionResponse, DownloadItem};
+pub use download_manager::{
+   BatchActionResult, BatchCreateResult, DownloadActionResponse, DownloadItem, DownloadStatus,
+   ResetSummary,
+};
 
 // Mobile model types (iOS, Android)
 #[cfg(mobile)]
@@ -18,6 +42,8 @@ mod mobile_types {
    pub struct CreateArgs {
       pub path: String,
       pub url: String,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      pub options: Option<super::DownloadOptions>,
    }
 
    #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -25,11 +51,11 @@ mod mobile_types {
    pub struct DownloadItem {
       pub url: String,
       pub path: String,
-      pub progress: f64,
+      pub progress: Option<f64>,
       pub status: DownloadStatus,
    }
 
-   #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+   #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
    #[serde(rename_all = "camelCase")]
    pub enum DownloadStatus {
       #[default]
@@ -38,6 +64,8 @@ mod mobile_types {
       Idle,
       InProgress,
       Paused,
+      Queued,
+      Failed,
       Canceled,
       Completed,
    }
@@ -50,6 +78,34 @@ mod mobile_types {
       pub is_expected_status: bool,
    }
 
+   #[derive(Debug, Clone, Serialize, Deserialize)]
+   #[serde(rename_all = "camelCase")]
+   pub struct BatchCreateResult {
+      pub path: String,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      pub response: Option<DownloadActionResponse>,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      pub error: Option<String>,
+   }
+
+   #[derive(Debug, Clone, Serialize, Deserialize)]
+   #[serde(rename_all = "camelCase")]
+   pub struct BatchActionResult {
+      pub path: String,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      pub response: Option<DownloadActionResponse>,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      pub error: Option<String>,
+   }
+
+   #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+   #[serde(rename_all = "camelCase")]
+   pub struct ResetSummary {
+      pub canceled: usize,
+      pub removed: usize,
+      pub temp_files_removed: usize,
+   }
+
    impl DownloadActionResponse {
       pub fn new(download: DownloadItem) -> Self {
          let expected_status = download.status.clone();
@@ -72,4 +128,7 @@ mod mobile_types {
 }
 
 #[cfg(mobile)]
-pub use mobile_types::{CreateArgs, DownloadActionResponse, DownloadItem, PathArgs};
+pub use mobile_types::{
+   BatchActionResult, BatchCreateResult, CreateArgs, DownloadActionResponse, DownloadItem,
+   DownloadStatus, PathArgs, ResetSummary,
+};